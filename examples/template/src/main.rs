@@ -30,7 +30,7 @@ extern "C" fn main(_argc: isize, _argv: *const *const u8) -> isize {
 
     println!("Hello World!");
 
-    player.play_buffer(mp3);
+    let _playback = player.play_owned(mp3).expect("MP3 playback failed");
 
     loop {
         Input::update(ControllerType::Gamecube);
@@ -0,0 +1,55 @@
+//! Print what changed in each frame of an input recording.
+//!
+//! ```text
+//! cargo run --example recording -- demo.ogci
+//! ```
+
+use std::{env, fs, process};
+
+use ogc_formats::recording::{Decoder, FIELD_COUNT, FIELDS, Kind, PORTS};
+
+/// Fields printed in hexadecimal.
+const BUTTONS: [&str; 4] = ["pad.buttons", "wpad.held", "wpad.down", "wpad.up"];
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: recording <file>");
+        process::exit(2);
+    };
+    let data = fs::read(&path).unwrap_or_else(|error| {
+        eprintln!("{path}: {error}");
+        process::exit(1);
+    });
+    let mut decoder = Decoder::new(data).unwrap_or_else(|error| {
+        eprintln!("{path}: {error}");
+        process::exit(1);
+    });
+
+    let mut last = [[0; FIELD_COUNT]; PORTS];
+    loop {
+        let frame = match decoder.next_frame() {
+            Ok(Some(frame)) => *frame,
+            Ok(None) => break,
+            Err(error) => {
+                eprintln!("{path}: frame {}: {error}", decoder.frame());
+                process::exit(1);
+            }
+        };
+        let index = decoder.frame() - 1;
+        for (port, (fields, last)) in frame.iter().zip(&last).enumerate() {
+            for (field, (&value, &old)) in FIELDS.iter().zip(fields.iter().zip(last)) {
+                if value == old {
+                    continue;
+                }
+                let name = field.name;
+                match field.kind {
+                    Kind::Float { .. } => println!("{index} {port} {name} {}", field.value(value)),
+                    _ if BUTTONS.contains(&name) => println!("{index} {port} {name} {value:#x}"),
+                    _ => println!("{index} {port} {name} {value}"),
+                }
+            }
+        }
+        last = frame;
+    }
+    println!("{} frames", decoder.frame());
+}
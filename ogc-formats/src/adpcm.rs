@@ -0,0 +1,791 @@
+//! Nintendo DSP-ADPCM, the compressed format the audio DSP plays natively.
+//!
+//! Samples are stored in 8 byte frames: a header byte holding the predictor index and scale
+//! followed by 14 signed 4-bit samples. Every channel has its own 8 pairs of predictor
+//! coefficients.
+//!
+//! Nothing here touches the hardware, so sounds can be encoded on the console at runtime as
+//! well as ahead of time, for example from a build script.
+//!
+//! ```rust
+//! use ogc_formats::adpcm;
+//!
+//! let samples: Vec<i16> = (0..1000).map(|i| (i % 100 - 50) * 300).collect();
+//! let loop_start = 200;
+//! let encoded = adpcm::encode(&samples, Some(loop_start));
+//! assert_eq!(adpcm::decode(&encoded.data, &encoded.info, samples.len()).len(), samples.len());
+//! ```
+
+use alloc::{vec, vec::Vec};
+
+/// Bytes in a frame.
+pub const FRAME_BYTES: usize = 8;
+/// Samples in a frame.
+pub const FRAME_SAMPLES: usize = 14;
+
+/// Number of bytes needed to store `samples` samples.
+pub const fn bytes_for_samples(samples: usize) -> usize {
+    samples.div_ceil(FRAME_SAMPLES) * FRAME_BYTES
+}
+
+/// Decoder state at a sample, used to start decoding in the middle of a channel.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Context {
+    /// Header byte of the frame the sample is in
+    pub predictor_scale: u8,
+    /// The previous sample
+    pub hist1: i16,
+    /// The sample before `hist1`
+    pub hist2: i16,
+}
+
+/// Everything needed to decode a channel.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AdpcmInfo {
+    /// 8 pairs of predictor coefficients in 5.11 fixed point
+    pub coefficients: [i16; 16],
+    /// Gain, always `0` in practice
+    pub gain: u16,
+    /// State at the first sample
+    pub context: Context,
+    /// State at the loop start
+    pub loop_context: Context,
+}
+
+/// Decodes a DSP-ADPCM channel frame by frame.
+#[derive(Clone, Debug)]
+pub struct Decoder {
+    coefficients: [i16; 16],
+    hist1: i16,
+    hist2: i16,
+}
+
+impl Decoder {
+    /// Start decoding a channel from its first sample.
+    pub fn new(info: &AdpcmInfo) -> Self {
+        Self::with_context(info.coefficients, info.context)
+    }
+
+    /// Start decoding at the sample `context` was taken at.
+    pub fn with_context(coefficients: [i16; 16], context: Context) -> Self {
+        Self {
+            coefficients,
+            hist1: context.hist1,
+            hist2: context.hist2,
+        }
+    }
+
+    /// Replace the history, for seeking.
+    pub fn set_history(&mut self, hist1: i16, hist2: i16) {
+        self.hist1 = hist1;
+        self.hist2 = hist2;
+    }
+
+    /// The last two decoded samples, most recent first.
+    pub fn history(&self) -> (i16, i16) {
+        (self.hist1, self.hist2)
+    }
+
+    /// Decode one frame.
+    pub fn decode_frame(&mut self, frame: &[u8; FRAME_BYTES], out: &mut [i16; FRAME_SAMPLES]) {
+        let predictor = usize::from((frame[0] >> 4) & 7);
+        let scale = 1i64 << (frame[0] & 0xF);
+        // Extreme coefficients and history overflow an `i32`, the result is clamped anyway.
+        let c1 = i64::from(self.coefficients[predictor * 2]);
+        let c2 = i64::from(self.coefficients[predictor * 2 + 1]);
+
+        for (i, sample) in out.iter_mut().enumerate() {
+            let byte = frame[1 + i / 2];
+            let nibble = if i % 2 == 0 { byte >> 4 } else { byte & 0xF };
+            // Sign extend the nibble.
+            let nibble = i64::from((nibble << 4) as i8 >> 4);
+
+            let predicted = c1 * i64::from(self.hist1) + c2 * i64::from(self.hist2);
+            let value = (((nibble * scale) << 11) + 1024 + predicted) >> 11;
+            let value = value.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+
+            self.hist2 = self.hist1;
+            self.hist1 = value;
+            *sample = value;
+        }
+    }
+
+    /// Decode whole frames of `data` into `out` and return the number of samples written.
+    ///
+    /// Stops at whichever runs out first, a trailing partial frame in `data` is ignored.
+    pub fn decode(&mut self, data: &[u8], out: &mut [i16]) -> usize {
+        let mut written = 0;
+        let mut samples = [0; FRAME_SAMPLES];
+        for frame in data.chunks_exact(FRAME_BYTES) {
+            if written == out.len() {
+                break;
+            }
+            self.decode_frame(frame.try_into().unwrap(), &mut samples);
+            let len = FRAME_SAMPLES.min(out.len() - written);
+            out[written..written + len].copy_from_slice(&samples[..len]);
+            written += len;
+        }
+        written
+    }
+}
+
+/// Decode `samples` samples of a channel starting at its first sample.
+pub fn decode(data: &[u8], info: &AdpcmInfo, samples: usize) -> Vec<i16> {
+    let mut out = vec![0; samples];
+    let written = Decoder::new(info).decode(data, &mut out);
+    out.truncate(written);
+    out
+}
+
+/// A channel encoded with [`encode`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoded {
+    /// The frames, ready to be played
+    pub data: Vec<u8>,
+    /// Coefficients and the initial and loop context
+    pub info: AdpcmInfo,
+}
+
+/// Encode a channel of 16-bit PCM.
+///
+/// `loop_start` fills in [`AdpcmInfo::loop_context`], a loop start that is not a multiple of
+/// [`FRAME_SAMPLES`] still works but players usually expect one.
+pub fn encode(samples: &[i16], loop_start: Option<usize>) -> Encoded {
+    let coefficients = compute_coefficients(samples);
+    let mut encoder = Encoder::new(coefficients);
+    let mut data = vec![0; bytes_for_samples(samples.len())];
+    let mut info = AdpcmInfo {
+        coefficients,
+        ..Default::default()
+    };
+
+    for (i, (frame, out)) in samples
+        .chunks(FRAME_SAMPLES)
+        .zip(data.chunks_exact_mut(FRAME_BYTES))
+        .enumerate()
+    {
+        let (hist1, hist2) = encoder.history();
+        let out: &mut [u8; FRAME_BYTES] = out.try_into().unwrap();
+        encoder.encode_frame(frame, out);
+
+        if i == 0 {
+            info.context.predictor_scale = out[0];
+        }
+        if let Some(start) = loop_start.filter(|start| start / FRAME_SAMPLES == i) {
+            // Decode the frame to get the samples right before the loop start.
+            let mut decoded = [0; FRAME_SAMPLES];
+            let mut decoder = Decoder::with_context(coefficients, Context::default());
+            decoder.set_history(hist1, hist2);
+            decoder.decode_frame(out, &mut decoded);
+
+            let offset = start % FRAME_SAMPLES;
+            let before = |n: usize| match offset.checked_sub(n) {
+                Some(i) => decoded[i],
+                None if n - offset == 1 => hist1,
+                None => hist2,
+            };
+            info.loop_context = Context {
+                predictor_scale: out[0],
+                hist1: before(1),
+                hist2: before(2),
+            };
+        }
+    }
+
+    Encoded { data, info }
+}
+
+/// Encodes PCM frame by frame with fixed coefficients.
+#[derive(Clone, Debug)]
+pub struct Encoder {
+    coefficients: [i16; 16],
+    hist1: i16,
+    hist2: i16,
+}
+
+impl Encoder {
+    /// Start encoding a channel with `coefficients`, usually from [`compute_coefficients`].
+    pub fn new(coefficients: [i16; 16]) -> Self {
+        Self {
+            coefficients,
+            hist1: 0,
+            hist2: 0,
+        }
+    }
+
+    /// The last two samples as the decoder will see them, most recent first.
+    pub fn history(&self) -> (i16, i16) {
+        (self.hist1, self.hist2)
+    }
+
+    /// Encode up to [`FRAME_SAMPLES`] samples into one frame, missing samples are zero.
+    ///
+    /// Every coefficient pair is tried and the one with the smallest error is picked.
+    pub fn encode_frame(&mut self, samples: &[i16], out: &mut [u8; FRAME_BYTES]) {
+        assert!(
+            samples.len() <= FRAME_SAMPLES,
+            "Frame of {} samples is larger than {FRAME_SAMPLES}",
+            samples.len()
+        );
+        let count = samples.len();
+        let mut pcm = [0i32; FRAME_SAMPLES + 2];
+        pcm[0] = self.hist2.into();
+        pcm[1] = self.hist1.into();
+        for (dst, &sample) in pcm[2..].iter_mut().zip(samples) {
+            *dst = sample.into();
+        }
+
+        let mut decoded = [[0i32; FRAME_SAMPLES + 2]; 8];
+        let mut nibbles = [[0i32; FRAME_SAMPLES]; 8];
+        let mut scales = [0i32; 8];
+        let mut errors = [0f64; 8];
+
+        for (i, pair) in self.coefficients.chunks_exact(2).enumerate() {
+            let (c1, c2) = (i64::from(pair[0]), i64::from(pair[1]));
+            let predict = |hist2: i32, hist1: i32| i64::from(hist2) * c2 + i64::from(hist1) * c1;
+            decoded[i][0] = pcm[0];
+            decoded[i][1] = pcm[1];
+
+            // Largest prediction error, to pick a starting scale.
+            let mut distance = 0i32;
+            for s in 0..count {
+                let predicted = (predict(pcm[s], pcm[s + 1]) / 2048) as i32;
+                decoded[i][s + 2] = predicted;
+                let error = (pcm[s + 2] - predicted).clamp(-32768, 32767);
+                if error.abs() > distance.abs() {
+                    distance = error;
+                }
+            }
+            let mut scale = 0;
+            while scale <= 12 && !(-8..=7).contains(&distance) {
+                scale += 1;
+                distance /= 2;
+            }
+            scale = if scale <= 1 { -1 } else { scale - 2 };
+
+            loop {
+                scale += 1;
+                errors[i] = 0.0;
+                let mut overflow = 0;
+                for s in 0..count {
+                    let predicted = predict(decoded[i][s], decoded[i][s + 1]);
+                    let target = (i64::from(pcm[s + 2]) << 11) - predicted;
+                    let step = target as f64 / f64::from(1 << scale) / 2048.0;
+                    let mut nibble = if target > 0 {
+                        (step + f64::from(0.499_999_9f32)) as i32
+                    } else {
+                        (step - f64::from(0.499_999_9f32)) as i32
+                    };
+                    if nibble < -8 {
+                        overflow = overflow.max(-8 - nibble);
+                        nibble = -8;
+                    } else if nibble > 7 {
+                        overflow = overflow.max(nibble - 7);
+                        nibble = 7;
+                    }
+                    nibbles[i][s] = nibble;
+
+                    let value = (predicted + (i64::from(nibble * (1 << scale)) << 11) + 1024) >> 11;
+                    let value = value.clamp(-32768, 32767) as i32;
+                    decoded[i][s + 2] = value;
+                    let error = f64::from(pcm[s + 2] - value);
+                    errors[i] += error * error;
+                }
+
+                let mut x = overflow + 8;
+                while x > 256 {
+                    scale += 1;
+                    if scale >= 12 {
+                        scale = 11;
+                    }
+                    x >>= 1;
+                }
+                if scale >= 12 || overflow <= 1 {
+                    break;
+                }
+            }
+            scales[i] = scale;
+        }
+
+        let mut best = 0;
+        for i in 1..8 {
+            if errors[i] < errors[best] {
+                best = i;
+            }
+        }
+
+        out[0] = ((best as u8) << 4) | (scales[best] as u8 & 0xF);
+        for (i, byte) in out[1..].iter_mut().enumerate() {
+            let high = if i * 2 < count {
+                nibbles[best][i * 2]
+            } else {
+                0
+            };
+            let low = if i * 2 + 1 < count {
+                nibbles[best][i * 2 + 1]
+            } else {
+                0
+            };
+            *byte = ((high << 4) | (low & 0xF)) as u8;
+        }
+
+        if count > 0 {
+            self.hist1 = decoded[best][count + 1] as i16;
+            self.hist2 = decoded[best][count] as i16;
+        }
+    }
+}
+
+type Vec3 = [f64; 3];
+
+/// Compute the 8 coefficient pairs that best predict `samples`.
+///
+/// The pairs are fitted to the autocorrelation of every frame, then refined by repeatedly
+/// splitting them and clustering the frames around them.
+pub fn compute_coefficients(samples: &[i16]) -> [i16; 16] {
+    let mut records = Vec::with_capacity(samples.len().div_ceil(FRAME_SAMPLES));
+    // The previous frame followed by the current one.
+    let mut window = [0i16; FRAME_SAMPLES * 2];
+
+    for frame in samples.chunks(FRAME_SAMPLES) {
+        window.copy_within(FRAME_SAMPLES.., 0);
+        window[FRAME_SAMPLES..].fill(0);
+        window[FRAME_SAMPLES..FRAME_SAMPLES + frame.len()].copy_from_slice(frame);
+
+        let mut vec = inner_product(&window);
+        if vec[0].abs() > 10.0 {
+            let mut mtx = outer_product(&window);
+            let mut indices = [0; 3];
+            if !analyze_ranges(&mut mtx, &mut indices) {
+                bidirectional_filter(&mtx, &indices, &mut vec);
+                if !quadratic_merge(&mut vec) {
+                    records.push(finish_record(vec));
+                }
+            }
+        }
+    }
+
+    if records.is_empty() {
+        return [0; 16];
+    }
+
+    let mut average = [1.0, 0.0, 0.0];
+    for record in &records {
+        let filtered = matrix_filter(record);
+        average[1] += filtered[1];
+        average[2] += filtered[2];
+    }
+    average[1] /= records.len() as f64;
+    average[2] /= records.len() as f64;
+
+    let mut best = [[0.0; 3]; 8];
+    best[0] = merge_finish_record(&average);
+    for split in 0..3 {
+        let count = 1 << split;
+        for i in 0..count {
+            best[count + i] = [best[i][0], best[i][1] - 0.01, best[i][2]];
+        }
+        filter_records(&mut best, count * 2, &records);
+    }
+
+    let mut coefficients = [0; 16];
+    for (pair, vec) in coefficients.chunks_exact_mut(2).zip(&best) {
+        pair[0] = to_fixed(-vec[1]);
+        pair[1] = to_fixed(-vec[2]);
+    }
+    coefficients
+}
+
+/// Round `value` to 5.11 fixed point, halfway cases away from zero.
+fn to_fixed(value: f64) -> i16 {
+    let value = (value * 2048.0).clamp(-32768.0, 32767.0);
+    // Truncating after adding a half rounds like `f64::round`, which needs `std`.
+    (value + 0.5f64.copysign(value)) as i16
+}
+
+fn inner_product(window: &[i16; FRAME_SAMPLES * 2]) -> Vec3 {
+    let mut out = [0.0; 3];
+    for (i, value) in out.iter_mut().enumerate() {
+        for x in FRAME_SAMPLES..FRAME_SAMPLES * 2 {
+            *value -= f64::from(window[x - i]) * f64::from(window[x]);
+        }
+    }
+    out
+}
+
+fn outer_product(window: &[i16; FRAME_SAMPLES * 2]) -> [Vec3; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for x in 1..=2 {
+        for y in 1..=2 {
+            for z in FRAME_SAMPLES..FRAME_SAMPLES * 2 {
+                out[x][y] += f64::from(window[z - x]) * f64::from(window[z - y]);
+            }
+        }
+    }
+    out
+}
+
+/// LU decompose `mtx` in place with partial pivoting, returns `true` if it is singular.
+#[allow(clippy::needless_range_loop)]
+fn analyze_ranges(mtx: &mut [Vec3; 3], indices: &mut [usize; 3]) -> bool {
+    let mut recips = [0.0; 3];
+    for x in 1..=2 {
+        let value = mtx[x][1].abs().max(mtx[x][2].abs());
+        if value < f64::EPSILON {
+            return true;
+        }
+        recips[x] = 1.0 / value;
+    }
+
+    let mut max_index = 0;
+    for i in 1..=2 {
+        for x in 1..i {
+            let mut tmp = mtx[x][i];
+            for y in 1..x {
+                tmp -= mtx[x][y] * mtx[y][i];
+            }
+            mtx[x][i] = tmp;
+        }
+
+        let mut value = 0.0;
+        for x in i..=2 {
+            let mut tmp = mtx[x][i];
+            for y in 1..i {
+                tmp -= mtx[x][y] * mtx[y][i];
+            }
+            mtx[x][i] = tmp;
+            let scaled = tmp.abs() * recips[x];
+            if scaled >= value {
+                value = scaled;
+                max_index = x;
+            }
+        }
+
+        if max_index != i {
+            mtx.swap(max_index, i);
+            recips[max_index] = recips[i];
+        }
+        indices[i] = max_index;
+        if mtx[i][i] == 0.0 {
+            return true;
+        }
+        if i != 2 {
+            let recip = 1.0 / mtx[i][i];
+            for row in mtx.iter_mut().skip(i + 1) {
+                row[i] *= recip;
+            }
+        }
+    }
+
+    let (min, max) = (1..=2).fold((1.0e10f64, 0.0f64), |(min, max), i| {
+        let value = mtx[i][i].abs();
+        (min.min(value), max.max(value))
+    });
+    min / max < 1.0e-10
+}
+
+/// Solve the system decomposed by [`analyze_ranges`].
+fn bidirectional_filter(mtx: &[Vec3; 3], indices: &[usize; 3], vec: &mut Vec3) {
+    let mut first = 0;
+    for i in 1..=2 {
+        let index = indices[i];
+        let mut tmp = vec[index];
+        vec[index] = vec[i];
+        if first != 0 {
+            for y in first..i {
+                tmp -= vec[y] * mtx[i][y];
+            }
+        } else if tmp != 0.0 {
+            first = i;
+        }
+        vec[i] = tmp;
+    }
+
+    for i in (1..=2).rev() {
+        let mut tmp = vec[i];
+        for y in i + 1..=2 {
+            tmp -= vec[y] * mtx[i][y];
+        }
+        vec[i] = tmp / mtx[i][i];
+    }
+    vec[0] = 1.0;
+}
+
+/// Returns `true` if the filter in `vec` is unstable.
+fn quadratic_merge(vec: &mut Vec3) -> bool {
+    let v2 = vec[2];
+    let tmp = 1.0 - v2 * v2;
+    if tmp == 0.0 {
+        return true;
+    }
+    let v0 = (vec[0] - v2 * v2) / tmp;
+    let v1 = (vec[1] - vec[1] * v2) / tmp;
+    vec[0] = v0;
+    vec[1] = v1;
+    v1.abs() > 1.0
+}
+
+fn finish_record(mut vec: Vec3) -> Vec3 {
+    for value in &mut vec[1..] {
+        *value = value.clamp(-0.999_999_999_9, 0.999_999_999_9);
+    }
+    [1.0, vec[2] * vec[1] + vec[1], vec[2]]
+}
+
+fn matrix_filter(src: &Vec3) -> Vec3 {
+    let mut mtx = [[0.0; 3]; 3];
+    mtx[2][0] = 1.0;
+    for i in 1..=2 {
+        mtx[2][i] = -src[i];
+    }
+    for i in (1..=2).rev() {
+        let value = 1.0 - mtx[i][i] * mtx[i][i];
+        for y in 1..=i {
+            mtx[i - 1][y] = (mtx[i][i] * mtx[i][y] + mtx[i][y]) / value;
+        }
+    }
+
+    let mut dst = [1.0, 0.0, 0.0];
+    for i in 1..=2 {
+        for y in 1..=i {
+            dst[i] += mtx[i][y] * dst[i - y];
+        }
+    }
+    dst
+}
+
+fn merge_finish_record(src: &Vec3) -> Vec3 {
+    let mut tmp = [0.0; 3];
+    let mut dst = [1.0, 0.0, 0.0];
+    let mut value = src[0];
+    for i in 1..=2 {
+        let mut sum = 0.0;
+        for y in 1..i {
+            sum += dst[y] * src[i - y];
+        }
+        dst[i] = if value > 0.0 {
+            -(sum + src[i]) / value
+        } else {
+            0.0
+        };
+        tmp[i] = dst[i];
+        for y in 1..i {
+            dst[y] += dst[i] * dst[i - y];
+        }
+        value *= 1.0 - dst[i] * dst[i];
+    }
+    finish_record(tmp)
+}
+
+fn contrast_vectors(a: &Vec3, b: &Vec3) -> f64 {
+    let value = (b[2] * b[1] - b[1]) / (1.0 - b[2] * b[2]);
+    let v1 = a[0] * a[0] + a[1] * a[1] + a[2] * a[2];
+    let v2 = a[0] * a[1] + a[1] * a[2];
+    let v3 = a[0] * a[2];
+    v1 + 2.0 * value * v2 + 2.0 * (-b[1] * value - b[2]) * v3
+}
+
+/// Move each of the first `count` vectors in `best` to the mean of the records closest to it.
+fn filter_records(best: &mut [Vec3; 8], count: usize, records: &[Vec3]) {
+    for _ in 0..2 {
+        let mut sums = [[0.0; 3]; 8];
+        let mut counts = [0u32; 8];
+        for record in records {
+            let mut closest = 0;
+            let mut min = 1.0e30;
+            for (i, vec) in best[..count].iter().enumerate() {
+                let value = contrast_vectors(vec, record);
+                if value < min {
+                    min = value;
+                    closest = i;
+                }
+            }
+            counts[closest] += 1;
+            let filtered = matrix_filter(record);
+            for (sum, value) in sums[closest].iter_mut().zip(filtered) {
+                *sum += value;
+            }
+        }
+
+        for i in 0..count {
+            if counts[i] > 0 {
+                for sum in &mut sums[i] {
+                    *sum /= f64::from(counts[i]);
+                }
+            }
+            best[i] = merge_finish_record(&sums[i]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    /// A frame using predictor `0` with the given scale and nibbles.
+    fn frame(scale: u8, nibbles: [i8; FRAME_SAMPLES]) -> [u8; FRAME_BYTES] {
+        let mut frame = [scale, 0, 0, 0, 0, 0, 0, 0];
+        for (i, pair) in nibbles.chunks_exact(2).enumerate() {
+            frame[1 + i] = (pair[0] as u8) << 4 | (pair[1] as u8 & 0xF);
+        }
+        frame
+    }
+
+    /// Two sines at 32 kHz.
+    fn tones(len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|i| {
+                let t = i as f64 / 32000.0 * core::f64::consts::TAU;
+                (std::primitive::f64::sin(t * 440.0) * 12000.0
+                    + std::primitive::f64::sin(t * 1250.0) * 5000.0) as i16
+            })
+            .collect()
+    }
+
+    fn snr(original: &[i16], decoded: &[i16]) -> f64 {
+        let square = |x: f64| x * x;
+        let signal: f64 = original.iter().map(|&s| square(f64::from(s))).sum();
+        let noise: f64 = original
+            .iter()
+            .zip(decoded)
+            .map(|(&a, &b)| square(f64::from(a) - f64::from(b)))
+            .sum();
+        10.0 * std::primitive::f64::log10(signal / noise)
+    }
+
+    #[test]
+    fn decode_frame() {
+        let mut coefficients = [0; 16];
+        coefficients[0] = 2048;
+        let context = Context {
+            predictor_scale: 0,
+            hist1: 100,
+            hist2: 0,
+        };
+        let mut decoder = Decoder::with_context(coefficients, context);
+        let mut out = [0; FRAME_SAMPLES];
+        decoder.decode_frame(
+            &frame(1, [1, -1, 7, -8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            &mut out,
+        );
+        assert_eq!(out[..5], [102, 100, 114, 98, 98]);
+        assert_eq!(decoder.history(), (98, 98));
+
+        // Predictions saturate instead of wrapping.
+        decoder.set_history(32767, 0);
+        decoder.decode_frame(&frame(12, [7; FRAME_SAMPLES]), &mut out);
+        assert_eq!(out, [32767; FRAME_SAMPLES]);
+
+        // Partial frames are ignored and `out` bounds the samples decoded.
+        let data = [frame(0, [1; FRAME_SAMPLES]); 2].concat();
+        let mut decoder = Decoder::with_context(coefficients, Context::default());
+        let mut out = [0; 20];
+        assert_eq!(decoder.decode(&data[..12], &mut out), FRAME_SAMPLES);
+        assert_eq!(decoder.decode(&data, &mut out[..5]), 5);
+    }
+
+    #[test]
+    fn extreme_coefficients() {
+        // Headers can hold any coefficients, these overflow a 32-bit prediction.
+        let context = Context {
+            predictor_scale: 0,
+            hist1: i16::MIN,
+            hist2: i16::MIN,
+        };
+        let mut decoder = Decoder::with_context([i16::MIN; 16], context);
+        let mut out = [0; FRAME_SAMPLES];
+        decoder.decode_frame(&frame(15, [-8; FRAME_SAMPLES]), &mut out);
+        assert_eq!(out[0], i16::MAX);
+        assert_eq!(out[1], i16::MIN);
+
+        let mut decoder = Decoder::with_context([i16::MAX; 16], Context::default());
+        decoder.set_history(i16::MAX, i16::MAX);
+        decoder.decode_frame(&frame(15, [7; FRAME_SAMPLES]), &mut out);
+        assert_eq!(out, [i16::MAX; FRAME_SAMPLES]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let samples = tones(5000);
+        let encoded = encode(&samples, Some(2800));
+        assert_eq!(encoded.data.len(), bytes_for_samples(samples.len()));
+
+        let decoded = decode(&encoded.data, &encoded.info, samples.len());
+        assert_eq!(decoded.len(), samples.len());
+        let snr = snr(&samples, &decoded);
+        assert!(snr > 30.0, "SNR of {snr} dB");
+
+        let info = encoded.info;
+        assert_eq!(info.context.predictor_scale, encoded.data[0]);
+        assert_eq!(
+            info.loop_context.predictor_scale,
+            encoded.data[2800 / 14 * 8]
+        );
+        assert_eq!(info.loop_context.hist1, decoded[2799]);
+        assert_eq!(info.loop_context.hist2, decoded[2798]);
+
+        // Decoding from the loop context continues exactly where the full decode is.
+        let mut decoder = Decoder::with_context(info.coefficients, info.loop_context);
+        let mut out = [0; 28];
+        decoder.decode(&encoded.data[2800 / 14 * 8..], &mut out);
+        assert_eq!(out, decoded[2800..2828]);
+    }
+
+    #[test]
+    fn loop_start_inside_a_frame() {
+        let samples = tones(100);
+        let encoded = encode(&samples, Some(29));
+        let decoded = decode(&encoded.data, &encoded.info, samples.len());
+        assert_eq!(encoded.info.loop_context.hist1, decoded[28]);
+        assert_eq!(encoded.info.loop_context.hist2, decoded[27]);
+
+        // Right after a frame boundary the history comes from the previous frame.
+        let encoded = encode(&samples, Some(15));
+        assert_eq!(encoded.info.loop_context.hist1, decoded[14]);
+        assert_eq!(encoded.info.loop_context.hist2, decoded[13]);
+    }
+
+    #[test]
+    fn edge_cases() {
+        let silence = encode(&[0; 30], None);
+        assert!(silence.data.iter().all(|&b| b == 0));
+        assert_eq!(silence.info.coefficients, [0; 16]);
+        assert_eq!(decode(&silence.data, &silence.info, 30), [0; 30]);
+
+        let empty = encode(&[], Some(0));
+        assert!(empty.data.is_empty());
+        assert!(decode(&empty.data, &empty.info, 0).is_empty());
+
+        // Noise with a partial last frame still decodes to the right length.
+        let mut seed = 12345u32;
+        let noise: Vec<i16> = (0..1001)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as i16
+            })
+            .collect();
+        let encoded = encode(&noise, None);
+        assert_eq!(encoded.data.len(), 72 * FRAME_BYTES);
+        assert_eq!(decode(&encoded.data, &encoded.info, 1001).len(), 1001);
+
+        // Full scale square waves must not overflow the encoder.
+        let square: Vec<i16> = (0..280)
+            .map(|i| if i / 7 % 2 == 0 { i16::MAX } else { i16::MIN })
+            .collect();
+        let encoded = encode(&square, None);
+        let decoded = decode(&encoded.data, &encoded.info, square.len());
+        assert!(snr(&square, &decoded) > 10.0);
+    }
+
+    #[test]
+    fn fixed_point() {
+        assert_eq!(to_fixed(0.5), 1024);
+        assert_eq!(to_fixed(-0.5), -1024);
+        assert_eq!(to_fixed(1.0 / 4096.0), 1);
+        assert_eq!(to_fixed(-1.0 / 4096.0), -1);
+        assert_eq!(to_fixed(100.0), i16::MAX);
+        assert_eq!(to_fixed(-100.0), i16::MIN);
+    }
+}
@@ -0,0 +1,865 @@
+//! The protocol spoken by ``ogc_rs::devserver``.
+//!
+//! A [`Session`] reads commands from a [`Connection`] and answers them, leaving everything that
+//! touches the console to a [`Handler`]. Both the line based protocol and HTTP/1.0 are served on
+//! the same connection, see ``ogc_rs::devserver`` for the commands.
+
+use alloc::{format, string::String, vec, vec::Vec};
+use core::{fmt, time::Duration};
+
+/// Largest NAND file accepted by `put`.
+pub const MAX_FILE: usize = 8 * 1024 * 1024;
+/// Largest DOL accepted by `run`.
+pub const MAX_DOL: usize = 32 * 1024 * 1024;
+/// Longest command or header line.
+pub const MAX_LINE: usize = 1024;
+/// How often a streaming `log` checks for new output.
+pub const LOG_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The reply to `help`.
+pub const HELP: &str = "help               list the commands
+log                stream console output until a key is pressed
+mem                arena bounds and sizes
+threads            registered threads
+ls <dir>           list a NAND directory
+get <file>         download a NAND file
+put <file> <len>   upload a NAND file
+run <len>          upload a DOL and run it
+quit               close the connection
+";
+
+/// Errors ending a session.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// The client sent a line longer than [`MAX_LINE`].
+    LineTooLong,
+    /// The client closed the connection before sending an announced upload.
+    UnexpectedEof,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LineTooLong => write!(f, "line is too long"),
+            Self::UnexpectedEof => write!(f, "connection closed before the upload was complete"),
+        }
+    }
+}
+
+impl core::error::Error for ProtocolError {}
+
+/// The connection to a client.
+pub trait Connection {
+    /// The error of a failed call, which also has to represent [`ProtocolError`]s.
+    type Error: From<ProtocolError>;
+
+    /// Read into `buf`, returning how many bytes were read. `0` means the client closed its
+    /// side.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// Write all of `data`.
+    fn write_all(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Set how long a read may wait, `None` waits forever.
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<(), Self::Error>;
+
+    /// Whether `error` was returned by a read that timed out.
+    fn is_timeout(error: &Self::Error) -> bool;
+
+    /// Close both directions, called before a program is run.
+    fn shutdown(&mut self);
+}
+
+/// Everything a [`Session`] asks of the console.
+pub trait Handler {
+    /// The error of a failed command, which is sent to the client as text.
+    type Error: fmt::Display;
+
+    /// The reply to `mem`.
+    fn mem(&mut self) -> String;
+
+    /// The reply to `threads`.
+    fn threads(&mut self) -> String;
+
+    /// The names in `dir`, one per line.
+    fn ls(&mut self, dir: &str) -> Result<String, Self::Error>;
+
+    /// The contents of the file at `path`.
+    fn read_file(&mut self, path: &str) -> Result<Vec<u8>, Self::Error>;
+
+    /// Replace the file at `path` with `data`.
+    fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Append the console output since `position` to `out` and move `position` past it, `0`
+    /// asks for everything still held.
+    fn log_since(&mut self, position: &mut u32, out: &mut Vec<u8>);
+
+    /// Check `program` can be run.
+    fn check_program(&mut self, program: &[u8]) -> Result<(), Self::Error>;
+
+    /// Run `program`, which passed [`Handler::check_program`]. On the console this does not
+    /// return.
+    fn run_program(&mut self, program: Vec<u8>);
+}
+
+/// A single line command.
+#[derive(Debug, PartialEq, Eq)]
+enum Command<'a> {
+    Help,
+    Log,
+    Mem,
+    Threads,
+    Ls(&'a str),
+    Get(&'a str),
+    Put(&'a str, usize),
+    Run(usize),
+    Quit,
+}
+
+impl<'a> Command<'a> {
+    fn parse(line: &'a str) -> Result<Self, &'static str> {
+        let mut words = line.split_ascii_whitespace();
+        let command = words.next().unwrap_or_default();
+        let mut arg = || words.next().ok_or("missing argument");
+        let len = |len: &str| len.parse::<usize>().map_err(|_| "invalid length");
+
+        let command = match command {
+            "help" | "?" => Self::Help,
+            "log" => Self::Log,
+            "mem" => Self::Mem,
+            "threads" => Self::Threads,
+            "ls" => Self::Ls(arg().unwrap_or("/")),
+            "get" => Self::Get(arg()?),
+            "put" => Self::Put(arg()?, len(arg()?)?),
+            "run" => Self::Run(len(arg()?)?),
+            "quit" | "exit" => Self::Quit,
+            _ => return Err("unknown command, try help"),
+        };
+        Ok(command)
+    }
+}
+
+/// Allocate room for an upload of `len` bytes, `None` if there is not enough memory.
+///
+/// Uploads are limited by [`MAX_FILE`] and [`MAX_DOL`] but those are still far more than the
+/// console may have left, so this must not abort.
+fn upload_buffer(len: usize) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    data.try_reserve_exact(len).ok()?;
+    Some(data)
+}
+
+/// A connected client.
+pub struct Session<S, H> {
+    stream: S,
+    handler: H,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    /// Set once the connection was shut down to run a program.
+    closed: bool,
+}
+
+impl<S: Connection, H: Handler> Session<S, H> {
+    /// Serve the client on `stream`, running commands with `handler`.
+    pub fn new(stream: S, handler: H) -> Self {
+        Self {
+            stream,
+            handler,
+            buf: vec![0; MAX_LINE],
+            start: 0,
+            end: 0,
+            closed: false,
+        }
+    }
+
+    /// Take the connection and handler back.
+    pub fn into_parts(self) -> (S, H) {
+        (self.stream, self.handler)
+    }
+
+    /// Serve the client until it quits, closes the connection or runs a program.
+    ///
+    /// An HTTP client gets a single response.
+    pub fn run(&mut self) -> Result<(), S::Error> {
+        let Some(line) = self.read_line()? else {
+            return Ok(());
+        };
+        if is_http(&line) {
+            return self.http(&line);
+        }
+
+        let mut line = Some(line);
+        while let Some(current) = line
+            && !self.closed
+        {
+            if current.trim().is_empty() {
+                line = self.read_line()?;
+                continue;
+            }
+
+            match Command::parse(&current) {
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => self.command(command)?,
+                Err(err) => self.write(format!("ERR {err}\n").as_bytes())?,
+            }
+            line = self.read_line()?;
+        }
+        Ok(())
+    }
+
+    /// Run a line command and write its reply.
+    fn command(&mut self, command: Command<'_>) -> Result<(), S::Error> {
+        let reply = match command {
+            Command::Help => Ok(String::from(HELP)),
+            Command::Mem => Ok(self.handler.mem()),
+            Command::Threads => Ok(self.handler.threads()),
+            Command::Ls(dir) => self.handler.ls(dir),
+            Command::Log => return self.stream_log(),
+            Command::Get(path) => {
+                return match self.handler.read_file(path) {
+                    Ok(data) => {
+                        self.write(format!("OK {}\n", data.len()).as_bytes())?;
+                        self.write(&data)
+                    }
+                    Err(err) => self.write(format!("ERR {err}\n").as_bytes()),
+                };
+            }
+            Command::Put(path, len) => {
+                let Some(data) = self.receive(len, MAX_FILE)? else {
+                    return Ok(());
+                };
+                self.handler.write_file(path, &data).map(|()| String::new())
+            }
+            Command::Run(len) => {
+                let Some(data) = self.receive(len, MAX_DOL)? else {
+                    return Ok(());
+                };
+                return self.run_program(data, false);
+            }
+            Command::Quit => return Ok(()),
+        };
+
+        match reply {
+            Ok(text) => {
+                self.write(b"OK\n")?;
+                self.write(text.as_bytes())?;
+                self.write(b".\n")
+            }
+            Err(err) => self.write(format!("ERR {err}\n").as_bytes()),
+        }
+    }
+
+    /// Answer `READY` and read `len` bytes, or answer `ERR` if `len` is over `max` or does not
+    /// fit in memory.
+    fn receive(&mut self, len: usize, max: usize) -> Result<Option<Vec<u8>>, S::Error> {
+        if len > max {
+            self.write(format!("ERR at most {max} bytes are accepted\n").as_bytes())?;
+            return Ok(None);
+        }
+        let Some(mut data) = upload_buffer(len) else {
+            self.write(format!("ERR not enough memory for {len} bytes\n").as_bytes())?;
+            return Ok(None);
+        };
+        self.write(b"READY\n")?;
+        self.read_exact(&mut data, len)?;
+        Ok(Some(data))
+    }
+
+    /// Check `data` can be run and run it, answering first since the connection does not
+    /// survive. `http` picks whether the answer is an HTTP response or a line.
+    fn run_program(&mut self, data: Vec<u8>, http: bool) -> Result<(), S::Error> {
+        match self.handler.check_program(&data) {
+            Ok(()) => {
+                if http {
+                    self.http_reply(200, b"running\n")?;
+                } else {
+                    self.write(b"OK\n")?;
+                }
+                self.stream.shutdown();
+                self.closed = true;
+                self.handler.run_program(data);
+                Ok(())
+            }
+            Err(err) if http => self.http_reply(400, format!("{err}\n").as_bytes()),
+            Err(err) => self.write(format!("ERR {err}\n").as_bytes()),
+        }
+    }
+
+    /// Send the buffered log and keep sending new output until the client sends anything.
+    fn stream_log(&mut self) -> Result<(), S::Error> {
+        self.write(b"OK\n")?;
+
+        let mut position = 0;
+        let mut out = Vec::new();
+        self.stream.set_read_timeout(Some(LOG_INTERVAL))?;
+        let result = loop {
+            out.clear();
+            self.handler.log_since(&mut position, &mut out);
+            if let Err(err) = self.write(&out) {
+                break Err(err);
+            }
+
+            if self.start < self.end {
+                break Ok(());
+            }
+            match self.fill() {
+                Err(err) if S::is_timeout(&err) => {}
+                Err(err) => break Err(err),
+                // Either a key or the client closed, both end the stream.
+                Ok(_) => break Ok(()),
+            }
+        };
+        self.stream.set_read_timeout(None)?;
+
+        // Drop the rest of the line that ended the stream.
+        if let Some(i) = self.buf[self.start..self.end]
+            .iter()
+            .position(|&b| b == b'\n')
+        {
+            self.start += i + 1;
+        }
+        result?;
+        self.write(b"\n.\n")
+    }
+
+    /// Serve one HTTP request whose request line is `line`.
+    fn http(&mut self, line: &str) -> Result<(), S::Error> {
+        let mut parts = line.split_ascii_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let target = parts.next().unwrap_or_default();
+
+        let mut content_length = 0;
+        while let Some(header) = self.read_line()? {
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':')
+                && name.trim().eq_ignore_ascii_case("Content-Length")
+            {
+                content_length = value.trim().parse().unwrap_or_default();
+            }
+        }
+
+        let path = target.split('?').next().unwrap_or_default();
+        let (status, body) = match (method, path) {
+            ("GET", "/") => (200, Ok(Vec::from(HELP))),
+            ("GET", "/log") => {
+                let mut out = Vec::new();
+                self.handler.log_since(&mut 0, &mut out);
+                (200, Ok(out))
+            }
+            ("GET", "/mem") => (200, Ok(Vec::from(self.handler.mem()))),
+            ("GET", "/threads") => (200, Ok(Vec::from(self.handler.threads()))),
+            ("GET", path) if path.starts_with("/ls") => {
+                let dir = path.strip_prefix("/ls").unwrap_or_default();
+                let dir = if dir.is_empty() { "/" } else { dir };
+                (200, self.handler.ls(dir).map(Vec::from))
+            }
+            ("GET", path) if path.starts_with("/files/") => {
+                (200, self.handler.read_file(&path["/files".len()..]))
+            }
+            ("PUT", path) if path.starts_with("/files/") => {
+                match self.http_upload(content_length, MAX_FILE)? {
+                    Ok(data) => (
+                        201,
+                        self.handler
+                            .write_file(&path["/files".len()..], &data)
+                            .map(|()| Vec::new()),
+                    ),
+                    Err(reason) => (413, Ok(Vec::from(reason))),
+                }
+            }
+            ("POST", "/run") => match self.http_upload(content_length, MAX_DOL)? {
+                Ok(data) => return self.run_program(data, true),
+                Err(reason) => (413, Ok(Vec::from(reason))),
+            },
+            _ => (404, Ok(Vec::from("not found\n"))),
+        };
+
+        match body {
+            Ok(body) => self.http_reply(status, &body),
+            Err(err) => self.http_reply(500, format!("{err}\n").as_bytes()),
+        }
+    }
+
+    /// Read an HTTP request body of `len` bytes, or the reason it was refused.
+    fn http_upload(
+        &mut self,
+        len: usize,
+        max: usize,
+    ) -> Result<Result<Vec<u8>, &'static str>, S::Error> {
+        if len > max {
+            return Ok(Err("file too large\n"));
+        }
+        let Some(mut data) = upload_buffer(len) else {
+            return Ok(Err("not enough memory\n"));
+        };
+        self.read_exact(&mut data, len)?;
+        Ok(Ok(data))
+    }
+
+    fn http_reply(&mut self, status: u16, body: &[u8]) -> Result<(), S::Error> {
+        let reason = match status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            413 => "Content Too Large",
+            _ => "Internal Server Error",
+        };
+        let head = format!(
+            "HTTP/1.0 {status} {reason}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+        self.write(head.as_bytes())?;
+        self.write(body)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), S::Error> {
+        self.stream.write_all(data)
+    }
+
+    /// Read more data into the buffer, returning how much was read.
+    fn fill(&mut self) -> Result<usize, S::Error> {
+        if self.start > 0 {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+        if self.end == self.buf.len() {
+            return Err(ProtocolError::LineTooLong.into());
+        }
+        let n = self.stream.read(&mut self.buf[self.end..])?;
+        self.end += n;
+        Ok(n)
+    }
+
+    /// Read a line without its line ending, `None` once the client closed the connection.
+    ///
+    /// Telnet option negotiation is skipped.
+    fn read_line(&mut self) -> Result<Option<String>, S::Error> {
+        loop {
+            if let Some(i) = self.buf[self.start..self.end]
+                .iter()
+                .position(|&b| b == b'\n')
+            {
+                let raw = &self.buf[self.start..self.start + i];
+                let mut line = Vec::with_capacity(raw.len());
+                let mut bytes = raw.iter().copied();
+                while let Some(b) = bytes.next() {
+                    match b {
+                        // IAC followed by a command and, for option commands, an option.
+                        0xFF => {
+                            if let Some(251..=254) = bytes.next() {
+                                bytes.next();
+                            }
+                        }
+                        b'\r' => {}
+                        b => line.push(b),
+                    }
+                }
+                self.start += i + 1;
+                return Ok(Some(String::from_utf8_lossy(&line).into_owned()));
+            }
+
+            if self.fill()? == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Read exactly `len` raw bytes into `data`, which already has room for them.
+    fn read_exact(&mut self, data: &mut Vec<u8>, len: usize) -> Result<(), S::Error> {
+        data.resize(len, 0);
+        let buffered = len.min(self.end - self.start);
+        data[..buffered].copy_from_slice(&self.buf[self.start..self.start + buffered]);
+        self.start += buffered;
+
+        let mut filled = buffered;
+        while filled < len {
+            match self.stream.read(&mut data[filled..])? {
+                0 => return Err(ProtocolError::UnexpectedEof.into()),
+                n => filled += n,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_http(line: &str) -> bool {
+    let mut parts = line.split_ascii_whitespace();
+    matches!(parts.next(), Some("GET" | "PUT" | "POST" | "HEAD"))
+        && parts.next().is_some_and(|target| target.starts_with('/'))
+        && parts
+            .next()
+            .is_some_and(|version| version.starts_with("HTTP/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{collections::BTreeMap, string::ToString};
+
+    extern crate std;
+    use std::{
+        io::{self, Read, Write},
+        net::{Shutdown, TcpListener, TcpStream},
+        thread,
+    };
+
+    /// Files in memory, a fixed log and the last program run.
+    #[derive(Default)]
+    pub struct Console {
+        files: BTreeMap<String, Vec<u8>>,
+        ran: Option<Vec<u8>>,
+    }
+
+    impl Handler for Console {
+        type Error = &'static str;
+
+        fn mem(&mut self) -> String {
+            String::from("plenty\n")
+        }
+
+        fn threads(&mut self) -> String {
+            String::from("main\n")
+        }
+
+        fn ls(&mut self, dir: &str) -> Result<String, &'static str> {
+            let mut out = String::new();
+            for name in self.files.keys().filter(|name| name.starts_with(dir)) {
+                out.push_str(name);
+                out.push('\n');
+            }
+            Ok(out)
+        }
+
+        fn read_file(&mut self, path: &str) -> Result<Vec<u8>, &'static str> {
+            self.files.get(path).cloned().ok_or("no such file")
+        }
+
+        fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), &'static str> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn log_since(&mut self, position: &mut u32, out: &mut Vec<u8>) {
+            let log = b"hello from the log";
+            out.extend_from_slice(&log[(*position as usize).min(log.len())..]);
+            *position = log.len() as u32;
+        }
+
+        fn check_program(&mut self, program: &[u8]) -> Result<(), &'static str> {
+            match program.starts_with(b"DOL") {
+                true => Ok(()),
+                false => Err("not a DOL"),
+            }
+        }
+
+        fn run_program(&mut self, program: Vec<u8>) {
+            self.ran = Some(program);
+        }
+    }
+
+    /// A client that sends `input` and then closes its side.
+    struct Client<'a> {
+        input: &'a [u8],
+        output: Vec<u8>,
+    }
+
+    impl Connection for Client<'_> {
+        type Error = ProtocolError;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ProtocolError> {
+            let n = buf.len().min(self.input.len());
+            buf[..n].copy_from_slice(&self.input[..n]);
+            self.input = &self.input[n..];
+            Ok(n)
+        }
+
+        fn write_all(&mut self, data: &[u8]) -> Result<(), ProtocolError> {
+            self.output.extend_from_slice(data);
+            Ok(())
+        }
+
+        fn set_read_timeout(&mut self, _: Option<Duration>) -> Result<(), ProtocolError> {
+            Ok(())
+        }
+
+        fn is_timeout(_: &ProtocolError) -> bool {
+            false
+        }
+
+        fn shutdown(&mut self) {}
+    }
+
+    fn serve(input: &[u8]) -> (Result<(), ProtocolError>, String) {
+        let mut session = Session::new(
+            Client {
+                input,
+                output: Vec::new(),
+            },
+            Console::default(),
+        );
+        let result = session.run();
+        let (client, _) = session.into_parts();
+        (result, String::from_utf8(client.output).unwrap())
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(Command::parse("help"), Ok(Command::Help));
+        assert_eq!(Command::parse("  ?  "), Ok(Command::Help));
+        assert_eq!(Command::parse("ls"), Ok(Command::Ls("/")));
+        assert_eq!(Command::parse("ls /tmp"), Ok(Command::Ls("/tmp")));
+        assert_eq!(Command::parse("get /a/b"), Ok(Command::Get("/a/b")));
+        assert_eq!(Command::parse("put /a 12"), Ok(Command::Put("/a", 12)));
+        assert_eq!(Command::parse("run 4096"), Ok(Command::Run(4096)));
+        assert_eq!(Command::parse("exit"), Ok(Command::Quit));
+
+        assert_eq!(Command::parse("get"), Err("missing argument"));
+        assert_eq!(Command::parse("put /a"), Err("missing argument"));
+        assert_eq!(Command::parse("put /a -1"), Err("invalid length"));
+        assert_eq!(Command::parse("run many"), Err("invalid length"));
+        assert_eq!(Command::parse("HELP"), Err("unknown command, try help"));
+    }
+
+    #[test]
+    fn line_protocol() {
+        let (result, output) =
+            serve(b"\xff\xfb\x01help\r\n\nbogus\nget\nput /a 99999999\nquit\nhelp\n");
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            output,
+            format!(
+                "OK\n{HELP}.\n\
+                 ERR unknown command, try help\n\
+                 ERR missing argument\n\
+                 ERR at most {MAX_FILE} bytes are accepted\n"
+            )
+        );
+
+        // The last line does not need a line ending, closing ends the session.
+        assert_eq!(serve(b"help"), (Ok(()), String::new()));
+        assert_eq!(serve(b""), (Ok(()), String::new()));
+
+        let (result, output) = serve(b"run 4\nnope");
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, "READY\nERR not a DOL\n");
+    }
+
+    #[test]
+    fn log() {
+        let (result, output) = serve(b"log\n");
+        assert_eq!(result, Ok(()));
+        assert_eq!(output, "OK\nhello from the log\n.\n");
+    }
+
+    #[test]
+    fn http() {
+        assert!(is_http("GET / HTTP/1.1"));
+        assert!(is_http("PUT /files/a HTTP/1.0"));
+        assert!(!is_http("GET /"));
+        assert!(!is_http("GET files HTTP/1.1"));
+        assert!(!is_http("get / HTTP/1.1"));
+
+        let (result, output) = serve(b"GET /?x=1 HTTP/1.1\r\nHost: wii\r\n\r\n");
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            output,
+            format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{HELP}",
+                HELP.len()
+            )
+        );
+
+        let (_, output) = serve(b"GET /nothing HTTP/1.1\r\n\r\n");
+        assert!(
+            output.starts_with("HTTP/1.0 404 Not Found\r\n"),
+            "{output:?}"
+        );
+        assert!(output.ends_with("\r\n\r\nnot found\n"), "{output:?}");
+
+        let (_, output) = serve(b"GET /files/missing HTTP/1.1\r\n\r\n");
+        assert!(
+            output.starts_with("HTTP/1.0 500 Internal Server Error\r\n"),
+            "{output:?}"
+        );
+        assert!(output.ends_with("\r\n\r\nno such file\n"), "{output:?}");
+
+        // Unknown methods are not taken for HTTP at all.
+        let (_, output) = serve(b"DELETE /files/a HTTP/1.1\r\n\r\n");
+        assert_eq!(output, "ERR unknown command, try help\n");
+
+        let request = format!(
+            "PUT /files/a HTTP/1.1\r\ncontent-length: {}\r\n\r\n",
+            MAX_FILE + 1
+        );
+        let (_, output) = serve(request.as_bytes());
+        assert!(
+            output.starts_with("HTTP/1.0 413 Content Too Large\r\n"),
+            "{output:?}"
+        );
+
+        let request = format!(
+            "POST /run HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_DOL + 1
+        );
+        let (_, output) = serve(request.as_bytes());
+        assert!(output.starts_with("HTTP/1.0 413 "), "{output:?}");
+
+        let (_, output) = serve(b"POST /run HTTP/1.1\r\nContent-Length: 4\r\n\r\nnope");
+        assert!(
+            output.starts_with("HTTP/1.0 400 Bad Request\r\n"),
+            "{output:?}"
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let mut line = vec![b'a'; MAX_LINE + 1];
+        line.push(b'\n');
+        let (result, output) = serve(&line);
+        assert_eq!(result, Err(ProtocolError::LineTooLong));
+        assert_eq!(output, "");
+
+        // `put` reads exactly the announced length, a client closing early is an error.
+        let (result, output) = serve(b"put /a 10\nshort");
+        assert_eq!(result, Err(ProtocolError::UnexpectedEof));
+        assert_eq!(output, "READY\n");
+
+        // Lengths that cannot be allocated are refused instead of aborting.
+        assert!(upload_buffer(usize::MAX).is_none());
+        assert_eq!(upload_buffer(16).unwrap().capacity(), 16);
+    }
+
+    #[derive(Debug)]
+    pub enum Error {
+        Io(io::ErrorKind),
+        Protocol(ProtocolError),
+    }
+
+    impl From<ProtocolError> for Error {
+        fn from(err: ProtocolError) -> Self {
+            Self::Protocol(err)
+        }
+    }
+
+    impl From<io::Error> for Error {
+        fn from(err: io::Error) -> Self {
+            Self::Io(err.kind())
+        }
+    }
+
+    impl Connection for TcpStream {
+        type Error = Error;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            Ok(Read::read(self, buf)?)
+        }
+
+        fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+            Ok(Write::write_all(self, data)?)
+        }
+
+        fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<(), Error> {
+            Ok(TcpStream::set_read_timeout(self, timeout)?)
+        }
+
+        fn is_timeout(error: &Error) -> bool {
+            matches!(
+                error,
+                Error::Io(io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+            )
+        }
+
+        fn shutdown(&mut self) {
+            let _ = TcpStream::shutdown(self, Shutdown::Both);
+        }
+    }
+
+    /// Serve one client on the loopback interface, which sends `input` and closes its side.
+    /// Returns what the server answered, how the session ended and the console it ran on.
+    fn serve_tcp(input: Vec<u8>, console: Console) -> (Vec<u8>, Result<(), Error>, Console) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut session = Session::new(stream, console);
+            let result = session.run();
+            (result, session.into_parts().1)
+        });
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        let writer = {
+            let mut client = client.try_clone().unwrap();
+            thread::spawn(move || {
+                // The server may close before reading everything, for example after `run`.
+                let _ = Write::write_all(&mut client, &input);
+                let _ = client.shutdown(Shutdown::Write);
+            })
+        };
+        let mut output = Vec::new();
+        Read::read_to_end(&mut client, &mut output).unwrap();
+        writer.join().unwrap();
+
+        let (result, console) = server.join().unwrap();
+        (output, result, console)
+    }
+
+    #[test]
+    fn tcp_line_protocol() {
+        let file: Vec<u8> = (0..100_000u32).map(|i| (i * 7) as u8).collect();
+        let mut input = format!("put /big {}\n", file.len()).into_bytes();
+        input.extend_from_slice(&file);
+        input.extend_from_slice(b"ls /\nget /big\nrun 5\nDOL!!help\n");
+
+        let (output, result, console) = serve_tcp(input, Console::default());
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(console.files["/big"], file);
+        assert_eq!(console.ran.as_deref(), Some(&b"DOL!!"[..]));
+
+        let mut expected = format!("READY\nOK\n.\nOK\n/big\n.\nOK {}\n", file.len()).into_bytes();
+        expected.extend_from_slice(&file);
+        expected.extend_from_slice(b"READY\nOK\n");
+        // Nothing is answered after the program started.
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn tcp_http() {
+        let body = vec![0x5Au8; 50_000];
+        let mut input = format!(
+            "PUT /files/a HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        input.extend_from_slice(&body);
+        let (output, result, console) = serve_tcp(input, Console::default());
+        assert!(result.is_ok(), "{result:?}");
+        assert!(
+            output.starts_with(b"HTTP/1.0 201 Created\r\n"),
+            "{output:?}"
+        );
+        assert_eq!(console.files["/a"], body);
+
+        let (output, _, _) = serve_tcp(b"GET /files/a HTTP/1.0\r\n\r\n".to_vec(), console);
+        let head = String::from_utf8_lossy(&output[..output.len() - body.len()]);
+        assert!(head.contains("Content-Length: 50000\r\n"), "{head:?}");
+        assert!(head.ends_with("\r\n\r\n"), "{head:?}");
+        assert_eq!(output[head.len()..], body);
+
+        // A body shorter than announced ends the session once the client closes.
+        let (output, result, _) = serve_tcp(
+            b"POST /run HTTP/1.1\r\nContent-Length: 10\r\n\r\nDOL".to_vec(),
+            Console::default(),
+        );
+        assert!(output.is_empty());
+        assert!(
+            matches!(result, Err(Error::Protocol(ProtocolError::UnexpectedEof))),
+            "{result:?}"
+        );
+    }
+}
@@ -0,0 +1,509 @@
+//! DNS messages for `A` queries over UDP, as sent by ``ogc_rs::network::dns::Resolver``.
+//!
+//! [`build_query`] and [`parse_response`] only deal with bytes, [`resolve`] runs the exchange
+//! over any [`Transport`], a socket on the console and a ``std`` socket in tests.
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt, net::Ipv4Addr};
+
+/// Largest response accepted over UDP.
+pub const MAX_MESSAGE: usize = 512;
+/// Longest encoded name allowed by RFC 1035.
+const MAX_NAME: usize = 255;
+/// Longest single label allowed by RFC 1035.
+const MAX_LABEL: usize = 63;
+/// How many compression pointers are followed before a name is considered malformed.
+const MAX_JUMPS: usize = 16;
+
+const TYPE_A: u16 = 1;
+const TYPE_CNAME: u16 = 5;
+const CLASS_IN: u16 = 1;
+
+/// Recursion desired.
+const FLAG_RD: u16 = 0x0100;
+/// Message is a response.
+const FLAG_QR: u16 = 0x8000;
+/// Message was truncated.
+const FLAG_TC: u16 = 0x0200;
+
+const RCODE_NXDOMAIN: u16 = 3;
+
+/// Errors building a query or reading its response.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DnsError {
+    /// The name is empty, too long or has an empty or too long label.
+    InvalidName,
+    /// The buffer is too small for the query.
+    BufferTooSmall,
+    /// The name does not exist or has no addresses.
+    NotFound,
+    /// The server truncated the response.
+    Truncated,
+    /// The response is malformed or the server reported an error.
+    Malformed,
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName => write!(f, "invalid host name"),
+            Self::BufferTooSmall => write!(f, "buffer is too small for the query"),
+            Self::NotFound => write!(f, "host has no addresses"),
+            Self::Truncated => write!(f, "response is truncated"),
+            Self::Malformed => write!(f, "response is malformed"),
+        }
+    }
+}
+
+impl core::error::Error for DnsError {}
+
+/// The answer to an `A` query.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Host {
+    /// The canonical name, after following `CNAME` records.
+    pub name: String,
+    /// The names that pointed to `name`.
+    pub aliases: Vec<String>,
+    /// The addresses of the host.
+    pub addresses: Vec<Ipv4Addr>,
+}
+
+/// A connected datagram socket to the server.
+pub trait Transport {
+    /// The error of a failed or timed out send or receive, which also has to represent
+    /// [`DnsError`]s to be returned from [`resolve`].
+    type Error: From<DnsError>;
+
+    /// Send `buf` as one datagram.
+    fn send(&self, buf: &[u8]) -> Result<usize, Self::Error>;
+
+    /// Receive one datagram into `buf`, failing once the read timeout passes.
+    fn recv(&self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+/// Ask the server behind `transport` for the addresses of `name`, taking a fresh query id from
+/// `next_id` for each of the `attempts`.
+///
+/// Responses to other queries, such as late answers to an earlier attempt, are skipped. Returns
+/// the error of the last receive once every attempt timed out.
+pub fn resolve<T: Transport>(
+    transport: &T,
+    name: &str,
+    attempts: u8,
+    mut next_id: impl FnMut() -> u16,
+) -> Result<Host, T::Error> {
+    let mut query = [0u8; MAX_MESSAGE];
+    let mut response = [0u8; MAX_MESSAGE];
+    let mut last = None;
+
+    for _ in 0..attempts.max(1) {
+        let id = next_id();
+        let len = build_query(id, name, &mut query)?;
+        transport.send(&query[..len])?;
+
+        loop {
+            let len = match transport.recv(&mut response) {
+                Ok(len) => len,
+                Err(err) => {
+                    last = Some(err);
+                    break;
+                }
+            };
+            if let Some(host) = parse_response(id, name, &response[..len])? {
+                return Ok(host);
+            }
+        }
+    }
+
+    Err(last.expect("at least one attempt is made"))
+}
+
+/// Write an `A` query for `name` with `id` into `buf`, returning its length.
+pub fn build_query(id: u16, name: &str, buf: &mut [u8]) -> Result<usize, DnsError> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name.len() + 2 > MAX_NAME {
+        return Err(DnsError::InvalidName);
+    }
+
+    let len = 12 + name.len() + 2 + 4;
+    let buf = buf.get_mut(..len).ok_or(DnsError::BufferTooSmall)?;
+
+    buf[..12].fill(0);
+    buf[0..2].copy_from_slice(&id.to_be_bytes());
+    buf[2..4].copy_from_slice(&FLAG_RD.to_be_bytes());
+    buf[4..6].copy_from_slice(&1u16.to_be_bytes());
+
+    let mut pos = 12;
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > MAX_LABEL {
+            return Err(DnsError::InvalidName);
+        }
+        buf[pos] = label.len() as u8;
+        buf[pos + 1..pos + 1 + label.len()].copy_from_slice(label.as_bytes());
+        pos += 1 + label.len();
+    }
+    buf[pos] = 0;
+    buf[pos + 1..pos + 3].copy_from_slice(&TYPE_A.to_be_bytes());
+    buf[pos + 3..pos + 5].copy_from_slice(&CLASS_IN.to_be_bytes());
+
+    Ok(pos + 5)
+}
+
+/// Parse the response to the query with `id` for `name`.
+///
+/// Returns `None` if `data` answers a different query.
+pub fn parse_response(id: u16, name: &str, data: &[u8]) -> Result<Option<Host>, DnsError> {
+    let malformed = DnsError::Malformed;
+
+    let header = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+    if data.len() < 12 {
+        return Err(malformed);
+    }
+    if header(0) != id {
+        return Ok(None);
+    }
+
+    let flags = header(2);
+    if flags & FLAG_QR == 0 {
+        return Err(malformed);
+    }
+    if flags & FLAG_TC != 0 {
+        return Err(DnsError::Truncated);
+    }
+    match flags & 0xF {
+        0 => {}
+        RCODE_NXDOMAIN => return Err(DnsError::NotFound),
+        _ => return Err(malformed),
+    }
+
+    let questions = header(4);
+    let answers = header(6);
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(data, pos).ok_or(malformed)? + 4;
+    }
+
+    let mut host = Host {
+        name: String::from(name.strip_suffix('.').unwrap_or(name)),
+        ..Host::default()
+    };
+
+    for _ in 0..answers {
+        let start = skip_name(data, pos).ok_or(malformed)?;
+        let fixed = data.get(start..start + 10).ok_or(malformed)?;
+        let kind = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let class = u16::from_be_bytes([fixed[2], fixed[3]]);
+        let rdlength = usize::from(u16::from_be_bytes([fixed[8], fixed[9]]));
+        let rdata = start + 10;
+        let next = rdata + rdlength;
+        let rdata_bytes = data.get(rdata..next).ok_or(malformed)?;
+
+        match (kind, class) {
+            (TYPE_A, CLASS_IN) if rdlength == 4 => host.addresses.push(Ipv4Addr::new(
+                rdata_bytes[0],
+                rdata_bytes[1],
+                rdata_bytes[2],
+                rdata_bytes[3],
+            )),
+            (TYPE_CNAME, CLASS_IN) => {
+                let target = read_name(data, rdata).ok_or(malformed)?;
+                let owner = core::mem::replace(&mut host.name, target);
+                host.aliases.push(owner);
+            }
+            _ => {}
+        }
+
+        pos = next;
+    }
+
+    if host.addresses.is_empty() {
+        Err(DnsError::NotFound)
+    } else {
+        Ok(Some(host))
+    }
+}
+
+/// The offset right after the name starting at `pos`.
+fn skip_name(data: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *data.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            len if len & 0xC0 == 0xC0 => return Some(pos + 2).filter(|&end| end <= data.len()),
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
+
+/// Decode the possibly compressed name starting at `pos`.
+fn read_name(data: &[u8], mut pos: usize) -> Option<String> {
+    let mut name = String::new();
+    let mut jumps = 0;
+
+    loop {
+        let len = *data.get(pos)?;
+        if len & 0xC0 == 0xC0 {
+            jumps += 1;
+            if jumps > MAX_JUMPS {
+                return None;
+            }
+            pos = usize::from(u16::from_be_bytes([len, *data.get(pos + 1)?]) & 0x3FFF);
+            continue;
+        }
+        if len == 0 {
+            return Some(name);
+        }
+
+        let label = data.get(pos + 1..pos + 1 + usize::from(len))?;
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(core::str::from_utf8(label).ok()?);
+        if name.len() > MAX_NAME {
+            return None;
+        }
+        pos += 1 + usize::from(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    extern crate std;
+    use std::{io, net::UdpSocket, thread, time::Duration};
+
+    /// A response to the query for `example.com` with `id` and `records` as answers.
+    fn response_to(id: u16, flags: u16, answers: u16, records: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; MAX_MESSAGE];
+        let len = build_query(id, "example.com", &mut data).unwrap();
+        data.truncate(len);
+        data[2..4].copy_from_slice(&(FLAG_QR | FLAG_RD | flags).to_be_bytes());
+        data[6..8].copy_from_slice(&answers.to_be_bytes());
+        data.extend_from_slice(records);
+        data
+    }
+
+    fn response(flags: u16, answers: u16, records: &[u8]) -> Vec<u8> {
+        response_to(0x1234, flags, answers, records)
+    }
+
+    /// An `A` record for the name at offset 12, through a compression pointer.
+    fn a_record(address: [u8; 4]) -> Vec<u8> {
+        let mut record = vec![0xC0, 12, 0, 1, 0, 1, 0, 0, 0x0E, 0x10, 0, 4];
+        record.extend_from_slice(&address);
+        record
+    }
+
+    #[test]
+    fn query() {
+        let mut buf = [0u8; MAX_MESSAGE];
+        let len = build_query(0xBEEF, "www.example.com.", &mut buf).unwrap();
+        let expected: &[u8] = &[
+            0xBE, 0xEF, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 3, b'w', b'w', b'w', 7, b'e', b'x',
+            b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 0, 1, 0, 1,
+        ];
+        assert_eq!(&buf[..len], expected);
+
+        assert_eq!(build_query(1, "", &mut buf), Err(DnsError::InvalidName));
+        assert_eq!(build_query(1, "a..b", &mut buf), Err(DnsError::InvalidName));
+        let label = "a".repeat(64);
+        assert_eq!(build_query(1, &label, &mut buf), Err(DnsError::InvalidName));
+        let name = ["a"; 128].join(".");
+        assert_eq!(build_query(1, &name, &mut buf), Err(DnsError::InvalidName));
+        assert_eq!(
+            build_query(1, "example.com", &mut buf[..20]),
+            Err(DnsError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn addresses() {
+        let mut records = a_record([93, 184, 216, 34]);
+        records.extend(a_record([10, 0, 0, 1]));
+        let data = response(0, 2, &records);
+        let host = parse_response(0x1234, "example.com.", &data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(host.name, "example.com");
+        assert!(host.aliases.is_empty());
+        assert_eq!(
+            host.addresses,
+            [Ipv4Addr::new(93, 184, 216, 34), Ipv4Addr::new(10, 0, 0, 1)]
+        );
+    }
+
+    #[test]
+    fn compressed_cname() {
+        // example.com CNAME cdn.<pointer to example.com>, then cdn.example.com A through a
+        // pointer to the CNAME target.
+        let mut records = vec![0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 6];
+        records.extend_from_slice(&[3, b'c', b'd', b'n', 0xC0, 12]);
+        let target = 12 + 17 + 12;
+        records.extend_from_slice(&[0xC0, target as u8, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+        records.extend_from_slice(&[1, 2, 3, 4]);
+        let data = response(0, 2, &records);
+
+        let host = parse_response(0x1234, "example.com", &data)
+            .unwrap()
+            .unwrap();
+        assert_eq!(host.name, "cdn.example.com");
+        assert_eq!(host.aliases, ["example.com"]);
+        assert_eq!(host.addresses, [Ipv4Addr::new(1, 2, 3, 4)]);
+    }
+
+    #[test]
+    fn pointer_loop() {
+        // A CNAME whose target points at itself.
+        let target = 12 + 17 + 12;
+        let mut records = vec![0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2];
+        records.extend_from_slice(&[0xC0, target as u8]);
+        let data = response(0, 1, &records);
+        assert_eq!(
+            parse_response(0x1234, "example.com", &data),
+            Err(DnsError::Malformed)
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let data = response(0, 1, &a_record([1, 2, 3, 4]));
+        assert_eq!(parse_response(0x4321, "example.com", &data), Ok(None));
+        let data = response(FLAG_TC, 1, &a_record([1, 2, 3, 4]));
+        assert_eq!(
+            parse_response(0x1234, "example.com", &data),
+            Err(DnsError::Truncated)
+        );
+        let data = response(RCODE_NXDOMAIN, 0, &[]);
+        assert_eq!(
+            parse_response(0x1234, "example.com", &data),
+            Err(DnsError::NotFound)
+        );
+        let data = response(2, 0, &[]);
+        assert_eq!(
+            parse_response(0x1234, "example.com", &data),
+            Err(DnsError::Malformed)
+        );
+        let data = response(0, 0, &[]);
+        assert_eq!(
+            parse_response(0x1234, "example.com", &data),
+            Err(DnsError::NotFound)
+        );
+        let mut query = response(0, 0, &[]);
+        query[2] &= !0x80;
+        assert_eq!(
+            parse_response(0x1234, "example.com", &query),
+            Err(DnsError::Malformed)
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let mut records = a_record([1, 2, 3, 4]);
+        records.extend(a_record([5, 6, 7, 8]));
+        let data = response(0, 2, &records);
+        assert!(parse_response(0x1234, "example.com", &data).is_ok());
+        // Every cut fails cleanly instead of reading past the end.
+        for len in 0..data.len() {
+            match parse_response(0x1234, "example.com", &data[..len]) {
+                Err(DnsError::Malformed) => {}
+                result => panic!("{len}: {result:?}"),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub enum Error {
+        Io(io::ErrorKind),
+        Dns(DnsError),
+    }
+
+    impl From<DnsError> for Error {
+        fn from(err: DnsError) -> Self {
+            Self::Dns(err)
+        }
+    }
+
+    impl Transport for UdpSocket {
+        type Error = Error;
+
+        fn send(&self, buf: &[u8]) -> Result<usize, Error> {
+            UdpSocket::send(self, buf).map_err(|err| Error::Io(err.kind()))
+        }
+
+        fn recv(&self, buf: &mut [u8]) -> Result<usize, Error> {
+            UdpSocket::recv(self, buf).map_err(|err| Error::Io(err.kind()))
+        }
+    }
+
+    /// A server on the loopback interface that answers the `n`th query with `answer(n, id)`,
+    /// sending nothing for an empty answer, and a client socket connected to it.
+    fn stub(
+        queries: usize,
+        answer: impl Fn(usize, u16) -> Vec<Vec<u8>> + Send + 'static,
+    ) -> (UdpSocket, thread::JoinHandle<()>) {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        client.connect(server.local_addr().unwrap()).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; MAX_MESSAGE];
+            for n in 0..queries {
+                let (len, from) = server.recv_from(&mut buf).unwrap();
+                let mut expected = [0u8; MAX_MESSAGE];
+                let id = u16::from_be_bytes([buf[0], buf[1]]);
+                let query_len = build_query(id, "example.com", &mut expected).unwrap();
+                assert_eq!(&buf[..len], &expected[..query_len]);
+                for message in answer(n, id) {
+                    server.send_to(&message, from).unwrap();
+                }
+            }
+        });
+
+        (client, handle)
+    }
+
+    #[test]
+    fn retries_and_skips_stray_responses() {
+        // The first query goes unanswered, the second one first gets a late answer to the first.
+        let (client, server) = stub(2, |n, id| match n {
+            0 => vec![],
+            _ => vec![
+                response_to(id.wrapping_sub(1), 0, 1, &a_record([9, 9, 9, 9])),
+                response_to(id, 0, 1, &a_record([1, 2, 3, 4])),
+            ],
+        });
+        let mut id = 100;
+        let host = resolve(&client, "example.com", 3, || {
+            id += 1;
+            id
+        })
+        .unwrap();
+        assert_eq!(host.addresses, [Ipv4Addr::new(1, 2, 3, 4)]);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn gives_up() {
+        let (client, server) = stub(2, |_, _| vec![]);
+        let error = resolve(&client, "example.com", 2, || 7).unwrap_err();
+        assert!(
+            matches!(
+                error,
+                Error::Io(io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+            ),
+            "{error:?}"
+        );
+        server.join().unwrap();
+
+        let (client, server) = stub(1, |_, id| vec![response_to(id, RCODE_NXDOMAIN, 0, &[])]);
+        let error = resolve(&client, "example.com", 3, || 7).unwrap_err();
+        assert!(matches!(error, Error::Dns(DnsError::NotFound)), "{error:?}");
+        server.join().unwrap();
+    }
+}
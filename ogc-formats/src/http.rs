@@ -0,0 +1,680 @@
+//! HTTP/1.x responses, as read by ``ogc_rs::network::http``.
+//!
+//! [`Response`] reads the status line and headers from any [`Read`] and then hands out the body,
+//! following `Content-Length`, chunked transfer encoding or the end of the connection.
+//!
+//! ```rust
+//! use ogc_formats::http::{Method, Response};
+//!
+//! let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\n\r\n";
+//! let mut response = Response::read_from(&raw[..], Method::Get)?;
+//! assert_eq!(response.status(), 200);
+//! assert_eq!(response.read_to_end()?, b"hi");
+//! # Ok::<(), ogc_formats::http::HttpError>(())
+//! ```
+
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
+
+/// Size of the receive buffer, which is also the longest accepted status or header line.
+pub const BUF_SIZE: usize = 8 * 1024;
+/// Most headers accepted in a response.
+pub const MAX_HEADERS: usize = 64;
+
+/// HTTP Errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HttpError {
+    /// The URL could not be parsed.
+    InvalidUrl,
+    /// The URL scheme is not `http`.
+    UnsupportedScheme,
+    /// The server did not send a valid HTTP/1.x response.
+    MalformedResponse,
+    /// A status line or header was too long, or there were too many headers.
+    HeaderTooLarge,
+    /// More redirects were returned than allowed.
+    TooManyRedirects,
+    /// The connection closed before the whole body was received.
+    UnexpectedEof,
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl => write!(f, "invalid URL"),
+            Self::UnsupportedScheme => write!(f, "only http:// URLs are supported"),
+            Self::MalformedResponse => write!(f, "malformed response"),
+            Self::HeaderTooLarge => write!(f, "response header too large"),
+            Self::TooManyRedirects => write!(f, "too many redirects"),
+            Self::UnexpectedEof => write!(f, "connection closed before the body was complete"),
+        }
+    }
+}
+
+impl core::error::Error for HttpError {}
+
+/// The request method.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// Fetch a resource.
+    Get,
+    /// Send data to a resource.
+    Post,
+    /// Fetch only the headers of a resource.
+    Head,
+}
+
+impl Method {
+    /// The method as it appears in the request line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Head => "HEAD",
+        }
+    }
+}
+
+/// The connection a [`Response`] is read from.
+pub trait Read {
+    /// The error of a failed read, which also has to represent [`HttpError`]s.
+    type Error: From<HttpError>;
+
+    /// Read into `buf`, returning how many bytes were read. `0` means the connection closed.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+}
+
+impl Read for &[u8] {
+    type Error = HttpError;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, HttpError> {
+        let n = buf.len().min(self.len());
+        let (head, tail) = self.split_at(n);
+        buf[..n].copy_from_slice(head);
+        *self = tail;
+        Ok(n)
+    }
+}
+
+/// A buffered connection.
+struct Conn<R> {
+    stream: R,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl<R: Read> Conn<R> {
+    fn new(stream: R) -> Self {
+        Self {
+            stream,
+            buf: vec![0; BUF_SIZE],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Read more data from the stream into the buffer, returning how much was read.
+    fn fill(&mut self) -> Result<usize, R::Error> {
+        if self.start > 0 {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+        if self.end == self.buf.len() {
+            return Err(HttpError::HeaderTooLarge.into());
+        }
+
+        let n = self.stream.read(&mut self.buf[self.end..])?;
+        self.end += n;
+        Ok(n)
+    }
+
+    /// Read a line ending in `\n`, without the line ending.
+    fn read_line(&mut self) -> Result<String, R::Error> {
+        loop {
+            if let Some(i) = self.buf[self.start..self.end]
+                .iter()
+                .position(|&b| b == b'\n')
+            {
+                let line = &self.buf[self.start..self.start + i];
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                let line = core::str::from_utf8(line)
+                    .map(String::from)
+                    .map_err(|_| HttpError::MalformedResponse);
+                self.start += i + 1;
+                return Ok(line?);
+            }
+
+            if self.fill()? == 0 {
+                return Err(HttpError::UnexpectedEof.into());
+            }
+        }
+    }
+
+    fn read(&mut self, out: &mut [u8]) -> Result<usize, R::Error> {
+        if self.start == self.end {
+            if out.len() >= self.buf.len() {
+                return self.stream.read(out);
+            }
+            if self.fill()? == 0 {
+                return Ok(0);
+            }
+        }
+
+        let n = out.len().min(self.end - self.start);
+        out[..n].copy_from_slice(&self.buf[self.start..self.start + n]);
+        self.start += n;
+        Ok(n)
+    }
+}
+
+/// How the end of the body is found.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Body {
+    /// There is no body.
+    Empty,
+    /// This many bytes are left.
+    Length(u64),
+    /// This many bytes are left in the current chunk, `done` once the last chunk was read.
+    Chunked { remaining: u64, done: bool },
+    /// The body ends when the connection closes.
+    Close,
+}
+
+/// A response whose head was read, the body is read as it arrives with [`Response::read`].
+pub struct Response<R> {
+    status: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    conn: Conn<R>,
+    body: Body,
+}
+
+impl<R: Read> Response<R> {
+    /// Read the response head from `reader`, leaving the body to be read from the [`Response`].
+    ///
+    /// `method` is the method of the request this answers, responses to `HEAD` have no body.
+    /// Interim responses such as `100 Continue` are skipped.
+    pub fn read_from(reader: R, method: Method) -> Result<Self, R::Error> {
+        let mut conn = Conn::new(reader);
+        loop {
+            let line = conn.read_line()?;
+            let (status, reason) = parse_status(&line)?;
+
+            let mut headers: Vec<(String, String)> = Vec::new();
+            loop {
+                let line = conn.read_line()?;
+                if line.is_empty() {
+                    break;
+                }
+
+                if line.starts_with([' ', '\t']) {
+                    // Obsolete line folding continues the previous value.
+                    let (_, value) = headers.last_mut().ok_or(HttpError::MalformedResponse)?;
+                    value.push(' ');
+                    value.push_str(line.trim());
+                    continue;
+                }
+
+                if headers.len() == MAX_HEADERS {
+                    return Err(HttpError::HeaderTooLarge.into());
+                }
+                let (name, value) = line.split_once(':').ok_or(HttpError::MalformedResponse)?;
+                headers.push((String::from(name.trim()), String::from(value.trim())));
+            }
+
+            // Skip interim responses such as `100 Continue`.
+            if (100..200).contains(&status) && status != 101 {
+                continue;
+            }
+
+            let mut response = Self {
+                status,
+                reason,
+                headers,
+                conn,
+                body: Body::Close,
+            };
+            response.body = response.body_kind(method)?;
+            return Ok(response);
+        }
+    }
+
+    fn body_kind(&self, method: Method) -> Result<Body, HttpError> {
+        if method == Method::Head || matches!(self.status, 100..=199 | 204 | 304) {
+            return Ok(Body::Empty);
+        }
+
+        if let Some(encoding) = self.header("Transfer-Encoding") {
+            let last = encoding.rsplit(',').next().unwrap_or_default().trim();
+            if last.eq_ignore_ascii_case("chunked") {
+                return Ok(Body::Chunked {
+                    remaining: 0,
+                    done: false,
+                });
+            }
+            return Ok(Body::Close);
+        }
+
+        match self.header("Content-Length") {
+            Some(len) => len
+                .parse()
+                .map(Body::Length)
+                .map_err(|_| HttpError::MalformedResponse),
+            None => Ok(Body::Close),
+        }
+    }
+
+    /// The status code.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// The reason phrase following the status code.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Whether the status code is in the `2xx` range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Every header in the order they were received.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// The value of the first header called `name`, compared case insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The length of the body if the server sent it up front.
+    pub fn content_length(&self) -> Option<u64> {
+        match self.body {
+            Body::Empty => Some(0),
+            Body::Length(_) => self.header("Content-Length")?.parse().ok(),
+            Body::Chunked { .. } | Body::Close => None,
+        }
+    }
+
+    /// Read part of the body into `buf`, returning how many bytes were read. `0` means the body
+    /// is complete.
+    ///
+    /// Fails with [`HttpError::UnexpectedEof`] if the connection closes before the body is
+    /// complete.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        match self.body {
+            Body::Empty | Body::Length(0) => Ok(0),
+            Body::Length(remaining) => {
+                let n = self.read_some(buf, remaining)?;
+                self.body = Body::Length(remaining - n as u64);
+                Ok(n)
+            }
+            Body::Chunked { done: true, .. } => Ok(0),
+            Body::Chunked { mut remaining, .. } => {
+                if remaining == 0 {
+                    remaining = self.read_chunk_size()?;
+                    if remaining == 0 {
+                        // Skip the trailer section.
+                        while !self.conn.read_line()?.is_empty() {}
+                        self.body = Body::Chunked {
+                            remaining: 0,
+                            done: true,
+                        };
+                        return Ok(0);
+                    }
+                }
+
+                let n = self.read_some(buf, remaining)?;
+                remaining -= n as u64;
+                if remaining == 0 && !self.conn.read_line()?.is_empty() {
+                    return Err(HttpError::MalformedResponse.into());
+                }
+                self.body = Body::Chunked {
+                    remaining,
+                    done: false,
+                };
+                Ok(n)
+            }
+            Body::Close => self.conn.read(buf),
+        }
+    }
+
+    /// Read at most `remaining` bytes, the connection may not close before that.
+    fn read_some(&mut self, buf: &mut [u8], remaining: u64) -> Result<usize, R::Error> {
+        let len = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+        match self.conn.read(&mut buf[..len])? {
+            0 => Err(HttpError::UnexpectedEof.into()),
+            n => Ok(n),
+        }
+    }
+
+    fn read_chunk_size(&mut self) -> Result<u64, R::Error> {
+        let line = self.conn.read_line()?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        u64::from_str_radix(size, 16).map_err(|_| HttpError::MalformedResponse.into())
+    }
+
+    /// Read the rest of the body into a `Vec`.
+    pub fn read_to_end(&mut self) -> Result<Vec<u8>, R::Error> {
+        let capacity = match self.body {
+            Body::Length(remaining) => usize::try_from(remaining).unwrap_or_default(),
+            _ => 0,
+        };
+        let mut body = Vec::with_capacity(capacity);
+        self.copy_to(|data| {
+            body.extend_from_slice(data);
+            Ok(())
+        })?;
+        Ok(body)
+    }
+
+    /// Stream the rest of the body into `sink` piece by piece, returning the number of bytes
+    /// copied.
+    pub fn copy_to<F>(&mut self, mut sink: F) -> Result<u64, R::Error>
+    where
+        F: FnMut(&[u8]) -> Result<(), R::Error>,
+    {
+        let mut buf = vec![0; BUF_SIZE];
+        let mut total = 0;
+        loop {
+            match self.read(&mut buf)? {
+                0 => return Ok(total),
+                n => {
+                    sink(&buf[..n])?;
+                    total += n as u64;
+                }
+            }
+        }
+    }
+}
+
+/// Split a status line into the status code and reason phrase.
+fn parse_status(line: &str) -> Result<(u16, String), HttpError> {
+    let malformed = HttpError::MalformedResponse;
+
+    let rest = line.strip_prefix("HTTP/1.").ok_or(malformed)?;
+    let mut parts = rest.splitn(3, ' ');
+    let minor = parts.next().ok_or(malformed)?;
+    let status = parts.next().ok_or(malformed)?;
+    let reason = parts.next().unwrap_or_default();
+
+    if minor.len() != 1 || !minor.as_bytes()[0].is_ascii_digit() || status.len() != 3 {
+        return Err(malformed);
+    }
+    let status = status.parse().map_err(|_| malformed)?;
+
+    Ok((status, String::from(reason)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    extern crate std;
+    use std::{
+        io::{self, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    fn parse(raw: &[u8], method: Method) -> Result<Response<&[u8]>, HttpError> {
+        Response::read_from(raw, method)
+    }
+
+    fn body(raw: &[u8]) -> Result<Vec<u8>, HttpError> {
+        parse(raw, Method::Get)?.read_to_end()
+    }
+
+    fn malformed<T: fmt::Debug>(result: Result<T, HttpError>) {
+        assert!(
+            matches!(result, Err(HttpError::MalformedResponse)),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn head() {
+        let mut response = parse(
+            b"HTTP/1.1 100 Continue\r\n\r\n\
+              HTTP/1.1 404 Not Found\r\n\
+              Server: test\r\n\
+              X-Folded: a\r\n b\r\n\
+              content-length: 4\r\n\r\n\
+              gone",
+            Method::Get,
+        )
+        .unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.reason(), "Not Found");
+        assert!(!response.is_success());
+        assert_eq!(response.header("SERVER"), Some("test"));
+        assert_eq!(response.header("X-Folded"), Some("a b"));
+        assert_eq!(response.headers().len(), 3);
+        assert_eq!(response.content_length(), Some(4));
+        assert_eq!(response.read_to_end().unwrap(), b"gone");
+    }
+
+    #[test]
+    fn content_length() {
+        assert_eq!(
+            body(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello, trailing").unwrap(),
+            b"hello"
+        );
+        assert_eq!(
+            body(b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\nignored").unwrap(),
+            b""
+        );
+
+        let mut response = parse(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+            Method::Get,
+        )
+        .unwrap();
+        assert_eq!(response.read_to_end(), Err(HttpError::UnexpectedEof));
+
+        malformed(
+            parse(
+                b"HTTP/1.1 200 OK\r\nContent-Length: ten\r\n\r\n",
+                Method::Get,
+            )
+            .map(|_| ()),
+        );
+    }
+
+    #[test]
+    fn chunked() {
+        assert_eq!(
+            body(
+                b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\n\r\n\
+                  5\r\nhello\r\n\
+                  7;name=value\r\n, world\r\n\
+                  0\r\nTrailer: yes\r\n\r\n\
+                  ignored"
+            )
+            .unwrap(),
+            b"hello, world"
+        );
+
+        let mut response = parse(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+            Method::Get,
+        )
+        .unwrap();
+        assert_eq!(response.content_length(), None);
+        let mut buf = [0; 2];
+        assert_eq!(response.read(&mut buf).unwrap(), 2);
+        assert_eq!(response.read(&mut buf).unwrap(), 1);
+        assert_eq!(response.read(&mut buf).unwrap(), 0);
+        assert_eq!(response.read(&mut buf).unwrap(), 0);
+
+        malformed(body(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nxyz\r\n",
+        ));
+        malformed(body(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabcd\r\n0\r\n\r\n",
+        ));
+        assert_eq!(
+            body(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n8\r\nabc"),
+            Err(HttpError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn close_delimited() {
+        assert_eq!(
+            body(b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nuntil the end").unwrap(),
+            b"until the end"
+        );
+        assert_eq!(
+            body(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip\r\n\r\nraw").unwrap(),
+            b"raw"
+        );
+    }
+
+    #[test]
+    fn no_body() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        let mut response = parse(raw, Method::Head).unwrap();
+        assert_eq!(response.content_length(), Some(0));
+        assert_eq!(response.read_to_end().unwrap(), b"");
+
+        assert_eq!(
+            body(b"HTTP/1.1 204 No Content\r\n\r\nignored").unwrap(),
+            b""
+        );
+        assert_eq!(
+            body(b"HTTP/1.1 304 Not Modified\r\nContent-Length: 3\r\n\r\nabc").unwrap(),
+            b""
+        );
+    }
+
+    #[test]
+    fn malformed_head() {
+        for raw in [
+            &b"HTTP/2 200 OK\r\n\r\n"[..],
+            b"HTTP/1.1 20 OK\r\n\r\n",
+            b"HTTP/1.1 abc OK\r\n\r\n",
+            b"HTTP/1.x 200 OK\r\n\r\n",
+            b"ICY 200 OK\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nno colon\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\n folded first\r\n\r\n",
+            b"HTTP/1.1 200 \xff\r\n\r\n",
+        ] {
+            malformed(parse(raw, Method::Get).map(|_| ()));
+        }
+
+        for raw in [
+            &b""[..],
+            b"HTTP/1.1 200 OK\r\n",
+            b"HTTP/1.1 200 OK\r\nA: b\r\n",
+        ] {
+            assert_eq!(
+                parse(raw, Method::Get).map(|_| ()),
+                Err(HttpError::UnexpectedEof)
+            );
+        }
+
+        let mut raw = Vec::from(&b"HTTP/1.1 200 OK\r\n"[..]);
+        for i in 0..=MAX_HEADERS {
+            raw.extend_from_slice(format!("X-{i}: {i}\r\n").as_bytes());
+        }
+        raw.extend_from_slice(b"\r\n");
+        assert_eq!(
+            parse(&raw, Method::Get).map(|_| ()),
+            Err(HttpError::HeaderTooLarge)
+        );
+
+        let mut raw = Vec::from(&b"HTTP/1.1 200 OK\r\nX-Long: "[..]);
+        raw.resize(2 * BUF_SIZE, b'a');
+        assert_eq!(
+            parse(&raw, Method::Get).map(|_| ()),
+            Err(HttpError::HeaderTooLarge)
+        );
+    }
+
+    #[derive(Debug)]
+    pub enum Error {
+        Io(io::ErrorKind),
+        Http(HttpError),
+    }
+
+    impl From<HttpError> for Error {
+        fn from(err: HttpError) -> Self {
+            Self::Http(err)
+        }
+    }
+
+    impl Read for TcpStream {
+        type Error = Error;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            io::Read::read(self, buf).map_err(|err| Error::Io(err.kind()))
+        }
+    }
+
+    /// A server on the loopback interface that writes `parts` with a pause in between, so the
+    /// response arrives split across reads, and a client connected to it.
+    fn serve(parts: &'static [&'static [u8]]) -> (TcpStream, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.set_nodelay(true).unwrap();
+            for part in parts {
+                stream.write_all(part).unwrap();
+                stream.flush().unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        (client, handle)
+    }
+
+    #[test]
+    fn loopback_chunked() {
+        let (client, server) = serve(&[
+            b"HTTP/1.1 200 OK\r\nTransfer-",
+            b"Encoding: chunked\r\nX-Split: a\r\n",
+            b" b\r\n\r\n5\r",
+            b"\nhel",
+            b"lo\r\n7;ext\r\n, world\r\n0\r\n",
+            b"Trailer: yes\r\n\r\n",
+        ]);
+        let mut response = Response::read_from(client, Method::Get).unwrap();
+        assert_eq!(response.header("x-split"), Some("a b"));
+        assert_eq!(response.read_to_end().unwrap(), b"hello, world");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn loopback_closed_early() {
+        let (client, server) = serve(&[
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n",
+            b"10\r\nonly part",
+        ]);
+        let mut response = Response::read_from(client, Method::Get).unwrap();
+        server.join().unwrap();
+        let error = response.read_to_end().unwrap_err();
+        assert!(
+            matches!(error, Error::Http(HttpError::UnexpectedEof)),
+            "{error:?}"
+        );
+    }
+}
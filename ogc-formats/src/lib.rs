@@ -0,0 +1,54 @@
+//! Encoders and decoders for the data formats used by ``ogc-rs``.
+//!
+//! This crate is ``no_std``, only needs ``alloc`` and has no dependencies outside the optional
+//! ``vorbis`` feature, so the same code runs on the console, in host tools and in build scripts.
+//! ``ogc-rs`` re-exports everything here where it belongs, for example [`adpcm`] as
+//! ``ogc_rs::audio::adpcm``, [`recording`] is the format of ``ogc_rs::input::Recorder``, [`dns`]
+//! holds the messages of ``ogc_rs::network::dns::Resolver``, [`http`] the response parser of
+//! ``ogc_rs::network::http``, [`mixer`] the software mixer behind ``ogc_rs::mixer``, [`si`] the
+//! controller answers of ``ogc_rs::mmio::controller`` and [`stream`] and [`wav`] the sound files
+//! of ``ogc_rs::audio``, along with ``vorbis`` when its feature is enabled.
+
+#![no_std]
+
+extern crate alloc;
+
+use core::fmt;
+
+pub mod adpcm;
+pub mod devserver;
+pub mod dns;
+pub mod http;
+pub mod mixer;
+pub mod recording;
+pub mod si;
+pub mod stream;
+#[cfg(feature = "vorbis")]
+pub mod vorbis;
+pub mod wav;
+
+/// Malformed or unsupported data in any of the formats here.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FormatError {
+    /// The data ended before something it promised, a chunk, packet or frame.
+    Truncated,
+    /// The data does not start with the expected magic.
+    InvalidMagic,
+    /// The data uses a codec, version or layout that is not supported.
+    Unsupported,
+    /// A value is out of range or does not fit with the rest.
+    Invalid,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "data is truncated"),
+            Self::InvalidMagic => write!(f, "data has the wrong magic"),
+            Self::Unsupported => write!(f, "codec, version or layout is not supported"),
+            Self::Invalid => write!(f, "value is out of range"),
+        }
+    }
+}
+
+impl core::error::Error for FormatError {}
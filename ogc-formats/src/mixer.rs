@@ -0,0 +1,984 @@
+//! A software mixer that plays any number of logical voices as one interleaved stereo stream.
+//!
+//! [`Mixer`] pulls samples from [`SampleSource`]s, resamples them to [`SAMPLE_RATE`], applies
+//! volume, pan and fades and sums everything into interleaved 16-bit stereo. It runs in the
+//! audio interrupt on the console, so nothing here allocates or frees while mixing: sources of
+//! voices that finish or are stopped are parked until [`Mixer::collect`].
+//!
+//! ```rust
+//! use ogc_formats::mixer::{Mixer, PcmSource, PlayOptions};
+//!
+//! let mut mixer = Mixer::new(32);
+//! mixer.play(PcmSource::new([1000, -1000], 1, 24000), PlayOptions::new().pan(-1.0));
+//!
+//! let mut out = [0; 8];
+//! mixer.mix(&mut out);
+//! assert_eq!(out, [1000, 0, 0, 0, -1000, 0, -500, 0]);
+//! ```
+
+use alloc::{boxed::Box, vec::Vec};
+use core::time::Duration;
+
+/// Rate of the mixed output, in Hz.
+pub const SAMPLE_RATE: u32 = 48000;
+
+const FRAC_BITS: u32 = 16;
+const FRAC_ONE: u32 = 1 << FRAC_BITS;
+/// Fastest a voice may advance through its source, in source frames per output frame.
+const MAX_STEP: u32 = 64;
+/// Frames mixed at once, bounds the stack used by [`Mixer::mix_add`].
+const CHUNK_FRAMES: usize = 256;
+/// Samples pulled from a source at once.
+const PULL_SAMPLES: usize = 128;
+
+/// Something a voice can play.
+///
+/// Decoders implement this to be played by a [`Mixer`].
+pub trait SampleSource {
+    /// Sample rate in Hz.
+    fn sample_rate(&self) -> u32;
+
+    /// Number of interleaved channels, `1` for mono or `2` for stereo.
+    fn channels(&self) -> u8;
+
+    /// Fill `buf` with interleaved samples and return how many were written.
+    ///
+    /// Returning `0` ends the source.
+    fn read(&mut self, buf: &mut [i16]) -> usize;
+
+    /// Go back to the start, used by looping voices.
+    ///
+    /// Returns `false` if the source cannot be rewound, which ends a looping voice.
+    fn rewind(&mut self) -> bool {
+        false
+    }
+}
+
+impl<S: SampleSource + ?Sized> SampleSource for Box<S> {
+    fn sample_rate(&self) -> u32 {
+        (**self).sample_rate()
+    }
+
+    fn channels(&self) -> u8 {
+        (**self).channels()
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        (**self).read(buf)
+    }
+
+    fn rewind(&mut self) -> bool {
+        (**self).rewind()
+    }
+}
+
+/// A [`SampleSource`] over samples already in memory.
+#[derive(Clone, Debug)]
+pub struct PcmSource<T> {
+    samples: T,
+    channels: u8,
+    sample_rate: u32,
+    position: usize,
+}
+
+impl<T: AsRef<[i16]>> PcmSource<T> {
+    /// Play interleaved `samples` with `channels` channels at `sample_rate` Hz.
+    pub fn new(samples: T, channels: u8, sample_rate: u32) -> Self {
+        assert!(
+            channels == 1 || channels == 2,
+            "Channel count {channels} is not 1 or 2"
+        );
+        Self {
+            samples,
+            channels,
+            sample_rate,
+            position: 0,
+        }
+    }
+
+    /// Return the samples.
+    pub fn into_inner(self) -> T {
+        self.samples
+    }
+}
+
+impl<T: AsRef<[i16]>> SampleSource for PcmSource<T> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        let remaining = &self.samples.as_ref()[self.position..];
+        let len = remaining.len().min(buf.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.position += len;
+        len
+    }
+
+    fn rewind(&mut self) -> bool {
+        self.position = 0;
+        true
+    }
+}
+
+/// Identifies a voice started with [`Mixer::play`].
+///
+/// Ids of voices that finished or were stolen are never reused, functions taking one do nothing
+/// once the voice is gone. The generation counting voices started in a slot is 64 bits wide, so
+/// it does not wrap in practice.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VoiceId {
+    slot: u16,
+    generation: u64,
+}
+
+/// Options to be passed when starting a voice.
+///
+/// # Examples
+///
+/// Play a looping sound at half volume that may be stolen by anything:
+///
+/// ```rust
+/// # use ogc_formats::mixer::PlayOptions;
+/// let options = PlayOptions::new().volume(0.5).looping(true).priority(0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlayOptions {
+    volume: f32,
+    pan: f32,
+    pitch: f32,
+    looping: bool,
+    priority: u8,
+    fade_in: Duration,
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        PlayOptions::new()
+    }
+}
+
+impl PlayOptions {
+    /// Create this struct with sensible default values.
+    pub fn new() -> Self {
+        Self {
+            volume: 1.0,
+            pan: 0.0,
+            pitch: 1.0,
+            looping: false,
+            priority: 128,
+            fade_in: Duration::ZERO,
+        }
+    }
+
+    /// Volume, `0.0` is silent and `1.0` plays the source unchanged.
+    #[must_use]
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    /// Pan, from `-1.0` for only the left channel to `1.0` for only the right channel.
+    #[must_use]
+    pub fn pan(mut self, pan: f32) -> Self {
+        self.pan = pan;
+        self
+    }
+
+    /// Playback speed, `2.0` plays an octave higher.
+    #[must_use]
+    pub fn pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        self
+    }
+
+    /// Start over when the source ends.
+    #[must_use]
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Priority used for voice stealing, higher values are kept longer.
+    #[must_use]
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Fade in from silence over `duration`.
+    #[must_use]
+    pub fn fade_in(mut self, duration: Duration) -> Self {
+        self.fade_in = duration;
+        self
+    }
+}
+
+fn duration_to_frames(duration: Duration) -> u32 {
+    let frames = duration.as_micros() * u128::from(SAMPLE_RATE) / 1_000_000;
+    u32::try_from(frames).unwrap_or(u32::MAX)
+}
+
+/// Linear volume ramp.
+struct Fade {
+    from: f32,
+    to: f32,
+    length: u32,
+    elapsed: u32,
+    stop: bool,
+}
+
+impl Fade {
+    fn volume(&self) -> f32 {
+        self.from + (self.to - self.from) * (self.elapsed as f32 / self.length as f32)
+    }
+}
+
+/// A voice ready to be started by [`Mixer::start`].
+///
+/// Creating one boxes the source and reads its first samples, so it can be done before
+/// interrupts are disabled.
+pub struct PendingVoice(Voice);
+
+impl PendingVoice {
+    /// Prepare playing `source` with `options`.
+    pub fn new<S: SampleSource + Send + 'static>(source: S, options: &PlayOptions) -> Self {
+        Self(Voice::new(Box::new(source), options))
+    }
+}
+
+struct Voice {
+    source: Box<dyn SampleSource + Send>,
+    channels: usize,
+    priority: u8,
+    started: u64,
+    volume: f32,
+    pan: f32,
+    pitch: f32,
+    looping: bool,
+    fade: Option<Fade>,
+    /// Source frames advanced per output frame, in 16.16 fixed point.
+    step: u32,
+    /// Position between `frames[0]` and `frames[1]`, in 16.16 fixed point.
+    position: u32,
+    frames: [[i32; 2]; 2],
+    /// Whether `frames[1]` is past the end of the source.
+    ended: bool,
+    finished: bool,
+    pending: [i16; PULL_SAMPLES],
+    pending_len: usize,
+    pending_pos: usize,
+}
+
+impl Voice {
+    fn new(source: Box<dyn SampleSource + Send>, options: &PlayOptions) -> Self {
+        let mut voice = Self {
+            channels: usize::from(source.channels().clamp(1, 2)),
+            source,
+            priority: options.priority,
+            started: 0,
+            volume: options.volume,
+            pan: options.pan,
+            pitch: options.pitch,
+            looping: options.looping,
+            fade: None,
+            step: 0,
+            position: 0,
+            frames: [[0; 2]; 2],
+            ended: false,
+            finished: false,
+            pending: [0; PULL_SAMPLES],
+            pending_len: 0,
+            pending_pos: 0,
+        };
+        voice.update_step();
+        if !options.fade_in.is_zero() {
+            voice.volume = 0.0;
+            voice.fade_to(options.volume, options.fade_in, false);
+        }
+
+        match voice.pull() {
+            Some(frame) => voice.frames[0] = frame,
+            None => voice.finished = true,
+        }
+        voice.pull_next();
+        voice
+    }
+
+    fn update_step(&mut self) {
+        let rate = self.source.sample_rate() as f32 * self.pitch.max(0.0) / SAMPLE_RATE as f32;
+        let step = rate * FRAC_ONE as f32;
+        self.step = step.clamp(1.0, (MAX_STEP * FRAC_ONE) as f32) as u32;
+    }
+
+    fn fade_to(&mut self, volume: f32, duration: Duration, stop: bool) {
+        let length = duration_to_frames(duration);
+        if length == 0 {
+            self.volume = volume;
+            self.fade = None;
+            self.finished |= stop;
+        } else {
+            self.fade = Some(Fade {
+                from: self.volume,
+                to: volume,
+                length,
+                elapsed: 0,
+                stop,
+            });
+        }
+    }
+
+    /// Read the next frame of the source, rewinding it once if looping.
+    fn pull(&mut self) -> Option<[i32; 2]> {
+        if self.pending_pos + self.channels > self.pending_len && !self.refill() {
+            return None;
+        }
+
+        let left = i32::from(self.pending[self.pending_pos]);
+        let right = i32::from(self.pending[self.pending_pos + self.channels - 1]);
+        self.pending_pos += self.channels;
+        Some([left, right])
+    }
+
+    fn refill(&mut self) -> bool {
+        let len = PULL_SAMPLES - PULL_SAMPLES % self.channels;
+        let mut rewound = false;
+        loop {
+            let read = self.source.read(&mut self.pending[..len]).min(len);
+            if read >= self.channels {
+                self.pending_len = read - read % self.channels;
+                self.pending_pos = 0;
+                return true;
+            }
+            // Only rewind once, a source that stays empty must not hang the mixer.
+            if rewound || !self.looping || !self.source.rewind() {
+                return false;
+            }
+            rewound = true;
+        }
+    }
+
+    /// Move `frames[1]` into `frames[0]` and pull the next frame.
+    fn advance_source(&mut self) {
+        if self.ended {
+            self.finished = true;
+            return;
+        }
+        self.frames[0] = self.frames[1];
+        self.pull_next();
+    }
+
+    fn pull_next(&mut self) {
+        match self.pull() {
+            Some(frame) => self.frames[1] = frame,
+            None => {
+                self.frames[1] = [0; 2];
+                self.ended = true;
+            }
+        }
+    }
+
+    fn gains(&self, master: f32) -> [f32; 2] {
+        let volume = self.volume * master;
+        let pan = self.pan.clamp(-1.0, 1.0);
+        [volume * (1.0 - pan).min(1.0), volume * (1.0 + pan).min(1.0)]
+    }
+
+    /// Add this voice to interleaved stereo `acc`.
+    fn render(&mut self, acc: &mut [i32], master: f32) {
+        for out in acc.chunks_exact_mut(2) {
+            if self.finished {
+                return;
+            }
+
+            let gains = self.gains(master);
+            let [current, next] = self.frames;
+            let t = i64::from(self.position);
+            for channel in 0..2 {
+                let delta = i64::from(next[channel] - current[channel]);
+                let sample = current[channel] + ((delta * t) >> FRAC_BITS) as i32;
+                out[channel] += (sample as f32 * gains[channel]) as i32;
+            }
+
+            if let Some(fade) = &mut self.fade {
+                fade.elapsed += 1;
+                if fade.elapsed >= fade.length {
+                    self.volume = fade.to;
+                    self.finished |= fade.stop;
+                    self.fade = None;
+                } else {
+                    self.volume = fade.volume();
+                }
+            }
+
+            self.position += self.step;
+            while self.position >= FRAC_ONE && !self.finished {
+                self.position -= FRAC_ONE;
+                self.advance_source();
+            }
+        }
+    }
+}
+
+struct Slot {
+    generation: u64,
+    voice: Option<Voice>,
+}
+
+/// Mixes any number of voices into 48 kHz interleaved stereo.
+///
+/// At most `max_voices` voices play at once. Starting another one steals the voice with the
+/// lowest priority, the oldest one if several share it, unless that priority is higher than the
+/// new voice's.
+pub struct Mixer {
+    slots: Vec<Slot>,
+    master: f32,
+    started: u64,
+    /// Sources of ended voices waiting to be freed, never grown while mixing.
+    parked: Vec<Box<dyn SampleSource + Send>>,
+    /// Whether a source did not fit in `parked` since the last collection.
+    overflowed: bool,
+    leaked: usize,
+}
+
+impl Mixer {
+    /// Create a mixer playing at most `max_voices` voices at once.
+    pub fn new(max_voices: usize) -> Self {
+        assert!(
+            max_voices <= usize::from(u16::MAX),
+            "Voice count {max_voices} is > {}",
+            u16::MAX
+        );
+        Self {
+            slots: (0..max_voices)
+                .map(|_| Slot {
+                    generation: 0,
+                    voice: None,
+                })
+                .collect(),
+            master: 1.0,
+            started: 0,
+            parked: Vec::with_capacity(2 * max_voices),
+            overflowed: false,
+            leaked: 0,
+        }
+    }
+
+    /// Maximum number of voices playing at once.
+    pub fn max_voices(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Number of voices currently playing.
+    pub fn active_voices(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.voice.is_some())
+            .count()
+    }
+
+    /// Volume applied to every voice.
+    pub fn master_volume(&self) -> f32 {
+        self.master
+    }
+
+    /// Set the volume applied to every voice.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master = volume;
+    }
+
+    /// Start playing `source`.
+    ///
+    /// Returns `None` if every voice is busy with a higher priority than `options`, or if the
+    /// source is already empty.
+    pub fn play<S: SampleSource + Send + 'static>(
+        &mut self,
+        source: S,
+        options: PlayOptions,
+    ) -> Option<VoiceId> {
+        self.start(&mut Some(PendingVoice::new(source, &options)))
+    }
+
+    /// Start `voice`, taking it out of the option only if it gets a slot.
+    ///
+    /// Unlike [`Mixer::play`] this does not allocate, the voice is dropped by the caller if it
+    /// is left in the option.
+    pub fn start(&mut self, voice: &mut Option<PendingVoice>) -> Option<VoiceId> {
+        let priority = voice.as_ref()?.0.priority;
+        if voice.as_ref()?.0.finished {
+            return None;
+        }
+
+        let slot = match self.slots.iter().position(|slot| slot.voice.is_none()) {
+            Some(slot) => slot,
+            None => {
+                let (slot, victim) = self
+                    .slots
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, slot)| Some((i, slot.voice.as_ref()?)))
+                    .min_by_key(|(_, voice)| (voice.priority, voice.started))?;
+                if victim.priority > priority {
+                    return None;
+                }
+                slot
+            }
+        };
+
+        let mut voice = voice.take()?.0;
+        voice.started = self.started;
+        self.started += 1;
+
+        let previous = self.slots[slot].voice.replace(voice);
+        self.park(previous);
+        let slot_ref = &mut self.slots[slot];
+        slot_ref.generation += 1;
+        Some(VoiceId {
+            slot: slot as u16,
+            generation: slot_ref.generation,
+        })
+    }
+
+    fn voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        let slot = self.slots.get_mut(usize::from(id.slot))?;
+        if slot.generation == id.generation {
+            slot.voice.as_mut()
+        } else {
+            None
+        }
+    }
+
+    /// Whether the voice is still playing.
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.slots
+            .get(usize::from(id.slot))
+            .is_some_and(|slot| slot.generation == id.generation && slot.voice.is_some())
+    }
+
+    /// Stop the voice right away.
+    pub fn stop(&mut self, id: VoiceId) {
+        if self.is_playing(id) {
+            let voice = self.slots[usize::from(id.slot)].voice.take();
+            self.park(voice);
+        }
+    }
+
+    /// Stop every voice.
+    pub fn stop_all(&mut self) {
+        for i in 0..self.slots.len() {
+            let voice = self.slots[i].voice.take();
+            self.park(voice);
+        }
+    }
+
+    /// Set the volume of the voice, cancelling any fade.
+    pub fn set_volume(&mut self, id: VoiceId, volume: f32) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.volume = volume;
+            voice.fade = None;
+        }
+    }
+
+    /// Set the pan of the voice, see [`PlayOptions::pan`].
+    pub fn set_pan(&mut self, id: VoiceId, pan: f32) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.pan = pan;
+        }
+    }
+
+    /// Set the playback speed of the voice, see [`PlayOptions::pitch`].
+    pub fn set_pitch(&mut self, id: VoiceId, pitch: f32) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.pitch = pitch;
+            voice.update_step();
+        }
+    }
+
+    /// Set whether the voice starts over when its source ends.
+    pub fn set_looping(&mut self, id: VoiceId, looping: bool) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.looping = looping;
+        }
+    }
+
+    /// Change the priority of the voice, see [`PlayOptions::priority`].
+    pub fn set_priority(&mut self, id: VoiceId, priority: u8) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.priority = priority;
+        }
+    }
+
+    /// Ramp the volume of the voice to `volume` over `duration`.
+    pub fn fade_to(&mut self, id: VoiceId, volume: f32, duration: Duration) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.fade_to(volume, duration, false);
+        }
+    }
+
+    /// Fade the voice to silence over `duration`, then stop it.
+    pub fn fade_out(&mut self, id: VoiceId, duration: Duration) {
+        if let Some(voice) = self.voice_mut(id) {
+            voice.fade_to(0.0, duration, true);
+        }
+        self.reap();
+    }
+
+    fn reap(&mut self) {
+        for i in 0..self.slots.len() {
+            if self.slots[i]
+                .voice
+                .as_ref()
+                .is_some_and(|voice| voice.finished)
+            {
+                let voice = self.slots[i].voice.take();
+                self.park(voice);
+            }
+        }
+    }
+
+    /// Keep the source of an ended voice to be freed later.
+    ///
+    /// Once more voices ended than the mixer has room for since the last collection, the source
+    /// is leaked rather than freed here, see [`Mixer::leaked`].
+    fn park(&mut self, voice: Option<Voice>) {
+        let Some(voice) = voice else {
+            return;
+        };
+        if self.parked.len() < self.parked.capacity() {
+            self.parked.push(voice.source);
+        } else {
+            core::mem::forget(voice.source);
+            self.overflowed = true;
+            self.leaked += 1;
+        }
+    }
+
+    /// Free the sources of voices that finished or were stopped.
+    ///
+    /// ``ogc_rs::mixer::MixerOutput`` does this once interrupts are enabled again, call it when
+    /// driving the mixer yourself. If sources were leaked since the last collection the room for
+    /// them is doubled.
+    pub fn collect(&mut self) {
+        self.parked.clear();
+        if core::mem::take(&mut self.overflowed) {
+            let capacity = self.parked.capacity();
+            self.parked.reserve_exact(2 * capacity);
+        }
+    }
+
+    /// Number of sources leaked because more voices ended between two collections than there
+    /// was room for.
+    pub fn leaked(&self) -> usize {
+        self.leaked
+    }
+
+    /// Number of sources that can be parked between two collections.
+    pub fn parked_capacity(&self) -> usize {
+        self.parked.capacity()
+    }
+
+    /// Swap the parked sources into `spare`, which must be empty and at least as large, without
+    /// allocating or freeing anything.
+    ///
+    /// Returns whether a source was leaked since the last swap, in which case the caller should
+    /// grow `spare` before handing it back.
+    pub fn swap_parked(&mut self, spare: &mut Vec<Box<dyn SampleSource + Send>>) -> bool {
+        if !self.parked.is_empty() && spare.capacity() >= self.parked.capacity() {
+            core::mem::swap(&mut self.parked, spare);
+        }
+        core::mem::take(&mut self.overflowed)
+    }
+
+    /// Overwrite `out` with the next `out.len() / 2` interleaved stereo frames.
+    pub fn mix(&mut self, out: &mut [i16]) {
+        out.fill(0);
+        self.mix_add(out);
+    }
+
+    /// Add the next `out.len() / 2` interleaved stereo frames to `out`, saturating.
+    pub fn mix_add(&mut self, out: &mut [i16]) {
+        let mut acc = [0i32; CHUNK_FRAMES * 2];
+        for chunk in out.chunks_mut(CHUNK_FRAMES * 2) {
+            let acc = &mut acc[..chunk.len()];
+            acc.fill(0);
+            for voice in self.slots.iter_mut().filter_map(|slot| slot.voice.as_mut()) {
+                voice.render(acc, self.master);
+            }
+            for (sample, mixed) in chunk.iter_mut().zip(acc.iter()) {
+                *sample =
+                    (i32::from(*sample) + mixed).clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+            }
+        }
+        self.reap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{sync::Arc, vec};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    fn mix(mixer: &mut Mixer, frames: usize) -> Vec<i16> {
+        let mut out = vec![0; frames * 2];
+        mixer.mix(&mut out);
+        out
+    }
+
+    /// Duplicate mono `samples` into both channels.
+    fn both(samples: &[i16]) -> Vec<i16> {
+        samples.iter().flat_map(|&s| [s, s]).collect()
+    }
+
+    /// A source that counts how often it was dropped.
+    struct Counted(PcmSource<Vec<i16>>, Arc<AtomicUsize>);
+
+    impl SampleSource for Counted {
+        fn sample_rate(&self) -> u32 {
+            self.0.sample_rate()
+        }
+
+        fn channels(&self) -> u8 {
+            self.0.channels()
+        }
+
+        fn read(&mut self, buf: &mut [i16]) -> usize {
+            self.0.read(buf)
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.1.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn passthrough() {
+        let mut mixer = Mixer::new(4);
+        let id = mixer
+            .play(
+                PcmSource::new([1, -2, 3, -4, 5, -6], 2, SAMPLE_RATE),
+                PlayOptions::new(),
+            )
+            .unwrap();
+        assert!(mixer.is_playing(id));
+        assert_eq!(mix(&mut mixer, 4), [1, -2, 3, -4, 5, -6, 0, 0]);
+        assert!(!mixer.is_playing(id));
+        assert_eq!(mixer.active_voices(), 0);
+
+        let id = mixer
+            .play(PcmSource::new([7, 8], 1, SAMPLE_RATE), PlayOptions::new())
+            .unwrap();
+        assert_eq!(mix(&mut mixer, 3), both(&[7, 8, 0]));
+        assert!(!mixer.is_playing(id));
+
+        // An empty source never starts.
+        assert_eq!(
+            mixer.play(
+                PcmSource::new([0i16; 0], 1, SAMPLE_RATE),
+                PlayOptions::new()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn volume_and_pan() {
+        let mut mixer = Mixer::new(4);
+        let source = || PcmSource::new([1000, -1000], 1, SAMPLE_RATE);
+
+        mixer.play(source(), PlayOptions::new().pan(-1.0));
+        assert_eq!(mix(&mut mixer, 2), [1000, 0, -1000, 0]);
+
+        mixer.play(source(), PlayOptions::new().pan(0.5));
+        assert_eq!(mix(&mut mixer, 2), [500, 1000, -500, -1000]);
+
+        mixer.set_master_volume(0.5);
+        mixer.play(source(), PlayOptions::new().volume(0.5));
+        assert_eq!(mix(&mut mixer, 2), both(&[250, -250]));
+    }
+
+    #[test]
+    fn resample() {
+        let mut mixer = Mixer::new(1);
+        mixer.play(
+            PcmSource::new([0, 1000, 2000], 1, SAMPLE_RATE / 2),
+            PlayOptions::new(),
+        );
+        assert_eq!(
+            mix(&mut mixer, 8),
+            both(&[0, 500, 1000, 1500, 2000, 1000, 0, 0])
+        );
+
+        mixer.play(
+            PcmSource::new([0, 10, 20, 30, 40, 50], 1, SAMPLE_RATE),
+            PlayOptions::new().pitch(2.0),
+        );
+        assert_eq!(mix(&mut mixer, 4), both(&[0, 20, 40, 0]));
+    }
+
+    #[test]
+    fn looping() {
+        let mut mixer = Mixer::new(1);
+        let id = mixer
+            .play(
+                PcmSource::new([1, 2, 3], 1, SAMPLE_RATE),
+                PlayOptions::new().looping(true),
+            )
+            .unwrap();
+        assert_eq!(mix(&mut mixer, 7), both(&[1, 2, 3, 1, 2, 3, 1]));
+
+        mixer.set_looping(id, false);
+        assert_eq!(mix(&mut mixer, 4), both(&[2, 3, 0, 0]));
+        assert!(!mixer.is_playing(id));
+    }
+
+    #[test]
+    fn saturates() {
+        let mut mixer = Mixer::new(2);
+        for _ in 0..2 {
+            mixer.play(
+                PcmSource::new([30000, -30000], 1, SAMPLE_RATE),
+                PlayOptions::new(),
+            );
+        }
+        let mut out = [100, 100, 0, 0, 5, 5];
+        mixer.mix_add(&mut out);
+        assert_eq!(out, [32767, 32767, -32768, -32768, 5, 5]);
+    }
+
+    #[test]
+    fn fades() {
+        let frames = duration_to_frames(Duration::from_millis(1)) as usize;
+        assert_eq!(frames, 48);
+
+        let mut mixer = Mixer::new(1);
+        let id = mixer
+            .play(
+                PcmSource::new([4800; 256], 1, SAMPLE_RATE),
+                PlayOptions::new().fade_in(Duration::from_millis(1)),
+            )
+            .unwrap();
+        let out = mix(&mut mixer, frames + 1);
+        assert_eq!(out[..2], [0, 0]);
+        assert!(out.windows(4).step_by(2).all(|w| w[0] <= w[2]));
+        assert!(out[2 * frames - 2] < 4800);
+        assert_eq!(out[2 * frames..], [4800, 4800]);
+
+        mixer.fade_out(id, Duration::from_millis(1));
+        let out = mix(&mut mixer, frames + 1);
+        assert_eq!(out[..2], [4800, 4800]);
+        assert_eq!(out[2 * frames..], [0, 0]);
+        assert!(!mixer.is_playing(id));
+    }
+
+    #[test]
+    fn stealing() {
+        let mut mixer = Mixer::new(1);
+        let source = || PcmSource::new([1; 64], 1, SAMPLE_RATE);
+
+        let first = mixer
+            .play(source(), PlayOptions::new().priority(10))
+            .unwrap();
+        assert_eq!(mixer.play(source(), PlayOptions::new().priority(5)), None);
+        assert!(mixer.is_playing(first));
+
+        let second = mixer
+            .play(source(), PlayOptions::new().priority(10))
+            .unwrap();
+        assert_ne!(first, second);
+        assert!(!mixer.is_playing(first));
+        assert!(mixer.is_playing(second));
+
+        // Ids of replaced voices stay dead.
+        mixer.set_volume(first, 0.0);
+        assert_eq!(mix(&mut mixer, 1), [1, 1]);
+        mixer.stop(second);
+        assert_eq!(mixer.active_voices(), 0);
+    }
+
+    #[test]
+    fn parks_ended_sources() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let source = || Counted(PcmSource::new(vec![1; 4], 1, SAMPLE_RATE), drops.clone());
+
+        let mut mixer = Mixer::new(2);
+        mixer.play(source(), PlayOptions::new());
+        let id = mixer.play(source(), PlayOptions::new()).unwrap();
+        mixer.stop(id);
+        mix(&mut mixer, 8);
+        assert_eq!(mixer.active_voices(), 0);
+        assert_eq!(drops.load(Ordering::Relaxed), 0);
+
+        mixer.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+
+        // Past the room for parked sources they are leaked, and the room grows on collection.
+        for _ in 0..5 {
+            let id = mixer.play(source(), PlayOptions::new()).unwrap();
+            mixer.stop(id);
+        }
+        assert_eq!(drops.load(Ordering::Relaxed), 2);
+        assert_eq!(mixer.leaked(), 1);
+        mixer.collect();
+        assert_eq!(drops.load(Ordering::Relaxed), 6);
+        assert_eq!(mixer.parked_capacity(), 8);
+
+        let mut spare = Vec::with_capacity(8);
+        mixer.play(source(), PlayOptions::new());
+        mixer.stop_all();
+        assert!(!mixer.swap_parked(&mut spare));
+        assert_eq!(spare.len(), 1);
+        assert!(mixer.parked.is_empty());
+        assert_eq!(mixer.parked_capacity(), 8);
+    }
+
+    #[test]
+    fn ids_are_not_reused() {
+        let mut mixer = Mixer::new(1);
+        let first = mixer
+            .play(PcmSource::new([1; 4], 1, SAMPLE_RATE), PlayOptions::new())
+            .unwrap();
+        mixer.slots[0].generation = u64::from(u16::MAX);
+        let second = mixer
+            .play(PcmSource::new([1; 4], 1, SAMPLE_RATE), PlayOptions::new())
+            .unwrap();
+        assert_eq!(second.generation, u64::from(u16::MAX) + 1);
+        assert!(!mixer.is_playing(first));
+        assert!(mixer.is_playing(second));
+    }
+
+    #[test]
+    fn golden() {
+        // Stereo at the output rate, panned right.
+        let mut mixer = Mixer::new(4);
+        mixer.play(
+            PcmSource::new([1000, 2000, -1000, -2000, 3000, 4000], 2, SAMPLE_RATE),
+            PlayOptions::new().pan(0.5),
+        );
+        // Mono at a third of the output rate, at half volume.
+        mixer.play(
+            PcmSource::new([0, 3000, -3000], 1, SAMPLE_RATE / 3),
+            PlayOptions::new().volume(0.5),
+        );
+        // Mono at a quarter of the output rate, an octave up and fading in.
+        mixer.play(
+            PcmSource::new([800, 800, 800, 800], 1, SAMPLE_RATE / 4),
+            PlayOptions::new()
+                .pitch(2.0)
+                .fade_in(Duration::from_micros(125)),
+        );
+        assert_eq!(mix(&mut mixer, 12), GOLDEN);
+        assert_eq!(mixer.active_voices(), 0);
+    }
+
+    const GOLDEN: [i16; 24] = [
+        500, 2000, 132, -1368, 2765, 5265, 1899, 1899, 1033, 1033, 166, 166, -700, -700, -600,
+        -600, -500, -500, 0, 0, 0, 0, 0, 0,
+    ];
+}
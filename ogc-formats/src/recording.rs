@@ -0,0 +1,673 @@
+//! Recorded controller input, as written by ``ogc_rs::input::Recorder``.
+//!
+//! The input of a port is a fixed list of integer [`FIELDS`]: flags, button bits, raw readings
+//! and floats quantized to a fixed number of steps per unit. A recording stores how these
+//! change from frame to frame, every number is a LEB128 varint unless noted:
+//!
+//! - header: the magic `OGCI`, the format version as a big-endian `u16`, then a reserved `u16`.
+//! - frame: a `u8` with bit `n` set when port `n` changed, followed by each changed port in
+//!   port order. A frame where nothing changed is a single byte.
+//! - port: the number of fields that changed, then for each of them in field order how many
+//!   fields were skipped since the last one and the difference to its previous value, zigzag
+//!   encoded. Every field is 0 before the first frame.
+//!
+//! A field that moves by one step costs two bytes, a port whose stick moved diagonally five.
+//!
+//! ```rust
+//! use ogc_formats::recording::{Decoder, Encoder, FIELD_COUNT, PORTS, field};
+//!
+//! let mut frame = [[0; FIELD_COUNT]; PORTS];
+//! let mut encoder = Encoder::new();
+//! encoder.push(&frame);
+//! frame[0][field("pad.stick-x").unwrap()] = -42;
+//! encoder.push(&frame);
+//!
+//! let mut decoder = Decoder::new(encoder.into_bytes()).unwrap();
+//! decoder.next_frame().unwrap();
+//! assert_eq!(decoder.next_frame().unwrap(), Some(&frame));
+//! assert_eq!(decoder.next_frame().unwrap(), None);
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::FormatError;
+
+/// The magic a recording starts with.
+pub const MAGIC: &[u8; 4] = b"OGCI";
+/// Version of the format written by [`Encoder`], the only one [`Decoder`] reads.
+pub const VERSION: u16 = 1;
+/// Bytes before the first frame.
+pub const HEADER_LEN: usize = 8;
+/// Number of controller ports in a frame.
+pub const PORTS: usize = 4;
+
+/// What the integer stored for a field means.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Kind {
+    /// `0` or `1`
+    Bool,
+    /// Stored as is
+    Integer,
+    /// A float times `scale`, rounded to the nearest integer
+    Float {
+        /// Steps per unit
+        scale: f32,
+    },
+}
+
+/// A field of the input of a port.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Field {
+    /// Dotted name, `pad.stick-x` for example
+    pub name: &'static str,
+    /// How the value is stored
+    pub kind: Kind,
+}
+
+impl Field {
+    /// Turn `value` into what is stored for this field, rounding floats to their steps.
+    ///
+    /// Values outside the range of an `i32` saturate and NaN becomes 0.
+    pub fn quantize(&self, value: f32) -> i32 {
+        let value = match self.kind {
+            Kind::Float { scale } => value * scale,
+            Kind::Bool | Kind::Integer => value,
+        };
+        let rounded = if value < 0.0 {
+            value - 0.5
+        } else {
+            value + 0.5
+        };
+        // `as` truncates towards zero, saturates and maps NaN to 0.
+        rounded as i32
+    }
+
+    /// The value `stored` stands for.
+    pub fn value(&self, stored: i32) -> f32 {
+        match self.kind {
+            Kind::Float { scale } => stored as f32 / scale,
+            Kind::Bool | Kind::Integer => stored as f32,
+        }
+    }
+}
+
+const fn flag(name: &'static str) -> Field {
+    Field {
+        name,
+        kind: Kind::Bool,
+    }
+}
+
+const fn int(name: &'static str) -> Field {
+    Field {
+        name,
+        kind: Kind::Integer,
+    }
+}
+
+const fn float(name: &'static str, scale: f32) -> Field {
+    Field {
+        name,
+        kind: Kind::Float { scale },
+    }
+}
+
+/// Steps per unit of values from -1.0 to 1.0.
+const UNIT: f32 = 4096.0;
+/// Steps per pixel.
+const PIXEL: f32 = 16.0;
+/// Steps per degree.
+const DEGREE: f32 = 64.0;
+/// Steps per g, meter or kilogram.
+const FINE: f32 = 1024.0;
+
+/// Number of fields of a port.
+pub const FIELD_COUNT: usize = 91;
+
+/// The fields of a port, in the order they are stored.
+///
+/// A port holds a GameCube controller if `pad` is set and a Wii Remote if `wpad` is. Buttons
+/// are their libogc bits, `wpad.extension` counts the extension kinds from 0 in the order none,
+/// Nunchuk, Classic Controller, guitar, Balance Board, MotionPlus and unknown, then only the
+/// fields of that extension are used.
+pub const FIELDS: [Field; FIELD_COUNT] = [
+    flag("pad"),
+    flag("wpad"),
+    float("screen.width", PIXEL),
+    float("screen.height", PIXEL),
+    int("pad.buttons"),
+    int("pad.stick-x"),
+    int("pad.stick-y"),
+    int("pad.c-stick-x"),
+    int("pad.c-stick-y"),
+    int("pad.trigger-l"),
+    int("pad.trigger-r"),
+    int("pad.analog-a"),
+    int("pad.analog-b"),
+    flag("wpad.connected"),
+    int("wpad.held"),
+    int("wpad.down"),
+    int("wpad.up"),
+    flag("wpad.ir.valid"),
+    float("wpad.ir.x", PIXEL),
+    float("wpad.ir.y", PIXEL),
+    flag("wpad.ir.smooth-valid"),
+    float("wpad.ir.smooth-x", PIXEL),
+    float("wpad.ir.smooth-y", PIXEL),
+    float("wpad.ir.angle", DEGREE),
+    float("wpad.ir.distance", PIXEL),
+    float("wpad.ir.z", FINE),
+    flag("wpad.ir.dot-0"),
+    int("wpad.ir.dot-0.x"),
+    int("wpad.ir.dot-0.y"),
+    int("wpad.ir.dot-0.size"),
+    flag("wpad.ir.dot-1"),
+    int("wpad.ir.dot-1.x"),
+    int("wpad.ir.dot-1.y"),
+    int("wpad.ir.dot-1.size"),
+    flag("wpad.ir.dot-2"),
+    int("wpad.ir.dot-2.x"),
+    int("wpad.ir.dot-2.y"),
+    int("wpad.ir.dot-2.size"),
+    flag("wpad.ir.dot-3"),
+    int("wpad.ir.dot-3.x"),
+    int("wpad.ir.dot-3.y"),
+    int("wpad.ir.dot-3.size"),
+    float("wpad.orientation.roll", DEGREE),
+    float("wpad.orientation.pitch", DEGREE),
+    float("wpad.orientation.yaw", DEGREE),
+    int("wpad.accel.x"),
+    int("wpad.accel.y"),
+    int("wpad.accel.z"),
+    float("wpad.gforce.x", FINE),
+    float("wpad.gforce.y", FINE),
+    float("wpad.gforce.z", FINE),
+    int("wpad.battery-level"),
+    int("wpad.extension"),
+    int("unknown.kind"),
+    float("nunchuk.stick.x", UNIT),
+    float("nunchuk.stick.y", UNIT),
+    float("nunchuk.stick.angle", DEGREE),
+    float("nunchuk.stick.magnitude", UNIT),
+    int("nunchuk.accel.x"),
+    int("nunchuk.accel.y"),
+    int("nunchuk.accel.z"),
+    float("nunchuk.gforce.x", FINE),
+    float("nunchuk.gforce.y", FINE),
+    float("nunchuk.gforce.z", FINE),
+    float("nunchuk.orientation.roll", DEGREE),
+    float("nunchuk.orientation.pitch", DEGREE),
+    float("nunchuk.orientation.yaw", DEGREE),
+    float("classic.left-stick.x", UNIT),
+    float("classic.left-stick.y", UNIT),
+    float("classic.left-stick.angle", DEGREE),
+    float("classic.left-stick.magnitude", UNIT),
+    float("classic.right-stick.x", UNIT),
+    float("classic.right-stick.y", UNIT),
+    float("classic.right-stick.angle", DEGREE),
+    float("classic.right-stick.magnitude", UNIT),
+    float("classic.left-trigger", UNIT),
+    float("classic.right-trigger", UNIT),
+    float("guitar.stick.x", UNIT),
+    float("guitar.stick.y", UNIT),
+    float("guitar.stick.angle", DEGREE),
+    float("guitar.stick.magnitude", UNIT),
+    float("guitar.whammy-bar", UNIT),
+    float("board.top-left", FINE),
+    float("board.top-right", FINE),
+    float("board.bottom-left", FINE),
+    float("board.bottom-right", FINE),
+    float("board.x", UNIT),
+    float("board.y", UNIT),
+    int("motion-plus.rate-x"),
+    int("motion-plus.rate-y"),
+    int("motion-plus.rate-z"),
+];
+
+/// Index of the field called `name`.
+pub fn field(name: &str) -> Option<usize> {
+    FIELDS.iter().position(|field| field.name == name)
+}
+
+/// The stored fields of every port in one frame.
+pub type Frame = [[i32; FIELD_COUNT]; PORTS];
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Writes frames in the format described in [the module](self).
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    data: Vec<u8>,
+    last: Frame,
+    frames: u32,
+}
+
+impl Encoder {
+    /// A recording with only its header.
+    pub fn new() -> Self {
+        let mut data = Vec::with_capacity(HEADER_LEN);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&VERSION.to_be_bytes());
+        data.extend_from_slice(&[0; 2]);
+        Self {
+            data,
+            last: [[0; FIELD_COUNT]; PORTS],
+            frames: 0,
+        }
+    }
+
+    /// Append a frame.
+    pub fn push(&mut self, frame: &Frame) {
+        let changed = (0..PORTS)
+            .filter(|&port| frame[port] != self.last[port])
+            .fold(0u8, |mask, port| mask | 1 << port);
+        self.data.push(changed);
+        for (port, last) in self.last.iter().enumerate() {
+            if changed & 1 << port == 0 {
+                continue;
+            }
+            let fields = || (0..FIELD_COUNT).filter(|&index| frame[port][index] != last[index]);
+            write_varint(&mut self.data, fields().count() as u32);
+            let mut next = 0;
+            for index in fields() {
+                let delta = frame[port][index].wrapping_sub(last[index]);
+                write_varint(&mut self.data, (index - next) as u32);
+                write_varint(&mut self.data, ((delta << 1) ^ (delta >> 31)) as u32);
+                next = index + 1;
+            }
+        }
+        self.last = *frame;
+        self.frames += 1;
+    }
+
+    /// Number of frames written.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// The recording so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Finish the recording.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads the frames of a recording in order.
+#[derive(Debug, Clone)]
+pub struct Decoder<D> {
+    data: D,
+    offset: usize,
+    current: Frame,
+    frame: u32,
+}
+
+impl<D: AsRef<[u8]>> Decoder<D> {
+    /// Check the header of `data` and start at its first frame.
+    ///
+    /// # Errors
+    ///
+    /// [`FormatError::InvalidMagic`] when this is not a recording, [`FormatError::Unsupported`]
+    /// when it is another version of the format.
+    pub fn new(data: D) -> Result<Self, FormatError> {
+        let header = data
+            .as_ref()
+            .get(..HEADER_LEN)
+            .ok_or(FormatError::Truncated)?;
+        if &header[..4] != MAGIC {
+            return Err(FormatError::InvalidMagic);
+        }
+        if u16::from_be_bytes([header[4], header[5]]) != VERSION {
+            return Err(FormatError::Unsupported);
+        }
+        Ok(Self {
+            data,
+            offset: HEADER_LEN,
+            current: [[0; FIELD_COUNT]; PORTS],
+            frame: 0,
+        })
+    }
+
+    /// The next frame, `None` once the recording is over.
+    ///
+    /// A frame that fails to decode leaves the decoder where it was.
+    ///
+    /// # Errors
+    ///
+    /// [`FormatError::Truncated`] when the recording stops partway through a frame,
+    /// [`FormatError::Invalid`] when a frame holds values no encoder writes.
+    pub fn next_frame(&mut self) -> Result<Option<&Frame>, FormatError> {
+        if self.is_finished() {
+            return Ok(None);
+        }
+        let mut reader = Reader {
+            data: self.data.as_ref(),
+            offset: self.offset,
+        };
+        let changed = reader.byte()?;
+        if changed >> PORTS != 0 {
+            return Err(FormatError::Invalid);
+        }
+        let mut frame = self.current;
+        for (port, fields) in frame.iter_mut().enumerate() {
+            if changed & 1 << port == 0 {
+                continue;
+            }
+            let count = reader.varint()?;
+            let mut next = 0;
+            for _ in 0..count {
+                let index = (reader.varint()? as usize)
+                    .checked_add(next)
+                    .filter(|&index| index < FIELD_COUNT)
+                    .ok_or(FormatError::Invalid)?;
+                let delta = reader.varint()?;
+                let delta = (delta >> 1) as i32 ^ -((delta & 1) as i32);
+                fields[index] = fields[index].wrapping_add(delta);
+                next = index + 1;
+            }
+        }
+        self.offset = reader.offset;
+        self.current = frame;
+        self.frame += 1;
+        Ok(Some(&self.current))
+    }
+
+    /// Number of frames read so far.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Whether every frame was read.
+    pub fn is_finished(&self) -> bool {
+        self.offset >= self.data.as_ref().len()
+    }
+
+    /// Go back to the first frame.
+    pub fn rewind(&mut self) {
+        self.offset = HEADER_LEN;
+        self.current = [[0; FIELD_COUNT]; PORTS];
+        self.frame = 0;
+    }
+
+    /// The recording.
+    pub fn into_inner(self) -> D {
+        self.data
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, FormatError> {
+        let byte = *self.data.get(self.offset).ok_or(FormatError::Truncated)?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u32, FormatError> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            let bits = u32::from(byte & 0x7F);
+            // The fifth byte only has room for 4 bits.
+            if shift == 28 && bits > 0xF {
+                return Err(FormatError::Invalid);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(FormatError::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{format, string::String, vec};
+
+    const PAD_STATUS: &[(&str, char)] = &[
+        ("buttons", 'i'),
+        ("stick-x", 'i'),
+        ("stick-y", 'i'),
+        ("c-stick-x", 'i'),
+        ("c-stick-y", 'i'),
+        ("trigger-l", 'i'),
+        ("trigger-r", 'i'),
+        ("analog-a", 'i'),
+        ("analog-b", 'i'),
+    ];
+    const IR: &[(&str, char)] = &[
+        ("valid", 'b'),
+        ("x", 'f'),
+        ("y", 'f'),
+        ("smooth-valid", 'b'),
+        ("smooth-x", 'f'),
+        ("smooth-y", 'f'),
+        ("angle", 'f'),
+        ("distance", 'f'),
+        ("z", 'f'),
+    ];
+    const IR_DOT: &[(&str, char)] = &[("x", 'i'), ("y", 'i'), ("size", 'i')];
+    const ORIENTATION: &[(&str, char)] = &[("roll", 'f'), ("pitch", 'f'), ("yaw", 'f')];
+    const ACCEL: &[(&str, char)] = &[("x", 'i'), ("y", 'i'), ("z", 'i')];
+    const GFORCE: &[(&str, char)] = &[("x", 'f'), ("y", 'f'), ("z", 'f')];
+    const STICK: &[(&str, char)] = &[("x", 'f'), ("y", 'f'), ("angle", 'f'), ("magnitude", 'f')];
+    const BALANCE_BOARD: &[(&str, char)] = &[
+        ("top-left", 'f'),
+        ("top-right", 'f'),
+        ("bottom-left", 'f'),
+        ("bottom-right", 'f'),
+        ("x", 'f'),
+        ("y", 'f'),
+    ];
+    const MOTION_PLUS: &[(&str, char)] = &[("rate-x", 'i'), ("rate-y", 'i'), ("rate-z", 'i')];
+
+    /// The fields ``ogc_rs::input::recording`` writes, walking ``InputState`` and the structs in
+    /// it field by field in declaration order, with `b`, `i` and `f` for the kinds.
+    fn struct_layout() -> Vec<(String, char)> {
+        let mut layout = Vec::new();
+        let mut push = |prefix: &str, fields: &[(&str, char)]| {
+            for &(name, kind) in fields {
+                layout.push((format!("{prefix}{name}"), kind));
+            }
+        };
+        // InputState: whether pad and wpad are there, then the screen size.
+        push("", &[("pad", 'b'), ("wpad", 'b')]);
+        push("screen.", &[("width", 'f'), ("height", 'f')]);
+        push("pad.", PAD_STATUS);
+        // WPadState up to its IR, whose dots are options.
+        push(
+            "wpad.",
+            &[
+                ("connected", 'b'),
+                ("held", 'i'),
+                ("down", 'i'),
+                ("up", 'i'),
+            ],
+        );
+        push("wpad.ir.", IR);
+        for dot in 0..4 {
+            push("wpad.ir.", &[(&format!("dot-{dot}"), 'b')]);
+            push(&format!("wpad.ir.dot-{dot}."), IR_DOT);
+        }
+        push("wpad.orientation.", ORIENTATION);
+        push("wpad.accel.", ACCEL);
+        push("wpad.gforce.", GFORCE);
+        push("wpad.", &[("battery-level", 'i'), ("extension", 'i')]);
+        // Extension: the unknown kind, then every extension in the order of its variants.
+        push("unknown.", &[("kind", 'i')]);
+        push("nunchuk.stick.", STICK);
+        push("nunchuk.accel.", ACCEL);
+        push("nunchuk.gforce.", GFORCE);
+        push("nunchuk.orientation.", ORIENTATION);
+        push("classic.left-stick.", STICK);
+        push("classic.right-stick.", STICK);
+        push("classic.", &[("left-trigger", 'f'), ("right-trigger", 'f')]);
+        push("guitar.stick.", STICK);
+        push("guitar.", &[("whammy-bar", 'f')]);
+        push("board.", BALANCE_BOARD);
+        push("motion-plus.", MOTION_PLUS);
+        layout
+    }
+
+    fn frames() -> Vec<Frame> {
+        let stick = field("pad.stick-x").unwrap();
+        let buttons = field("wpad.held").unwrap();
+        let last = FIELD_COUNT - 1;
+        let mut frame = [[0; FIELD_COUNT]; PORTS];
+        let mut frames = vec![frame];
+        for value in [1, -1, 127, -128, i32::MAX, i32::MIN, 0] {
+            frame[0][stick] = value;
+            frame[3][last] = value.wrapping_mul(3);
+            frames.push(frame);
+        }
+        frame[1][buttons] = 0x8000_0000u32 as i32;
+        frames.push(frame);
+        frames.push(frame);
+        frame[2] = core::array::from_fn(|index| index as i32 * 1000 - 40000);
+        frames.push(frame);
+        frames
+    }
+
+    #[test]
+    fn fields() {
+        for (index, field) in FIELDS.iter().enumerate() {
+            assert_eq!(super::field(field.name), Some(index), "{}", field.name);
+        }
+        let x = FIELDS[field("wpad.ir.x").unwrap()];
+        assert_eq!(x.quantize(100.03), 1600);
+        assert_eq!(x.quantize(-0.04), -1);
+        assert_eq!(x.value(1601), 100.0625);
+        assert_eq!(x.quantize(f32::NAN), 0);
+        assert_eq!(x.quantize(1.0e20), i32::MAX);
+        assert_eq!(FIELDS[field("pad").unwrap()].quantize(1.0), 1);
+    }
+
+    #[test]
+    fn layout() {
+        let layout = struct_layout();
+        assert_eq!(layout.len(), FIELD_COUNT);
+        for (field, (name, kind)) in FIELDS.iter().zip(&layout) {
+            let stored = match field.kind {
+                Kind::Bool => 'b',
+                Kind::Integer => 'i',
+                Kind::Float { .. } => 'f',
+            };
+            assert_eq!((field.name, stored), (name.as_str(), *kind));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let frames = frames();
+        let mut encoder = Encoder::new();
+        for frame in &frames {
+            encoder.push(frame);
+        }
+        assert_eq!(encoder.frames(), frames.len() as u32);
+        let data = encoder.into_bytes();
+
+        let mut decoder = Decoder::new(&data).unwrap();
+        for _ in 0..2 {
+            for frame in &frames {
+                assert_eq!(decoder.next_frame(), Ok(Some(frame)));
+            }
+            assert_eq!(decoder.next_frame(), Ok(None));
+            assert!(decoder.is_finished());
+            assert_eq!(decoder.frame(), frames.len() as u32);
+            decoder.rewind();
+        }
+    }
+
+    #[test]
+    fn compact() {
+        let mut encoder = Encoder::new();
+        let mut frame = [[0; FIELD_COUNT]; PORTS];
+        encoder.push(&frame);
+        assert_eq!(encoder.as_bytes().len(), HEADER_LEN + 1);
+
+        let (x, y) = (field("pad.stick-x").unwrap(), field("pad.stick-y").unwrap());
+        frame[0][x] = 1;
+        frame[0][y] = -1;
+        encoder.push(&frame);
+        assert_eq!(encoder.as_bytes().len(), HEADER_LEN + 1 + 6);
+    }
+
+    #[test]
+    fn truncated() {
+        let mut encoder = Encoder::new();
+        for frame in &frames() {
+            encoder.push(frame);
+        }
+        let data = encoder.into_bytes();
+        for len in 0..HEADER_LEN {
+            assert_eq!(
+                Decoder::new(&data[..len]).err(),
+                Some(FormatError::Truncated)
+            );
+        }
+
+        // Every cut ends on a frame boundary or fails, without changing the decoder.
+        let complete = Decoder::new(&data[..]).unwrap();
+        for len in HEADER_LEN..data.len() {
+            let mut decoder = Decoder::new(&data[..len]).unwrap();
+            let mut reference = complete.clone();
+            loop {
+                match decoder.next_frame() {
+                    Ok(Some(frame)) => assert_eq!(reference.next_frame(), Ok(Some(frame))),
+                    Ok(None) => break,
+                    Err(error) => {
+                        assert_eq!(error, FormatError::Truncated);
+                        let frame = decoder.frame();
+                        assert_eq!(decoder.next_frame(), Err(FormatError::Truncated));
+                        assert_eq!(decoder.frame(), frame);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let mut header = MAGIC.to_vec();
+        header.extend([0, 2, 0, 0]);
+        assert_eq!(Decoder::new(&header).err(), Some(FormatError::Unsupported));
+        header[0] = b'X';
+        assert_eq!(Decoder::new(&header).err(), Some(FormatError::InvalidMagic));
+
+        let header = Encoder::new().into_bytes();
+        for frame in [
+            &[0x10][..],
+            &[0x01, 0x01, FIELD_COUNT as u8, 0x02],
+            &[0x01, 0x02, 0x00, 0x02, 0x7F, 0x02],
+            &[0x01, 0x01, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x1F],
+            &[0x01, 0x01, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x8F, 0x00],
+        ] {
+            let mut data = header.clone();
+            data.extend(frame);
+            let mut decoder = Decoder::new(data).unwrap();
+            assert_eq!(decoder.next_frame(), Err(FormatError::Invalid), "{frame:?}");
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Answers of GameCube controllers on the serial interface, as read by
+//! ``ogc_rs::mmio::controller``.
+//!
+//! Every device answers the identify command `0x00` with 3 bytes saying what it is. Controllers
+//! answer the poll command `0x40` in analog mode 3 with 8 bytes: two bytes of buttons, the main
+//! stick, the C stick and both triggers. Sticks and triggers are raw readings, made relative to
+//! the neutral [`Origin`] the controller reports for the origin command `0x41`.
+//!
+//! ```rust
+//! use ogc_formats::si::{self, Device, Origin};
+//!
+//! assert_eq!(si::decode_identify([0x09, 0x00, 0x20]), Device::Wired { rumble: true });
+//! let origin = Origin::from_response([0x00, 0x80, 0x7E, 0x81, 0x80, 0x80, 0x12, 0x00, 0, 0]);
+//! let status = si::decode_poll([0x01, 0x80, 0x8E, 0x81, 0x80, 0x80, 0x12, 0x00], &origin);
+//! assert_eq!((status.buttons, status.stick_x), (0x0100, 16));
+//! ```
+
+/// Device type bits of an identify response, as `SI_GetType` reports them.
+const TYPE_MASK: u32 = 0x1800_0000;
+const TYPE_GC: u32 = 0x0800_0000;
+const GC_WIRELESS: u32 = 0x8000_0000;
+const GC_NO_MOTOR: u32 = 0x2000_0000;
+const GC_STANDARD: u32 = 0x0100_0000;
+
+/// Bits of the first two poll bytes that are buttons.
+const BUTTONS: u16 = 0x1F7F;
+
+/// Bit of the first poll byte set when the controller wants its origin read again.
+pub const NEEDS_ORIGIN: u8 = 0x20;
+
+/// What answered an identify command.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Device {
+    /// A wired controller, which may lack a rumble motor
+    Wired {
+        /// Whether the controller has a rumble motor
+        rumble: bool,
+    },
+    /// A WaveBird receiver, with or without a controller paired
+    WaveBird,
+    /// Any other device, with its SI type
+    Other(u32),
+}
+
+/// Neutral stick and trigger positions of a controller.
+///
+/// Controllers report raw positions, which [`decode_poll`] makes relative to this.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Origin {
+    /// Raw main stick X
+    pub stick_x: u8,
+    /// Raw main stick Y
+    pub stick_y: u8,
+    /// Raw C stick X
+    pub c_stick_x: u8,
+    /// Raw C stick Y
+    pub c_stick_y: u8,
+    /// Raw left trigger
+    pub trigger_l: u8,
+    /// Raw right trigger
+    pub trigger_r: u8,
+}
+
+impl Default for Origin {
+    fn default() -> Self {
+        Self {
+            stick_x: 0x80,
+            stick_y: 0x80,
+            c_stick_x: 0x80,
+            c_stick_y: 0x80,
+            trigger_l: 0,
+            trigger_r: 0,
+        }
+    }
+}
+
+impl Origin {
+    /// Decode the 10 byte answer to an origin or recalibrate command.
+    pub fn from_response(response: [u8; 10]) -> Self {
+        Self {
+            stick_x: response[2],
+            stick_y: response[3],
+            c_stick_x: response[4],
+            c_stick_y: response[5],
+            trigger_l: response[6],
+            trigger_r: response[7],
+        }
+    }
+}
+
+/// A decoded poll, relative to the [`Origin`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Status {
+    /// Button bits, laid out like ``ogc_rs::input::PadButton``
+    pub buttons: u16,
+    /// Main stick X
+    pub stick_x: i8,
+    /// Main stick Y
+    pub stick_y: i8,
+    /// C stick X
+    pub c_stick_x: i8,
+    /// C stick Y
+    pub c_stick_y: i8,
+    /// Left trigger
+    pub trigger_l: u8,
+    /// Right trigger
+    pub trigger_r: u8,
+}
+
+/// Decode what a device is from the 3 byte answer to an identify command.
+pub fn decode_identify(response: [u8; 3]) -> Device {
+    let kind = (u32::from(response[0]) << 24) | (u32::from(response[1]) << 16);
+    if kind & TYPE_MASK != TYPE_GC {
+        Device::Other(kind)
+    } else if kind & GC_WIRELESS != 0 {
+        Device::WaveBird
+    } else if kind & GC_STANDARD != 0 {
+        Device::Wired {
+            rumble: kind & GC_NO_MOTOR == 0,
+        }
+    } else {
+        Device::Other(kind)
+    }
+}
+
+/// Decode the 8 byte answer to a poll in analog mode 3, relative to `origin`.
+///
+/// This gives the values ``ogc_rs::input::Pad`` reports through libogc.
+pub fn decode_poll(response: [u8; 8], origin: &Origin) -> Status {
+    let stick = |raw: u8, origin: u8| {
+        i8::try_from((i16::from(raw) - i16::from(origin)).clamp(-128, 127)).unwrap_or_default()
+    };
+    Status {
+        buttons: u16::from_be_bytes([response[0], response[1]]) & BUTTONS,
+        stick_x: stick(response[2], origin.stick_x),
+        stick_y: stick(response[3], origin.stick_y),
+        c_stick_x: stick(response[4], origin.c_stick_x),
+        c_stick_y: stick(response[5], origin.c_stick_y),
+        trigger_l: response[6].saturating_sub(origin.trigger_l),
+        trigger_r: response[7].saturating_sub(origin.trigger_r),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identify() {
+        assert_eq!(
+            decode_identify([0x09, 0x00, 0x20]),
+            Device::Wired { rumble: true }
+        );
+        assert_eq!(
+            decode_identify([0x29, 0x00, 0x20]),
+            Device::Wired { rumble: false }
+        );
+        assert_eq!(decode_identify([0xA8, 0x00, 0x00]), Device::WaveBird);
+        // A keyboard and a Game Boy Advance
+        assert_eq!(
+            decode_identify([0x08, 0x20, 0x00]),
+            Device::Other(0x0820_0000)
+        );
+        assert_eq!(
+            decode_identify([0x00, 0x04, 0x00]),
+            Device::Other(0x0004_0000)
+        );
+    }
+
+    #[test]
+    fn origin() {
+        assert_eq!(
+            Origin::from_response([0x00, 0x80, 0x7E, 0x81, 0x80, 0x7F, 0x12, 0x14, 0x00, 0x00]),
+            Origin {
+                stick_x: 0x7E,
+                stick_y: 0x81,
+                c_stick_x: 0x80,
+                c_stick_y: 0x7F,
+                trigger_l: 0x12,
+                trigger_r: 0x14,
+            }
+        );
+    }
+
+    #[test]
+    fn poll() {
+        let origin = Origin {
+            stick_x: 0x7E,
+            stick_y: 0x81,
+            trigger_l: 0x12,
+            ..Origin::default()
+        };
+        // Start and A held, with the origin request and the always set 0x80 bit outside the
+        // buttons.
+        let status = decode_poll([0x31, 0x80, 0xFF, 0x00, 0x90, 0x70, 0x10, 0xFF], &origin);
+        assert_eq!(
+            status,
+            Status {
+                buttons: 0x1100,
+                stick_x: 127,
+                stick_y: -128,
+                c_stick_x: 0x10,
+                c_stick_y: -0x10,
+                trigger_l: 0,
+                trigger_r: 0xFF,
+            }
+        );
+
+        let neutral = decode_poll([0, 0x80, 0x7E, 0x81, 0x80, 0x80, 0x12, 0], &origin);
+        assert_eq!(neutral, Status::default());
+    }
+}
@@ -0,0 +1,1012 @@
+//! Nintendo stream files: BRSTM, BCSTM, BFSTM and the BNS files used by channel banners.
+//!
+//! These hold PCM or DSP-ADPCM channels split into blocks, with each block interleaving the
+//! channels. [`Stream`] borrows the blocks from the file, they can be played natively as
+//! ADPCM or decoded to PCM.
+
+use alloc::vec::Vec;
+
+use crate::{
+    FormatError,
+    adpcm::{self, AdpcmInfo, Context, Decoder, FRAME_SAMPLES},
+    mixer::SampleSource,
+};
+
+mod bns;
+mod brstm;
+mod cstm;
+
+/// Sample encoding of a [`Stream`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Codec {
+    /// Signed 8-bit PCM
+    Pcm8,
+    /// Signed 16-bit PCM in the byte order of the file
+    Pcm16,
+    /// DSP-ADPCM, see [`adpcm`]
+    Adpcm,
+}
+
+impl Codec {
+    fn from_id(id: u8) -> Result<Self, FormatError> {
+        match id {
+            0 => Ok(Self::Pcm8),
+            1 => Ok(Self::Pcm16),
+            2 => Ok(Self::Adpcm),
+            _ => Err(FormatError::Unsupported),
+        }
+    }
+}
+
+/// Bounds checked reads in the byte order of the file.
+#[derive(Copy, Clone)]
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8], FormatError> {
+        let end = offset.checked_add(len).ok_or(FormatError::Truncated)?;
+        self.data.get(offset..end).ok_or(FormatError::Truncated)
+    }
+
+    fn magic(&self, offset: usize, magic: &[u8; 4]) -> Result<(), FormatError> {
+        if self.slice(offset, 4)? == magic {
+            Ok(())
+        } else {
+            Err(FormatError::InvalidMagic)
+        }
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, FormatError> {
+        Ok(self.slice(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, FormatError> {
+        let bytes = self.slice(offset, 2)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn i16(&self, offset: usize) -> Result<i16, FormatError> {
+        Ok(self.u16(offset)? as i16)
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, FormatError> {
+        let bytes = self.slice(offset, 4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    /// A `u32` offset relative to `base`.
+    fn offset(&self, base: usize, offset: usize) -> Result<usize, FormatError> {
+        base.checked_add(self.u32(offset)? as usize)
+            .ok_or(FormatError::Invalid)
+    }
+
+    /// Read an ADPCM info block, `gain` is only present in the older formats.
+    fn adpcm_info(&self, offset: usize, gain: bool) -> Result<AdpcmInfo, FormatError> {
+        let mut info = AdpcmInfo::default();
+        for (i, coefficient) in info.coefficients.iter_mut().enumerate() {
+            *coefficient = self.i16(offset + i * 2)?;
+        }
+        let mut offset = offset + 32;
+        if gain {
+            info.gain = self.u16(offset)?;
+            offset += 2;
+        }
+        let context = |offset| -> Result<Context, FormatError> {
+            Ok(Context {
+                // A u16 in the older formats and a u8 followed by padding in the newer ones.
+                predictor_scale: if gain {
+                    self.u16(offset)? as u8
+                } else {
+                    self.u8(offset)?
+                },
+                hist1: self.i16(offset + 2)?,
+                hist2: self.i16(offset + 4)?,
+            })
+        };
+        info.context = context(offset)?;
+        info.loop_context = context(offset + 6)?;
+        Ok(info)
+    }
+}
+
+/// History of every channel at fixed intervals.
+#[derive(Copy, Clone, Debug)]
+struct SeekTable<'a> {
+    data: &'a [u8],
+    /// Samples between entries
+    interval: u32,
+    big_endian: bool,
+}
+
+impl SeekTable<'_> {
+    fn history(&self, entry: usize, channel: usize, channels: usize) -> Option<(i16, i16)> {
+        let reader = Reader {
+            data: self.data,
+            big_endian: self.big_endian,
+        };
+        let offset = (entry * channels + channel) * 4;
+        Some((reader.i16(offset).ok()?, reader.i16(offset + 2).ok()?))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Channel {
+    /// Added to the offset of every block
+    offset: usize,
+    info: AdpcmInfo,
+}
+
+/// A parsed stream file borrowing its sample data from the file.
+///
+/// # Examples
+///
+/// ```rust
+/// use ogc_formats::{
+///     mixer::{Mixer, PlayOptions},
+///     stream::Stream,
+/// };
+///
+/// let stream = Stream::parse(include_bytes!("../testdata/pluck.brstm"))?;
+/// let mut mixer = Mixer::new(8);
+/// let id = mixer.play(stream.source(), PlayOptions::new().looping(stream.loop_start().is_some()));
+/// # Ok::<(), ogc_formats::FormatError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Stream<'a> {
+    codec: Codec,
+    sample_rate: u32,
+    sample_count: u32,
+    loop_start: Option<u32>,
+    big_endian: bool,
+    data: &'a [u8],
+    block_count: u32,
+    /// Bytes of each channel in a block
+    block_size: usize,
+    block_samples: u32,
+    final_block_size: usize,
+    /// Distance between the channels of the final block
+    final_block_stride: usize,
+    channels: Vec<Channel>,
+    seek_table: Option<SeekTable<'a>>,
+}
+
+impl<'a> Stream<'a> {
+    /// Parse a BRSTM, BCSTM, BFSTM or BNS file, picked by its magic.
+    pub fn parse(file: &'a [u8]) -> Result<Self, FormatError> {
+        let mut stream = match file.get(0..4).ok_or(FormatError::Truncated)? {
+            b"RSTM" => brstm::parse(file)?,
+            b"CSTM" | b"FSTM" => cstm::parse(file)?,
+            b"BNS " => bns::parse(file)?,
+            _ => return Err(FormatError::InvalidMagic),
+        };
+        stream.validate()?;
+        // Seeking relies on every entry starting a frame.
+        stream.seek_table = stream.seek_table.filter(|table| {
+            table.interval != 0 && (table.interval as usize).is_multiple_of(FRAME_SAMPLES)
+        });
+        Ok(stream)
+    }
+
+    fn validate(&self) -> Result<(), FormatError> {
+        if self.channels.is_empty() || self.block_count == 0 || self.block_samples == 0 {
+            return Err(FormatError::Invalid);
+        }
+        if self
+            .loop_start
+            .is_some_and(|start| start >= self.sample_count)
+        {
+            return Err(FormatError::Invalid);
+        }
+
+        let full_blocks = self.block_count - 1;
+        let final_samples = self
+            .block_samples
+            .checked_mul(full_blocks)
+            .and_then(|samples| self.sample_count.checked_sub(samples))
+            .filter(|&samples| samples <= self.block_samples)
+            .ok_or(FormatError::Invalid)?;
+        let (block_bytes, final_bytes) = match self.codec {
+            Codec::Pcm8 => (self.block_samples as usize, final_samples as usize),
+            Codec::Pcm16 => (self.block_samples as usize * 2, final_samples as usize * 2),
+            Codec::Adpcm => {
+                if !(self.block_samples as usize).is_multiple_of(FRAME_SAMPLES) {
+                    return Err(FormatError::Unsupported);
+                }
+                (
+                    adpcm::bytes_for_samples(self.block_samples as usize),
+                    adpcm::bytes_for_samples(final_samples as usize),
+                )
+            }
+        };
+        if (full_blocks > 0 && self.block_size < block_bytes) || self.final_block_size < final_bytes
+        {
+            return Err(FormatError::Invalid);
+        }
+
+        for channel in 0..self.channels.len() {
+            if full_blocks > 0 {
+                self.block_range(full_blocks - 1, channel)?;
+            }
+            self.block_range(full_blocks, channel)?;
+        }
+        Ok(())
+    }
+
+    fn block_range(&self, block: u32, channel: usize) -> Result<&'a [u8], FormatError> {
+        let block = block as usize;
+        let last = block + 1 == self.block_count as usize;
+        let (stride, len) = if last {
+            (self.final_block_stride, self.final_block_size)
+        } else {
+            (self.block_size, self.block_size)
+        };
+        let start = block
+            .checked_mul(self.block_size * self.channels.len())
+            .and_then(|start| start.checked_add(self.channels[channel].offset))
+            .and_then(|start| start.checked_add(channel * stride))
+            .ok_or(FormatError::Invalid)?;
+        self.data
+            .get(start..start.checked_add(len).ok_or(FormatError::Invalid)?)
+            .ok_or(FormatError::Truncated)
+    }
+
+    fn block(&self, block: u32, channel: usize) -> &'a [u8] {
+        // Every block was checked by `validate`.
+        self.block_range(block, channel).unwrap()
+    }
+
+    /// Sample encoding.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Number of channels.
+    pub fn channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of samples in each channel.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Sample the stream jumps back to after its last sample, if it loops.
+    pub fn loop_start(&self) -> Option<u32> {
+        self.loop_start
+    }
+
+    /// Whether [`Codec::Pcm16`] samples are big endian.
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// Coefficients and initial state of an ADPCM channel.
+    ///
+    /// Returns `None` if the stream is PCM.
+    pub fn adpcm_info(&self, channel: usize) -> Option<&AdpcmInfo> {
+        (self.codec == Codec::Adpcm).then(|| &self.channels[channel].info)
+    }
+
+    /// The blocks of `channel` in order, as stored in the file.
+    pub fn channel_blocks(&self, channel: usize) -> impl Iterator<Item = &'a [u8]> + '_ {
+        assert!(
+            channel < self.channels.len(),
+            "Channel {channel} is out of range"
+        );
+        (0..self.block_count).map(move |block| self.block(block, channel))
+    }
+
+    /// Copy `channel` into one contiguous buffer in the format it is stored in.
+    ///
+    /// For ADPCM this is what the DSP plays, together with [`Stream::adpcm_info`].
+    pub fn channel_data(&self, channel: usize) -> Vec<u8> {
+        let bytes = match self.codec {
+            Codec::Pcm8 => self.sample_count as usize,
+            Codec::Pcm16 => self.sample_count as usize * 2,
+            Codec::Adpcm => adpcm::bytes_for_samples(self.sample_count as usize),
+        };
+        let mut data: Vec<u8> = self.channel_blocks(channel).flatten().copied().collect();
+        data.truncate(bytes);
+        data
+    }
+
+    /// Decode `channel` to 16-bit signed PCM.
+    pub fn decode_channel(&self, channel: usize) -> Vec<i16> {
+        assert!(
+            channel < self.channels.len(),
+            "Channel {channel} is out of range"
+        );
+        let mut source = StreamSource::new(self.clone(), [channel, channel], 1);
+        let mut samples = alloc::vec![0; self.sample_count as usize];
+        let mut written = 0;
+        while written < samples.len() {
+            let read = source.read(&mut samples[written..]);
+            if read == 0 {
+                break;
+            }
+            written += read;
+        }
+        samples
+    }
+
+    /// A [`SampleSource`] playing the first two channels, or the only one.
+    pub fn source(&self) -> StreamSource<'a> {
+        match self.channels.len() {
+            1 => StreamSource::new(self.clone(), [0, 0], 1),
+            _ => StreamSource::new(self.clone(), [0, 1], 2),
+        }
+    }
+
+    /// A [`SampleSource`] playing `left` and `right` as a stereo pair.
+    ///
+    /// Streams with several tracks store each one as its own pair of channels.
+    pub fn source_channels(&self, left: usize, right: usize) -> StreamSource<'a> {
+        assert!(
+            left < self.channels.len() && right < self.channels.len(),
+            "Channels {left} and {right} are out of range"
+        );
+        StreamSource::new(self.clone(), [left, right], 2)
+    }
+}
+
+/// A [`SampleSource`] decoding a [`Stream`].
+///
+/// Rewinding jumps to the loop start of the stream, or to its first sample if it does not loop.
+#[derive(Clone, Debug)]
+pub struct StreamSource<'a> {
+    stream: Stream<'a>,
+    selected: [usize; 2],
+    count: usize,
+    decoders: [Decoder; 2],
+    /// First sample of the next frame
+    position: u32,
+    frame: [[i16; FRAME_SAMPLES]; 2],
+    frame_pos: usize,
+    frame_len: usize,
+    /// Samples to decode and drop after seeking to a point before the target
+    skip: u32,
+}
+
+impl<'a> StreamSource<'a> {
+    fn new(stream: Stream<'a>, selected: [usize; 2], count: usize) -> Self {
+        let decoders = selected.map(|channel| Decoder::new(&stream.channels[channel].info));
+        Self {
+            stream,
+            selected,
+            count,
+            decoders,
+            position: 0,
+            frame: [[0; FRAME_SAMPLES]; 2],
+            frame_pos: 0,
+            frame_len: 0,
+            skip: 0,
+        }
+    }
+
+    /// Move to `target`, starting from the closest point the decoder state is known at.
+    fn seek(&mut self, target: u32) {
+        self.frame_pos = 0;
+        self.frame_len = 0;
+        self.skip = 0;
+
+        if self.stream.codec != Codec::Adpcm {
+            self.position = target;
+            return;
+        }
+
+        let channels = self.stream.channels.len();
+        let infos = self
+            .selected
+            .map(|channel| self.stream.channels[channel].info);
+        let (position, history) = if target == 0 {
+            (
+                0,
+                infos.map(|info| (info.context.hist1, info.context.hist2)),
+            )
+        } else if Some(target) == self.stream.loop_start
+            && (target as usize).is_multiple_of(FRAME_SAMPLES)
+        {
+            let history = infos.map(|info| (info.loop_context.hist1, info.loop_context.hist2));
+            (target, history)
+        } else if let Some((table, history)) = self.stream.seek_table.and_then(|table| {
+            let entry = (target / table.interval) as usize;
+            let left = table.history(entry, self.selected[0], channels)?;
+            let right = table.history(entry, self.selected[1], channels)?;
+            Some((table, [left, right]))
+        }) {
+            (target - target % table.interval, history)
+        } else {
+            (
+                0,
+                infos.map(|info| (info.context.hist1, info.context.hist2)),
+            )
+        };
+
+        for (decoder, (hist1, hist2)) in self.decoders.iter_mut().zip(history) {
+            decoder.set_history(hist1, hist2);
+        }
+        self.position = position;
+        self.skip = target - position;
+    }
+
+    /// Decode the next frame of every selected channel.
+    fn next_frame(&mut self) -> bool {
+        let stream = &self.stream;
+        if self.position >= stream.sample_count {
+            return false;
+        }
+
+        let block = self.position / stream.block_samples;
+        let in_block = (self.position % stream.block_samples) as usize;
+        let len = FRAME_SAMPLES
+            .min(stream.block_samples as usize - in_block)
+            .min((stream.sample_count - self.position) as usize);
+
+        for i in 0..self.count {
+            let data = stream.block(block, self.selected[i]);
+            let out = &mut self.frame[i];
+            match stream.codec {
+                Codec::Pcm8 => {
+                    for (sample, &byte) in out.iter_mut().zip(&data[in_block..in_block + len]) {
+                        *sample = i16::from(byte as i8) << 8;
+                    }
+                }
+                Codec::Pcm16 => {
+                    let bytes = &data[in_block * 2..(in_block + len) * 2];
+                    for (sample, bytes) in out.iter_mut().zip(bytes.chunks_exact(2)) {
+                        let bytes = [bytes[0], bytes[1]];
+                        *sample = if stream.big_endian {
+                            i16::from_be_bytes(bytes)
+                        } else {
+                            i16::from_le_bytes(bytes)
+                        };
+                    }
+                }
+                Codec::Adpcm => {
+                    let offset = in_block / FRAME_SAMPLES * adpcm::FRAME_BYTES;
+                    let frame = data[offset..offset + adpcm::FRAME_BYTES]
+                        .try_into()
+                        .unwrap();
+                    self.decoders[i].decode_frame(frame, out);
+                }
+            }
+        }
+
+        self.position += len as u32;
+        let skipped = self.skip.min(len as u32);
+        self.skip -= skipped;
+        self.frame_pos = skipped as usize;
+        self.frame_len = len;
+        true
+    }
+}
+
+impl SampleSource for StreamSource<'_> {
+    fn sample_rate(&self) -> u32 {
+        self.stream.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        self.count as u8
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        let mut written = 0;
+        while written + self.count <= buf.len() {
+            if self.frame_pos == self.frame_len && !self.next_frame() {
+                break;
+            }
+            if self.frame_pos == self.frame_len {
+                continue;
+            }
+            for i in 0..self.count {
+                buf[written + i] = self.frame[i][self.frame_pos];
+            }
+            self.frame_pos += 1;
+            written += self.count;
+        }
+        written
+    }
+
+    fn rewind(&mut self) -> bool {
+        self.seek(self.stream.loop_start.unwrap_or(0));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// ADPCM frames whose samples step by `step` from the history, with predictor `0` being
+    /// the coefficient pair `(2048, 0)`.
+    fn ramp(frames: usize, step: i8) -> Vec<u8> {
+        let nibble = step as u8 & 0xF;
+        let mut frame = [nibble << 4 | nibble; 8];
+        frame[0] = 0;
+        frame.repeat(frames)
+    }
+
+    fn be16(out: &mut Vec<u8>, value: u16) {
+        out.extend(value.to_be_bytes());
+    }
+
+    fn be32(out: &mut Vec<u8>, value: u32) {
+        out.extend(value.to_be_bytes());
+    }
+
+    fn set32(out: &mut [u8], offset: usize, value: u32) {
+        out[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// A big endian ADPCM info block with gain, as in `RSTM` and `BNS`.
+    fn adpcm_info(out: &mut Vec<u8>, hist: i16, loop_hist: i16) {
+        let mut coefficients = [0i16; 16];
+        coefficients[0] = 2048;
+        for coefficient in coefficients {
+            be16(out, coefficient as u16);
+        }
+        for value in [0, 0, hist as u16, 0, 0, loop_hist as u16, 0, 0] {
+            be16(out, value);
+        }
+    }
+
+    /// A stereo ADPCM `RSTM` at 32 kHz, the left channel counts up from `0` and the right one
+    /// down from `1000`.
+    fn brstm(blocks: u32, samples: u32, loop_start: Option<u32>, adpc: bool) -> Vec<u8> {
+        const BLOCK_SAMPLES: u32 = 28;
+        const INFO: usize = 0x20;
+        const CHANNEL_TABLE: usize = 0x60;
+        let block_size = adpcm::bytes_for_samples(BLOCK_SAMPLES as usize);
+        let final_samples = samples - (blocks - 1) * BLOCK_SAMPLES;
+        let final_size = adpcm::bytes_for_samples(final_samples as usize);
+        let final_stride = final_size.next_multiple_of(32);
+
+        let mut head = Vec::new();
+        for offset in [INFO, 0x58, CHANNEL_TABLE] {
+            be32(&mut head, 0x0100_0000);
+            be32(&mut head, offset as u32);
+        }
+        head.resize(INFO, 0);
+        head.extend([2, u8::from(loop_start.is_some()), 2, 0]);
+        be16(&mut head, 32000);
+        be16(&mut head, 0);
+        for value in [
+            loop_start.unwrap_or(0),
+            samples,
+            0,
+            blocks,
+            block_size as u32,
+            BLOCK_SAMPLES,
+            final_size as u32,
+            final_samples,
+            final_stride as u32,
+            BLOCK_SAMPLES,
+            4,
+        ] {
+            be32(&mut head, value);
+        }
+        head.resize(CHANNEL_TABLE, 0);
+        head.extend([2, 0, 0, 0]);
+        let channel_info = CHANNEL_TABLE as u32 + 4 + 16;
+        for offset in [
+            channel_info,
+            channel_info + 8,
+            channel_info + 16,
+            channel_info + 64,
+        ] {
+            be32(&mut head, 0x0100_0000);
+            be32(&mut head, offset);
+        }
+        adpcm_info(&mut head, 0, 7000);
+        adpcm_info(&mut head, 1000, 7001);
+
+        let mut file = vec![0; 0x40];
+        file[..6].copy_from_slice(b"RSTM\xFE\xFF");
+        let head_at = file.len();
+        file.extend(b"HEAD");
+        be32(&mut file, head.len() as u32 + 8);
+        file.extend(&head);
+        let adpc_at = file.len();
+        if adpc {
+            file.extend(b"ADPC");
+            be32(&mut file, 8 + blocks * 8);
+            for block in 0..blocks {
+                let done = (block * BLOCK_SAMPLES) as i16;
+                for hist in [done, 1000 - done] {
+                    be16(&mut file, hist as u16);
+                    be16(&mut file, 0);
+                }
+            }
+        }
+        let data_at = file.len();
+        for block in 0..blocks {
+            let last = block + 1 == blocks;
+            for step in [1, -1] {
+                if last {
+                    let mut data = ramp(final_size / 8, step);
+                    data.resize(final_stride, 0);
+                    file.extend(data);
+                } else {
+                    file.extend(ramp(block_size / 8, step));
+                }
+            }
+        }
+        set32(&mut file, 0x10, head_at as u32);
+        set32(&mut file, 0x18, if adpc { adpc_at as u32 } else { 0 });
+        set32(&mut file, head_at + 8 + INFO + 0x10, data_at as u32);
+        file
+    }
+
+    /// A stereo `BNS` of 30 samples looping at 14, counting up and down from `0`.
+    fn bns() -> Vec<u8> {
+        let samples = 30;
+        let bytes = adpcm::bytes_for_samples(samples as usize) as u32;
+        let mut info = Vec::from([0, 1, 2, 0]);
+        be16(&mut info, 32000);
+        be16(&mut info, 0);
+        for value in [14, samples, 0x18, 0, 0x20, 0x2C, 0, 0x38, 0, bytes, 0x68, 0] {
+            be32(&mut info, value);
+        }
+        adpcm_info(&mut info, 0, 14);
+        adpcm_info(&mut info, 0, -14);
+
+        let mut file = Vec::from(&b"BNS "[..]);
+        be32(&mut file, 0xFEFF_0100);
+        be32(&mut file, 0);
+        be16(&mut file, 0x20);
+        be16(&mut file, 2);
+        be32(&mut file, 0x20);
+        be32(&mut file, info.len() as u32 + 8);
+        be32(&mut file, 0x20 + info.len() as u32 + 8);
+        be32(&mut file, 8 + 2 * bytes);
+        file.extend(b"INFO");
+        be32(&mut file, info.len() as u32 + 8);
+        file.extend(&info);
+        file.extend(b"DATA");
+        be32(&mut file, 8 + 2 * bytes);
+        file.extend(ramp(bytes as usize / 8, 1));
+        file.extend(ramp(bytes as usize / 8, -1));
+        file
+    }
+
+    /// A little endian mono 16-bit PCM `CSTM` of `1..=6` in blocks of 4 samples.
+    fn cstm() -> Vec<u8> {
+        let le16 = |out: &mut Vec<u8>, value: u16| out.extend(value.to_le_bytes());
+        let le32 = |out: &mut Vec<u8>, value: u32| out.extend(value.to_le_bytes());
+
+        let mut info = Vec::from(&b"INFO\0\0\0\0"[..]);
+        for (id, offset) in [(0x4100, 0x18), (0x0101, 0), (0x0101, 0x58)] {
+            le16(&mut info, id);
+            le16(&mut info, 0);
+            le32(&mut info, offset);
+        }
+        info.resize(8 + 0x18, 0);
+        info.extend([1, 0, 1, 0]);
+        for value in [44100, 0, 6, 2, 8, 4, 4, 2, 32, 0, 4] {
+            le32(&mut info, value);
+        }
+        le16(&mut info, 0x1F00);
+        le16(&mut info, 0);
+        le32(&mut info, 0x18);
+        info.resize(8 + 0x58, 0);
+        le32(&mut info, 1);
+        le16(&mut info, 0x4102);
+        le16(&mut info, 0);
+        le32(&mut info, 12);
+        info.extend([0; 8]);
+
+        let mut data = Vec::from(&b"DATA\0\0\0\0"[..]);
+        data.resize(0x20, 0);
+        for sample in 1..=6 {
+            le16(&mut data, sample);
+        }
+        data.resize(0x40, 0);
+
+        let mut file = vec![0; 0x40];
+        file[..6].copy_from_slice(b"CSTM\xFF\xFE");
+        file[0x10..0x12].copy_from_slice(&2u16.to_le_bytes());
+        for (i, (id, offset)) in [(INFO_ID, 0x40), (DATA_ID, 0x40 + info.len())]
+            .into_iter()
+            .enumerate()
+        {
+            let entry = 0x14 + i * 12;
+            file[entry..entry + 2].copy_from_slice(&id.to_le_bytes());
+            file[entry + 4..entry + 8].copy_from_slice(&(offset as u32).to_le_bytes());
+        }
+        file.extend(info);
+        file.extend(data);
+        file
+    }
+
+    const INFO_ID: u16 = 0x4000;
+    const DATA_ID: u16 = 0x4002;
+
+    /// The shortest prefix of `file` that parses, checking that no prefix panics.
+    fn shortest(file: &[u8]) -> usize {
+        (0..=file.len())
+            .find(|&len| Stream::parse(&file[..len]).is_ok())
+            .unwrap()
+    }
+
+    #[test]
+    fn brstm_decode() {
+        for adpc in [true, false] {
+            let file = brstm(3, 70, Some(33), adpc);
+            let stream = Stream::parse(&file).unwrap();
+            assert_eq!(stream.codec(), Codec::Adpcm);
+            assert_eq!(stream.channels(), 2);
+            assert_eq!(stream.sample_rate(), 32000);
+            assert_eq!(stream.sample_count(), 70);
+            assert_eq!(stream.loop_start(), Some(33));
+            assert!(stream.is_big_endian());
+            assert_eq!(stream.adpcm_info(1).unwrap().context.hist1, 1000);
+            assert_eq!(stream.channel_blocks(1).count(), 3);
+            assert_eq!(stream.channel_data(0).len(), 40);
+            assert_eq!(stream.decode_channel(0), (1..=70).collect::<Vec<i16>>());
+            assert_eq!(
+                stream.decode_channel(1),
+                (1..=70).map(|i| 1000 - i).collect::<Vec<i16>>()
+            );
+
+            // Rewinding seeks to the loop start through the seek table or by decoding from the
+            // start.
+            let mut source = stream.source();
+            let mut buf = [0; 200];
+            assert_eq!(source.read(&mut buf), 140);
+            assert_eq!(buf[..4], [1, 999, 2, 998]);
+            assert!(source.rewind());
+            assert_eq!(source.read(&mut buf), 2 * (70 - 33));
+            assert_eq!(buf[..2], [34, 1000 - 34]);
+        }
+
+        // A frame aligned loop start resumes from the loop context.
+        let file = brstm(3, 70, Some(28), false);
+        let mut source = Stream::parse(&file).unwrap().source();
+        let mut buf = [0; 200];
+        source.read(&mut buf);
+        source.rewind();
+        source.read(&mut buf[..2]);
+        assert_eq!(buf[..2], [7001, 7000]);
+    }
+
+    #[test]
+    fn brstm_malformed() {
+        let file = brstm(3, 70, Some(33), true);
+        let parse = |offset: usize, bytes: &[u8]| {
+            let mut file = file.clone();
+            file[offset..offset + bytes.len()].copy_from_slice(bytes);
+            Stream::parse(&file).map(|_| ())
+        };
+        // Offsets of the file header and the stream info.
+        let info = 0x48 + 0x20;
+
+        assert_eq!(parse(0, b"RSTX"), Err(FormatError::InvalidMagic));
+        assert_eq!(parse(4, &[0xFF, 0xFE]), Err(FormatError::Invalid));
+        assert_eq!(parse(0x40, b"HEAX"), Err(FormatError::InvalidMagic));
+        assert_eq!(parse(0x10, &[0xFF; 4]), Err(FormatError::Truncated));
+        assert_eq!(parse(info, &[3]), Err(FormatError::Unsupported));
+        assert_eq!(parse(info + 2, &[0]), Err(FormatError::Invalid));
+        // Loop start at or past the end.
+        assert_eq!(
+            parse(info + 8, &70u32.to_be_bytes()),
+            Err(FormatError::Invalid)
+        );
+        // More samples than the blocks hold.
+        assert_eq!(
+            parse(info + 0xC, &71u32.to_be_bytes()),
+            Err(FormatError::Invalid)
+        );
+        assert_eq!(
+            parse(info + 0x14, &0u32.to_be_bytes()),
+            Err(FormatError::Invalid)
+        );
+        assert_eq!(parse(info + 0x14, &[0xFF; 4]), Err(FormatError::Invalid));
+        // Blocks that do not hold whole frames.
+        assert_eq!(
+            parse(info + 0x1C, &27u32.to_be_bytes()),
+            Err(FormatError::Unsupported)
+        );
+        assert_eq!(
+            parse(info + 0x18, &8u32.to_be_bytes()),
+            Err(FormatError::Invalid)
+        );
+        assert_eq!(parse(info + 0x10, &[0xFF; 4]), Err(FormatError::Truncated));
+
+        // Every cut fails cleanly until only the padding of the final block is missing.
+        assert_eq!(shortest(&file), file.len() - 24);
+    }
+
+    #[test]
+    fn bns_decode() {
+        let file = bns();
+        let stream = Stream::parse(&file).unwrap();
+        assert_eq!(stream.codec(), Codec::Adpcm);
+        assert_eq!(stream.sample_rate(), 32000);
+        assert_eq!(stream.loop_start(), Some(14));
+        assert_eq!(stream.adpcm_info(0).unwrap().loop_context.hist1, 14);
+        assert_eq!(stream.decode_channel(0), (1..=30).collect::<Vec<i16>>());
+        assert_eq!(
+            stream.decode_channel(1),
+            (1..=30).map(|i| -i).collect::<Vec<i16>>()
+        );
+
+        let mut source = stream.source();
+        let mut buf = [0; 100];
+        assert_eq!(source.read(&mut buf), 60);
+        source.rewind();
+        assert_eq!(source.read(&mut buf), 32);
+        assert_eq!(buf[..2], [15, -15]);
+    }
+
+    #[test]
+    fn bns_malformed() {
+        let file = bns();
+        let parse = |offset: usize, bytes: &[u8]| {
+            let mut file = file.clone();
+            file[offset..offset + bytes.len()].copy_from_slice(bytes);
+            Stream::parse(&file).map(|_| ())
+        };
+
+        assert_eq!(
+            parse(4, &[0xFE, 0xFF, 0x01, 0x01]),
+            Err(FormatError::Invalid)
+        );
+        assert_eq!(parse(0x20, b"INFX"), Err(FormatError::InvalidMagic));
+        // Only ADPCM is supported.
+        assert_eq!(parse(0x28, &[1]), Err(FormatError::Unsupported));
+        assert_eq!(parse(0x30, &30u32.to_be_bytes()), Err(FormatError::Invalid));
+        // More samples than the channel data holds.
+        assert_eq!(
+            parse(0x34, &43u32.to_be_bytes()),
+            Err(FormatError::Truncated)
+        );
+        assert_eq!(parse(0x10, &[0xFF; 4]), Err(FormatError::Truncated));
+        assert_eq!(shortest(&file), file.len());
+    }
+
+    #[test]
+    fn cstm_decode() {
+        let file = cstm();
+        let stream = Stream::parse(&file).unwrap();
+        assert_eq!(stream.codec(), Codec::Pcm16);
+        assert_eq!(stream.sample_rate(), 44100);
+        assert_eq!(stream.channels(), 1);
+        assert!(!stream.is_big_endian());
+        assert!(stream.adpcm_info(0).is_none());
+        assert_eq!(stream.loop_start(), None);
+        assert_eq!(stream.decode_channel(0), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(stream.channel_data(0).len(), 12);
+
+        let mut source = stream.source();
+        let mut buf = [0; 8];
+        assert_eq!(source.read(&mut buf), 6);
+        assert!(source.rewind());
+        assert_eq!(source.read(&mut buf[..2]), 2);
+        assert_eq!(buf[..2], [1, 2]);
+    }
+
+    #[test]
+    fn cstm_malformed() {
+        let file = cstm();
+        let parse = |offset: usize, bytes: &[u8]| {
+            let mut file = file.clone();
+            file[offset..offset + bytes.len()].copy_from_slice(bytes);
+            Stream::parse(&file).map(|_| ())
+        };
+        // Offset of the stream info.
+        let stream = 0x40 + 8 + 0x18;
+
+        assert_eq!(parse(4, &[0xFF, 0xFF]), Err(FormatError::Invalid));
+        assert_eq!(parse(0x10, &[0, 0]), Err(FormatError::Truncated));
+        assert_eq!(parse(0x14, &[0, 0]), Err(FormatError::Truncated));
+        assert_eq!(parse(0x40, b"INFX"), Err(FormatError::InvalidMagic));
+        assert_eq!(parse(stream, &[7]), Err(FormatError::Unsupported));
+        // The channel table disagrees about the channel count.
+        assert_eq!(parse(stream + 2, &[2]), Err(FormatError::Invalid));
+        assert_eq!(parse(stream + 0x10, &[3]), Err(FormatError::Invalid));
+        assert_eq!(shortest(&file), file.len() - 20);
+
+        let mut file = file;
+        file[0..4].copy_from_slice(b"FSTM");
+        assert!(Stream::parse(&file).is_ok());
+        file[0..4].copy_from_slice(b"XSTM");
+        assert_eq!(
+            Stream::parse(&file).map(|_| ()),
+            Err(FormatError::InvalidMagic)
+        );
+        assert_eq!(
+            Stream::parse(b"RST").map(|_| ()),
+            Err(FormatError::Truncated)
+        );
+    }
+
+    /// Interleaved 16-bit little endian samples.
+    fn pcm(bytes: &[u8]) -> Vec<i16> {
+        bytes
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect()
+    }
+
+    fn read_all(source: &mut StreamSource<'_>, buf: &mut [i16]) -> usize {
+        let mut written = 0;
+        loop {
+            match source.read(&mut buf[written..]) {
+                0 => return written,
+                read => written += read,
+            }
+        }
+    }
+
+    /// Check a file written by `testdata/generate.py` against the PCM decoded by the script.
+    fn fixture(file: &[u8], expected: &[u8], blocks: usize, loop_start: u32) {
+        let expected = pcm(expected);
+        let stream = Stream::parse(file).unwrap();
+        assert_eq!(stream.codec(), Codec::Adpcm);
+        assert_eq!(stream.channels(), 2);
+        assert_eq!(stream.sample_rate(), 11025);
+        assert_eq!(stream.sample_count() as usize, expected.len() / 2);
+        assert_eq!(stream.loop_start(), Some(loop_start));
+        assert_eq!(stream.channel_blocks(0).count(), blocks);
+        for channel in 0..2 {
+            let samples: Vec<i16> = expected.iter().skip(channel).step_by(2).copied().collect();
+            assert_eq!(stream.decode_channel(channel), samples);
+        }
+
+        let mut source = stream.source();
+        let mut buf = vec![0; expected.len() + 2];
+        assert_eq!(read_all(&mut source, &mut buf), expected.len());
+        assert!(buf[..expected.len()] == expected[..]);
+        assert!(source.rewind());
+        let looped = &expected[loop_start as usize * 2..];
+        assert_eq!(read_all(&mut source, &mut buf), looped.len());
+        assert!(&buf[..looped.len()] == looped);
+    }
+
+    #[test]
+    fn brstm_fixture() {
+        // The loop starts in the middle of a frame of the second block, so rewinding goes
+        // through the seek table.
+        fixture(
+            include_bytes!("../testdata/pluck.brstm"),
+            include_bytes!("../testdata/pluck.pcm"),
+            2,
+            16000,
+        );
+    }
+
+    #[test]
+    fn bcstm_fixture() {
+        let file = include_bytes!("../testdata/pluck.bcstm");
+        fixture(file, include_bytes!("../testdata/pluck.pcm"), 2, 14 * 1100);
+        assert!(!Stream::parse(file).unwrap().is_big_endian());
+    }
+
+    #[test]
+    fn bns_fixture() {
+        fixture(
+            include_bytes!("../testdata/pluck.bns"),
+            include_bytes!("../testdata/pluck-bns.pcm"),
+            1,
+            14 * 100,
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! `BNS`, the banner sound of Wii channels.
+//!
+//! Always DSP-ADPCM, every channel is stored in one piece.
+
+use alloc::vec::Vec;
+
+use super::{Channel, Codec, Reader, Stream};
+use crate::{
+    FormatError,
+    adpcm::{self, FRAME_SAMPLES},
+};
+
+pub(super) fn parse(file: &[u8]) -> Result<Stream<'_>, FormatError> {
+    let reader = Reader {
+        data: file,
+        big_endian: true,
+    };
+    reader.magic(0, b"BNS ")?;
+    if reader.u32(4)? != 0xFEFF_0100 {
+        return Err(FormatError::Invalid);
+    }
+
+    let info = reader.u32(0x10)? as usize;
+    let data = reader.u32(0x18)? as usize;
+    let data_size = reader.u32(0x1C)? as usize;
+    reader.magic(info, b"INFO")?;
+    reader.magic(data, b"DATA")?;
+
+    // Offsets in the info chunk are relative to the start of its data.
+    let base = info + 8;
+    if reader.u8(base)? != 0 {
+        return Err(FormatError::Unsupported);
+    }
+    let looping = reader.u8(base + 1)? != 0;
+    let channel_count = usize::from(reader.u8(base + 2)?);
+    let loop_start = reader.u32(base + 8)?;
+    let sample_count = reader.u32(base + 0xC)?;
+    let channel_table = reader.offset(base, base + 0x10)?;
+
+    let mut channels = Vec::with_capacity(channel_count);
+    for i in 0..channel_count {
+        let channel = reader.offset(base, channel_table + i * 4)?;
+        channels.push(Channel {
+            offset: reader.u32(channel)? as usize,
+            info: reader.adpcm_info(reader.offset(base, channel + 4)?, true)?,
+        });
+    }
+
+    // One block holding each channel in full.
+    let block_samples = (sample_count as usize).next_multiple_of(FRAME_SAMPLES);
+    Ok(Stream {
+        codec: Codec::Adpcm,
+        sample_rate: u32::from(reader.u16(base + 4)?),
+        sample_count,
+        loop_start: looping.then_some(loop_start),
+        big_endian: true,
+        data: reader.slice(data + 8, data_size.saturating_sub(8))?,
+        block_count: 1,
+        block_size: 0,
+        block_samples: u32::try_from(block_samples).map_err(|_| FormatError::Invalid)?,
+        final_block_size: adpcm::bytes_for_samples(sample_count as usize),
+        final_block_stride: 0,
+        channels,
+        seek_table: None,
+    })
+}
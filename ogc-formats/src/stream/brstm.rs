@@ -0,0 +1,69 @@
+//! `RSTM`, the stream format of Wii games.
+
+use alloc::vec::Vec;
+
+use super::{Channel, Codec, Reader, SeekTable, Stream};
+use crate::FormatError;
+
+pub(super) fn parse(file: &[u8]) -> Result<Stream<'_>, FormatError> {
+    let reader = Reader {
+        data: file,
+        big_endian: true,
+    };
+    reader.magic(0, b"RSTM")?;
+    if reader.u16(4)? != 0xFEFF {
+        return Err(FormatError::Invalid);
+    }
+
+    let head = reader.u32(0x10)? as usize;
+    reader.magic(head, b"HEAD")?;
+    // Offsets in the header are relative to the start of its data.
+    let base = head + 8;
+    let info = reader.offset(base, base + 4)?;
+    let channel_table = reader.offset(base, base + 20)?;
+
+    let codec = Codec::from_id(reader.u8(info)?)?;
+    let looping = reader.u8(info + 1)? != 0;
+    let channel_count = usize::from(reader.u8(info + 2)?);
+    let loop_start = reader.u32(info + 8)?;
+    let data = reader.u32(info + 0x10)? as usize;
+
+    let mut channels = Vec::with_capacity(channel_count);
+    for i in 0..channel_count {
+        let channel = reader.offset(base, channel_table + 8 + i * 8)?;
+        let info = match codec {
+            Codec::Adpcm => reader.adpcm_info(reader.offset(base, channel + 4)?, true)?,
+            _ => Default::default(),
+        };
+        channels.push(Channel { offset: 0, info });
+    }
+
+    let adpc = reader.u32(0x18)? as usize;
+    let seek_table = if codec == Codec::Adpcm && adpc != 0 {
+        reader.magic(adpc, b"ADPC")?;
+        let len = (reader.u32(adpc + 4)? as usize).saturating_sub(8);
+        Some(SeekTable {
+            data: reader.slice(adpc + 8, len)?,
+            interval: reader.u32(info + 0x2C)?,
+            big_endian: true,
+        })
+    } else {
+        None
+    };
+
+    Ok(Stream {
+        codec,
+        sample_rate: u32::from(reader.u16(info + 4)?),
+        sample_count: reader.u32(info + 0xC)?,
+        loop_start: looping.then_some(loop_start),
+        big_endian: true,
+        data: file.get(data..).ok_or(FormatError::Truncated)?,
+        block_count: reader.u32(info + 0x14)?,
+        block_size: reader.u32(info + 0x18)? as usize,
+        block_samples: reader.u32(info + 0x1C)?,
+        final_block_size: reader.u32(info + 0x20)? as usize,
+        final_block_stride: reader.u32(info + 0x28)? as usize,
+        channels,
+        seek_table,
+    })
+}
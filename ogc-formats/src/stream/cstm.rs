@@ -0,0 +1,93 @@
+//! `CSTM` and `FSTM`, the stream formats of 3DS and Wii U games.
+//!
+//! Both share one layout, the byte order mark picks the endianness.
+
+use alloc::vec::Vec;
+
+use super::{Channel, Codec, Reader, SeekTable, Stream};
+use crate::FormatError;
+
+const INFO_BLOCK: u16 = 0x4000;
+const SEEK_BLOCK: u16 = 0x4001;
+const DATA_BLOCK: u16 = 0x4002;
+
+pub(super) fn parse(file: &[u8]) -> Result<Stream<'_>, FormatError> {
+    let mut reader = Reader {
+        data: file,
+        big_endian: false,
+    };
+    reader.big_endian = match reader.slice(4, 2)? {
+        [0xFF, 0xFE] => false,
+        [0xFE, 0xFF] => true,
+        _ => return Err(FormatError::Invalid),
+    };
+
+    let (mut info, mut seek, mut data) = (None, None, None);
+    for i in 0..usize::from(reader.u16(0x10)?) {
+        let entry = 0x14 + i * 12;
+        let offset = reader.u32(entry + 4)? as usize;
+        match reader.u16(entry)? {
+            INFO_BLOCK => info = Some(offset),
+            SEEK_BLOCK => seek = Some(offset),
+            DATA_BLOCK => data = Some(offset),
+            _ => {}
+        }
+    }
+    let info = info.ok_or(FormatError::Truncated)?;
+    let data = data.ok_or(FormatError::Truncated)?;
+    reader.magic(info, b"INFO")?;
+    reader.magic(data, b"DATA")?;
+
+    // References in the info block are relative to the start of its data.
+    let base = info + 8;
+    let stream = reader.offset(base, base + 4)?;
+    let channel_table = reader.offset(base, base + 20)?;
+
+    let codec = Codec::from_id(reader.u8(stream)?)?;
+    let looping = reader.u8(stream + 1)? != 0;
+    let channel_count = usize::from(reader.u8(stream + 2)?);
+    let loop_start = reader.u32(stream + 8)?;
+    let samples = reader.offset(data + 8, stream + 0x34)?;
+
+    if reader.u32(channel_table)? as usize != channel_count {
+        return Err(FormatError::Invalid);
+    }
+    let mut channels = Vec::with_capacity(channel_count);
+    for i in 0..channel_count {
+        let channel = reader.offset(channel_table, channel_table + 8 + i * 8)?;
+        let info = match codec {
+            Codec::Adpcm => reader.adpcm_info(reader.offset(channel, channel + 4)?, false)?,
+            _ => Default::default(),
+        };
+        channels.push(Channel { offset: 0, info });
+    }
+
+    let seek_table = match seek {
+        Some(seek) if codec == Codec::Adpcm => {
+            reader.magic(seek, b"SEEK")?;
+            let len = (reader.u32(seek + 4)? as usize).saturating_sub(8);
+            Some(SeekTable {
+                data: reader.slice(seek + 8, len)?,
+                interval: reader.u32(stream + 0x2C)?,
+                big_endian: reader.big_endian,
+            })
+        }
+        _ => None,
+    };
+
+    Ok(Stream {
+        codec,
+        sample_rate: reader.u32(stream + 4)?,
+        sample_count: reader.u32(stream + 0xC)?,
+        loop_start: looping.then_some(loop_start),
+        big_endian: reader.big_endian,
+        data: file.get(samples..).ok_or(FormatError::Truncated)?,
+        block_count: reader.u32(stream + 0x10)?,
+        block_size: reader.u32(stream + 0x14)? as usize,
+        block_samples: reader.u32(stream + 0x18)?,
+        final_block_size: reader.u32(stream + 0x1C)? as usize,
+        final_block_stride: reader.u32(stream + 0x24)? as usize,
+        channels,
+        seek_table,
+    })
+}
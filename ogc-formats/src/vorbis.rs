@@ -0,0 +1,425 @@
+//! Ogg Vorbis decoding, behind the `vorbis` feature.
+//!
+//! [`Vorbis`] decodes Vorbis I streams page by page from any [`Read`] source, so music can be
+//! played straight from a file without loading it whole. Only floor type 1 is supported, which
+//! is what every encoder since Vorbis 1.0 produces. Opus streams are not supported.
+//!
+//! The float math comes from `num-traits` with `libm`, the only dependency of this crate and
+//! the reason the decoder is behind a feature.
+//!
+//! ```rust
+//! use ogc_formats::vorbis::Vorbis;
+//!
+//! let mut vorbis = Vorbis::new(&include_bytes!("vorbis/testdata/sine.ogg")[..])?;
+//! assert_eq!(vorbis.decode_to_end()?.len(), 2500 * 2);
+//! # Ok::<(), ogc_formats::FormatError>(())
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::{FormatError, mixer::SampleSource};
+
+mod bits;
+mod codebook;
+mod ogg;
+mod setup;
+mod synthesis;
+
+use bits::BitReader;
+use ogg::PacketReader;
+use setup::Setup;
+use synthesis::Synthesis;
+
+/// Where a [`Vorbis`] reads the Ogg stream from.
+pub trait Read {
+    /// Error of the reader, malformed streams are reported as a [`FormatError`].
+    type Error: From<FormatError>;
+
+    /// Read into `buf`, returning how many bytes were read or `0` at the end.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+
+    /// Fill `buf` completely, failing if the stream ends first.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+/// A [`Read`] that can go back to its start, needed to loop.
+pub trait Rewind: Read {
+    /// Move back to the first byte.
+    fn rewind(&mut self) -> Result<(), Self::Error>;
+}
+
+/// A stream held in memory, ending early is [`FormatError::Truncated`].
+impl Read for &[u8] {
+    type Error = FormatError;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, FormatError> {
+        let len = buf.len().min(self.len());
+        let (head, rest) = self.split_at(len);
+        buf[..len].copy_from_slice(head);
+        *self = rest;
+        Ok(len)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), FormatError> {
+        if buf.len() > self.len() {
+            *self = &[];
+            return Err(FormatError::Truncated);
+        }
+        self.read(buf).map(|_| ())
+    }
+}
+
+/// Check the packet type and `vorbis` magic at the start of a header packet.
+fn header_body(packet: &[u8], kind: u8) -> Result<&[u8], FormatError> {
+    if packet.len() < 7 {
+        return Err(FormatError::Truncated);
+    }
+    if packet[0] != kind || &packet[1..7] != b"vorbis" {
+        return Err(FormatError::InvalidMagic);
+    }
+    Ok(&packet[7..])
+}
+
+/// A streaming Ogg Vorbis decoder.
+///
+/// Only mono and stereo streams are supported. Reading and decoding errors end playback when
+/// used as a [`SampleSource`], see [`Vorbis::error`].
+pub struct Vorbis<R: Read> {
+    packets: PacketReader<R>,
+    channels: u8,
+    sample_rate: u32,
+    setup: Setup,
+    synthesis: Synthesis,
+    /// Interleaved samples of the last packet
+    pcm: Vec<i16>,
+    /// Next sample of `pcm`
+    consumed: usize,
+    /// Frames decoded since the start
+    position: u64,
+    error: Option<R::Error>,
+}
+
+impl<R: Read> Vorbis<R> {
+    /// Read the headers of the first Vorbis stream in `reader`.
+    pub fn new(reader: R) -> Result<Self, R::Error> {
+        let mut packets = PacketReader::new(reader);
+        let mut next_header = |kind| -> Result<Vec<u8>, R::Error> {
+            packets.next_packet()?.ok_or(FormatError::Truncated)?;
+            Ok(header_body(packets.packet(), kind).map(<[u8]>::to_vec)?)
+        };
+
+        let identification = next_header(1)?;
+        next_header(3)?;
+        let setup = next_header(5)?;
+
+        let mut bits = BitReader::new(&identification);
+        let mut read = |count| bits.read(count).ok_or(FormatError::Truncated);
+        if read(32)? != 0 {
+            return Err(FormatError::Unsupported.into());
+        }
+        let channels = read(8)?;
+        let sample_rate = read(32)?;
+        // Maximum, nominal and minimum bitrate.
+        for _ in 0..3 {
+            read(32)?;
+        }
+        let short = read(4)?;
+        let long = read(4)?;
+        if read(1)? == 0 || sample_rate == 0 || !(6..=13).contains(&short) || long < short {
+            return Err(FormatError::Invalid.into());
+        }
+        if !(6..=13).contains(&long) {
+            return Err(FormatError::Invalid.into());
+        }
+        if !matches!(channels, 1 | 2) {
+            return Err(FormatError::Unsupported.into());
+        }
+
+        let mut bits = BitReader::new(&setup);
+        let setup = Setup::read(&mut bits, channels as u8)?;
+        Ok(Self {
+            packets,
+            channels: channels as u8,
+            sample_rate,
+            setup,
+            synthesis: Synthesis::new(channels as usize, [1 << short, 1 << long]),
+            pcm: Vec::new(),
+            consumed: 0,
+            position: 0,
+            error: None,
+        })
+    }
+
+    /// Number of channels, `1` or `2`.
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// Sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Frames decoded since the start of the stream.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// The error that ended playback as a [`SampleSource`], if any.
+    pub fn error(&self) -> Option<&R::Error> {
+        self.error.as_ref()
+    }
+
+    /// Return the reader.
+    pub fn into_inner(self) -> R {
+        self.packets.into_inner()
+    }
+
+    /// Decode the next packet that holds samples, returning `false` at the end of the stream.
+    fn decode_packet(&mut self) -> Result<bool, R::Error> {
+        loop {
+            let Some(info) = self.packets.next_packet()? else {
+                return Ok(false);
+            };
+            self.pcm.clear();
+            self.consumed = 0;
+            let mut frames =
+                self.synthesis
+                    .decode(&self.setup, self.packets.packet(), &mut self.pcm)
+                    as u64;
+
+            // The granule position of the last page is the length of the stream, the last
+            // packet is padded up to a whole block.
+            if info.last
+                && let Some(granule) = info.granule
+            {
+                frames = frames.min(granule.saturating_sub(self.position));
+                self.pcm
+                    .truncate(frames as usize * usize::from(self.channels));
+            }
+            self.position += frames;
+
+            if frames > 0 {
+                return Ok(true);
+            }
+            if info.last {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Fill `buf` with interleaved samples, returning how many were written.
+    ///
+    /// `0` means the end of the stream was reached or `buf` is empty.
+    pub fn decode(&mut self, buf: &mut [i16]) -> Result<usize, R::Error> {
+        let mut written = 0;
+        while written < buf.len() {
+            if self.consumed == self.pcm.len() && !self.decode_packet()? {
+                break;
+            }
+            let available = &self.pcm[self.consumed..];
+            let len = available.len().min(buf.len() - written);
+            buf[written..written + len].copy_from_slice(&available[..len]);
+            self.consumed += len;
+            written += len;
+        }
+        Ok(written)
+    }
+
+    /// Decode everything that is left to interleaved samples.
+    pub fn decode_to_end(&mut self) -> Result<Vec<i16>, R::Error> {
+        let mut samples = Vec::new();
+        let mut chunk = [0; 1024];
+        loop {
+            match self.decode(&mut chunk)? {
+                0 => return Ok(samples),
+                len => samples.extend_from_slice(&chunk[..len]),
+            }
+        }
+    }
+}
+
+impl<R: Rewind> Vorbis<R> {
+    /// Go back to the start of the stream.
+    pub fn rewind(&mut self) -> Result<(), R::Error> {
+        self.packets.get_mut().rewind()?;
+        self.packets.reset();
+        for _ in 0..3 {
+            self.packets.next_packet()?.ok_or(FormatError::Truncated)?;
+        }
+        self.synthesis.reset();
+        self.pcm.clear();
+        self.consumed = 0;
+        self.position = 0;
+        self.error = None;
+        Ok(())
+    }
+}
+
+impl<R: Rewind> SampleSource for Vorbis<R> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        if self.error.is_some() {
+            return 0;
+        }
+        self.decode(buf).unwrap_or_else(|error| {
+            self.error = Some(error);
+            0
+        })
+    }
+
+    fn rewind(&mut self) -> bool {
+        Vorbis::rewind(self).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// A slice that can be rewound.
+    struct Cursor<'a> {
+        data: &'a [u8],
+        rest: &'a [u8],
+    }
+
+    impl Read for Cursor<'_> {
+        type Error = FormatError;
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, FormatError> {
+            self.rest.read(buf)
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), FormatError> {
+            self.rest.read_exact(buf)
+        }
+    }
+
+    impl Rewind for Cursor<'_> {
+        fn rewind(&mut self) -> Result<(), FormatError> {
+            self.rest = self.data;
+            Ok(())
+        }
+    }
+
+    /// A 440 Hz stereo sine at 44.1 kHz encoded by libvorbis, from the samples of the `audrey`
+    /// crate. It is cut after its fifth audio packet, with the granule position of the last
+    /// page ending it at 2500 frames, so it switches from a short to a long block and trims the
+    /// end.
+    const SINE: &[u8] = include_bytes!("vorbis/testdata/sine.ogg");
+    /// What `lewton` decodes from [`SINE`], little endian and interleaved.
+    const SINE_PCM: &[u8] = include_bytes!("vorbis/testdata/sine.pcm");
+    const SINE_FRAMES: usize = 2500;
+
+    fn reference() -> Vec<i16> {
+        SINE_PCM
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect()
+    }
+
+    fn open(data: &[u8]) -> Result<Vorbis<Cursor<'_>>, FormatError> {
+        Vorbis::new(Cursor { data, rest: data })
+    }
+
+    fn assert_close(decoded: &[i16], expected: &[i16]) {
+        assert_eq!(decoded.len(), expected.len());
+        for (i, (&a, &b)) in decoded.iter().zip(expected).enumerate() {
+            // Rounding in the float math may differ from the reference decoder by one step.
+            assert!(
+                (i32::from(a) - i32::from(b)).abs() <= 1,
+                "sample {i}: {a} != {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn decode() {
+        let mut vorbis = open(SINE).unwrap();
+        assert_eq!(vorbis.channels(), 2);
+        assert_eq!(vorbis.sample_rate(), 44100);
+
+        let decoded = vorbis.decode_to_end().unwrap();
+        assert_eq!(decoded.len(), SINE_FRAMES * 2);
+        assert_eq!(vorbis.position(), SINE_FRAMES as u64);
+        assert_close(&decoded, &reference());
+        assert_eq!(vorbis.decode(&mut [0; 16]).unwrap(), 0);
+    }
+
+    #[test]
+    fn streaming() {
+        let expected = open(SINE).unwrap().decode_to_end().unwrap();
+
+        let mut vorbis = open(SINE).unwrap();
+        let mut decoded = Vec::new();
+        let mut buf = [0; 301];
+        for len in [1, 7, 300, 301].into_iter().cycle() {
+            match vorbis.decode(&mut buf[..len]).unwrap() {
+                0 => break,
+                read => decoded.extend_from_slice(&buf[..read]),
+            }
+        }
+        assert_eq!(decoded, expected);
+
+        vorbis.rewind().unwrap();
+        assert_eq!(vorbis.position(), 0);
+        let mut buf = vec![0; expected.len() + 10];
+        assert_eq!(SampleSource::read(&mut vorbis, &mut buf), expected.len());
+        assert_eq!(buf[..expected.len()], expected);
+        assert!(SampleSource::rewind(&mut vorbis));
+        assert_eq!(vorbis.decode_to_end().unwrap(), expected);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(open(b"RIFF").err(), Some(FormatError::Truncated));
+        let mut data = SINE.to_vec();
+        data[0] = b'X';
+        assert_eq!(open(&data).err(), Some(FormatError::InvalidMagic));
+
+        // A flipped bit fails the page checksum.
+        let mut data = SINE.to_vec();
+        data[40] ^= 1;
+        assert_eq!(open(&data).err(), Some(FormatError::Invalid));
+
+        // The first page has to start the stream.
+        let mut data = SINE.to_vec();
+        data[5] = 0;
+        assert_eq!(open(&data).err(), Some(FormatError::Invalid));
+
+        // Only Vorbis I is supported.
+        let mut data = SINE.to_vec();
+        data[35] = 1;
+        data[22..26].fill(0);
+        let crc = ogg::crc32(0, &data[..58]);
+        data[22..26].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(open(&data).err(), Some(FormatError::Unsupported));
+    }
+
+    #[test]
+    fn truncated() {
+        // Cut inside the identification, comment and setup headers.
+        for len in [0, 20, 58, 1000, 4000] {
+            assert!(open(&SINE[..len]).is_err(), "{len} bytes");
+        }
+
+        // Cut inside the last page, which is lost as a whole.
+        let mut vorbis = open(&SINE[..SINE.len() - 10]).unwrap();
+        assert_eq!(vorbis.decode_to_end().err(), Some(FormatError::Truncated));
+        let mut buf = [0; 64];
+        assert_eq!(SampleSource::read(&mut vorbis, &mut buf), 0);
+        assert_eq!(vorbis.error(), None);
+
+        let mut vorbis = open(&SINE[..SINE.len() - 10]).unwrap();
+        assert_eq!(SampleSource::read(&mut vorbis, &mut buf), 0);
+        assert_eq!(vorbis.error(), Some(&FormatError::Truncated));
+    }
+}
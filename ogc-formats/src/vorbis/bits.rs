@@ -0,0 +1,58 @@
+//! The LSB first bit packing of Vorbis packets.
+
+/// Reads bits from a packet, running out of bits is the end of packet condition.
+pub(super) struct BitReader<'a> {
+    data: &'a [u8],
+    /// Next bit to read, counted from the start
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    /// Read `count` bits, at most 32, as an unsigned integer.
+    pub(super) fn read(&mut self, count: u32) -> Option<u32> {
+        debug_assert!(count <= 32);
+        if count as usize > self.remaining() {
+            self.position = self.data.len() * 8;
+            return None;
+        }
+        let (value, _) = self.peek(count);
+        self.position += count as usize;
+        Some(value)
+    }
+
+    pub(super) fn read_bool(&mut self) -> Option<bool> {
+        self.read(1).map(|bit| bit != 0)
+    }
+
+    /// The next `count` bits, at most 32, without consuming them.
+    ///
+    /// Bits past the end of the packet read as zero, the second value is how many are real.
+    pub(super) fn peek(&self, count: u32) -> (u32, u32) {
+        let available = self.remaining().min(count as usize) as u32;
+        let start = self.position / 8;
+        let mut word = 0u64;
+        for (i, &byte) in self.data[start..].iter().take(5).enumerate() {
+            word |= u64::from(byte) << (8 * i);
+        }
+        let mask = (1u64 << available) - 1;
+        (((word >> (self.position % 8)) & mask) as u32, available)
+    }
+
+    /// Consume `count` bits that were looked at with [`BitReader::peek`].
+    pub(super) fn skip(&mut self, count: u32) {
+        self.position = (self.position + count as usize).min(self.data.len() * 8);
+    }
+}
+
+/// Number of bits needed to store `value`.
+pub(super) fn ilog(value: u32) -> u32 {
+    32 - value.leading_zeros()
+}
@@ -0,0 +1,273 @@
+//! Huffman codebooks and their vector quantization tables.
+
+use alloc::{vec, vec::Vec};
+// The float methods of `std` shadow these in host tests.
+#[cfg_attr(test, allow(unused_imports))]
+use num_traits::Float;
+
+use super::bits::{BitReader, ilog};
+use crate::FormatError;
+
+/// Codewords up to this long are decoded with a single table lookup.
+const FAST_BITS: u32 = 10;
+/// Marks a leaf of the decoding tree, the rest is the entry.
+const LEAF: u32 = 1 << 31;
+
+pub(super) struct Codebook {
+    pub(super) dimensions: usize,
+    /// `entry << 6 | length` indexed by the next [`FAST_BITS`] bits, zero if no codeword fits
+    fast: Vec<u32>,
+    /// Children of each node, `0` for none
+    tree: Vec<[u32; 2]>,
+    /// `dimensions` values for every entry
+    vectors: Option<Vec<f32>>,
+}
+
+/// Largest `r` with `r.pow(dimensions) <= entries`.
+fn lookup1_values(entries: u32, dimensions: u32) -> u32 {
+    let fits = |r: u32| {
+        r.checked_pow(dimensions)
+            .is_some_and(|value| value <= entries)
+    };
+    let mut r = (entries as f32).powf(1.0 / dimensions as f32) as u32;
+    while fits(r + 1) {
+        r += 1;
+    }
+    while r > 0 && !fits(r) {
+        r -= 1;
+    }
+    r
+}
+
+/// Unpack the 32-bit float format of codebooks.
+pub(super) fn float32_unpack(value: u32) -> f32 {
+    let mantissa = (value & 0x1F_FFFF) as f32;
+    let exponent = ((value & 0x7FE0_0000) >> 21) as i32;
+    let mantissa = if value & 0x8000_0000 != 0 {
+        -mantissa
+    } else {
+        mantissa
+    };
+    mantissa * 2f32.powi(exponent - 788)
+}
+
+impl Codebook {
+    pub(super) fn read(bits: &mut BitReader<'_>) -> Result<Self, FormatError> {
+        let mut read = |count| bits.read(count).ok_or(FormatError::Truncated);
+        if read(24)? != 0x56_4342 {
+            return Err(FormatError::InvalidMagic);
+        }
+        let dimensions = read(16)?;
+        let entries = read(24)?;
+        if dimensions == 0 && entries != 0 {
+            return Err(FormatError::Invalid);
+        }
+
+        // Lengths of zero are unused entries.
+        let mut lengths = vec![0u8; entries as usize];
+        if read(1)? == 0 {
+            let sparse = read(1)? != 0;
+            for length in &mut lengths {
+                if !sparse || read(1)? != 0 {
+                    *length = read(5)? as u8 + 1;
+                }
+            }
+        } else {
+            let mut entry = 0;
+            let mut length = read(5)? + 1;
+            while entry < entries {
+                let count = read(ilog(entries - entry))?;
+                if entry + count > entries || length > 32 {
+                    return Err(FormatError::Invalid);
+                }
+                lengths[entry as usize..(entry + count) as usize].fill(length as u8);
+                entry += count;
+                length += 1;
+            }
+        }
+
+        let vectors = match read(4)? {
+            0 => None,
+            lookup_type @ (1 | 2) => {
+                let minimum = float32_unpack(read(32)?);
+                let delta = float32_unpack(read(32)?);
+                let value_bits = read(4)? + 1;
+                let sequence = read(1)? != 0;
+                let lookup_values = if lookup_type == 1 {
+                    lookup1_values(entries, dimensions)
+                } else {
+                    entries
+                        .checked_mul(dimensions)
+                        .ok_or(FormatError::Invalid)?
+                };
+                let mut multiplicands = Vec::with_capacity(lookup_values as usize);
+                for _ in 0..lookup_values {
+                    multiplicands.push(read(value_bits)? as f32);
+                }
+                if multiplicands.is_empty() {
+                    return Err(FormatError::Invalid);
+                }
+                Some(Self::unpack_vectors(
+                    &multiplicands,
+                    lookup_type,
+                    entries,
+                    dimensions,
+                    minimum,
+                    delta,
+                    sequence,
+                ))
+            }
+            _ => return Err(FormatError::Unsupported),
+        };
+
+        let mut codebook = Self {
+            dimensions: dimensions as usize,
+            fast: vec![0; 1 << FAST_BITS],
+            tree: vec![[0; 2]],
+            vectors,
+        };
+        codebook.assign_codewords(&lengths)?;
+        Ok(codebook)
+    }
+
+    fn unpack_vectors(
+        multiplicands: &[f32],
+        lookup_type: u32,
+        entries: u32,
+        dimensions: u32,
+        minimum: f32,
+        delta: f32,
+        sequence: bool,
+    ) -> Vec<f32> {
+        let dimensions = dimensions as usize;
+        let lookup_values = multiplicands.len();
+        let mut vectors = Vec::with_capacity(entries as usize * dimensions);
+        for entry in 0..entries as usize {
+            let mut last = 0.0;
+            let mut divisor = 1;
+            for i in 0..dimensions {
+                let offset = if lookup_type == 1 {
+                    let offset = (entry / divisor) % lookup_values;
+                    divisor = divisor.saturating_mul(lookup_values);
+                    offset
+                } else {
+                    entry * dimensions + i
+                };
+                let value = multiplicands[offset] * delta + minimum + last;
+                if sequence {
+                    last = value;
+                }
+                vectors.push(value);
+            }
+        }
+        vectors
+    }
+
+    /// Give each used entry the lowest free codeword of its length, in entry order.
+    fn assign_codewords(&mut self, lengths: &[u8]) -> Result<(), FormatError> {
+        // Lowest free codeword of each length, left aligned, zero if there is none.
+        let mut available = [0u32; 33];
+        let mut first = true;
+        for (entry, &length) in lengths.iter().enumerate() {
+            let length = u32::from(length);
+            if length == 0 {
+                continue;
+            }
+            let codeword = if first {
+                first = false;
+                for (i, free) in available
+                    .iter_mut()
+                    .enumerate()
+                    .take(length as usize + 1)
+                    .skip(1)
+                {
+                    *free = 1 << (32 - i);
+                }
+                0
+            } else {
+                let mut z = length;
+                while z > 0 && available[z as usize] == 0 {
+                    z -= 1;
+                }
+                if z == 0 {
+                    return Err(FormatError::Invalid);
+                }
+                let codeword = available[z as usize];
+                available[z as usize] = 0;
+                for y in z + 1..=length {
+                    available[y as usize] = codeword + (1 << (32 - y));
+                }
+                codeword
+            };
+            self.insert(
+                entry as u32,
+                (u64::from(codeword) >> (32 - length)) as u32,
+                length,
+            );
+        }
+        Ok(())
+    }
+
+    /// Add `codeword`, `length` bits read most significant bit first, for `entry`.
+    fn insert(&mut self, entry: u32, codeword: u32, length: u32) {
+        let mut node = 0;
+        for i in (0..length).rev() {
+            let bit = ((codeword >> i) & 1) as usize;
+            if i == 0 {
+                self.tree[node][bit] = LEAF | entry;
+            } else {
+                let child = self.tree[node][bit];
+                node = if child == 0 || child & LEAF != 0 {
+                    self.tree.push([0; 2]);
+                    let index = self.tree.len() - 1;
+                    self.tree[node][bit] = index as u32;
+                    index
+                } else {
+                    child as usize
+                };
+            }
+        }
+
+        if length <= FAST_BITS {
+            // The stream holds the first bit of the codeword in the lowest bit.
+            let reversed = codeword.reverse_bits() >> (32 - length);
+            for high in 0..1 << (FAST_BITS - length) {
+                self.fast[(reversed | high << length) as usize] = entry << 6 | length;
+            }
+        }
+    }
+
+    /// Decode one entry number, `None` at the end of the packet.
+    pub(super) fn decode(&self, bits: &mut BitReader<'_>) -> Option<u32> {
+        let (peeked, available) = bits.peek(FAST_BITS);
+        let fast = self.fast[peeked as usize];
+        let length = fast & 63;
+        if length != 0 && length <= available {
+            bits.skip(length);
+            return Some(fast >> 6);
+        }
+
+        let mut node = 0;
+        loop {
+            let bit = bits.read(1)? as usize;
+            match self.tree[node][bit] {
+                0 => return None,
+                child if child & LEAF != 0 => return Some(child & !LEAF),
+                child => node = child as usize,
+            }
+        }
+    }
+
+    /// Decode one entry and look up its vector.
+    ///
+    /// `None` at the end of the packet or if the codebook has no vectors.
+    pub(super) fn decode_vector(&self, bits: &mut BitReader<'_>) -> Option<&[f32]> {
+        let entry = self.decode(bits)? as usize;
+        let vectors = self.vectors.as_ref()?;
+        vectors.get(entry * self.dimensions..(entry + 1) * self.dimensions)
+    }
+
+    pub(super) fn has_vectors(&self) -> bool {
+        self.vectors.is_some()
+    }
+}
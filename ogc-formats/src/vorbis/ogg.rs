@@ -0,0 +1,179 @@
+//! Splitting an Ogg bitstream into packets.
+
+use alloc::{vec, vec::Vec};
+
+use super::Read;
+use crate::FormatError;
+
+/// Header flag of a page that continues the last packet of the previous page.
+const CONTINUED: u8 = 1;
+/// Header flag of the first page of a stream.
+const FIRST_PAGE: u8 = 2;
+/// Header flag of the last page of a stream.
+const LAST_PAGE: u8 = 4;
+/// Granule position of pages on which no packet ends.
+const NO_GRANULE: u64 = u64::MAX;
+
+/// A packet returned by [`PacketReader::next_packet`].
+#[derive(Copy, Clone, Debug)]
+pub(super) struct PacketInfo {
+    /// Granule position of the page if this is the last packet that ends on it
+    pub(super) granule: Option<u64>,
+    /// Whether this is the last packet of the stream
+    pub(super) last: bool,
+}
+
+pub(super) fn crc32(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        let mut crc = crc ^ (u32::from(byte) << 24);
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// Reads the packets of the first logical stream of an Ogg file, skipping any others.
+pub(super) struct PacketReader<R> {
+    reader: R,
+    serial: Option<u32>,
+    lacing: Vec<u8>,
+    /// Next lacing value of the current page
+    segment: usize,
+    body: Vec<u8>,
+    /// Next byte of `body`
+    offset: usize,
+    flags: u8,
+    granule: u64,
+    packet: Vec<u8>,
+    ended: bool,
+}
+
+impl<R: Read> PacketReader<R> {
+    pub(super) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            serial: None,
+            lacing: Vec::new(),
+            segment: 0,
+            body: Vec::new(),
+            offset: 0,
+            flags: 0,
+            granule: NO_GRANULE,
+            packet: Vec::new(),
+            ended: false,
+        }
+    }
+
+    pub(super) fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub(super) fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Start over, after the reader was moved back to the start of the file.
+    pub(super) fn reset(&mut self) {
+        self.serial = None;
+        self.lacing.clear();
+        self.segment = 0;
+        self.flags = 0;
+        self.packet.clear();
+        self.ended = false;
+    }
+
+    /// The data of the packet returned by the last [`PacketReader::next_packet`].
+    pub(super) fn packet(&self) -> &[u8] {
+        &self.packet
+    }
+
+    /// Read the next page of our stream, returning `false` at the end of the file.
+    fn next_page(&mut self) -> Result<bool, R::Error> {
+        loop {
+            let mut header = [0u8; 27];
+            if self.reader.read(&mut header[..1])? == 0 {
+                return Ok(false);
+            }
+            self.reader.read_exact(&mut header[1..])?;
+            if &header[..4] != b"OggS" {
+                return Err(FormatError::InvalidMagic.into());
+            }
+            if header[4] != 0 {
+                return Err(FormatError::Unsupported.into());
+            }
+
+            let flags = header[5];
+            let granule = u64::from_le_bytes(header[6..14].try_into().unwrap());
+            let serial = u32::from_le_bytes(header[14..18].try_into().unwrap());
+            let crc = u32::from_le_bytes(header[22..26].try_into().unwrap());
+            let mut lacing = vec![0; usize::from(header[26])];
+            self.reader.read_exact(&mut lacing)?;
+            let len = lacing.iter().map(|&len| usize::from(len)).sum();
+            self.body.resize(len, 0);
+            self.reader.read_exact(&mut self.body)?;
+
+            header[22..26].fill(0);
+            let computed = [&header[..], &lacing, &self.body]
+                .iter()
+                .fold(0, |crc, data| crc32(crc, data));
+            if computed != crc {
+                return Err(FormatError::Invalid.into());
+            }
+
+            match self.serial {
+                None if flags & FIRST_PAGE != 0 => self.serial = Some(serial),
+                None => return Err(FormatError::Invalid.into()),
+                Some(ours) if ours != serial => continue,
+                Some(_) => {}
+            }
+
+            // A page that does not continue a packet drops any half read one.
+            if flags & CONTINUED == 0 {
+                self.packet.clear();
+            }
+            self.lacing = lacing;
+            self.segment = 0;
+            self.offset = 0;
+            self.flags = flags;
+            self.granule = granule;
+            return Ok(true);
+        }
+    }
+
+    /// Read the next packet, `None` at the end of the stream.
+    pub(super) fn next_packet(&mut self) -> Result<Option<PacketInfo>, R::Error> {
+        self.packet.clear();
+        loop {
+            if self.segment == self.lacing.len() {
+                if self.ended || self.flags & LAST_PAGE != 0 {
+                    self.ended = true;
+                    return Ok(None);
+                }
+                if !self.next_page()? {
+                    self.ended = true;
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            let len = usize::from(self.lacing[self.segment]);
+            self.packet
+                .extend_from_slice(&self.body[self.offset..self.offset + len]);
+            self.offset += len;
+            self.segment += 1;
+            if len < 255 {
+                let last_on_page = self.lacing[self.segment..].iter().all(|&len| len == 255);
+                let granule = (last_on_page && self.granule != NO_GRANULE).then_some(self.granule);
+                return Ok(Some(PacketInfo {
+                    granule,
+                    last: last_on_page && self.flags & LAST_PAGE != 0,
+                }));
+            }
+        }
+    }
+}
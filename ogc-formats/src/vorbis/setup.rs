@@ -0,0 +1,344 @@
+//! The setup header: codebooks, floors, residues, mappings and modes.
+
+use alloc::{vec, vec::Vec};
+
+use super::{
+    bits::{BitReader, ilog},
+    codebook::Codebook,
+};
+use crate::FormatError;
+
+pub(super) struct Floor {
+    pub(super) partition_classes: Vec<u8>,
+    pub(super) classes: Vec<FloorClass>,
+    pub(super) multiplier: u8,
+    /// X position of each value, in the order they are decoded
+    pub(super) xs: Vec<u16>,
+    /// Indices of `xs` sorted by position
+    pub(super) sorted: Vec<u8>,
+    /// Closest lower and higher neighbours of each value among the ones decoded before it
+    pub(super) neighbours: Vec<(u8, u8)>,
+}
+
+pub(super) struct FloorClass {
+    pub(super) dimensions: u8,
+    pub(super) subclass_bits: u8,
+    pub(super) master_book: u8,
+    /// `None` for subclasses that are always zero
+    pub(super) subclass_books: Vec<Option<u8>>,
+}
+
+pub(super) struct Residue {
+    pub(super) kind: u8,
+    pub(super) begin: u32,
+    pub(super) end: u32,
+    pub(super) partition_size: u32,
+    pub(super) classifications: u8,
+    pub(super) class_book: u8,
+    /// The book of each pass for every classification
+    pub(super) books: Vec<[Option<u8>; 8]>,
+}
+
+pub(super) struct Mapping {
+    /// Magnitude and angle channels, undone in reverse order
+    pub(super) couplings: Vec<(u8, u8)>,
+    /// Submap of each channel
+    pub(super) mux: Vec<u8>,
+    /// Floor and residue of each submap
+    pub(super) submaps: Vec<(u8, u8)>,
+}
+
+pub(super) struct Mode {
+    pub(super) long_block: bool,
+    pub(super) mapping: u8,
+}
+
+pub(super) struct Setup {
+    pub(super) codebooks: Vec<Codebook>,
+    pub(super) floors: Vec<Floor>,
+    pub(super) residues: Vec<Residue>,
+    pub(super) mappings: Vec<Mapping>,
+    pub(super) modes: Vec<Mode>,
+}
+
+fn read(bits: &mut BitReader<'_>, count: u32) -> Result<u32, FormatError> {
+    bits.read(count).ok_or(FormatError::Truncated)
+}
+
+/// Read `count` bits that index into a list of `len` items.
+fn read_index(bits: &mut BitReader<'_>, count: u32, len: usize) -> Result<u8, FormatError> {
+    let index = read(bits, count)?;
+    if (index as usize) < len {
+        Ok(index as u8)
+    } else {
+        Err(FormatError::Invalid)
+    }
+}
+
+impl Setup {
+    /// Parse the setup header packet, after the packet type and `vorbis` magic.
+    pub(super) fn read(bits: &mut BitReader<'_>, channels: u8) -> Result<Self, FormatError> {
+        let count = read(bits, 8)? + 1;
+        let mut codebooks = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            codebooks.push(Codebook::read(bits)?);
+        }
+
+        // Time domain transforms are placeholders that must be zero.
+        for _ in 0..read(bits, 6)? + 1 {
+            if read(bits, 16)? != 0 {
+                return Err(FormatError::Unsupported);
+            }
+        }
+
+        let count = read(bits, 6)? + 1;
+        let mut floors = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            floors.push(Floor::read(bits, &codebooks)?);
+        }
+
+        let count = read(bits, 6)? + 1;
+        let mut residues = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            residues.push(Residue::read(bits, &codebooks)?);
+        }
+
+        let count = read(bits, 6)? + 1;
+        let mut mappings = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            mappings.push(Mapping::read(bits, channels, floors.len(), residues.len())?);
+        }
+
+        let count = read(bits, 6)? + 1;
+        let mut modes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let long_block = read(bits, 1)? != 0;
+            if read(bits, 16)? != 0 || read(bits, 16)? != 0 {
+                return Err(FormatError::Unsupported);
+            }
+            let mapping = read_index(bits, 8, mappings.len())?;
+            modes.push(Mode {
+                long_block,
+                mapping,
+            });
+        }
+
+        if read(bits, 1)? == 0 {
+            return Err(FormatError::Invalid);
+        }
+        Ok(Self {
+            codebooks,
+            floors,
+            residues,
+            mappings,
+            modes,
+        })
+    }
+}
+
+impl Floor {
+    fn read(bits: &mut BitReader<'_>, codebooks: &[Codebook]) -> Result<Self, FormatError> {
+        // Floor 0 was replaced by floor 1 before Vorbis 1.0 was released, no encoder uses it.
+        if read(bits, 16)? != 1 {
+            return Err(FormatError::Unsupported);
+        }
+
+        let partitions = read(bits, 5)?;
+        let mut partition_classes = Vec::with_capacity(partitions as usize);
+        for _ in 0..partitions {
+            partition_classes.push(read(bits, 4)? as u8);
+        }
+
+        let class_count = partition_classes.iter().max().map_or(0, |&max| max + 1);
+        let mut classes = Vec::with_capacity(usize::from(class_count));
+        for _ in 0..class_count {
+            let dimensions = read(bits, 3)? as u8 + 1;
+            let subclass_bits = read(bits, 2)? as u8;
+            let master_book = if subclass_bits != 0 {
+                read_index(bits, 8, codebooks.len())?
+            } else {
+                0
+            };
+            let mut subclass_books = Vec::with_capacity(1 << subclass_bits);
+            for _ in 0..1 << subclass_bits {
+                let book = read(bits, 8)?;
+                subclass_books.push(if book == 0 {
+                    None
+                } else if (book as usize - 1) < codebooks.len() {
+                    Some(book as u8 - 1)
+                } else {
+                    return Err(FormatError::Invalid);
+                });
+            }
+            classes.push(FloorClass {
+                dimensions,
+                subclass_bits,
+                master_book,
+                subclass_books,
+            });
+        }
+
+        let multiplier = read(bits, 2)? as u8 + 1;
+        let range_bits = read(bits, 4)? as u8;
+        let mut xs = vec![0, 1 << range_bits];
+        for &class in &partition_classes {
+            for _ in 0..classes[usize::from(class)].dimensions {
+                xs.push(read(bits, u32::from(range_bits))? as u16);
+            }
+        }
+        if xs.len() > 65 {
+            return Err(FormatError::Invalid);
+        }
+
+        let mut sorted: Vec<u8> = (0..xs.len() as u8).collect();
+        sorted.sort_by_key(|&i| xs[usize::from(i)]);
+        if sorted
+            .windows(2)
+            .any(|pair| xs[usize::from(pair[0])] == xs[usize::from(pair[1])])
+        {
+            return Err(FormatError::Invalid);
+        }
+
+        let mut neighbours = vec![(0, 0); xs.len()];
+        for i in 2..xs.len() {
+            let x = xs[i];
+            let mut low = 0;
+            let mut high = 1;
+            for (j, &other) in xs[..i].iter().enumerate() {
+                if other < x && other > xs[low] {
+                    low = j;
+                }
+                if other > x && other < xs[high] {
+                    high = j;
+                }
+            }
+            neighbours[i] = (low as u8, high as u8);
+        }
+
+        Ok(Self {
+            partition_classes,
+            classes,
+            multiplier,
+            xs,
+            sorted,
+            neighbours,
+        })
+    }
+}
+
+impl Residue {
+    fn read(bits: &mut BitReader<'_>, codebooks: &[Codebook]) -> Result<Self, FormatError> {
+        let kind = read(bits, 16)?;
+        if kind > 2 {
+            return Err(FormatError::Unsupported);
+        }
+        let begin = read(bits, 24)?;
+        let end = read(bits, 24)?;
+        let partition_size = read(bits, 24)? + 1;
+        let classifications = read(bits, 6)? as u8 + 1;
+        let class_book = read_index(bits, 8, codebooks.len())?;
+
+        let mut cascades = Vec::with_capacity(usize::from(classifications));
+        for _ in 0..classifications {
+            let low = read(bits, 3)?;
+            let high = if read(bits, 1)? != 0 {
+                read(bits, 5)?
+            } else {
+                0
+            };
+            cascades.push(high << 3 | low);
+        }
+
+        let mut books = Vec::with_capacity(cascades.len());
+        for cascade in cascades {
+            let mut passes = [None; 8];
+            for (pass, book) in passes.iter_mut().enumerate() {
+                if cascade & (1 << pass) != 0 {
+                    let index = read_index(bits, 8, codebooks.len())?;
+                    if !codebooks[usize::from(index)].has_vectors() {
+                        return Err(FormatError::Invalid);
+                    }
+                    *book = Some(index);
+                }
+            }
+            books.push(passes);
+        }
+
+        // Each codeword of the class book holds the classifications of `dimensions` partitions.
+        let class_codebook = &codebooks[usize::from(class_book)];
+        if class_codebook.dimensions == 0 {
+            return Err(FormatError::Invalid);
+        }
+
+        Ok(Self {
+            kind: kind as u8,
+            begin,
+            end,
+            partition_size,
+            classifications,
+            class_book,
+            books,
+        })
+    }
+}
+
+impl Mapping {
+    fn read(
+        bits: &mut BitReader<'_>,
+        channels: u8,
+        floors: usize,
+        residues: usize,
+    ) -> Result<Self, FormatError> {
+        if read(bits, 16)? != 0 {
+            return Err(FormatError::Unsupported);
+        }
+
+        let submap_count = if read(bits, 1)? != 0 {
+            read(bits, 4)? + 1
+        } else {
+            1
+        };
+
+        let mut couplings = Vec::new();
+        if read(bits, 1)? != 0 {
+            let channel_bits = ilog(u32::from(channels) - 1);
+            for _ in 0..read(bits, 8)? + 1 {
+                let magnitude = read_index(bits, channel_bits, usize::from(channels))?;
+                let angle = read_index(bits, channel_bits, usize::from(channels))?;
+                if magnitude == angle {
+                    return Err(FormatError::Invalid);
+                }
+                couplings.push((magnitude, angle));
+            }
+        }
+
+        if read(bits, 2)? != 0 {
+            return Err(FormatError::Invalid);
+        }
+
+        let mux = if submap_count > 1 {
+            let mut mux = Vec::with_capacity(usize::from(channels));
+            for _ in 0..channels {
+                mux.push(read_index(bits, 4, submap_count as usize)?);
+            }
+            mux
+        } else {
+            vec![0; usize::from(channels)]
+        };
+
+        let mut submaps = Vec::with_capacity(submap_count as usize);
+        for _ in 0..submap_count {
+            // Unused time configuration.
+            read(bits, 8)?;
+            let floor = read_index(bits, 8, floors)?;
+            let residue = read_index(bits, 8, residues)?;
+            submaps.push((floor, residue));
+        }
+
+        Ok(Self {
+            couplings,
+            mux,
+            submaps,
+        })
+    }
+}
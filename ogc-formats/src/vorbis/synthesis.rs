@@ -0,0 +1,644 @@
+//! Decoding audio packets: floors, residues, channel coupling and the inverse MDCT.
+
+use alloc::{vec, vec::Vec};
+use core::f32::consts::{FRAC_PI_2, PI};
+// The float methods of `std` shadow these in host tests.
+#[cfg_attr(test, allow(unused_imports))]
+use num_traits::Float;
+
+use super::{
+    bits::{BitReader, ilog},
+    codebook::Codebook,
+    setup::{Floor, Residue, Setup},
+};
+
+/// Ratio between neighbouring steps of the floor curve, about 0.55dB.
+const FLOOR_STEP: f32 = 0.062_961_3;
+/// Amplitude of floor value zero, floor value 255 is 1.0.
+const FLOOR_MIN: f32 = 1.064_986_3e-7;
+
+/// Turns blocks of packets into PCM.
+pub(super) struct Synthesis {
+    block_sizes: [usize; 2],
+    /// Rising half of the window for short and long overlaps
+    slopes: [Vec<f32>; 2],
+    imdct: [Imdct; 2],
+    /// Amplitude of each floor value
+    floor_table: [f32; 256],
+    /// Size of the previous block, `None` right after a reset
+    previous: Option<usize>,
+    /// Windowed second half of the previous block of each channel
+    overlap: Vec<Vec<f32>>,
+    spectra: Vec<Vec<f32>>,
+    blocks: Vec<Vec<f32>>,
+    floor_values: Vec<Vec<i32>>,
+    used: Vec<bool>,
+    residue_scratch: Vec<f32>,
+    classifications: Vec<Vec<u8>>,
+}
+
+impl Synthesis {
+    pub(super) fn new(channels: usize, block_sizes: [usize; 2]) -> Self {
+        let slope = |len: usize| {
+            (0..len)
+                .map(|i| {
+                    let x = ((i as f32 + 0.5) / len as f32 * FRAC_PI_2).sin();
+                    (FRAC_PI_2 * x * x).sin()
+                })
+                .collect()
+        };
+        let long = block_sizes[1];
+        let mut floor_table = [0.0; 256];
+        for (i, value) in floor_table.iter_mut().enumerate() {
+            *value = FLOOR_MIN * (FLOOR_STEP * i as f32).exp();
+        }
+        Self {
+            block_sizes,
+            slopes: [slope(block_sizes[0] / 2), slope(long / 2)],
+            imdct: [Imdct::new(block_sizes[0]), Imdct::new(long)],
+            floor_table,
+            previous: None,
+            overlap: vec![vec![0.0; long / 2]; channels],
+            spectra: vec![vec![0.0; long / 2]; channels],
+            blocks: vec![vec![0.0; long]; channels],
+            floor_values: vec![Vec::with_capacity(65); channels],
+            used: vec![false; channels],
+            residue_scratch: Vec::new(),
+            classifications: vec![Vec::new(); channels],
+        }
+    }
+
+    /// Forget the previous block, the next packet only primes the overlap.
+    pub(super) fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// Decode one audio packet and append its interleaved samples to `pcm`.
+    ///
+    /// Returns how many frames were added. Packets that are not audio or are cut off before the
+    /// floors are skipped.
+    pub(super) fn decode(&mut self, setup: &Setup, packet: &[u8], pcm: &mut Vec<i16>) -> usize {
+        let mut bits = BitReader::new(packet);
+        if bits.read(1) != Some(0) {
+            return 0;
+        }
+        let Some(mode) = bits
+            .read(ilog(setup.modes.len() as u32 - 1))
+            .and_then(|mode| setup.modes.get(mode as usize))
+        else {
+            return 0;
+        };
+        let n = self.block_sizes[usize::from(mode.long_block)];
+        let (previous_long, next_long) = if mode.long_block {
+            match (bits.read_bool(), bits.read_bool()) {
+                (Some(previous), Some(next)) => (previous, next),
+                _ => return 0,
+            }
+        } else {
+            (false, false)
+        };
+        let mapping = &setup.mappings[usize::from(mode.mapping)];
+        let half = n / 2;
+        let channels = self.spectra.len();
+
+        for channel in 0..channels {
+            let (floor, _) = mapping.submaps[usize::from(mapping.mux[channel])];
+            let values = &mut self.floor_values[channel];
+            self.used[channel] = decode_floor(
+                &setup.floors[usize::from(floor)],
+                &setup.codebooks,
+                &mut bits,
+                values,
+            )
+            .is_some();
+        }
+
+        // Coupled channels need the residue of both as soon as one of them has a floor.
+        let mut needs_residue = self.used.clone();
+        for &(magnitude, angle) in &mapping.couplings {
+            let (magnitude, angle) = (usize::from(magnitude), usize::from(angle));
+            if needs_residue[magnitude] || needs_residue[angle] {
+                needs_residue[magnitude] = true;
+                needs_residue[angle] = true;
+            }
+        }
+
+        for spectrum in &mut self.spectra {
+            spectrum[..half].fill(0.0);
+        }
+        for (submap, &(_, residue)) in mapping.submaps.iter().enumerate() {
+            let members: Vec<usize> = (0..channels)
+                .filter(|&channel| usize::from(mapping.mux[channel]) == submap)
+                .collect();
+            self.decode_residue(
+                &setup.residues[usize::from(residue)],
+                &setup.codebooks,
+                &mut bits,
+                &members,
+                &needs_residue,
+                half,
+            );
+        }
+
+        for &(magnitude, angle) in mapping.couplings.iter().rev() {
+            let (magnitude, angle) = (usize::from(magnitude), usize::from(angle));
+            let (low, high) = self.spectra.split_at_mut(magnitude.max(angle));
+            let (magnitudes, angles) = if magnitude < angle {
+                (&mut low[magnitude], &mut high[0])
+            } else {
+                (&mut high[0], &mut low[angle])
+            };
+            for (m, a) in magnitudes[..half].iter_mut().zip(&mut angles[..half]) {
+                let (new_m, new_a) = match (*m > 0.0, *a > 0.0) {
+                    (true, true) => (*m, *m - *a),
+                    (true, false) => (*m + *a, *m),
+                    (false, true) => (*m, *m + *a),
+                    (false, false) => (*m - *a, *m),
+                };
+                *m = new_m;
+                *a = new_a;
+            }
+        }
+
+        let long = usize::from(mode.long_block);
+        for channel in 0..channels {
+            let spectrum = &mut self.spectra[channel][..half];
+            if self.used[channel] {
+                let (floor, _) = mapping.submaps[usize::from(mapping.mux[channel])];
+                apply_floor(
+                    &setup.floors[usize::from(floor)],
+                    &self.floor_values[channel],
+                    &self.floor_table,
+                    spectrum,
+                );
+            } else {
+                spectrum.fill(0.0);
+            }
+
+            let block = &mut self.blocks[channel][..n];
+            self.imdct[long].inverse(spectrum, block);
+            apply_window(
+                block,
+                &self.slopes,
+                self.block_sizes[0],
+                [previous_long, mode.long_block, next_long],
+            );
+        }
+
+        let frames = match self.previous {
+            Some(previous) => {
+                let frames = previous / 4 + n / 4;
+                pcm.reserve(frames * channels);
+                for t in 0..frames {
+                    let current = (t + n / 4).checked_sub(previous / 4);
+                    for channel in 0..channels {
+                        let mut value = if t < previous / 2 {
+                            self.overlap[channel][t]
+                        } else {
+                            0.0
+                        };
+                        if let Some(current) = current.filter(|&i| i < n) {
+                            value += self.blocks[channel][current];
+                        }
+                        pcm.push((value * 32768.0).round().clamp(-32768.0, 32767.0) as i16);
+                    }
+                }
+                frames
+            }
+            None => 0,
+        };
+
+        for channel in 0..channels {
+            self.overlap[channel][..half].copy_from_slice(&self.blocks[channel][half..n]);
+        }
+        self.previous = Some(n);
+        frames
+    }
+
+    fn decode_residue(
+        &mut self,
+        residue: &Residue,
+        codebooks: &[Codebook],
+        bits: &mut BitReader<'_>,
+        members: &[usize],
+        needs_residue: &[bool],
+        half: usize,
+    ) {
+        if residue.kind == 2 {
+            if members.iter().all(|&channel| !needs_residue[channel]) {
+                return;
+            }
+            let mut vector = core::mem::take(&mut self.residue_scratch);
+            vector.clear();
+            vector.resize(half * members.len(), 0.0);
+            decode_partitions(
+                residue,
+                codebooks,
+                bits,
+                &mut [vector.as_mut_slice()],
+                &mut self.classifications[..1],
+            );
+            for (i, frame) in vector.chunks_exact(members.len()).enumerate() {
+                for (&channel, &value) in members.iter().zip(frame) {
+                    self.spectra[channel][i] = value;
+                }
+            }
+            self.residue_scratch = vector;
+        } else {
+            let mut vectors: Vec<&mut [f32]> = Vec::with_capacity(members.len());
+            for (channel, spectrum) in self.spectra.iter_mut().enumerate() {
+                if members.contains(&channel) && needs_residue[channel] {
+                    vectors.push(&mut spectrum[..half]);
+                }
+            }
+            let count = vectors.len();
+            decode_partitions(
+                residue,
+                codebooks,
+                bits,
+                &mut vectors,
+                &mut self.classifications[..count],
+            );
+        }
+    }
+}
+
+/// Multiply `block` by its window, the slopes depend on the size of the neighbouring blocks.
+///
+/// `long` holds whether the previous, this and the next block are long.
+fn apply_window(block: &mut [f32], slopes: &[Vec<f32>; 2], short: usize, long: [bool; 3]) {
+    let n = block.len();
+    let [previous_long, current_long, next_long] = long;
+    let (left_slope, left_start) = if current_long && !previous_long {
+        (&slopes[0], n / 4 - short / 4)
+    } else {
+        (&slopes[usize::from(current_long)], 0)
+    };
+    let (right_slope, right_start) = if current_long && !next_long {
+        (&slopes[0], n * 3 / 4 - short / 4)
+    } else {
+        (&slopes[usize::from(current_long)], n / 2)
+    };
+
+    block[..left_start].fill(0.0);
+    for (sample, weight) in block[left_start..].iter_mut().zip(left_slope) {
+        *sample *= weight;
+    }
+    let right_end = right_start + right_slope.len();
+    for (sample, weight) in block[right_start..right_end]
+        .iter_mut()
+        .zip(right_slope.iter().rev())
+    {
+        *sample *= weight;
+    }
+    block[right_end..].fill(0.0);
+}
+
+/// Read the floor 1 values of a channel into `values`.
+///
+/// `None` if the channel is unused in this packet, either by its flag or because the packet
+/// ends before the values.
+fn decode_floor(
+    floor: &Floor,
+    codebooks: &[Codebook],
+    bits: &mut BitReader<'_>,
+    values: &mut Vec<i32>,
+) -> Option<()> {
+    if !bits.read_bool()? {
+        return None;
+    }
+
+    let range = [256, 128, 86, 64][usize::from(floor.multiplier - 1)];
+    let value_bits = ilog(range - 1);
+    values.clear();
+    values.push(bits.read(value_bits)? as i32);
+    values.push(bits.read(value_bits)? as i32);
+
+    for &class in &floor.partition_classes {
+        let class = &floor.classes[usize::from(class)];
+        let mask = (1 << class.subclass_bits) - 1;
+        let mut subclasses = if class.subclass_bits > 0 {
+            codebooks[usize::from(class.master_book)].decode(bits)?
+        } else {
+            0
+        };
+        for _ in 0..class.dimensions {
+            let book = class.subclass_books[(subclasses & mask) as usize];
+            subclasses >>= class.subclass_bits;
+            values.push(match book {
+                Some(book) => codebooks[usize::from(book)].decode(bits)? as i32,
+                None => 0,
+            });
+        }
+    }
+    Some(())
+}
+
+/// Predict the value at `x` on the line through two points.
+fn render_point(x0: i32, y0: i32, x1: i32, y1: i32, x: i32) -> i32 {
+    let dy = y1 - y0;
+    let offset = dy.abs() * (x - x0) / (x1 - x0);
+    if dy < 0 { y0 - offset } else { y0 + offset }
+}
+
+/// Multiply `spectrum` by the floor curve described by the decoded `values`.
+fn apply_floor(floor: &Floor, values: &[i32], table: &[f32; 256], spectrum: &mut [f32]) {
+    let range = [256, 128, 86, 64][usize::from(floor.multiplier - 1)];
+    let count = floor.xs.len();
+
+    // Turn the values into offsets from the line through their neighbours.
+    let mut final_values = [0i32; 65];
+    let mut active = [false; 65];
+    final_values[0] = values[0];
+    final_values[1] = values[1];
+    active[0] = true;
+    active[1] = true;
+    for i in 2..count {
+        let (low, high) = floor.neighbours[i];
+        let (low, high) = (usize::from(low), usize::from(high));
+        let predicted = render_point(
+            i32::from(floor.xs[low]),
+            final_values[low],
+            i32::from(floor.xs[high]),
+            final_values[high],
+            i32::from(floor.xs[i]),
+        );
+        let value = values[i];
+        let high_room = range - predicted;
+        let low_room = predicted;
+        let room = high_room.min(low_room) * 2;
+        if value == 0 {
+            final_values[i] = predicted;
+            continue;
+        }
+        active[low] = true;
+        active[high] = true;
+        active[i] = true;
+        final_values[i] = if value >= room {
+            if high_room > low_room {
+                value - low_room + predicted
+            } else {
+                predicted - value + high_room - 1
+            }
+        } else if value % 2 == 1 {
+            predicted - (value + 1) / 2
+        } else {
+            predicted + value / 2
+        };
+    }
+
+    let multiplier = i32::from(floor.multiplier);
+    let mut lx = 0;
+    let mut ly = final_values[usize::from(floor.sorted[0])] * multiplier;
+    let mut hx = 0;
+    let mut hy = 0;
+    for &i in &floor.sorted[1..] {
+        let i = usize::from(i);
+        if active[i] {
+            hx = i32::from(floor.xs[i]);
+            hy = final_values[i] * multiplier;
+            render_line(lx, ly, hx, hy, table, spectrum);
+            lx = hx;
+            ly = hy;
+        }
+    }
+    let len = spectrum.len() as i32;
+    if hx < len {
+        render_line(hx, hy, len, hy, table, spectrum);
+    }
+}
+
+/// Multiply `spectrum` by the floor line from `(x0, y0)` up to but not including `x1`.
+fn render_line(x0: i32, y0: i32, x1: i32, y1: i32, table: &[f32; 256], spectrum: &mut [f32]) {
+    let dy = y1 - y0;
+    let adx = x1 - x0;
+    if adx <= 0 {
+        return;
+    }
+    let base = dy / adx;
+    let step = if dy < 0 { base - 1 } else { base + 1 };
+    let ady = dy.abs() - base.abs() * adx;
+    let mut y = y0;
+    let mut err = 0;
+    let end = x1.min(spectrum.len() as i32);
+    for x in x0..end {
+        if x > x0 {
+            err += ady;
+            if err >= adx {
+                err -= adx;
+                y += step;
+            } else {
+                y += base;
+            }
+        }
+        spectrum[x as usize] *= table[y.clamp(0, 255) as usize];
+    }
+}
+
+/// Decode the partitions of a residue into `vectors`, one per channel that is decoded.
+///
+/// Decoding stops quietly at the end of the packet.
+fn decode_partitions(
+    residue: &Residue,
+    codebooks: &[Codebook],
+    bits: &mut BitReader<'_>,
+    vectors: &mut [&mut [f32]],
+    classifications: &mut [Vec<u8>],
+) {
+    let Some(len) = vectors.first().map(|vector| vector.len()) else {
+        return;
+    };
+    let begin = (residue.begin as usize).min(len);
+    let end = (residue.end as usize).min(len);
+    let partition_size = residue.partition_size as usize;
+    let partitions = (end - begin) / partition_size;
+    if partitions == 0 {
+        return;
+    }
+
+    let class_book = &codebooks[usize::from(residue.class_book)];
+    let per_codeword = class_book.dimensions;
+    let classes = u32::from(residue.classifications);
+    for list in classifications.iter_mut() {
+        list.clear();
+        list.resize(partitions + per_codeword, 0);
+    }
+
+    for pass in 0..8 {
+        let mut partition = 0;
+        while partition < partitions {
+            if pass == 0 {
+                for list in classifications.iter_mut() {
+                    let Some(mut value) = class_book.decode(bits) else {
+                        return;
+                    };
+                    for i in (0..per_codeword).rev() {
+                        list[partition + i] = (value % classes) as u8;
+                        value /= classes;
+                    }
+                }
+            }
+
+            for _ in 0..per_codeword {
+                if partition >= partitions {
+                    break;
+                }
+                let offset = begin + partition * partition_size;
+                for (vector, list) in vectors.iter_mut().zip(classifications.iter()) {
+                    let class = usize::from(list[partition]);
+                    let Some(book) = residue.books[class][pass] else {
+                        continue;
+                    };
+                    let book = &codebooks[usize::from(book)];
+                    let target = &mut vector[offset..offset + partition_size];
+                    let complete = if residue.kind == 0 {
+                        decode_interleaved(book, bits, target)
+                    } else {
+                        decode_sequential(book, bits, target)
+                    };
+                    if complete.is_none() {
+                        return;
+                    }
+                }
+                partition += 1;
+            }
+        }
+    }
+}
+
+/// Residue 0 partitions: the values of each vector are spread across the partition.
+fn decode_interleaved(book: &Codebook, bits: &mut BitReader<'_>, target: &mut [f32]) -> Option<()> {
+    let step = target.len() / book.dimensions;
+    for i in 0..step {
+        let entry = book.decode_vector(bits)?;
+        for (j, value) in entry.iter().enumerate() {
+            target[i + j * step] += value;
+        }
+    }
+    Some(())
+}
+
+/// Residue 1 and 2 partitions: the values of each vector are next to each other.
+fn decode_sequential(book: &Codebook, bits: &mut BitReader<'_>, target: &mut [f32]) -> Option<()> {
+    let mut i = 0;
+    while i < target.len() {
+        let entry = book.decode_vector(bits)?;
+        for (sample, value) in target[i..].iter_mut().zip(entry) {
+            *sample += value;
+        }
+        i += entry.len();
+    }
+    Some(())
+}
+
+#[derive(Copy, Clone, Default)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    fn from_angle(angle: f32) -> Self {
+        Self {
+            re: angle.cos(),
+            im: angle.sin(),
+        }
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Self {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
+
+/// Inverse MDCT of one block size, through a complex FFT of a quarter of its size.
+struct Imdct {
+    /// Twiddles applied before the FFT
+    pre: Vec<Complex>,
+    /// Twiddles applied after the FFT
+    post: Vec<Complex>,
+    /// Roots of unity of the FFT
+    roots: Vec<Complex>,
+    bit_reverse: Vec<u16>,
+    buffer: Vec<Complex>,
+    dct: Vec<f32>,
+}
+
+impl Imdct {
+    fn new(n: usize) -> Self {
+        let quarter = n / 4;
+        let theta = 2.0 * PI / n as f32;
+        let bits = quarter.trailing_zeros();
+        Self {
+            pre: (0..quarter)
+                .map(|p| Complex::from_angle(-theta * p as f32))
+                .collect(),
+            post: (0..quarter)
+                .map(|j| Complex::from_angle(-theta * (j as f32 + 0.25)))
+                .collect(),
+            roots: (0..quarter / 2)
+                .map(|k| Complex::from_angle(-2.0 * PI * k as f32 / quarter as f32))
+                .collect(),
+            bit_reverse: (0..quarter)
+                .map(|i| (i.reverse_bits() >> (usize::BITS - bits)) as u16)
+                .collect(),
+            buffer: vec![Complex::default(); quarter],
+            dct: vec![0.0; n / 2],
+        }
+    }
+
+    /// Transform the `n / 2` coefficients of `input` into the `n` samples of `output`.
+    fn inverse(&mut self, input: &[f32], output: &mut [f32]) {
+        let half = input.len();
+        let quarter = half / 2;
+
+        // A DCT-IV of the coefficients, the even and odd ones paired up into complex values.
+        for p in 0..quarter {
+            let value = Complex {
+                re: input[2 * p],
+                im: input[half - 1 - 2 * p],
+            };
+            self.buffer[usize::from(self.bit_reverse[p])] = value.mul(self.pre[p]);
+        }
+
+        let mut size = 2;
+        while size <= quarter {
+            let stride = quarter / size;
+            for start in (0..quarter).step_by(size) {
+                for k in 0..size / 2 {
+                    let a = self.buffer[start + k];
+                    let b = self.buffer[start + k + size / 2].mul(self.roots[k * stride]);
+                    self.buffer[start + k] = Complex {
+                        re: a.re + b.re,
+                        im: a.im + b.im,
+                    };
+                    self.buffer[start + k + size / 2] = Complex {
+                        re: a.re - b.re,
+                        im: a.im - b.im,
+                    };
+                }
+            }
+            size *= 2;
+        }
+
+        for j in 0..quarter {
+            let value = self.buffer[j].mul(self.post[j]);
+            self.dct[2 * j] = value.re;
+            self.dct[half - 1 - 2 * j] = -value.im;
+        }
+
+        // The DCT-IV is the middle of the output, the rest follows from its symmetry.
+        let (head, tail) = output.split_at_mut(half);
+        let (first, second) = head.split_at_mut(quarter);
+        let (third, fourth) = tail.split_at_mut(quarter);
+        first.copy_from_slice(&self.dct[quarter..]);
+        for (i, sample) in second.iter_mut().chain(third.iter_mut()).enumerate() {
+            *sample = -self.dct[half - 1 - i];
+        }
+        for (sample, &value) in fourth.iter_mut().zip(&self.dct[..quarter]) {
+            *sample = -value;
+        }
+    }
+}
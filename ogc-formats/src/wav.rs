@@ -0,0 +1,385 @@
+//! RIFF WAV files holding 8 or 16-bit PCM.
+
+use alloc::vec::Vec;
+
+use crate::{FormatError, mixer::SampleSource};
+
+/// Wave format tag for integer PCM.
+const FORMAT_PCM: u16 = 1;
+/// Wave format tag for `WAVE_FORMAT_EXTENSIBLE`, the real tag is in the sub format.
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, FormatError> {
+    let bytes = data.get(offset..offset + 2).ok_or(FormatError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, FormatError> {
+    let bytes = data.get(offset..offset + 4).ok_or(FormatError::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// A parsed WAV file borrowing its samples from the file.
+///
+/// # Examples
+///
+/// ```rust
+/// use ogc_formats::{
+///     mixer::{Mixer, PlayOptions},
+///     wav::Wav,
+/// };
+///
+/// let wav = Wav::parse(include_bytes!("../testdata/pluck-pcm16.wav"))?;
+/// let mut mixer = Mixer::new(8);
+/// mixer.play(wav.source(), PlayOptions::new());
+/// # Ok::<(), ogc_formats::FormatError>(())
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Wav<'a> {
+    channels: u8,
+    sample_rate: u32,
+    bits_per_sample: u8,
+    data: &'a [u8],
+    loop_points: Option<(u32, u32)>,
+}
+
+impl<'a> Wav<'a> {
+    /// Parse the headers of a WAV file.
+    ///
+    /// Only 8-bit unsigned and 16-bit signed PCM with one or two channels is supported.
+    pub fn parse(file: &'a [u8]) -> Result<Self, FormatError> {
+        if file.len() < 12 {
+            return Err(FormatError::Truncated);
+        }
+        if &file[0..4] != b"RIFF" || &file[8..12] != b"WAVE" {
+            return Err(FormatError::InvalidMagic);
+        }
+
+        let mut format = None;
+        let mut data = None;
+        let mut loop_points = None;
+        let mut offset = 12;
+        while offset + 8 <= file.len() {
+            let id = &file[offset..offset + 4];
+            let len = read_u32(file, offset + 4)? as usize;
+            let body = offset + 8;
+            // Writers are allowed to leave the last chunk short.
+            let chunk = &file[body..file.len().min(body.saturating_add(len))];
+
+            match id {
+                b"fmt " => {
+                    let mut tag = read_u16(chunk, 0)?;
+                    if tag == FORMAT_EXTENSIBLE {
+                        tag = read_u16(chunk, 24)?;
+                    }
+                    let channels = read_u16(chunk, 2)?;
+                    let sample_rate = read_u32(chunk, 4)?;
+                    let bits_per_sample = read_u16(chunk, 14)?;
+                    format = Some((tag, channels, sample_rate, bits_per_sample));
+                }
+                b"data" => data = Some(chunk),
+                // Only the first loop is used.
+                b"smpl" if read_u32(chunk, 28)? > 0 => {
+                    loop_points = Some((read_u32(chunk, 44)?, read_u32(chunk, 48)?));
+                }
+                _ => {}
+            }
+
+            // Chunks are padded to an even length.
+            offset = body.saturating_add(len).saturating_add(len & 1);
+        }
+
+        let (tag, channels, sample_rate, bits_per_sample) = format.ok_or(FormatError::Truncated)?;
+        let data = data.ok_or(FormatError::Truncated)?;
+        if tag != FORMAT_PCM || !matches!(bits_per_sample, 8 | 16) {
+            return Err(FormatError::Unsupported);
+        }
+        if !matches!(channels, 1 | 2) {
+            return Err(FormatError::Unsupported);
+        }
+        if sample_rate == 0 {
+            return Err(FormatError::Invalid);
+        }
+
+        let wav = Self {
+            channels: channels as u8,
+            sample_rate,
+            bits_per_sample: bits_per_sample as u8,
+            data,
+            loop_points: None,
+        };
+        let frames = wav.frames() as u32;
+        Ok(Self {
+            loop_points: loop_points.filter(|&(start, end)| start <= end && end < frames),
+            ..wav
+        })
+    }
+
+    /// Number of channels, `1` or `2`.
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// Sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Bits per sample, `8` or `16`.
+    pub fn bits_per_sample(&self) -> u8 {
+        self.bits_per_sample
+    }
+
+    /// Number of samples in each channel.
+    pub fn frames(&self) -> usize {
+        self.data.len() / self.frame_bytes()
+    }
+
+    fn frame_bytes(&self) -> usize {
+        usize::from(self.channels) * usize::from(self.bits_per_sample / 8)
+    }
+
+    /// First and last frame of the loop from the `smpl` chunk, both inclusive.
+    pub fn loop_points(&self) -> Option<(u32, u32)> {
+        self.loop_points
+    }
+
+    /// The raw samples, whole frames only.
+    ///
+    /// 8-bit samples are unsigned and 16-bit samples are little endian, these can be handed to
+    /// `ASND` as is with the ``VoiceFormat`` ``ogc-rs`` converts a [`Wav`] to.
+    pub fn data(&self) -> &'a [u8] {
+        &self.data[..self.frames() * self.frame_bytes()]
+    }
+
+    fn sample(&self, index: usize) -> i16 {
+        if self.bits_per_sample == 8 {
+            (i16::from(self.data[index]) - 128) << 8
+        } else {
+            i16::from_le_bytes([self.data[index * 2], self.data[index * 2 + 1]])
+        }
+    }
+
+    /// Iterate over the interleaved samples as 16-bit signed PCM.
+    pub fn samples(&self) -> impl Iterator<Item = i16> + 'a {
+        let wav = *self;
+        (0..self.frames() * usize::from(self.channels)).map(move |i| wav.sample(i))
+    }
+
+    /// Decode every sample to interleaved 16-bit signed PCM.
+    pub fn decode(&self) -> Vec<i16> {
+        self.samples().collect()
+    }
+
+    /// A [`SampleSource`] playing this file.
+    ///
+    /// If the file has loop points the source ends after the loop end and rewinds to the loop
+    /// start.
+    pub fn source(&self) -> WavSource<'a> {
+        WavSource {
+            wav: *self,
+            position: 0,
+        }
+    }
+}
+
+/// A [`SampleSource`] decoding a [`Wav`], see [`Wav::source`].
+#[derive(Clone, Debug)]
+pub struct WavSource<'a> {
+    wav: Wav<'a>,
+    /// Next sample, counted in samples rather than frames
+    position: usize,
+}
+
+impl WavSource<'_> {
+    fn end(&self) -> usize {
+        let frames = match self.wav.loop_points {
+            Some((_, end)) => end as usize + 1,
+            None => self.wav.frames(),
+        };
+        frames * usize::from(self.wav.channels)
+    }
+}
+
+impl SampleSource for WavSource<'_> {
+    fn sample_rate(&self) -> u32 {
+        self.wav.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        self.wav.channels
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        let len = buf.len().min(self.end().saturating_sub(self.position));
+        for (i, sample) in buf[..len].iter_mut().enumerate() {
+            *sample = self.wav.sample(self.position + i);
+        }
+        self.position += len;
+        len
+    }
+
+    fn rewind(&mut self) -> bool {
+        let start = self.wav.loop_points.map_or(0, |(start, _)| start as usize);
+        self.position = start * usize::from(self.wav.channels);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// A 22050 Hz file with a `fmt ` chunk, an odd sized chunk to skip, an optional `smpl`
+    /// chunk and `data`.
+    fn wav(
+        tag: u16,
+        channels: u16,
+        bits: u16,
+        data: &[u8],
+        loop_points: Option<(u32, u32)>,
+    ) -> Vec<u8> {
+        let mut file = Vec::from(&b"RIFF\0\0\0\0WAVEfmt "[..]);
+        file.extend(16u32.to_le_bytes());
+        file.extend(tag.to_le_bytes());
+        file.extend(channels.to_le_bytes());
+        file.extend(22050u32.to_le_bytes());
+        file.extend([0; 6]);
+        file.extend(bits.to_le_bytes());
+        file.extend(b"LIST\x03\0\0\0abc\0");
+        if let Some((start, end)) = loop_points {
+            let mut smpl = [0; 60];
+            smpl[28..32].copy_from_slice(&1u32.to_le_bytes());
+            smpl[44..48].copy_from_slice(&start.to_le_bytes());
+            smpl[48..52].copy_from_slice(&end.to_le_bytes());
+            file.extend(b"smpl");
+            file.extend(60u32.to_le_bytes());
+            file.extend(smpl);
+        }
+        file.extend(b"data");
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend(data);
+        file
+    }
+
+    #[test]
+    fn pcm16() {
+        let data: Vec<u8> = [1i16, -2, 300, -400, 5]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let file = wav(FORMAT_PCM, 2, 16, &data, None);
+        let wav = Wav::parse(&file).unwrap();
+        assert_eq!(wav.channels(), 2);
+        assert_eq!(wav.sample_rate(), 22050);
+        assert_eq!(wav.bits_per_sample(), 16);
+        // The trailing half frame is dropped.
+        assert_eq!(wav.frames(), 2);
+        assert_eq!(wav.data(), &data[..8]);
+        assert_eq!(wav.decode(), [1, -2, 300, -400]);
+        assert_eq!(wav.loop_points(), None);
+    }
+
+    #[test]
+    fn pcm8_loop() {
+        let file = wav(FORMAT_PCM, 1, 8, &[128, 255, 0, 129, 130], Some((1, 3)));
+        let wav = Wav::parse(&file).unwrap();
+        assert_eq!(wav.decode(), [0, 32512, -32768, 256, 512]);
+        assert_eq!(wav.loop_points(), Some((1, 3)));
+
+        let mut source = wav.source();
+        let mut buf = [0; 8];
+        assert_eq!(source.read(&mut buf), 4);
+        assert!(source.rewind());
+        assert_eq!(source.read(&mut buf), 3);
+        assert_eq!(buf[..3], [32512, -32768, 256]);
+
+        // Loop points past the end are ignored.
+        let file = self::wav(FORMAT_PCM, 1, 8, &[128; 4], Some((1, 4)));
+        assert_eq!(Wav::parse(&file).unwrap().loop_points(), None);
+        let file = self::wav(FORMAT_PCM, 1, 8, &[128; 4], Some((3, 2)));
+        assert_eq!(Wav::parse(&file).unwrap().loop_points(), None);
+    }
+
+    #[test]
+    fn extensible() {
+        let mut file = wav(FORMAT_EXTENSIBLE, 1, 16, &[1, 0], None);
+        // Grow `fmt ` to the extensible size with the PCM sub format.
+        let mut extension = vec![0; 24];
+        extension[8..10].copy_from_slice(&FORMAT_PCM.to_le_bytes());
+        file[16..20].copy_from_slice(&40u32.to_le_bytes());
+        file.splice(36..36, extension);
+        assert_eq!(Wav::parse(&file).unwrap().decode(), [1]);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            Wav::parse(&wav(3, 1, 32, &[0; 4], None)),
+            Err(FormatError::Unsupported)
+        );
+        assert_eq!(
+            Wav::parse(&wav(FORMAT_PCM, 1, 24, &[0; 6], None)),
+            Err(FormatError::Unsupported)
+        );
+        assert_eq!(
+            Wav::parse(&wav(FORMAT_PCM, 3, 16, &[0; 6], None)),
+            Err(FormatError::Unsupported)
+        );
+
+        let mut file = wav(FORMAT_PCM, 1, 16, &[0; 4], None);
+        file[24..28].fill(0);
+        assert_eq!(Wav::parse(&file), Err(FormatError::Invalid));
+
+        let mut file = wav(FORMAT_PCM, 1, 16, &[0; 4], None);
+        file[8..12].copy_from_slice(b"AVI ");
+        assert_eq!(Wav::parse(&file), Err(FormatError::InvalidMagic));
+        assert_eq!(Wav::parse(b"RIFX"), Err(FormatError::Truncated));
+
+        // A huge chunk length must not overflow or read out of bounds.
+        let mut file = wav(FORMAT_PCM, 1, 16, &[0; 4], None);
+        file[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(Wav::parse(&file), Err(FormatError::Truncated));
+    }
+
+    #[test]
+    fn truncated() {
+        let file = wav(FORMAT_PCM, 2, 16, &[0; 16], Some((0, 1)));
+        let data = file.len() - 16;
+        for len in 0..data {
+            assert!(Wav::parse(&file[..len]).is_err(), "{len} bytes parsed");
+        }
+        // A short `data` chunk keeps the frames that are there.
+        for len in data..file.len() {
+            assert_eq!(Wav::parse(&file[..len]).unwrap().frames(), (len - data) / 4);
+        }
+    }
+
+    #[test]
+    fn pluck() {
+        // Stereo at 11025 Hz with a `LIST` chunk in front of `data`.
+        let file = include_bytes!("../testdata/pluck-pcm16.wav");
+        let wav = Wav::parse(file).unwrap();
+        assert_eq!(wav.channels(), 2);
+        assert_eq!(wav.sample_rate(), 11025);
+        assert_eq!(wav.bits_per_sample(), 16);
+        assert_eq!(wav.frames(), 3307);
+        let data = &file[file.len() - 3307 * 4..];
+        assert_eq!(wav.data(), data);
+        let samples: Vec<i16> = data
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(wav.decode(), samples);
+
+        let wav = Wav::parse(include_bytes!("../testdata/pluck-pcm8.wav")).unwrap();
+        assert_eq!(wav.bits_per_sample(), 8);
+        assert_eq!(wav.frames(), 3307);
+        let samples: Vec<i16> = include_bytes!("../testdata/pluck-pcm8.pcm")
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+            .collect();
+        assert_eq!(wav.decode(), samples);
+    }
+}
@@ -0,0 +1,406 @@
+#!/usr/bin/env python3
+"""Build the stream fixtures in this directory from pluck-pcm16.wav.
+
+pluck-pcm16.wav and pluck-pcm8.wav are the sample files of the CPython test suite
+(Lib/test/audiodata), recorded audio rather than synthetic ramps.
+
+No BRSTM, BCSTM or BNS encoder is available outside of game SDKs and Windows tools, so this
+script writes them itself following the layouts documented by vgmstream and wiibrew. It does not
+share any code with the Rust parsers: the DSP-ADPCM encoder and decoder and every header are
+written from the documentation, and the decoder here produces the expected PCM the tests compare
+against.
+
+    pluck.brstm  stereo, 2 blocks of 0x2000 bytes, ADPC seek table, loop start inside a frame
+    pluck.bcstm  stereo, little endian, SEEK block, frame aligned loop start
+    pluck.bns    stereo banner sound, frame aligned loop start
+    pluck.pcm    the decoded ADPCM of pluck.brstm and pluck.bcstm, interleaved 16-bit LE
+    pluck-bns.pcm  the decoded ADPCM of pluck.bns
+    pluck-pcm8.pcm pluck-pcm8.wav as 16-bit LE, (byte - 128) << 8
+
+Run it from this directory, the output is deterministic.
+"""
+
+import struct
+import wave
+
+FRAME_SAMPLES = 14
+FRAME_BYTES = 8
+BLOCK_BYTES = 0x2000
+BLOCK_SAMPLES = BLOCK_BYTES // FRAME_BYTES * FRAME_SAMPLES
+
+# Eight predictors in 5.11 fixed point, from silence to a strong second order predictor.
+COEFFICIENTS = [
+    (0, 0),
+    (2048, 0),
+    (4096, -2048),
+    (3584, -1536),
+    (3072, -1024),
+    (1536, 0),
+    (3840, -1792),
+    (1024, 0),
+]
+
+
+def clamp16(value):
+    return max(-32768, min(32767, value))
+
+
+def predict(nibble, scale, c1, c2, hist1, hist2):
+    return clamp16(((nibble << scale) * 2048 + 1024 + c1 * hist1 + c2 * hist2) >> 11)
+
+
+def encode(samples):
+    """Encode a channel, trying every predictor and scale for each frame.
+
+    Returns the frames and the decoded samples.
+    """
+    data = bytearray()
+    decoded = []
+    hist1 = hist2 = 0
+    for start in range(0, len(samples), FRAME_SAMPLES):
+        frame = samples[start : start + FRAME_SAMPLES]
+        frame += [0] * (FRAME_SAMPLES - len(frame))
+        best = None
+        for predictor, (c1, c2) in enumerate(COEFFICIENTS):
+            for scale in range(12):
+                h1, h2 = hist1, hist2
+                nibbles, out, error = [], [], 0
+                for sample in frame:
+                    guess = (c1 * h1 + c2 * h2 + 1024) >> 11
+                    nibble = round((sample - guess) / (1 << scale))
+                    nibble = max(-8, min(7, nibble))
+                    value = predict(nibble, scale, c1, c2, h1, h2)
+                    error += (value - sample) ** 2
+                    nibbles.append(nibble & 0xF)
+                    out.append(value)
+                    h1, h2 = value, h1
+                if best is None or error < best[0]:
+                    best = (error, predictor, scale, nibbles, out)
+        _, predictor, scale, nibbles, out = best
+        data.append(predictor << 4 | scale)
+        for i in range(0, FRAME_SAMPLES, 2):
+            data.append(nibbles[i] << 4 | nibbles[i + 1])
+        decoded += out
+        hist1, hist2 = out[-1], out[-2]
+    return bytes(data), decoded[: len(samples)]
+
+
+def decode(data, count):
+    """Decode a channel from its first sample, independently of `encode`."""
+    out = []
+    hist1 = hist2 = 0
+    for start in range(0, len(data), FRAME_BYTES):
+        header = data[start]
+        c1, c2 = COEFFICIENTS[(header >> 4) & 7]
+        scale = header & 0xF
+        for i in range(FRAME_SAMPLES):
+            byte = data[start + 1 + i // 2]
+            nibble = byte >> 4 if i % 2 == 0 else byte & 0xF
+            if nibble >= 8:
+                nibble -= 16
+            value = predict(nibble, scale, c1, c2, hist1, hist2)
+            out.append(value)
+            hist1, hist2 = value, hist1
+    return out[:count]
+
+
+class Channel:
+    def __init__(self, samples, loop_start):
+        self.data, decoded = encode(samples)
+        self.pcm = decode(self.data, len(samples))
+        assert self.pcm == decoded
+        self.count = len(samples)
+        self.loop_start = loop_start
+
+    def context(self, sample):
+        """Header byte, hist1 and hist2 before `sample`."""
+        header = self.data[sample // FRAME_SAMPLES * FRAME_BYTES] if sample < self.count else 0
+        hist1 = self.pcm[sample - 1] if sample >= 1 else 0
+        hist2 = self.pcm[sample - 2] if sample >= 2 else 0
+        return header, hist1, hist2
+
+    def adpcm_info(self, endian, gain):
+        """The DSP-ADPCM info block, with the gain and 16-bit header of the Wii formats or the
+        8-bit header and padding of the 3DS and Wii U ones."""
+        out = b"".join(struct.pack(endian + "hh", *pair) for pair in COEFFICIENTS)
+        if gain:
+            out += struct.pack(endian + "H", 0)
+        for header, hist1, hist2 in (self.context(0), self.context(self.loop_start)):
+            if gain:
+                out += struct.pack(endian + "Hhh", header, hist1, hist2)
+            else:
+                out += struct.pack(endian + "BBhh", header, 0, hist1, hist2)
+        return out + b"\0\0"
+
+    def block(self, index):
+        return self.data[index * BLOCK_BYTES : (index + 1) * BLOCK_BYTES]
+
+    def seek_entries(self):
+        """hist1 and hist2 at the start of every block."""
+        blocks = -(-self.count // BLOCK_SAMPLES)
+        return [self.context(i * BLOCK_SAMPLES)[1:] for i in range(blocks)]
+
+
+def pad(data, align):
+    return data + b"\0" * (-len(data) % align)
+
+
+def chunk(magic, body, align):
+    body = pad(body, align) if align else body
+    return magic + struct.pack(">I", len(body) + 8) + body
+
+
+def interleave(channels):
+    """Blocks of every channel in turn, the final block padded to 32 bytes."""
+    blocks = -(-channels[0].count // BLOCK_SAMPLES)
+    out = b""
+    for i in range(blocks):
+        for channel in channels:
+            out += pad(channel.block(i), 0x20)
+    return out, blocks
+
+
+def block_layout(channels):
+    count = channels[0].count
+    blocks = -(-count // BLOCK_SAMPLES)
+    final_samples = count - (blocks - 1) * BLOCK_SAMPLES
+    final_bytes = -(-final_samples // FRAME_SAMPLES) * FRAME_BYTES
+    return blocks, final_samples, final_bytes, final_bytes + (-final_bytes % 0x20)
+
+
+def brstm(channels, rate):
+    count, loop_start = channels[0].count, channels[0].loop_start
+    blocks, final_samples, final_bytes, final_padded = block_layout(channels)
+
+    # HEAD: three references to the stream info, track table and channel table.
+    info_offset = 0x18
+    info = struct.pack(
+        ">BBBBHHIIIIIIIIIII",
+        2,  # DSP-ADPCM
+        1,  # loops
+        len(channels),
+        0,
+        rate,
+        0,
+        loop_start,
+        count,
+        0,  # absolute offset of the samples, patched below
+        blocks,
+        BLOCK_BYTES,
+        BLOCK_SAMPLES,
+        final_bytes,
+        final_samples,
+        final_padded,
+        BLOCK_SAMPLES,  # samples per seek entry
+        4,  # bytes per seek entry
+    )
+    tracks_offset = info_offset + len(info)
+    tracks = struct.pack(">BBH", 1, 0, 0) + struct.pack(">BBHI", 1, 0, 0, tracks_offset + 12)
+    tracks += struct.pack(">BBBB", len(channels), 0, 1, 0)
+    tracks = pad(tracks, 4)
+    table_offset = tracks_offset + len(tracks)
+    table_len = 4 + 8 * len(channels)
+    table = struct.pack(">B3x", len(channels))
+    body = b""
+    for i, channel in enumerate(channels):
+        entry = table_offset + table_len + i * (8 + 0x30)
+        table += struct.pack(">BBHI", 1, 0, 0, entry)
+        body += struct.pack(">BBHI", 1, 0, 0, entry + 8) + channel.adpcm_info(">", True)
+    refs = b"".join(
+        struct.pack(">BBHI", 1, 0, 0, offset)
+        for offset in (info_offset, tracks_offset, table_offset)
+    )
+    head = chunk(b"HEAD", refs + info + tracks + table + body, 0x20)
+
+    entries = [channel.seek_entries() for channel in channels]
+    seek = b"".join(
+        struct.pack(">hh", *entries[c][i]) for i in range(blocks) for c in range(len(channels))
+    )
+    adpc = chunk(b"ADPC", seek, 0x20)
+
+    samples, _ = interleave(channels)
+    data = chunk(b"DATA", struct.pack(">I", 0x18) + b"\0" * 0x14 + samples, 0x20)
+
+    head_at = 0x40
+    adpc_at = head_at + len(head)
+    data_at = adpc_at + len(adpc)
+    size = data_at + len(data)
+    header = b"RSTM" + struct.pack(
+        ">HHIHHIIIIII",
+        0xFEFF,
+        0x0100,
+        size,
+        0x40,
+        3,
+        head_at,
+        len(head),
+        adpc_at,
+        len(adpc),
+        data_at,
+        len(data),
+    )
+    file = bytearray(pad(header, 0x40) + head + adpc + data)
+    # The absolute offset of the samples in the stream info.
+    struct.pack_into(">I", file, head_at + 8 + info_offset + 0x10, data_at + 0x20)
+    return bytes(file)
+
+
+def bcstm(channels, rate):
+    count, loop_start = channels[0].count, channels[0].loop_start
+    blocks, final_samples, final_bytes, final_padded = block_layout(channels)
+
+    def ref(kind, offset):
+        return struct.pack("<HHi", kind, 0, offset)
+
+    # INFO: references to the stream info, track table and channel table, all relative to the
+    # start of its body.
+    stream_offset = 0x18
+    stream = struct.pack(
+        "<BBBBIIIIIIIIIII",
+        2,  # DSP-ADPCM
+        1,  # loops
+        len(channels),
+        0,  # regions
+        rate,
+        loop_start,
+        count,
+        blocks,
+        BLOCK_BYTES,
+        BLOCK_SAMPLES,
+        final_bytes,
+        final_samples,
+        final_padded,
+        4,  # bytes per seek entry
+        BLOCK_SAMPLES,  # samples per seek entry
+    ) + ref(0x1F00, 0x18)  # samples, relative to the body of DATA
+    tracks_offset = stream_offset + len(stream)
+    # One track holding every channel, its references are relative to the track itself.
+    track = struct.pack("<BBH", 127, 64, 0) + ref(0x0100, 12) + struct.pack("<I", len(channels))
+    track += pad(bytes(range(len(channels))), 4)
+    tracks = struct.pack("<I", 1) + ref(0x4101, 12) + track
+    table_offset = tracks_offset + len(tracks)
+    table_len = 4 + 8 * len(channels)
+    table = struct.pack("<I", len(channels))
+    body = b""
+    for i, channel in enumerate(channels):
+        entry_len = 8 + len(channel.adpcm_info("<", False))
+        # Relative to the start of the table, the ADPCM info relative to the channel info.
+        table += ref(0x4102, table_len + i * entry_len)
+        body += ref(0x0300, 8) + channel.adpcm_info("<", False)
+    refs = ref(0x4100, stream_offset) + ref(0x0101, tracks_offset) + ref(0x0101, table_offset)
+    info = b"INFO" + struct.pack("<I", 0)
+    info += pad(refs + stream + tracks + table + body, 0x20)
+    info = info[:4] + struct.pack("<I", len(info)) + info[8:]
+
+    entries = [channel.seek_entries() for channel in channels]
+    seek = b"".join(
+        struct.pack("<hh", *entries[c][i]) for i in range(blocks) for c in range(len(channels))
+    )
+    seek = pad(b"SEEK" + struct.pack("<I", 0) + seek, 0x20)
+    seek = seek[:4] + struct.pack("<I", len(seek)) + seek[8:]
+
+    samples, _ = interleave(channels)
+    data = b"DATA" + struct.pack("<I", 0) + b"\0" * 0x18 + samples
+    data = data[:4] + struct.pack("<I", len(data)) + data[8:]
+
+    info_at = 0x40
+    seek_at = info_at + len(info)
+    data_at = seek_at + len(seek)
+    size = data_at + len(data)
+    header = b"CSTM" + bytes([0xFF, 0xFE]) + struct.pack("<HIIHH", 0x40, 0x02000000, size, 3, 0)
+    for kind, offset, length in (
+        (0x4000, info_at, len(info)),
+        (0x4001, seek_at, len(seek)),
+        (0x4002, data_at, len(data)),
+    ):
+        header += struct.pack("<HHII", kind, 0, offset, length)
+    return pad(header, 0x40) + info + seek + data
+
+
+def bns(channels, rate):
+    count, loop_start = channels[0].count, channels[0].loop_start
+    # INFO: the stream info, a table of channel entries and their ADPCM infos, all offsets
+    # relative to the start of its body.
+    table_offset = 0x18
+    entries_offset = table_offset + 4 * len(channels)
+    infos_offset = entries_offset + 12 * len(channels)
+    body = struct.pack(
+        ">BBBBHHIIII",
+        0,  # DSP-ADPCM
+        1,  # loops
+        len(channels),
+        0,
+        rate,
+        0,
+        loop_start,
+        count,
+        table_offset,
+        0,
+    )
+    data = b""
+    entries = b""
+    infos = b""
+    for i, channel in enumerate(channels):
+        body += struct.pack(">I", entries_offset + 12 * i)
+        entries += struct.pack(">III", len(data), infos_offset + 0x30 * i, 0)
+        infos += channel.adpcm_info(">", True)
+        data += channel.data
+    # Unlike the other chunks INFO is not padded, DATA follows it at 0xC0.
+    info = chunk(b"INFO", body + entries + infos, 0)
+    data = chunk(b"DATA", data, 0x20)
+
+    info_at = 0x20
+    data_at = info_at + len(info)
+    header = b"BNS " + struct.pack(
+        ">IIHHIIII",
+        0xFEFF0100,
+        data_at + len(data),
+        0x20,
+        2,
+        info_at,
+        len(info),
+        data_at,
+        len(data),
+    )
+    return header + info + data
+
+
+def pcm(channels):
+    return b"".join(
+        struct.pack("<" + "h" * len(channels), *frame)
+        for frame in zip(*(channel.pcm for channel in channels))
+    )
+
+
+def main():
+    with wave.open("pluck-pcm16.wav") as file:
+        rate = file.getframerate()
+        frames = file.readframes(file.getnframes())
+    samples = struct.unpack("<%dh" % (len(frames) // 2), frames)
+    left, right = list(samples[0::2]), list(samples[1::2])
+
+    # Five plucks make for more than one block.
+    long = [Channel(left * 5, 16000), Channel(right * 5, 16000)]
+    with open("pluck.brstm", "wb") as out:
+        out.write(brstm(long, rate))
+    aligned = [Channel(left * 5, 14 * 1100), Channel(right * 5, 14 * 1100)]
+    with open("pluck.bcstm", "wb") as out:
+        out.write(bcstm(aligned, rate))
+    assert pcm(long) == pcm(aligned)
+    with open("pluck.pcm", "wb") as out:
+        out.write(pcm(long))
+
+    short = [Channel(left, 14 * 100), Channel(right, 14 * 100)]
+    with open("pluck.bns", "wb") as out:
+        out.write(bns(short, rate))
+    with open("pluck-bns.pcm", "wb") as out:
+        out.write(pcm(short))
+
+    with wave.open("pluck-pcm8.wav") as file:
+        frames = file.readframes(file.getnframes())
+    with open("pluck-pcm8.pcm", "wb") as out:
+        out.write(b"".join(struct.pack("<h", (byte - 128) << 8) for byte in frames))
+
+
+if __name__ == "__main__":
+    main()
@@ -1,6 +1,10 @@
-use core::time::Duration;
+//! The ``aesnd`` module of ``ogc-rs``.
+//!
+//! This module implements a safe wrapper around the audio functions found in ``aesndlib.h``.
 
-use crate::ffi;
+use core::{marker::PhantomData, ptr::NonNull, time::Duration};
+
+use crate::{OgcError, Result, error::AudioError, ffi, utils::Buf32};
 use alloc::boxed::Box;
 use ffi::AESNDPB;
 use libc::c_void;
@@ -67,85 +71,81 @@ impl Aesnd {
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_stop`")]
     pub fn set_voice_stop(play_state: &mut AESNDPB, stop: bool) {
         unsafe {
             ffi::AESND_SetVoiceStop(play_state, stop);
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_mute`")]
     pub fn set_voice_mute(play_state: &mut AESNDPB, mute: bool) {
         unsafe {
             ffi::AESND_SetVoiceMute(play_state, mute);
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_loop`")]
     pub fn set_voice_loop(play_state: &mut AESNDPB, loop_: bool) {
         unsafe {
             ffi::AESND_SetVoiceLoop(play_state, loop_);
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_format`")]
     pub fn set_voice_format(play_state: &mut AESNDPB, format: AudioFormat) {
         unsafe {
             ffi::AESND_SetVoiceFormat(play_state, format as u32);
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_stream`")]
     pub fn set_voice_stream(play_state: &mut AESNDPB, stream: bool) {
         unsafe {
             ffi::AESND_SetVoiceStream(play_state, stream);
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_frequency`")]
     pub fn set_voice_frequency(play_state: &mut AESNDPB, frequency: f32) {
         unsafe {
             ffi::AESND_SetVoiceFrequency(play_state, frequency);
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_volume`")]
     pub fn set_voice_volume(play_state: &mut AESNDPB, volume: (f32, f32)) {
         unsafe {
-            ffi::AESND_SetVoiceVolume(
-                play_state,
-                (volume.0 * 255.0) as u16,
-                (volume.1 * 255.0) as u16,
-            );
+            ffi::AESND_SetVoiceVolume(play_state, self::volume(volume.0), self::volume(volume.1));
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_delay`")]
     pub fn set_voice_delay(play_state: &mut AESNDPB, delay: u32) {
         unsafe {
             ffi::AESND_SetVoiceDelay(play_state, delay);
         }
     }
 
+    /// `buffer` is handed to `AESND` as it is, so it must be 32 byte aligned, a multiple of 32
+    /// bytes long and stay alive until the voice is done with it. [`AesndVoice`] copies
+    /// unaligned samples and keeps them alive instead.
+    ///
+    /// # Panics
+    /// If `buffer` is not aligned or padded.
+    #[deprecated(note = "use `AesndVoice::set_buffer`")]
     pub fn set_voice_buffer(play_state: &mut AESNDPB, buffer: &[u8]) {
-        //if already aligned just use the buffer.
-        if buffer.as_ptr().align_offset(32) == 0 && buffer.len().is_multiple_of(32) {
-            unsafe {
-                ffi::AESND_SetVoiceBuffer(
-                    play_state,
-                    buffer.as_ptr() as *const c_void,
-                    buffer.len().try_into().unwrap(),
-                );
-            }
-        } else {
-            // othersize copy and allocate a buffer for AESND :)
-            let align_buf = crate::utils::alloc_aligned_buffer(buffer);
-            assert!(
-                align_buf.len().is_multiple_of(32),
-                "Buffer is not padded to 32 bytes"
-            );
-            unsafe {
-                ffi::AESND_SetVoiceBuffer(
-                    play_state,
-                    align_buf.as_ptr() as *const c_void,
-                    align_buf.len().try_into().unwrap(),
-                );
-            }
+        check_aligned(buffer);
+        unsafe {
+            ffi::AESND_SetVoiceBuffer(play_state, buffer.as_ptr().cast(), buffer.len() as u32);
         }
     }
 
+    /// `buffer` has the same requirements as in [`Aesnd::set_voice_buffer`].
+    ///
+    /// # Panics
+    /// If `buffer` is not aligned or padded.
+    #[deprecated(note = "use `AesndVoice::play`")]
     pub fn play_voice(
         play_state: &mut AESNDPB,
         format: AudioFormat,
@@ -154,38 +154,21 @@ impl Aesnd {
         delay: u32,
         loop_: bool,
     ) {
-        if buffer.as_ptr().align_offset(32) == 0 && buffer.len().is_multiple_of(32) {
-            unsafe {
-                ffi::AESND_PlayVoice(
-                    play_state,
-                    format as u32,
-                    buffer.as_ptr() as *const c_void,
-                    buffer.len().try_into().unwrap(),
-                    frequency,
-                    delay,
-                    loop_,
-                );
-            }
-        } else {
-            let align_buf = crate::utils::alloc_aligned_buffer(buffer);
-            assert!(
-                align_buf.len().is_multiple_of(32),
-                "Buffer is not padded to 32 bytes"
+        check_aligned(buffer);
+        unsafe {
+            ffi::AESND_PlayVoice(
+                play_state,
+                format as u32,
+                buffer.as_ptr().cast(),
+                buffer.len() as u32,
+                frequency,
+                delay,
+                loop_,
             );
-            unsafe {
-                ffi::AESND_PlayVoice(
-                    play_state,
-                    format as u32,
-                    align_buf.as_ptr() as *const c_void,
-                    align_buf.len().try_into().unwrap(),
-                    frequency,
-                    delay,
-                    loop_,
-                );
-            }
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::set_callback`")]
     pub fn register_voice_callback(
         play_state: &mut AESNDPB,
         callback: Option<unsafe extern "C" fn(*mut AESNDPB, u32, *mut c_void)>,
@@ -195,7 +178,343 @@ impl Aesnd {
         }
     }
 
+    #[deprecated(note = "use `AesndVoice::new`")]
     pub fn new_playstate() -> AESNDPB {
         unsafe { *ffi::AESND_AllocateVoiceWithArg(None, core::ptr::null_mut()) }
     }
 }
+
+/// Make sure `AESND` can read `buffer` in place and flush it.
+fn check_aligned(buffer: &[u8]) {
+    assert!(
+        buffer.as_ptr().align_offset(32) == 0 && buffer.len().is_multiple_of(32),
+        "AESND buffers must be 32 byte aligned and padded to 32 bytes"
+    );
+    flush(buffer);
+}
+
+/// State a voice callback is called with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum VoiceState {
+    /// The voice stopped playing
+    Stopped,
+    /// The voice is playing
+    Running,
+    /// A streaming voice finished its buffer and wants the next one
+    Stream,
+    /// A state this module does not know about
+    Unknown(u32),
+}
+
+impl From<u32> for VoiceState {
+    fn from(value: u32) -> Self {
+        match value {
+            ffi::VOICE_STATE_STOPPED => Self::Stopped,
+            ffi::VOICE_STATE_RUNNING => Self::Running,
+            ffi::VOICE_STATE_STREAM => Self::Stream,
+            val => Self::Unknown(val),
+        }
+    }
+}
+
+type VoiceClosure<'a> = dyn FnMut(&mut VoiceRef<'_>, VoiceState) + Send + 'a;
+
+/// The sample buffer a voice plays from.
+enum Samples<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Buf32),
+}
+
+impl Samples<'_> {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Borrowed(samples) => samples,
+            Self::Owned(samples) => samples.as_slice(),
+        }
+    }
+}
+
+/// Borrow `samples` if `AESND` can read them in place, otherwise copy them into an aligned
+/// buffer padded to 32 bytes.
+fn aligned(samples: &[u8]) -> Samples<'_> {
+    if samples.as_ptr().align_offset(32) == 0 && samples.len().is_multiple_of(32) {
+        Samples::Borrowed(samples)
+    } else {
+        let mut buf = Buf32::new(samples.len());
+        buf.as_mut_slice()[..samples.len()].copy_from_slice(samples);
+        Samples::Owned(buf)
+    }
+}
+
+/// Flush `samples` out of the data cache so the DSP sees them.
+fn flush(samples: &[u8]) {
+    unsafe { ffi::DCFlushRange(samples.as_ptr().cast_mut().cast(), samples.len() as u32) };
+}
+
+/// Convert a volume where `1.0` is full volume into what `AESND` expects.
+fn volume(volume: f32) -> u16 {
+    (volume.clamp(0.0, 1.0) * 255.0) as u16
+}
+
+/// A voice allocated from `AESND`.
+///
+/// The voice keeps the samples it plays from alive, either by borrowing them for `'a` or by
+/// owning an aligned copy. It is stopped and freed when dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut voice = AesndVoice::new()?;
+/// voice.set_callback(|_, state| {
+///     if state == VoiceState::Stopped {
+///         // the sound effect finished
+///     }
+/// });
+/// voice.play(AudioFormat::VoiceMono16, &SAMPLES, 32000.0, 0, false);
+/// ```
+pub struct AesndVoice<'a> {
+    pb: NonNull<AESNDPB>,
+    samples: Option<Samples<'a>>,
+    // Double boxed so a thin pointer can be handed to `AESND` as the callback argument.
+    callback: Option<Box<Box<VoiceClosure<'a>>>>,
+}
+
+impl<'a> AesndVoice<'a> {
+    /// Allocate a voice.
+    ///
+    /// Fails with [`AudioError::NoFreeVoice`] if every voice is in use.
+    pub fn new() -> Result<Self> {
+        let pb = unsafe { ffi::AESND_AllocateVoiceWithArg(None, core::ptr::null_mut()) };
+        let pb = NonNull::new(pb).ok_or(OgcError::Audio(AudioError::NoFreeVoice))?;
+        Ok(Self {
+            pb,
+            samples: None,
+            callback: None,
+        })
+    }
+
+    /// The underlying `AESND` play state.
+    pub fn as_raw(&self) -> *mut AESNDPB {
+        self.pb.as_ptr()
+    }
+
+    /// Flush `samples`, hand them to `AESND` with `submit` and keep them alive.
+    ///
+    /// The samples played so far are only freed once `submit` pointed the voice elsewhere.
+    fn hold(
+        &mut self,
+        samples: Samples<'a>,
+        submit: impl FnOnce(*mut AESNDPB, *const c_void, u32),
+    ) {
+        let slice = samples.as_slice();
+        flush(slice);
+        let (ptr, len) = (slice.as_ptr().cast(), slice.len() as u32);
+
+        let previous = self.samples.replace(samples);
+        submit(self.pb.as_ptr(), ptr, len);
+        drop(previous);
+    }
+
+    fn start(
+        &mut self,
+        format: AudioFormat,
+        samples: Samples<'a>,
+        frequency: f32,
+        delay: u32,
+        looped: bool,
+    ) {
+        self.hold(samples, |pb, ptr, len| unsafe {
+            ffi::AESND_PlayVoice(pb, format as u32, ptr, len, frequency, delay, looped);
+        });
+    }
+
+    /// Play `samples` at `frequency` Hz after `delay` milliseconds.
+    ///
+    /// The samples are borrowed if they are 32 byte aligned and their length is a multiple of
+    /// 32, otherwise they are copied.
+    pub fn play(
+        &mut self,
+        format: AudioFormat,
+        samples: &'a [u8],
+        frequency: f32,
+        delay: u32,
+        looped: bool,
+    ) {
+        self.start(format, aligned(samples), frequency, delay, looped);
+    }
+
+    /// Like [`AesndVoice::play`] but takes ownership of an aligned buffer.
+    pub fn play_owned(
+        &mut self,
+        format: AudioFormat,
+        samples: Buf32,
+        frequency: f32,
+        delay: u32,
+        looped: bool,
+    ) {
+        self.start(format, Samples::Owned(samples), frequency, delay, looped);
+    }
+
+    /// Replace the samples the voice plays from without restarting it.
+    ///
+    /// Like [`AesndVoice::play`] the samples are copied unless they are aligned.
+    pub fn set_buffer(&mut self, samples: &'a [u8]) {
+        self.hold(aligned(samples), |pb, ptr, len| unsafe {
+            ffi::AESND_SetVoiceBuffer(pb, ptr, len);
+        });
+    }
+
+    /// Like [`AesndVoice::set_buffer`] but takes ownership of an aligned buffer.
+    pub fn set_buffer_owned(&mut self, samples: Buf32) {
+        self.hold(Samples::Owned(samples), |pb, ptr, len| unsafe {
+            ffi::AESND_SetVoiceBuffer(pb, ptr, len);
+        });
+    }
+
+    /// Call `callback` from the audio interrupt whenever the state of the voice changes.
+    ///
+    /// The callback runs in interrupt context, it must not block or allocate.
+    pub fn set_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut VoiceRef<'_>, VoiceState) + Send + 'a,
+    {
+        let mut callback: Box<Box<VoiceClosure<'a>>> = Box::new(Box::new(callback));
+        let arg = (&raw mut *callback).cast::<c_void>();
+        unsafe {
+            ffi::AESND_RegisterVoiceCallbackWithArg(self.pb.as_ptr(), Some(voice_callback), arg);
+        }
+        // The previous callback can no longer be called once the new one is registered.
+        self.callback = Some(callback);
+    }
+
+    /// Remove the callback set with [`AesndVoice::set_callback`].
+    pub fn clear_callback(&mut self) {
+        unsafe {
+            ffi::AESND_RegisterVoiceCallbackWithArg(self.pb.as_ptr(), None, core::ptr::null_mut());
+        }
+        self.callback = None;
+    }
+
+    /// Stop or resume the voice.
+    pub fn set_stop(&mut self, stop: bool) {
+        unsafe { ffi::AESND_SetVoiceStop(self.pb.as_ptr(), stop) };
+    }
+
+    /// Stop the voice.
+    pub fn stop(&mut self) {
+        self.set_stop(true);
+    }
+
+    /// Mute or unmute the voice.
+    pub fn set_mute(&mut self, mute: bool) {
+        unsafe { ffi::AESND_SetVoiceMute(self.pb.as_ptr(), mute) };
+    }
+
+    /// Loop the samples or play them once.
+    pub fn set_loop(&mut self, looped: bool) {
+        unsafe { ffi::AESND_SetVoiceLoop(self.pb.as_ptr(), looped) };
+    }
+
+    /// Change the sample format.
+    pub fn set_format(&mut self, format: AudioFormat) {
+        unsafe { ffi::AESND_SetVoiceFormat(self.pb.as_ptr(), format as u32) };
+    }
+
+    /// Enable streaming, the callback is called with [`VoiceState::Stream`] once the buffer
+    /// was played.
+    pub fn set_stream(&mut self, stream: bool) {
+        unsafe { ffi::AESND_SetVoiceStream(self.pb.as_ptr(), stream) };
+    }
+
+    /// Change the playback frequency in Hz.
+    pub fn set_frequency(&mut self, frequency: f32) {
+        unsafe { ffi::AESND_SetVoiceFrequency(self.pb.as_ptr(), frequency) };
+    }
+
+    /// Change the volume of each channel, `1.0` is full volume.
+    pub fn set_volume(&mut self, left: f32, right: f32) {
+        unsafe { ffi::AESND_SetVoiceVolume(self.pb.as_ptr(), volume(left), volume(right)) };
+    }
+
+    /// Delay the start of playback by `delay` milliseconds.
+    pub fn set_delay(&mut self, delay: u32) {
+        unsafe { ffi::AESND_SetVoiceDelay(self.pb.as_ptr(), delay) };
+    }
+}
+
+impl Drop for AesndVoice<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::AESND_SetVoiceStop(self.pb.as_ptr(), true);
+            ffi::AESND_FreeVoice(self.pb.as_ptr());
+        }
+    }
+}
+
+// SAFETY: the play state is only touched through `AESND`, which disables interrupts, and the
+// callback is required to be `Send`.
+unsafe impl Send for AesndVoice<'_> {}
+
+/// The voice a callback was called for.
+///
+/// Only the settings that are safe to change from the audio interrupt are available.
+pub struct VoiceRef<'v> {
+    pb: NonNull<AESNDPB>,
+    _voice: PhantomData<&'v mut AESNDPB>,
+}
+
+impl VoiceRef<'_> {
+    /// Stop or resume the voice.
+    pub fn set_stop(&mut self, stop: bool) {
+        unsafe { ffi::AESND_SetVoiceStop(self.pb.as_ptr(), stop) };
+    }
+
+    /// Mute or unmute the voice.
+    pub fn set_mute(&mut self, mute: bool) {
+        unsafe { ffi::AESND_SetVoiceMute(self.pb.as_ptr(), mute) };
+    }
+
+    /// Change the playback frequency in Hz.
+    pub fn set_frequency(&mut self, frequency: f32) {
+        unsafe { ffi::AESND_SetVoiceFrequency(self.pb.as_ptr(), frequency) };
+    }
+
+    /// Change the volume of each channel, `1.0` is full volume.
+    pub fn set_volume(&mut self, left: f32, right: f32) {
+        unsafe { ffi::AESND_SetVoiceVolume(self.pb.as_ptr(), volume(left), volume(right)) };
+    }
+
+    /// Hand the voice the next buffer to stream from.
+    ///
+    /// # Safety
+    ///
+    /// `samples` must be 32 byte aligned, a multiple of 32 bytes long, flushed from the data
+    /// cache and stay alive until the voice moves on to another buffer or is dropped.
+    pub unsafe fn set_buffer(&mut self, samples: &[u8]) {
+        unsafe {
+            ffi::AESND_SetVoiceBuffer(
+                self.pb.as_ptr(),
+                samples.as_ptr().cast(),
+                samples.len() as u32,
+            );
+        }
+    }
+}
+
+unsafe extern "C" fn voice_callback(pb: *mut AESNDPB, state: u32, arg: *mut c_void) {
+    let Some(pb) = NonNull::new(pb) else {
+        return;
+    };
+    if arg.is_null() {
+        return;
+    }
+
+    // SAFETY: `arg` was registered by `AesndVoice::set_callback` and is unregistered before the
+    // closure is dropped.
+    let callback = unsafe { &mut *arg.cast::<Box<VoiceClosure<'_>>>() };
+    let mut voice = VoiceRef {
+        pb,
+        _voice: PhantomData,
+    };
+    callback(&mut voice, VoiceState::from(state));
+}
@@ -2,16 +2,15 @@
 //!
 //! This module implements a safe wrapper around the audio functions found in ``asndlib.h``.
 
-use crate::{OgcError, Result, ffi};
-use alloc::format;
+use crate::{OgcError, Result, error::AudioError, ffi};
 use core::time::Duration;
 
 macro_rules! if_not {
-    ($valid:ident => $error_output:expr_2021, $var:ident $(,)*) => {
+    ($valid:ident, $var:ident $(,)*) => {
         if $var == ffi::$valid as _ {
             Ok(())
         } else {
-            Err(OgcError::Audio(format!($error_output, $var)))
+            Err(OgcError::Audio(AudioError::from_asnd($var)))
         }
     };
 }
@@ -124,7 +123,7 @@ pub enum VoiceFormat {
 }
 
 impl VoiceFormat {
-    fn as_i32(&self) -> i32 {
+    pub(crate) fn as_i32(&self) -> i32 {
         match self {
             VoiceFormat::Mono8Bit => 0,
             VoiceFormat::Mono16Bit => 1,
@@ -230,7 +229,7 @@ impl Asnd {
             )
         };
 
-        if_not!(SND_OK => "Asnd::set_voice() failed with error {}!", err)
+        if_not!(SND_OK, err)
     }
 
     /// Sets a PCM voice to play infinitely. See `Asnd::set_voice()` as it is largely identical.
@@ -251,14 +250,14 @@ impl Asnd {
             )
         };
 
-        if_not!(SND_OK => "Asnd::set_infinite_voice() failed with error {}", err)
+        if_not!(SND_OK, err)
     }
 
     /// Adds a PCM voice to play from the second buffer. Sound buffer must be 32-byte
     /// aligned and have same sample format as first buffer. This must only be called after
     /// `Asnd::set_voice()`, which must return `Ok()`.
     /// The buffer MUST be aligned and padded to 32 bytes.
-    fn add_voice(voice: u32, sound_buffer: &mut [u8]) -> Result<()> {
+    pub(crate) fn add_voice(voice: u32, sound_buffer: &mut [u8]) -> Result<()> {
         assert!(voice < 16, "Voice index {voice} is >= 16");
         Self::validate_buffer(sound_buffer);
 
@@ -270,7 +269,7 @@ impl Asnd {
             )
         };
 
-        if_not!(SND_OK => "Asnd::add_voice() failed with error {}", err)
+        if_not!(SND_OK, err)
     }
 
     /// Stops the selected voice. If the voice is used in song mode, you need to
@@ -278,21 +277,25 @@ impl Asnd {
     pub fn stop_voice(voice: u32) -> Result<()> {
         assert!(voice < 16, "Voice index {voice} is >= 16");
         let err = unsafe { ffi::ASND_StopVoice(voice as i32) };
-        if_not!(SND_OK => "Asnd::stop_voice() failed with error {}", err)
+        if_not!(SND_OK, err)
     }
 
     /// Pauses the selected voice. Can also be used to resume voice.
     pub fn pause_voice(voice: u32, pause: bool) -> Result<()> {
         assert!(voice < 16, "Voice index {voice} is >= 16");
         let err = unsafe { ffi::ASND_PauseVoice(voice as i32, pause as i32) };
-        if_not!(SND_OK => "Asnd::pause_voice() failed with error {}", err)
+        if_not!(SND_OK, err)
     }
 
     /// Returns the state of the selected voice.
     pub fn status_voice(voice: u32) -> Result<()> {
         assert!(voice < 16, "Voice index {voice} is >= 16");
         let err = unsafe { ffi::ASND_StatusVoice(voice as i32) };
-        if_not!(SND_WORKING => "Asnd::status_voice() failed with error {}", err)
+        match err {
+            x if x == ffi::SND_WORKING as _ => Ok(()),
+            x if x == ffi::SND_INVALID as _ => Err(OgcError::Audio(AudioError::Invalid)),
+            x => Err(OgcError::Audio(AudioError::NotPlaying(x))),
+        }
     }
 
     /// Returns the first unused voice. Fails if no voices are available.
@@ -300,9 +303,7 @@ impl Asnd {
         let err = unsafe { ffi::ASND_GetFirstUnusedVoice() };
         match err {
             x if x < 16 => Ok(x as u32),
-            _ => Err(OgcError::Audio(format!(
-                "Asnd::get_first_unused_voice() failed with error {err}",
-            ))),
+            _ => Err(OgcError::Audio(AudioError::NoFreeVoice)),
         }
     }
 
@@ -311,7 +312,7 @@ impl Asnd {
     pub fn change_pitch_voice(voice: u32, pitch: u32) -> Result<()> {
         assert!(voice < 16, "Voice index {voice} is >= 16");
         let err = unsafe { ffi::ASND_ChangePitchVoice(voice as i32, pitch as i32) };
-        if_not!(SND_OK => "Asnd::change_pitch_voice() failed with error {}", err)
+        if_not!(SND_OK, err)
     }
 
     /// Changes the voice volume in real time. This function can be used to create
@@ -321,7 +322,7 @@ impl Asnd {
         let err = unsafe {
             ffi::ASND_ChangeVolumeVoice(voice as i32, volume_left as i32, volume_right as i32)
         };
-        if_not!(SND_OK => "Asnd::change_volume_voice() failed with error {}", err)
+        if_not!(SND_OK, err)
     }
 
     /// Returns the voice tick counter. This value represents the number of ticks
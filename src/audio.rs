@@ -1,12 +1,21 @@
 //! The ``audio`` module of ``ogc-rs``.
 //!
-//! This module implements a safe wrapper around the audio functions found in ``audio.h``.
+//! This module implements a safe wrapper around the audio functions found in ``audio.h``,
+//! along with decoders for the audio formats used on the Wii.
 
 use crate::ffi;
-use alloc::boxed::Box;
-use core::{convert::TryFrom, mem, ptr};
+use core::{convert::TryFrom, ptr};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+pub mod adpcm;
+mod dma;
+pub mod stream;
+#[cfg(feature = "vorbis")]
+pub mod vorbis;
+pub mod wav;
+
+pub use dma::AudioStream;
+
 /// Represents the audio service.
 /// No audio control can be done until an instance of this struct is created.
 /// This service can only be created once!
@@ -21,11 +30,26 @@ pub enum PlayState {
 }
 
 /// The sample rate of the ``audio`` service.
-#[derive(IntoPrimitive, TryFromPrimitive, Debug, Eq, PartialEq)]
+#[derive(IntoPrimitive, TryFromPrimitive, Copy, Clone, Debug, Eq, PartialEq)]
 #[repr(u32)]
 pub enum SampleRate {
     FortyEightKhz = 1,
-    ThirtySixKhz = 0,
+    ThirtyTwoKhz = 0,
+}
+
+impl SampleRate {
+    /// The hardware rate is 32 kHz, this name was a mistake.
+    #[deprecated(note = "use `SampleRate::ThirtyTwoKhz`")]
+    #[allow(non_upper_case_globals)]
+    pub const ThirtySixKhz: Self = Self::ThirtyTwoKhz;
+
+    /// The rate in Hz.
+    pub fn hz(&self) -> u32 {
+        match self {
+            Self::FortyEightKhz => 48000,
+            Self::ThirtyTwoKhz => 32000,
+        }
+    }
 }
 
 /// Implementation of the audio service.
@@ -42,16 +66,21 @@ impl Audio {
     }
 
     /// Initialize an audio DMA transfer.
-    fn init_dma(data: &[u8]) {
-        unsafe {
-            // libogc has strict restrictions on data alignment and length.
-            assert_eq!(
-                32,
-                mem::align_of_val(data),
-                "Data is not aligned correctly."
-            );
-            assert_eq!(0, data.len() % 32, "Data length is not a multiple of 32.");
+    ///
+    /// # Safety
+    ///
+    /// The hardware keeps reading `data` after this returns, it must stay alive until another
+    /// buffer was queued and started or DMA was stopped.
+    pub unsafe fn init_dma(data: &[u8]) {
+        // libogc has strict restrictions on data alignment and length.
+        assert_eq!(
+            0,
+            data.as_ptr() as usize % 32,
+            "Data is not aligned correctly."
+        );
+        assert_eq!(0, data.len() % 32, "Data length is not a multiple of 32.");
 
+        unsafe {
             ffi::AUDIO_InitDMA(data.as_ptr() as u32, data.len() as u32);
         }
     }
@@ -60,107 +89,105 @@ impl Audio {
     ///
     /// Starts to transfer the data from main memory to the audio interface through DMA.
     /// This call should follow the call to ``init_dma`` which is used to initialize DMA transfers.
-    fn start_dma() {
+    pub fn start_dma() {
         unsafe {
             ffi::AUDIO_StartDMA();
         }
     }
 
     /// Stop the previously started audio DMA operation.
-    fn stop_dma() {
+    pub fn stop_dma() {
         unsafe {
             ffi::AUDIO_StopDMA();
         }
     }
 
     /// Register a user callback function for the ``audio`` streaming interface.
-    fn register_stream_callback<F>(callback: Box<F>)
-    where
-        F: Fn(u32),
-    {
-        // TODO: Check if this implementation can be changed.
-        let ptr = Box::into_raw(callback);
-
-        unsafe {
-            let code: extern "C" fn(smp_cnt: u32) = mem::transmute(ptr);
-            // TODO: Do something with the returned callback.
-            let _ = ffi::AUDIO_RegisterStreamCallback(Some(code));
-        }
+    ///
+    /// Returns the previously registered callback.
+    ///
+    /// # Safety
+    ///
+    /// The callback runs in an interrupt, it must not allocate, block or touch state that is not
+    /// protected against interrupts.
+    pub unsafe fn register_stream_callback(
+        callback: Option<unsafe extern "C" fn(u32)>,
+    ) -> Option<unsafe extern "C" fn(u32)> {
+        unsafe { ffi::AUDIO_RegisterStreamCallback(callback) }
     }
 
     /// Register a user callback function for the audio DMA interface.
     ///
-    /// This callback will be called whenever the audio DMA requests new data.
-    /// Internally the DMA buffers are double buffered.
-    fn register_dma_callback<F>(callback: Box<F>)
-    where
-        F: Fn(),
-    {
-        // TODO: Check if this implementation can be changed.
-        let ptr = Box::into_raw(callback);
-
-        unsafe {
-            let code: extern "C" fn() = mem::transmute(ptr);
-            // TODO: Do something with the returned callback.
-            let _ = ffi::AUDIO_RegisterDMACallback(Some(code));
-        }
+    /// This callback will be called from an interrupt whenever the audio DMA starts playing a
+    /// buffer, the next one should be queued with ``init_dma`` then. Returns the previously
+    /// registered callback. [`AudioStream`] wraps this safely.
+    ///
+    /// # Safety
+    ///
+    /// The callback runs in an interrupt, it must not allocate, block or touch state that is not
+    /// protected against interrupts. No [`AudioStream`] may be alive, it relies on its own
+    /// callback staying registered and would stop being refilled.
+    pub unsafe fn register_dma_callback(
+        callback: Option<unsafe extern "C" fn()>,
+    ) -> Option<unsafe extern "C" fn()> {
+        unsafe { ffi::AUDIO_RegisterDMACallback(callback) }
     }
 
     /// Get the count of bytes, left to play, from the audio DMA interface.
-    fn get_dma_bytes_left() -> u32 {
+    pub fn get_dma_bytes_left() -> u32 {
         unsafe { ffi::AUDIO_GetDMABytesLeft() }
     }
 
     /// Get the audio DMA flag.
-    fn get_dma_enable_flag() -> u16 {
+    pub fn get_dma_enable_flag() -> u16 {
         unsafe { ffi::AUDIO_GetDMAEnableFlag() }
     }
 
     /// Get the DMA transfer length configured in the audio DMA interface.
-    fn get_dma_length() -> u32 {
+    pub fn get_dma_length() -> u32 {
         unsafe { ffi::AUDIO_GetDMALength() }
     }
 
     /// Get the main memory address for the DMA operation.
-    fn get_dma_address() -> u32 {
+    pub fn get_dma_address() -> u32 {
         unsafe { ffi::AUDIO_GetDMAStartAddr() }
     }
 
     /// Reset the stream sample count register.
-    fn reset_sample_count() {
+    pub fn reset_sample_count() {
         unsafe {
             ffi::AUDIO_ResetStreamSampleCnt();
         }
     }
 
     /// Set the sample count for the stream trigger.
-    fn set_trigger_count(count: u32) {
+    pub fn set_trigger_count(count: u32) {
         unsafe {
             ffi::AUDIO_SetStreamTrigger(count);
         }
     }
 
     /// Get streaming sample rate.
-    fn get_samplerate() -> SampleRate {
+    pub fn get_samplerate() -> SampleRate {
         let r = unsafe { ffi::AUDIO_GetStreamSampleRate() };
         SampleRate::try_from(r).unwrap()
     }
 
     /// Get the sampling rate for the DSP interface.
-    fn get_dsp_samplerate() -> SampleRate {
+    pub fn get_dsp_samplerate() -> SampleRate {
         let r = unsafe { ffi::AUDIO_GetDSPSampleRate() };
         SampleRate::try_from(r).unwrap()
     }
 
     /// Set the sample rate for the streaming audio interface.
-    fn set_samplerate(samplerate: SampleRate) {
+    pub fn set_samplerate(samplerate: SampleRate) {
         unsafe {
             ffi::AUDIO_SetStreamSampleRate(samplerate.into());
         }
     }
 
     /// Set the sampling rate for the DSP interface.
-    fn set_dsp_samplerate(samplerate: SampleRate) {
+    pub fn set_dsp_samplerate(samplerate: SampleRate) {
         // TODO: Check implementation.
         let sample_rate: u32 = samplerate.into();
 
@@ -170,35 +197,35 @@ impl Audio {
     }
 
     /// Get the play state from the streaming audio interface.
-    fn get_playstate() -> PlayState {
+    pub fn get_playstate() -> PlayState {
         let r = unsafe { ffi::AUDIO_GetStreamPlayState() };
         PlayState::try_from(r).unwrap()
     }
 
     /// Set the play state for the streaming audio interface.
-    fn set_playstate(playstate: PlayState) {
+    pub fn set_playstate(playstate: PlayState) {
         unsafe {
             ffi::AUDIO_SetStreamPlayState(playstate.into());
         }
     }
 
     /// Get streaming volume on the left channel.
-    fn get_volume_left() -> u8 {
+    pub fn get_volume_left() -> u8 {
         unsafe { ffi::AUDIO_GetStreamVolLeft() }
     }
 
     /// Set streaming volume on the left channel.
-    fn set_volume_left(volume: u8) {
+    pub fn set_volume_left(volume: u8) {
         unsafe { ffi::AUDIO_SetStreamVolLeft(volume) }
     }
 
     /// Get streaming volume on the right channel.
-    fn get_volume_right() -> u8 {
+    pub fn get_volume_right() -> u8 {
         unsafe { ffi::AUDIO_GetStreamVolRight() }
     }
 
     /// Set streaming volume on the right channel.
-    fn set_volume_right(volume: u8) {
+    pub fn set_volume_right(volume: u8) {
         unsafe { ffi::AUDIO_SetStreamVolRight(volume) }
     }
 }
@@ -0,0 +1,6 @@
+//! Nintendo DSP-ADPCM, the compressed format the audio DSP plays natively.
+//!
+//! This is [`ogc_formats::adpcm`], which builds on the host as well so sounds can be encoded
+//! by build scripts and tools with the exact same code.
+
+pub use ogc_formats::adpcm::*;
@@ -0,0 +1,234 @@
+//! Streaming PCM straight to the audio interface through DMA.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, Ordering},
+    time::Duration,
+};
+
+use super::{Audio, SampleRate};
+use crate::{OgcError, Result, error::AudioError, ffi, time::Instant, utils::Buf32};
+
+type FillFn = dyn FnMut(&mut [i16]) + Send;
+
+/// State shared with the DMA interrupt.
+struct State {
+    buffers: Vec<Buf32>,
+    /// Bytes of each buffer that are played
+    len: usize,
+    /// Buffer the hardware is playing
+    playing: usize,
+    fill: Box<FillFn>,
+    running: bool,
+    period: Duration,
+    last_interrupt: Option<Instant>,
+    underruns: u32,
+}
+
+impl State {
+    fn fill(&mut self, index: usize) {
+        let buffer = &mut self.buffers[index];
+        // SAFETY: buffers are 32 byte aligned and `len` is a multiple of 4.
+        let samples = unsafe {
+            core::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<i16>(), self.len / 2)
+        };
+        (self.fill)(samples);
+        unsafe { ffi::DCFlushRange(buffer.as_mut_ptr().cast(), self.len as u32) };
+    }
+
+    fn queue(&self, index: usize) {
+        // SAFETY: the buffers live as long as the stream, which stops DMA before freeing them.
+        unsafe { Audio::init_dma(&self.buffers[index][..self.len]) };
+    }
+}
+
+/// The only stream, the DMA callback does not get a user pointer.
+static STATE: AtomicPtr<State> = AtomicPtr::new(ptr::null_mut());
+
+unsafe extern "C" fn dma_callback() {
+    let state = STATE.load(Ordering::Acquire);
+    if state.is_null() {
+        return;
+    }
+    // SAFETY: user code only touches the state with interrupts disabled.
+    let state = unsafe { &mut *state };
+    if !state.running {
+        return;
+    }
+
+    // An interrupt that came more than half a buffer late means the hardware played a buffer
+    // again.
+    let now = Instant::now();
+    if let Some(last) = state.last_interrupt
+        && now.duration_since(last) > state.period + state.period / 2
+    {
+        state.underruns = state.underruns.wrapping_add(1);
+    }
+    state.last_interrupt = Some(now);
+
+    // The queued buffer just started, refill the one that finished and queue the next.
+    let count = state.buffers.len();
+    state.playing = (state.playing + 1) % count;
+    state.fill((state.playing + count - 1) % count);
+    state.queue((state.playing + 1) % count);
+}
+
+/// Plays 16-bit stereo PCM through the audio DMA interface.
+///
+/// The stream owns its DMA buffers and calls the fill closure from the DMA interrupt every time
+/// the hardware starts playing one, with the interleaved samples of the next free buffer. The
+/// closure must not block or allocate.
+///
+/// This is the lowest latency path to the hardware but it cannot be combined with `ASND` or
+/// `AESND`, which use the same interface.
+///
+/// # Examples
+///
+/// ```rust
+/// let audio = Audio::init();
+/// let mut phase = 0u32;
+/// let mut stream = AudioStream::new(&audio, SampleRate::FortyEightKhz, 512, 2, move |samples| {
+///     for frame in samples.chunks_exact_mut(2) {
+///         let value = if phase < 55 { 8000 } else { -8000 };
+///         phase = (phase + 1) % 110;
+///         frame.fill(value);
+///     }
+/// })?;
+/// stream.start();
+/// ```
+pub struct AudioStream {
+    state: NonNull<State>,
+}
+
+// SAFETY: the state is only touched with interrupts disabled.
+unsafe impl Send for AudioStream {}
+
+impl AudioStream {
+    /// Create a stream of `buffer_count` buffers holding `frames` stereo frames each.
+    ///
+    /// `frames` is rounded up to a multiple of 8 so buffers stay a multiple of 32 bytes.
+    /// Each buffer adds its length to the latency, two is the minimum. Only one stream can exist
+    /// at a time, creating a second one fails with [`AudioError::Invalid`].
+    pub fn new<F>(
+        _audio: &Audio,
+        sample_rate: SampleRate,
+        frames: usize,
+        buffer_count: usize,
+        fill: F,
+    ) -> Result<Self>
+    where
+        F: FnMut(&mut [i16]) + Send + 'static,
+    {
+        if buffer_count < 2 || frames == 0 {
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        let frames = frames.next_multiple_of(8);
+        let len = frames * 4;
+        let mut buffers = Vec::with_capacity(buffer_count);
+        for _ in 0..buffer_count {
+            buffers.push(Buf32::try_new(len)?);
+        }
+
+        let state = Box::new(State {
+            buffers,
+            len,
+            playing: 0,
+            fill: Box::new(fill),
+            running: false,
+            period: Duration::from_micros(frames as u64 * 1_000_000 / u64::from(sample_rate.hz())),
+            last_interrupt: None,
+            underruns: 0,
+        });
+        let state = NonNull::from(Box::leak(state));
+        if STATE
+            .compare_exchange(
+                ptr::null_mut(),
+                state.as_ptr(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            drop(unsafe { Box::from_raw(state.as_ptr()) });
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        Audio::set_dsp_samplerate(sample_rate);
+        // SAFETY: `STATE` was set just above, `dma_callback` only touches it.
+        unsafe { Audio::register_dma_callback(Some(dma_callback)) };
+        Ok(Self { state })
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        // SAFETY: the DMA interrupt is the only other user of the state.
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            let result = f(&mut *self.state.as_ptr());
+            ffi::IRQ_Restore(level);
+            result
+        }
+    }
+
+    /// Fill the buffers and start playing.
+    pub fn start(&mut self) {
+        self.with_state(|state| {
+            if state.running {
+                return;
+            }
+            // The first interrupt comes as soon as the first buffer starts and fills the last
+            // one, so filling it here would skip a buffer of audio.
+            let count = state.buffers.len();
+            for index in 0..count - 1 {
+                state.fill(index);
+            }
+            state.playing = count - 1;
+            state.last_interrupt = None;
+            state.running = true;
+            state.queue(0);
+            Audio::start_dma();
+        });
+    }
+
+    /// Stop playing, [`AudioStream::start`] continues with fresh buffers.
+    pub fn stop(&mut self) {
+        self.with_state(|state| {
+            state.running = false;
+            Audio::stop_dma();
+        });
+    }
+
+    /// Whether the stream is playing.
+    pub fn is_running(&self) -> bool {
+        self.with_state(|state| state.running)
+    }
+
+    /// Number of times the interrupt came too late and a buffer was played twice.
+    pub fn underruns(&self) -> u32 {
+        self.with_state(|state| state.underruns)
+    }
+
+    /// Stereo frames in each buffer.
+    pub fn frames(&self) -> usize {
+        self.with_state(|state| state.len / 4)
+    }
+
+    /// Time it takes to play one buffer.
+    pub fn buffer_duration(&self) -> Duration {
+        self.with_state(|state| state.period)
+    }
+}
+
+impl Drop for AudioStream {
+    fn drop(&mut self) {
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            Audio::stop_dma();
+            Audio::register_dma_callback(None);
+            STATE.store(ptr::null_mut(), Ordering::Release);
+            ffi::IRQ_Restore(level);
+            drop(Box::from_raw(self.state.as_ptr()));
+        }
+    }
+}
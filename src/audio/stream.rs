@@ -0,0 +1,6 @@
+//! Nintendo stream files: BRSTM, BCSTM, BFSTM and the BNS files used by channel banners.
+//!
+//! This is [`ogc_formats::stream`], which is tested on the host against sample files of every
+//! format.
+
+pub use ogc_formats::stream::*;
@@ -0,0 +1,136 @@
+//! Ogg Vorbis decoding, behind the `vorbis` feature.
+//!
+//! [`Vorbis`] decodes Vorbis I streams page by page from any [`Read`] source, so music can be
+//! played straight from a file without loading it whole. The decoder lives in
+//! [`ogc_formats::vorbis`], where it is tested on the host. Only floor type 1 is supported,
+//! which is what every encoder since Vorbis 1.0 produces. Opus streams are not supported.
+//!
+//! Decoding a long block takes a while, so it should not run in an audio interrupt: wrap the
+//! decoder in a [`Prefetch`](crate::mixer::Prefetch) to decode on its own thread. Loops are
+//! decoded once and played by an [`InfiniteVoice`](crate::mixer::InfiniteVoice).
+//!
+//! ```rust
+//! let music = Vorbis::new(File::open("sd:/music/title.ogg")?)?;
+//! let music = Prefetch::new(music, Duration::from_millis(250), 80)?;
+//! mixer.play(music, PlayOptions::new());
+//!
+//! let ambience = InfiniteVoice::new(Vorbis::new(File::open("sd:/music/wind.ogg")?)?, 1)?;
+//! ```
+
+use alloc::vec::Vec;
+
+use ogc_formats::vorbis;
+
+use crate::{
+    OgcError, Result,
+    error::{AudioError, FormatError},
+    io::{Read, Seek},
+    mixer::SampleSource,
+};
+
+/// An [`OgcError`] from the reader, or a malformed stream as [`AudioError::Format`].
+struct Error(OgcError);
+
+impl From<FormatError> for Error {
+    fn from(value: FormatError) -> Self {
+        Self(OgcError::Audio(AudioError::Format(value)))
+    }
+}
+
+/// Adapts [`Read`] and [`Seek`] to the reader of [`ogc_formats::vorbis`].
+struct Reader<R>(R);
+
+impl<R: Read> vorbis::Read for Reader<R> {
+    type Error = Error;
+
+    fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Error> {
+        self.0.read(buf).map_err(Error)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> core::result::Result<(), Error> {
+        self.0.read_exact(buf).map_err(Error)
+    }
+}
+
+impl<R: Read + Seek> vorbis::Rewind for Reader<R> {
+    fn rewind(&mut self) -> core::result::Result<(), Error> {
+        self.0.rewind().map_err(Error)
+    }
+}
+
+/// A streaming Ogg Vorbis decoder.
+///
+/// Only mono and stereo streams are supported. Reading and decoding errors end playback when
+/// used as a [`SampleSource`], see [`Vorbis::error`].
+pub struct Vorbis<R: Read>(vorbis::Vorbis<Reader<R>>);
+
+impl<R: Read> Vorbis<R> {
+    /// Read the headers of the first Vorbis stream in `reader`.
+    pub fn new(reader: R) -> Result<Self> {
+        vorbis::Vorbis::new(Reader(reader))
+            .map(Self)
+            .map_err(|Error(error)| error)
+    }
+
+    /// Number of channels, `1` or `2`.
+    pub fn channels(&self) -> u8 {
+        self.0.channels()
+    }
+
+    /// Sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.0.sample_rate()
+    }
+
+    /// Frames decoded since the start of the stream.
+    pub fn position(&self) -> u64 {
+        self.0.position()
+    }
+
+    /// The error that ended playback as a [`SampleSource`], if any.
+    pub fn error(&self) -> Option<OgcError> {
+        self.0.error().map(|Error(error)| *error)
+    }
+
+    /// Return the reader.
+    pub fn into_inner(self) -> R {
+        self.0.into_inner().0
+    }
+
+    /// Fill `buf` with interleaved samples, returning how many were written.
+    ///
+    /// `0` means the end of the stream was reached or `buf` is empty.
+    pub fn decode(&mut self, buf: &mut [i16]) -> Result<usize> {
+        self.0.decode(buf).map_err(|Error(error)| error)
+    }
+
+    /// Decode everything that is left to interleaved samples.
+    pub fn decode_to_end(&mut self) -> Result<Vec<i16>> {
+        self.0.decode_to_end().map_err(|Error(error)| error)
+    }
+}
+
+impl<R: Read + Seek> Vorbis<R> {
+    /// Go back to the start of the stream.
+    pub fn rewind(&mut self) -> Result<()> {
+        self.0.rewind().map_err(|Error(error)| error)
+    }
+}
+
+impl<R: Read + Seek> SampleSource for Vorbis<R> {
+    fn sample_rate(&self) -> u32 {
+        self.0.sample_rate()
+    }
+
+    fn channels(&self) -> u8 {
+        self.0.channels()
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        SampleSource::read(&mut self.0, buf)
+    }
+
+    fn rewind(&mut self) -> bool {
+        SampleSource::rewind(&mut self.0)
+    }
+}
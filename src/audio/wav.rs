@@ -0,0 +1,19 @@
+//! RIFF WAV files holding 8 or 16-bit PCM.
+//!
+//! This is [`ogc_formats::wav`], which is tested on the host against sample files.
+
+pub use ogc_formats::wav::*;
+
+use crate::asnd::VoiceFormat;
+
+/// The `ASND` format of [`Wav::data`].
+impl From<&Wav<'_>> for VoiceFormat {
+    fn from(wav: &Wav<'_>) -> Self {
+        match (wav.channels(), wav.bits_per_sample()) {
+            (1, 8) => VoiceFormat::Mono8BitU,
+            (2, 8) => VoiceFormat::Stereo8BitU,
+            (1, _) => VoiceFormat::Mono16BitLE,
+            _ => VoiceFormat::Stereo16BitLe,
+        }
+    }
+}
@@ -42,9 +42,7 @@ impl Console {
         };
 
         if init < 0 {
-            Err(OgcError::Console(
-                "Failed to allocate memory for framebuffer!".into(),
-            ))
+            Err(OgcError::Console(init))
         } else {
             Ok(())
         }
@@ -81,6 +79,9 @@ impl Console {
 
     /// Print a formatted string to the console screen through ``printf``.
     pub fn print(formatted_string: &str) {
+        #[cfg(feature = "devserver")]
+        crate::devserver::log::push(formatted_string.as_bytes());
+
         // Create a buffer.
         let mut buffer = String::new();
 
@@ -108,3 +109,83 @@ impl Console {
         }
     }
 }
+
+impl crate::io::Write for Console {
+    /// Invalid UTF-8 is printed as replacement characters.
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        Self::print(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// How many times a read polls an idle USB Gecko before returning what it has.
+const GECKO_POLL_RETRIES: i32 = 16;
+
+/// A USB Gecko in one of the memory card slots, a raw byte pipe to a PC.
+///
+/// This talks to the adapter directly, [`Console::enable_gecko`] mirrors the console onto it
+/// instead.
+///
+/// # Examples
+///
+/// ```rust
+/// if let Some(mut gecko) = Gecko::new(1) {
+///     gecko.write_all(b"hello from the wii\n")?;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Gecko {
+    channel: i32,
+}
+
+impl Gecko {
+    /// The USB Gecko in slot `channel`, `0` for slot A and `1` for slot B.
+    ///
+    /// Returns `None` if there is no USB Gecko in that slot.
+    pub fn new(channel: i32) -> Option<Self> {
+        unsafe { ffi::usb_isgeckoalive(channel) }.then_some(Self { channel })
+    }
+
+    /// The slot this USB Gecko is in.
+    pub fn channel(&self) -> i32 {
+        self.channel
+    }
+}
+
+impl crate::io::Read for Gecko {
+    /// Blocks until at least one byte arrives, then returns whatever else is already waiting.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let Some((first, rest)) = buf.split_first_mut() else {
+            return Ok(0);
+        };
+
+        unsafe { ffi::usb_recvbuffer_safe(self.channel, (first as *mut u8).cast(), 1) };
+        let len = i32::try_from(rest.len()).unwrap_or(i32::MAX);
+        let more = unsafe {
+            ffi::usb_recvbuffer_safe_ex(
+                self.channel,
+                rest.as_mut_ptr().cast(),
+                len,
+                GECKO_POLL_RETRIES,
+            )
+        };
+        Ok(1 + more.max(0) as usize)
+    }
+}
+
+impl crate::io::Write for Gecko {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let len = i32::try_from(buf.len()).unwrap_or(i32::MAX);
+        let sent = unsafe { ffi::usb_sendbuffer_safe(self.channel, buf.as_ptr().cast(), len) };
+        Ok(sent.max(0) as usize)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        unsafe { ffi::usb_flush(self.channel) };
+        Ok(())
+    }
+}
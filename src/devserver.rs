@@ -0,0 +1,400 @@
+//! The ``devserver`` module of ``ogc-rs``.
+//!
+//! A remote debug server for development builds, enabled with the ``devserver`` feature. It
+//! listens on a TCP port and speaks two protocols on the same port:
+//!
+//! * A line based one for ``telnet`` or ``nc``. Every command is answered with `OK` followed by
+//!   its output and a line holding a single `.`, or with `ERR` and a message.
+//! * Plain HTTP/1.0 for browsers and ``curl``.
+//!
+//! | Line command        | HTTP                 | Description                                |
+//! |---------------------|----------------------|--------------------------------------------|
+//! | `help`              | `GET /`              | List the commands                          |
+//! | `log`               | `GET /log`           | Console output, the line command streams it |
+//! | `mem`               | `GET /mem`           | Arena bounds and sizes                     |
+//! | `threads`           | `GET /threads`       | Threads spawned with [`lwp::Builder`]      |
+//! | `ls <dir>`          | `GET /ls/<dir>`      | List a NAND directory                      |
+//! | `get <file>`        | `GET /files/<file>`  | Download a NAND file                       |
+//! | `put <file> <len>`  | `PUT /files/<file>`  | Upload a NAND file                         |
+//! | `run <len>`         | `POST /run`          | Upload a DOL and run it                    |
+//! | `quit`              |                      | Close the connection                       |
+//!
+//! `get` answers `OK <len>` followed by the raw contents. `put` and `run` answer `READY`, then
+//! expect exactly `<len>` raw bytes.
+//!
+//! Everything printed with [`print!`](crate::print) and [`println!`](crate::println) is kept in
+//! a 16 KiB ring buffer for `log`.
+//!
+//! The protocol itself is [`ogc_formats::devserver`], which is tested on the host against a TCP
+//! client. Uploads that do not fit in the free memory are refused instead of aborting.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let _network = Network::init()?;
+//! DevServer::bind((Ipv4Addr::UNSPECIFIED, DevServer::DEFAULT_PORT))?.spawn(48)?;
+//! ```
+//!
+//! ```sh
+//! curl --data-binary @app.dol http://wii:4405/run
+//! ```
+
+use alloc::{boxed::Box, ffi::CString, format, string::String, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    ffi::{CStr, c_void},
+    fmt::{self, Write as _},
+    mem,
+    net::SocketAddrV4,
+    time::Duration,
+};
+
+use crate::{
+    OgcError, Result,
+    error::Errno,
+    ffi, io,
+    ios::{self, Mode, fs},
+    lwp::{self, Thread},
+    network::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    system::System,
+    utils::Buf32,
+};
+use ogc_formats::devserver::{Connection, Handler, Session};
+
+pub mod dol;
+pub(crate) mod log;
+
+/// Most entries listed by `ls`.
+const MAX_ENTRIES: usize = 128;
+
+/// Data shared with other threads, only touched with interrupts disabled.
+struct Shared<T>(UnsafeCell<T>);
+
+// SAFETY: every access happens inside `critical`.
+unsafe impl<T> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    const fn new(value: T) -> Self {
+        Self(UnsafeCell::new(value))
+    }
+
+    /// # Safety
+    /// Must only be called inside `critical` and the reference must not escape it.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn get(&self) -> &mut T {
+        unsafe { &mut *self.0.get() }
+    }
+}
+
+/// Run `f` with interrupts disabled, which keeps every other thread out on this single core.
+fn critical<R>(f: impl FnOnce() -> R) -> R {
+    let level = unsafe { ffi::IRQ_Disable() };
+    let result = f();
+    unsafe { ffi::IRQ_Restore(level) };
+    result
+}
+
+static THREADS: Shared<Vec<(&'static str, Thread)>> = Shared::new(Vec::new());
+
+/// Add `thread` to the list shown by `threads`.
+///
+/// Threads spawned with [`lwp::Builder`] are added automatically as `lwp`, registering them again
+/// renames them.
+pub fn register_thread(name: &'static str, thread: &Thread) {
+    // Nothing may allocate with interrupts disabled, so a full list is grown by swapping in
+    // `spare`, which is allocated and freed out here.
+    let mut spare = Vec::new();
+    loop {
+        let needed = critical(|| {
+            // SAFETY: interrupts are disabled.
+            let threads = unsafe { THREADS.get() };
+            if let Some(entry) = threads.iter_mut().find(|(_, t)| t.id() == thread.id()) {
+                entry.0 = name;
+                return None;
+            }
+            if threads.len() == threads.capacity() {
+                if spare.capacity() <= threads.len() {
+                    return Some(threads.len() + 1);
+                }
+                spare.append(threads);
+                mem::swap(threads, &mut spare);
+            }
+            threads.push((name, thread.clone()));
+            None
+        });
+        match needed {
+            Some(len) => spare = Vec::with_capacity(len.max(8)),
+            None => return,
+        }
+    }
+}
+
+/// Remove `thread` from the list shown by `threads`.
+pub fn unregister_thread(thread: &Thread) {
+    critical(|| {
+        // SAFETY: interrupts are disabled.
+        unsafe { THREADS.get() }.retain(|(_, t)| t.id() != thread.id());
+    });
+}
+
+/// A remote debug server.
+///
+/// Clients are served one at a time, see the [module documentation](self) for the protocol.
+pub struct DevServer {
+    listener: TcpListener,
+}
+
+impl DevServer {
+    /// The port used by the examples and tools.
+    pub const DEFAULT_PORT: u16 = 4405;
+
+    /// Listen for clients on `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    /// The address the server listens on.
+    pub fn local_addr(&self) -> Result<SocketAddrV4> {
+        self.listener.local_addr()
+    }
+
+    /// Wait for a client and serve it until it disconnects.
+    pub fn serve_one(&self) -> Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        Session::new(stream, Console).run()
+    }
+
+    /// Serve clients forever.
+    pub fn run(&self) -> ! {
+        loop {
+            // A client going away must not take the server down with it.
+            let _ = self.serve_one();
+        }
+    }
+
+    /// Serve clients forever on a new thread with `priority`.
+    pub fn spawn(self, priority: u8) -> Result<Thread> {
+        unsafe extern "C" fn entry(arg: *mut c_void) -> *mut c_void {
+            // SAFETY: `arg` was created from a `Box<DevServer>` in `spawn`.
+            let server = unsafe { Box::from_raw(arg.cast::<DevServer>()) };
+            server.run()
+        }
+
+        let arg = Box::into_raw(Box::new(self));
+        let thread = lwp::Builder::new()
+            .arg(arg.cast())
+            .stack_size(32 * 1024)
+            .priority(priority)
+            .spawn(Some(entry));
+        match thread {
+            Ok(thread) => {
+                register_thread("devserver", &thread);
+                Ok(thread)
+            }
+            Err(err) => {
+                // SAFETY: the thread was not created so `arg` is still owned here.
+                drop(unsafe { Box::from_raw(arg) });
+                Err(err)
+            }
+        }
+    }
+}
+
+impl Connection for TcpStream {
+    type Error = OgcError;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        io::Read::read(self, buf)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> Result<()> {
+        io::Write::write_all(self, data)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn is_timeout(error: &OgcError) -> bool {
+        *error == OgcError::Network(Errno::ETIMEDOUT)
+    }
+
+    fn shutdown(&mut self) {
+        let _ = TcpStream::shutdown(self, Shutdown::Both);
+    }
+}
+
+/// Why a command failed, sent to the client as text.
+enum Failure {
+    Ogc(OgcError),
+    Dol(dol::DolError),
+}
+
+impl From<OgcError> for Failure {
+    fn from(value: OgcError) -> Self {
+        Self::Ogc(value)
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ogc(err) => err.fmt(f),
+            Self::Dol(err) => err.fmt(f),
+        }
+    }
+}
+
+/// Runs the commands of a [`Session`] on this console.
+struct Console;
+
+impl Handler for Console {
+    type Error = Failure;
+
+    fn mem(&mut self) -> String {
+        mem()
+    }
+
+    fn threads(&mut self) -> String {
+        threads()
+    }
+
+    fn ls(&mut self, dir: &str) -> core::result::Result<String, Failure> {
+        Ok(ls(dir)?)
+    }
+
+    fn read_file(&mut self, path: &str) -> core::result::Result<Vec<u8>, Failure> {
+        Ok(read_file(path)?)
+    }
+
+    fn write_file(&mut self, path: &str, data: &[u8]) -> core::result::Result<(), Failure> {
+        Ok(write_file(path, data)?)
+    }
+
+    fn log_since(&mut self, position: &mut u32, out: &mut Vec<u8>) {
+        log::read_since(position, out);
+    }
+
+    fn check_program(&mut self, program: &[u8]) -> core::result::Result<(), Failure> {
+        dol::Loader::new(program).map(drop).map_err(Failure::Dol)
+    }
+
+    fn run_program(&mut self, program: Vec<u8>) {
+        // SAFETY: the client asked for this program to be replaced.
+        let Err(err) = unsafe { dol::run(&program) };
+        unreachable!("checked DOL failed to load: {err}");
+    }
+}
+
+fn mem() -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "MEM1 arena {:p} - {:p}, {} bytes free",
+        System::get_arena_1_lo(),
+        System::get_arena_1_hi(),
+        System::get_arena_1_size()
+    );
+    let _ = writeln!(
+        out,
+        "MEM2 arena {:p} - {:p}, {} bytes free",
+        System::get_arena_2_lo(),
+        System::get_arena_2_hi(),
+        System::get_arena_2_size()
+    );
+    out
+}
+
+fn threads() -> String {
+    // Copy the list into a buffer allocated beforehand, retrying if it grew in between.
+    let threads = loop {
+        // SAFETY: interrupts are disabled.
+        let len = critical(|| unsafe { THREADS.get() }.len());
+        let mut threads = Vec::with_capacity(len);
+        let copied = critical(|| {
+            // SAFETY: interrupts are disabled.
+            let registered = unsafe { THREADS.get() };
+            let fits = registered.len() <= threads.capacity();
+            if fits {
+                threads.extend_from_slice(registered);
+            }
+            fits
+        });
+        if copied {
+            break threads;
+        }
+    };
+
+    let mut out = String::new();
+    let current = lwp::current().id();
+    for (name, thread) in threads {
+        let state = if thread.is_suspended() {
+            "suspended"
+        } else {
+            "running"
+        };
+        let marker = if thread.id() == current { " *" } else { "" };
+        let _ = writeln!(out, "{:#010x} {name:<16} {state}{marker}", thread.id());
+    }
+    out
+}
+
+fn ls(dir: &str) -> Result<String> {
+    let entries = fs::read_directory::<MAX_ENTRIES>(dir)?;
+    let mut out = String::new();
+    for name in entries.names() {
+        out.push_str(name);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>> {
+    let len = fs::get_file_stats(path)?.size();
+    let c_path = CString::new(path).map_err(|_| ios::Error::Invalid)?;
+
+    let mut buf = Buf32::try_new(len.max(1))?;
+    let file = ios::open(&c_path, Mode::Read)?;
+    let res = ios::read(file, &mut buf[..len]);
+    let _ = ios::close(file);
+
+    let read = usize::try_from(res?).map_err(|_| ios::Error::Invalid)?;
+    Ok(buf[..read].to_vec())
+}
+
+/// Replace `path` with `data`, creating it with the attributes of its directory if needed.
+///
+/// `data` is written to a file of the same name in `/tmp` first and then renamed over `path`,
+/// so a failed upload never leaves `path` half written.
+fn write_file(path: &str, data: &[u8]) -> Result<()> {
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    let tmp = format!("/tmp/{name}");
+    let c_tmp = CString::new(tmp.as_str()).map_err(|_| ios::Error::Invalid)?;
+    let attributes = match fs::get_attributes(path) {
+        Ok(attributes) => attributes,
+        Err(_) => fs::get_attributes(if dir.is_empty() { "/" } else { dir })?,
+    };
+    let _ = fs::delete(&tmp);
+    fs::create_file(attributes.with_path(&tmp)?)?;
+
+    let res = write_new(&c_tmp, data).and_then(|()| Ok(fs::rename(&tmp, path)?));
+    if res.is_err() {
+        let _ = fs::delete(&tmp);
+    }
+    res
+}
+
+fn write_new(path: &CStr, data: &[u8]) -> Result<()> {
+    let mut buf = Buf32::try_new(data.len().max(1))?;
+    buf[..data.len()].copy_from_slice(data);
+    let file = ios::open(path, Mode::Write)?;
+    let res = ios::write(file, &buf[..data.len()]);
+    let _ = ios::close(file);
+
+    if usize::try_from(res?).map_err(|_| ios::Error::Invalid)? != data.len() {
+        return Err(ios::Error::Invalid.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,329 @@
+//! Parsing and launching DOL executables.
+
+use alloc::vec::Vec;
+use core::{fmt, ops::Range};
+
+use crate::{ffi, system::System};
+
+/// Size of the DOL header.
+const HEADER_SIZE: usize = 0x100;
+const TEXT_COUNT: usize = 7;
+const DATA_COUNT: usize = 11;
+
+/// Memory a DOL may load into: `MEM1` above the exception vectors and `MEM2`, both cached.
+const LOADABLE: [Range<u32>; 2] = [0x8000_3000..0x8180_0000, 0x9000_0000..0x9400_0000];
+
+/// Where the loader stub is copied to, the area homebrew loaders keep free for their stubs.
+const STUB_ADDR: u32 = 0x8000_1800;
+
+unsafe extern "C" {
+    static ogc_rs_dol_stub: u8;
+    static ogc_rs_dol_stub_end: u8;
+}
+
+// Copies every section into place and jumps to the entry point. It runs from `STUB_ADDR` with
+// interrupts disabled so it must not call anything.
+//
+// r3: table of (destination, source, length) words ending with a zero length, a zero source
+//     fills the destination with zeroes
+// r4: entry point
+core::arch::global_asm!(
+    ".section .text.ogc_rs_dol_stub,\"ax\",@progbits",
+    ".global ogc_rs_dol_stub",
+    ".global ogc_rs_dol_stub_end",
+    ".balign 32",
+    "ogc_rs_dol_stub:",
+    "1:  lwz 5, 0(3)",
+    "    lwz 6, 4(3)",
+    "    lwz 7, 8(3)",
+    "    addi 3, 3, 12",
+    "    cmpwi 7, 0",
+    "    beq 6f",
+    "    mr 8, 5",
+    "    add 9, 5, 7",
+    "    mtctr 7",
+    "    addi 5, 5, -1",
+    "    cmpwi 6, 0",
+    "    beq 3f",
+    "    addi 6, 6, -1",
+    "2:  lbzu 10, 1(6)",
+    "    stbu 10, 1(5)",
+    "    bdnz 2b",
+    "    b 4f",
+    "3:  li 10, 0",
+    "    stbu 10, 1(5)",
+    "    bdnz 3b",
+    "4:  rlwinm 8, 8, 0, 0, 26",
+    "5:  dcbst 0, 8",
+    "    sync",
+    "    icbi 0, 8",
+    "    addi 8, 8, 32",
+    "    cmplw 8, 9",
+    "    blt 5b",
+    "    sync",
+    "    isync",
+    "    b 1b",
+    "6:  mtctr 4",
+    "    bctr",
+    "ogc_rs_dol_stub_end:",
+    ".previous",
+);
+
+/// Why a DOL could not be loaded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DolError {
+    /// The file is smaller than the header.
+    TooSmall,
+    /// A section points past the end of the file.
+    Truncated,
+    /// A section would load outside of `MEM1` and `MEM2`, or over memory needed to load it.
+    BadAddress(u32),
+    /// The entry point is not inside a text section.
+    BadEntry(u32),
+    /// There is not enough free `MEM2` to stage the file.
+    TooLarge,
+}
+
+impl fmt::Display for DolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall => write!(f, "file is smaller than a DOL header"),
+            Self::Truncated => write!(f, "a section points past the end of the file"),
+            Self::BadAddress(addr) => write!(f, "a section cannot be loaded at {addr:#010x}"),
+            Self::BadEntry(addr) => write!(f, "entry point {addr:#010x} is not in a text section"),
+            Self::TooLarge => write!(f, "not enough free MEM2 to stage the file"),
+        }
+    }
+}
+
+impl core::error::Error for DolError {}
+
+/// A section of a DOL.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// Offset of the contents in the file
+    pub offset: u32,
+    /// Load address
+    pub address: u32,
+    /// Length in bytes
+    pub size: u32,
+}
+
+impl Section {
+    fn range(&self) -> Range<u32> {
+        self.address..self.address.saturating_add(self.size)
+    }
+}
+
+/// The header of a DOL executable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dol {
+    text: Vec<Section>,
+    data: Vec<Section>,
+    bss: Range<u32>,
+    entry: u32,
+}
+
+impl Dol {
+    /// Parse and validate the header of `file`.
+    ///
+    /// # Errors
+    /// See [`DolError`]
+    pub fn parse(file: &[u8]) -> Result<Self, DolError> {
+        let header = file.get(..HEADER_SIZE).ok_or(DolError::TooSmall)?;
+        let word = |offset: usize| {
+            u32::from_be_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        let sections = |first: usize, count: usize| {
+            (first..first + count)
+                .map(|i| Section {
+                    offset: word(i * 4),
+                    address: word(0x48 + i * 4),
+                    size: word(0x90 + i * 4),
+                })
+                .filter(|section| section.size != 0)
+                .collect::<Vec<_>>()
+        };
+
+        let dol = Self {
+            text: sections(0, TEXT_COUNT),
+            data: sections(TEXT_COUNT, DATA_COUNT),
+            bss: word(0xD8)..word(0xD8).saturating_add(word(0xDC)),
+            entry: word(0xE0),
+        };
+
+        for section in dol.sections() {
+            let end = section.offset as usize + section.size as usize;
+            if end > file.len() {
+                return Err(DolError::Truncated);
+            }
+            check_loadable(&section.range())?;
+        }
+        if !dol.bss.is_empty() {
+            check_loadable(&dol.bss)?;
+        }
+        if !dol
+            .text
+            .iter()
+            .any(|text| text.range().contains(&dol.entry))
+        {
+            return Err(DolError::BadEntry(dol.entry));
+        }
+
+        Ok(dol)
+    }
+
+    /// The text and data sections.
+    pub fn sections(&self) -> impl Iterator<Item = &Section> {
+        self.text.iter().chain(&self.data)
+    }
+
+    /// The range cleared to zero before the sections are loaded.
+    pub fn bss(&self) -> Range<u32> {
+        self.bss.clone()
+    }
+
+    /// The address execution starts at.
+    pub fn entry(&self) -> u32 {
+        self.entry
+    }
+}
+
+fn check_loadable(range: &Range<u32>) -> Result<(), DolError> {
+    if LOADABLE
+        .iter()
+        .any(|area| area.start <= range.start && range.end <= area.end)
+    {
+        Ok(())
+    } else {
+        Err(DolError::BadAddress(range.start))
+    }
+}
+
+fn overlaps(a: &Range<u32>, b: &Range<u32>) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+/// A DOL that was checked and can be launched with [`Loader::run`].
+pub struct Loader<'a> {
+    file: &'a [u8],
+    table: Vec<u32>,
+    staging: usize,
+    staged_len: usize,
+    stub: *const u8,
+    stub_len: usize,
+    entry: u32,
+}
+
+impl<'a> Loader<'a> {
+    /// Parse `file` and make sure it can be loaded without overwriting the memory used to load
+    /// it.
+    ///
+    /// The file is staged at the top of `MEM2` and a small stub copies the sections into place,
+    /// so the running program may be overwritten.
+    ///
+    /// # Errors
+    /// See [`DolError`]
+    pub fn new(file: &'a [u8]) -> Result<Self, DolError> {
+        let dol = Dol::parse(file)?;
+
+        let entries = dol.sections().count() + usize::from(!dol.bss.is_empty()) + 1;
+        let table_len = entries * 12;
+        let staged_len = (table_len + file.len()).next_multiple_of(32);
+
+        let hi = System::get_arena_2_hi() as usize;
+        let lo = System::get_arena_2_lo() as usize;
+        let staging = hi.checked_sub(staged_len).ok_or(DolError::TooLarge)? & !31;
+        if staging < lo {
+            return Err(DolError::TooLarge);
+        }
+
+        // SAFETY: the stub symbols are defined by the `global_asm!` above.
+        let (stub, stub_len) = unsafe {
+            let start = &raw const ogc_rs_dol_stub;
+            let end = &raw const ogc_rs_dol_stub_end;
+            (start, end as usize - start as usize)
+        };
+
+        let reserved = [
+            staging as u32..hi as u32,
+            STUB_ADDR..STUB_ADDR + stub_len as u32,
+        ];
+        for range in dol.sections().map(Section::range).chain([dol.bss()]) {
+            if let Some(reserved) = reserved.iter().find(|reserved| overlaps(&range, reserved)) {
+                return Err(DolError::BadAddress(reserved.start.max(range.start)));
+            }
+        }
+
+        let mut table = Vec::with_capacity(entries * 3);
+        if !dol.bss.is_empty() {
+            table.extend([dol.bss.start, 0, dol.bss.end - dol.bss.start]);
+        }
+        let file_start = (staging + table_len) as u32;
+        for section in dol.sections() {
+            table.extend([section.address, file_start + section.offset, section.size]);
+        }
+        table.extend([0, 0, 0]);
+
+        Ok(Self {
+            file,
+            table,
+            staging,
+            staged_len,
+            stub,
+            stub_len,
+            entry: dol.entry,
+        })
+    }
+
+    /// Shut the system down and run the DOL.
+    ///
+    /// # Safety
+    ///
+    /// This ends the running program without running destructors and jumps into the file, which
+    /// must be a program for this console.
+    pub unsafe fn run(self) -> ! {
+        let table_len = self.table.len() * 4;
+        unsafe {
+            ffi::SYS_ResetSystem(ffi::SYS_SHUTDOWN as i32, 0, 0);
+            ffi::IRQ_Disable();
+
+            let staged = self.staging as *mut u8;
+            core::ptr::copy_nonoverlapping(self.table.as_ptr().cast::<u8>(), staged, table_len);
+            core::ptr::copy_nonoverlapping(
+                self.file.as_ptr(),
+                staged.add(table_len),
+                self.file.len(),
+            );
+            ffi::DCFlushRange(staged.cast(), self.staged_len as u32);
+
+            let stub_addr = STUB_ADDR as *mut u8;
+            core::ptr::copy_nonoverlapping(self.stub, stub_addr, self.stub_len);
+            ffi::DCFlushRange(stub_addr.cast(), self.stub_len as u32);
+            ffi::ICInvalidateRange(stub_addr.cast(), self.stub_len as u32);
+
+            let stub: extern "C" fn(*const u32, u32) -> ! = core::mem::transmute(stub_addr);
+            stub(staged.cast(), self.entry)
+        }
+    }
+}
+
+/// Shut the system down and run the DOL in `file`, see [`Loader`].
+///
+/// Only returns if `file` cannot be loaded.
+///
+/// # Errors
+/// See [`DolError`]
+///
+/// # Safety
+///
+/// See [`Loader::run`]
+pub unsafe fn run(file: &[u8]) -> Result<!, DolError> {
+    let loader = Loader::new(file)?;
+    unsafe { loader.run() }
+}
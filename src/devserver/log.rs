@@ -0,0 +1,53 @@
+//! Ring buffer holding the most recent console output.
+
+use alloc::vec::Vec;
+
+use super::{Shared, critical};
+
+/// How many bytes of output are kept.
+const LOG_SIZE: usize = 16 * 1024;
+
+struct Ring {
+    buf: [u8; LOG_SIZE],
+    /// Total bytes ever written, wrapping.
+    written: u32,
+}
+
+static LOG: Shared<Ring> = Shared::new(Ring {
+    buf: [0; LOG_SIZE],
+    written: 0,
+});
+
+/// Append `data` to the log, dropping the oldest output once full.
+pub(crate) fn push(data: &[u8]) {
+    let data = &data[data.len().saturating_sub(LOG_SIZE)..];
+    critical(|| {
+        // SAFETY: interrupts are disabled, nothing else can touch the ring.
+        let ring = unsafe { LOG.get() };
+        let start = ring.written as usize % LOG_SIZE;
+        let first = data.len().min(LOG_SIZE - start);
+        ring.buf[start..start + first].copy_from_slice(&data[..first]);
+        ring.buf[..data.len() - first].copy_from_slice(&data[first..]);
+        ring.written = ring.written.wrapping_add(data.len() as u32);
+    });
+}
+
+/// Append everything written since `position` to `out` and move `position` to the end.
+///
+/// Output that was already dropped from the ring is skipped, a `position` of `0` returns
+/// everything still held.
+pub(crate) fn read_since(position: &mut u32, out: &mut Vec<u8>) {
+    critical(|| {
+        // SAFETY: interrupts are disabled, nothing else can touch the ring.
+        let ring = unsafe { LOG.get() };
+        let pending = (ring.written.wrapping_sub(*position) as usize).min(LOG_SIZE);
+        let held = (ring.written as usize).min(LOG_SIZE);
+        let len = pending.min(held);
+
+        let start = ring.written.wrapping_sub(len as u32) as usize % LOG_SIZE;
+        let first = len.min(LOG_SIZE - start);
+        out.extend_from_slice(&ring.buf[start..start + first]);
+        out.extend_from_slice(&ring.buf[..len - first]);
+        *position = ring.written;
+    });
+}
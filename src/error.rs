@@ -1,37 +1,479 @@
 //! Custom Error Implementation for ``ogc-rs``.
+//!
+//! Every module error converts into [`OgcError`] so `?` works across modules. None of the error
+//! types allocate.
 
-use alloc::string::String;
-use core::fmt;
+use core::{alloc::Layout, fmt};
+
+use ogc_formats::{devserver::ProtocolError, dns::DnsError};
+
+use crate::{ios, mutex::LockError, sysconf};
+
+/// Errors parsing files, shared with the host side [`ogc_formats`].
+pub use ogc_formats::FormatError;
+/// HTTP Errors, shared with the response parser in [`ogc_formats::http`].
+pub use ogc_formats::http::HttpError;
 
 /// Custom Result Type that uses the error type.
 pub type Result<T> = core::result::Result<T, OgcError>;
 
 /// Custom Error Type
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum OgcError {
-    Network(String),
-    Audio(String),
-    Console(String),
-    System(String),
+    /// An `IOS` request failed.
+    Ios(ios::Error),
+    /// Reading or writing the system configuration failed.
+    SysConf(sysconf::Error),
+    /// A socket call failed.
+    Network(Errno),
+    /// An HTTP request failed.
+    Http(HttpError),
+    /// A [`Read`](crate::io::Read) or [`Write`](crate::io::Write) call failed.
+    Io(IoError),
+    /// A thread call failed.
+    Lwp(Errno),
+    /// A mutex call failed.
+    Lock(LockError),
+    /// A graphics call was given invalid arguments.
+    Gx(GxError),
+    /// An audio call failed.
+    Audio(AudioError),
+    /// A controller call failed.
+    Input(InputError),
+    /// Console initialization failed with this return code.
+    Console(i32),
+    /// A system call failed with this return code.
+    System(i32),
+    /// An allocation failed.
+    Alloc(AllocError),
 }
 
-impl fmt::Debug for OgcError {
+impl fmt::Display for OgcError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            OgcError::Ios(err) => write!(f, "[ OGC - IOS ]: {err}"),
+            OgcError::SysConf(err) => write!(f, "[ OGC - SysConf ]: {err}"),
             OgcError::Network(err) => write!(f, "[ OGC - Network ]: {err}"),
+            OgcError::Http(err) => write!(f, "[ OGC - HTTP ]: {err}"),
+            OgcError::Io(err) => write!(f, "[ OGC - IO ]: {err}"),
+            OgcError::Lwp(err) => write!(f, "[ OGC - LWP ]: {err}"),
+            OgcError::Lock(err) => write!(f, "[ OGC - Mutex ]: {err}"),
+            OgcError::Gx(err) => write!(f, "[ OGC - GX ]: {err}"),
             OgcError::Audio(err) => write!(f, "[ OGC - Audio ]: {err}"),
-            OgcError::Console(err) => write!(f, "[ OGC - Console ]: {err}"),
-            OgcError::System(err) => write!(f, "[ OGC - System ]: {err}"),
+            OgcError::Input(err) => write!(f, "[ OGC - Input ]: {err}"),
+            OgcError::Console(err) => write!(f, "[ OGC - Console ]: error code {err}"),
+            OgcError::System(err) => write!(f, "[ OGC - System ]: error code {err}"),
+            OgcError::Alloc(err) => write!(f, "[ OGC - Alloc ]: {err}"),
         }
     }
 }
 
-impl fmt::Display for OgcError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl core::error::Error for OgcError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
         match self {
-            OgcError::Network(err) => write!(f, "[ OGC - Network ]: {err}"),
-            OgcError::Audio(err) => write!(f, "[ OGC - Audio ]: {err}"),
-            OgcError::Console(err) => write!(f, "[ OGC - Console ]: {err}"),
-            OgcError::System(err) => write!(f, "[ OGC - System ]: {err}"),
+            OgcError::Ios(err) => Some(err),
+            OgcError::SysConf(err) => Some(err),
+            OgcError::Network(err) | OgcError::Lwp(err) => Some(err),
+            OgcError::Http(err) => Some(err),
+            OgcError::Io(err) => Some(err),
+            OgcError::Lock(err) => Some(err),
+            OgcError::Gx(err) => Some(err),
+            OgcError::Audio(err) => Some(err),
+            OgcError::Input(err) => Some(err),
+            OgcError::Alloc(err) => Some(err),
+            OgcError::Console(_) | OgcError::System(_) => None,
+        }
+    }
+}
+
+impl From<ios::Error> for OgcError {
+    fn from(value: ios::Error) -> Self {
+        Self::Ios(value)
+    }
+}
+
+impl From<sysconf::Error> for OgcError {
+    fn from(value: sysconf::Error) -> Self {
+        match value {
+            sysconf::Error::Ios(err) => Self::Ios(err),
+            err => Self::SysConf(err),
+        }
+    }
+}
+
+impl From<LockError> for OgcError {
+    fn from(value: LockError) -> Self {
+        Self::Lock(value)
+    }
+}
+
+impl From<HttpError> for OgcError {
+    fn from(value: HttpError) -> Self {
+        Self::Http(value)
+    }
+}
+
+impl From<IoError> for OgcError {
+    fn from(value: IoError) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<GxError> for OgcError {
+    fn from(value: GxError) -> Self {
+        Self::Gx(value)
+    }
+}
+
+impl From<AudioError> for OgcError {
+    fn from(value: AudioError) -> Self {
+        Self::Audio(value)
+    }
+}
+
+impl From<InputError> for OgcError {
+    fn from(value: InputError) -> Self {
+        Self::Input(value)
+    }
+}
+
+impl From<DnsError> for OgcError {
+    fn from(value: DnsError) -> Self {
+        Self::Network(match value {
+            DnsError::InvalidName => Errno::EINVAL,
+            DnsError::BufferTooSmall | DnsError::Truncated => Errno::EMSGSIZE,
+            DnsError::NotFound => Errno::EHOSTUNREACH,
+            DnsError::Malformed => Errno::EIO,
+        })
+    }
+}
+
+impl From<ProtocolError> for OgcError {
+    fn from(value: ProtocolError) -> Self {
+        match value {
+            ProtocolError::LineTooLong => Self::Network(Errno::EMSGSIZE),
+            ProtocolError::UnexpectedEof => Self::Io(IoError::UnexpectedEof),
+        }
+    }
+}
+
+impl From<AllocError> for OgcError {
+    fn from(value: AllocError) -> Self {
+        Self::Alloc(value)
+    }
+}
+
+/// An `errno` value as used by newlib.
+///
+/// libogc reports socket and thread failures as negative `errno` values, use
+/// [`Errno::from_return`] to turn such a return code into an [`Errno`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Errno(pub i32);
+
+macro_rules! errno {
+    ($($(#[$meta:meta])* $name:ident = $val:literal,)*) => {
+        impl Errno {
+            $($(#[$meta])* pub const $name: Errno = Errno($val);)*
+
+            /// The symbolic name of this value, if it is known.
+            pub fn name(self) -> Option<&'static str> {
+                match self.0 {
+                    $($val => Some(stringify!($name)),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+errno! {
+    /// Operation not permitted
+    EPERM = 1,
+    /// No such file or directory
+    ENOENT = 2,
+    /// Interrupted system call
+    EINTR = 4,
+    /// I/O error
+    EIO = 5,
+    /// Bad file descriptor
+    EBADF = 9,
+    /// Resource temporarily unavailable
+    EAGAIN = 11,
+    /// Out of memory
+    ENOMEM = 12,
+    /// Permission denied
+    EACCES = 13,
+    /// Bad address
+    EFAULT = 14,
+    /// Device or resource busy
+    EBUSY = 16,
+    /// File exists
+    EEXIST = 17,
+    /// Invalid argument
+    EINVAL = 22,
+    /// Too many open files
+    EMFILE = 24,
+    /// Broken pipe
+    EPIPE = 32,
+    /// Resource deadlock would occur
+    EDEADLK = 45,
+    /// Function not implemented
+    ENOSYS = 88,
+    /// Operation not supported on socket
+    EOPNOTSUPP = 95,
+    /// Connection reset by peer
+    ECONNRESET = 104,
+    /// No buffer space available
+    ENOBUFS = 105,
+    /// Address family not supported
+    EAFNOSUPPORT = 106,
+    /// Socket operation on non-socket
+    ENOTSOCK = 108,
+    /// Protocol not available
+    ENOPROTOOPT = 109,
+    /// Connection refused
+    ECONNREFUSED = 111,
+    /// Address already in use
+    EADDRINUSE = 112,
+    /// Software caused connection abort
+    ECONNABORTED = 113,
+    /// Network is unreachable
+    ENETUNREACH = 114,
+    /// Network is down
+    ENETDOWN = 115,
+    /// Connection timed out
+    ETIMEDOUT = 116,
+    /// Host is unreachable
+    EHOSTUNREACH = 118,
+    /// Operation now in progress
+    EINPROGRESS = 119,
+    /// Operation already in progress
+    EALREADY = 120,
+    /// Message too long
+    EMSGSIZE = 122,
+    /// Address not available
+    EADDRNOTAVAIL = 125,
+    /// Socket is already connected
+    EISCONN = 127,
+    /// Socket is not connected
+    ENOTCONN = 128,
+}
+
+impl Errno {
+    /// Operation would block, the same value as [`Errno::EAGAIN`]
+    pub const EWOULDBLOCK: Errno = Errno::EAGAIN;
+
+    /// Convert a negative libogc return code into an [`Errno`].
+    pub fn from_return(ret: i32) -> Self {
+        Errno(ret.saturating_abs())
+    }
+}
+
+impl fmt::Debug for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "Errno({name})"),
+            None => write!(f, "Errno({})", self.0),
         }
     }
 }
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name} ({})", self.0),
+            None => write!(f, "errno {}", self.0),
+        }
+    }
+}
+
+impl core::error::Error for Errno {}
+
+/// I/O Errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IoError {
+    /// The source ended before the buffer was filled.
+    UnexpectedEof,
+    /// The sink stopped accepting bytes.
+    WriteZero,
+    /// A seek would move before the start or out of the supported range.
+    InvalidSeek,
+    /// The data was not valid UTF-8.
+    InvalidData,
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "unexpected end of file"),
+            Self::WriteZero => write!(f, "write returned zero bytes"),
+            Self::InvalidSeek => write!(f, "invalid seek position"),
+            Self::InvalidData => write!(f, "data is not valid UTF-8"),
+        }
+    }
+}
+
+impl core::error::Error for IoError {}
+
+/// Graphics Errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GxError {
+    /// A texture was larger than the 1024x1024 the hardware supports.
+    TextureTooLarge {
+        /// Requested width
+        width: u16,
+        /// Requested height
+        height: u16,
+    },
+    /// A buffer handed to the GPU was not 32 byte aligned.
+    Misaligned,
+}
+
+impl fmt::Display for GxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TextureTooLarge { width, height } => write!(
+                f,
+                "texture of {width}x{height} is larger than the maximum of 1024x1024"
+            ),
+            Self::Misaligned => write!(f, "buffer is not 32 byte aligned"),
+        }
+    }
+}
+
+impl core::error::Error for GxError {}
+
+/// Audio Errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AudioError {
+    /// An invalid voice or argument was passed.
+    Invalid,
+    /// Every voice is already in use.
+    NoFreeVoice,
+    /// The voice is not playing, this contains its current status.
+    NotPlaying(i32),
+    /// Any other return code.
+    Unknown(i32),
+    /// An audio file could not be parsed.
+    Format(FormatError),
+}
+
+impl AudioError {
+    /// Convert an `ASND` return code into an [`AudioError`].
+    pub fn from_asnd(code: i32) -> Self {
+        if code == crate::ffi::SND_INVALID as _ {
+            Self::Invalid
+        } else {
+            Self::Unknown(code)
+        }
+    }
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid => write!(f, "an invalid voice or argument was provided"),
+            Self::NoFreeVoice => write!(f, "no free voice was available"),
+            Self::NotPlaying(status) => write!(f, "the voice was not playing, status {status}"),
+            Self::Unknown(code) => write!(f, "unknown error code {code}"),
+            Self::Format(err) => write!(f, "invalid audio file: {err}"),
+        }
+    }
+}
+
+impl core::error::Error for AudioError {}
+
+/// Controller Errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InputError {
+    /// No controller is connected to the port.
+    NoController,
+    /// The controller is still being set up.
+    NotReady,
+    /// Sending to the controller failed.
+    Transfer,
+    /// The port does not exist.
+    BadChannel,
+    /// An invalid argument was passed.
+    Invalid,
+    /// The controller or libogc cannot do this.
+    Unsupported,
+    /// An input recording could not be read.
+    Recording(FormatError),
+    /// Any other return code.
+    Unknown(i32),
+}
+
+impl InputError {
+    /// Convert a `WPAD` return code into an [`InputError`].
+    pub fn from_wpad(code: i32) -> Self {
+        use crate::ffi;
+        match code {
+            ffi::WPAD_ERR_NO_CONTROLLER => Self::NoController,
+            ffi::WPAD_ERR_NOT_READY => Self::NotReady,
+            ffi::WPAD_ERR_TRANSFER => Self::Transfer,
+            ffi::WPAD_ERR_BAD_CHANNEL => Self::BadChannel,
+            ffi::WPAD_ERR_BADVALUE => Self::Invalid,
+            code => Self::Unknown(code),
+        }
+    }
+
+    /// Convert a `PAD` error code into an [`InputError`].
+    pub fn from_pad(code: i32) -> Self {
+        use crate::ffi;
+        match code {
+            ffi::PAD_ERR_NO_CONTROLLER => Self::NoController,
+            ffi::PAD_ERR_NOT_READY => Self::NotReady,
+            ffi::PAD_ERR_TRANSFER => Self::Transfer,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoController => write!(f, "no controller is connected"),
+            Self::NotReady => write!(f, "the controller is not ready"),
+            Self::Transfer => write!(f, "sending to the controller failed"),
+            Self::BadChannel => write!(f, "the controller port does not exist"),
+            Self::Invalid => write!(f, "an invalid argument was provided"),
+            Self::Unsupported => write!(f, "not supported by the controller"),
+            Self::Recording(error) => write!(f, "input recording: {error}"),
+            Self::Unknown(code) => write!(f, "unknown error code {code}"),
+        }
+    }
+}
+
+impl core::error::Error for InputError {}
+
+/// An allocation of `size` bytes aligned to `align` failed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllocError {
+    /// Requested size in bytes
+    pub size: usize,
+    /// Requested alignment in bytes
+    pub align: usize,
+}
+
+impl From<Layout> for AllocError {
+    fn from(value: Layout) -> Self {
+        Self {
+            size: value.size(),
+            align: value.align(),
+        }
+    }
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to allocate {} bytes aligned to {}",
+            self.size, self.align
+        )
+    }
+}
+
+impl core::error::Error for AllocError {}
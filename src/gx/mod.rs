@@ -13,6 +13,7 @@ use voladdress::{Safe, VolAddress};
 
 use num_traits::Float;
 
+use crate::error::GxError;
 use crate::ffi::{self, Mtx as Mtx34, Mtx44};
 use crate::gx::regs::BPReg;
 use crate::utils::mem;
@@ -400,14 +401,28 @@ impl Fifo {
     /// Constructs a new `Fifo` with the given size.
     ///
     /// If the given size is less than the minimum, the minimum size is used.
-    pub fn with_size(mut size: usize) -> Self {
+    pub fn with_size(size: usize) -> Self {
+        match Self::try_with_size(size) {
+            Ok(fifo) => fifo,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Constructs a new `Fifo` with the given size, returning an error if the buffer could not be
+    /// allocated.
+    ///
+    /// If the given size is less than the minimum, the minimum size is used.
+    ///
+    /// # Errors
+    /// Returns [`OgcError::Alloc`](crate::OgcError::Alloc) if the allocator is out of memory.
+    pub fn try_with_size(mut size: usize) -> crate::Result<Self> {
         let mut fifo = core::mem::MaybeUninit::zeroed();
 
         if size < Fifo::MIN_SIZE {
             size = Fifo::MIN_SIZE;
         }
 
-        let mut buf = crate::utils::Buf32::new(size);
+        let mut buf = crate::utils::Buf32::try_new(size)?;
 
         // SAFETY:
         // + original libogc source suggests that available init functions don't
@@ -420,7 +435,7 @@ impl Fifo {
                 buf.as_mut_ptr().map_addr(mem::to_uncached) as *mut _,
                 buf.len() as u32,
             );
-            Fifo(fifo.assume_init())
+            Ok(Fifo(fifo.assume_init()))
         }
     }
 
@@ -874,10 +889,33 @@ impl<'a> Texture<'a> {
         wrap_t: WrapMode,
         mipmap: bool,
     ) -> Texture<'a> {
+        match Self::try_new(img, width, height, format, wrap_s, wrap_t, mipmap) {
+            Ok(texture) => texture,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Same as [`Texture::new`] but returns an error instead of panicking when `img` is not 32
+    /// byte aligned or the texture is larger than 1024x1024.
+    ///
+    /// # Errors
+    /// See [`GxError`]
+    pub fn try_new(
+        img: &'a [u8],
+        width: u16,
+        height: u16,
+        format: u8,
+        wrap_s: WrapMode,
+        wrap_t: WrapMode,
+        mipmap: bool,
+    ) -> Result<Texture<'a>, GxError> {
         let texture = core::mem::MaybeUninit::zeroed();
-        assert_eq!(0, img.as_ptr().align_offset(32));
-        assert!(width <= 1024, "max width for texture is 1024");
-        assert!(height <= 1024, "max height for texture is 1024");
+        if img.as_ptr().align_offset(32) != 0 {
+            return Err(GxError::Misaligned);
+        }
+        if width > 1024 || height > 1024 {
+            return Err(GxError::TextureTooLarge { width, height });
+        }
         unsafe {
             cache::data_cache_flush(img);
         }
@@ -892,7 +930,7 @@ impl<'a> Texture<'a> {
                 wrap_t as u8,
                 mipmap as u8,
             );
-            Texture(texture.assume_init(), PhantomData)
+            Ok(Texture(texture.assume_init(), PhantomData))
         }
     }
 
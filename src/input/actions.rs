@@ -0,0 +1,763 @@
+//! Named actions bound to any controller input.
+
+use super::{ControllerPort, Extension, Pad, PadButton, PadStatus, WPad, WPadButton, WPadState};
+use crate::{Result, error::InputError};
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+use num_traits::Float;
+
+/// Main stick deflection libogc reports at the edge of the gate.
+const STICK_RANGE: f32 = 100.0;
+/// Analog trigger travel before the click.
+const TRIGGER_RANGE: f32 = 200.0;
+/// Tilt reported as a full deflection, in degrees.
+const TILT_RANGE: f32 = 90.0;
+/// Value an action has to reach to count as held.
+const PRESS_THRESHOLD: f32 = 0.5;
+
+/// An analog input, see [`Binding::Axis`].
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Axis {
+    PadStickX,
+    PadStickY,
+    PadCStickX,
+    PadCStickY,
+    PadTriggerL,
+    PadTriggerR,
+    NunchukStickX,
+    NunchukStickY,
+    ClassicLeftStickX,
+    ClassicLeftStickY,
+    ClassicRightStickX,
+    ClassicRightStickY,
+    ClassicTriggerL,
+    ClassicTriggerR,
+    /// Where the remote points, -1.0 at the left edge of the screen to 1.0 at the right
+    IrX,
+    /// Where the remote points, -1.0 at the top edge of the screen to 1.0 at the bottom
+    IrY,
+    /// Remote roll, 90° to either side is a full deflection
+    Roll,
+    /// Remote pitch, 90° up or down is a full deflection
+    Pitch,
+}
+
+const AXES: [(&str, Axis); 18] = [
+    ("pad.stick-x", Axis::PadStickX),
+    ("pad.stick-y", Axis::PadStickY),
+    ("pad.c-stick-x", Axis::PadCStickX),
+    ("pad.c-stick-y", Axis::PadCStickY),
+    ("pad.trigger-l", Axis::PadTriggerL),
+    ("pad.trigger-r", Axis::PadTriggerR),
+    ("nunchuk.stick-x", Axis::NunchukStickX),
+    ("nunchuk.stick-y", Axis::NunchukStickY),
+    ("classic.left-stick-x", Axis::ClassicLeftStickX),
+    ("classic.left-stick-y", Axis::ClassicLeftStickY),
+    ("classic.right-stick-x", Axis::ClassicRightStickX),
+    ("classic.right-stick-y", Axis::ClassicRightStickY),
+    ("classic.trigger-l", Axis::ClassicTriggerL),
+    ("classic.trigger-r", Axis::ClassicTriggerR),
+    ("remote.ir-x", Axis::IrX),
+    ("remote.ir-y", Axis::IrY),
+    ("remote.roll", Axis::Roll),
+    ("remote.pitch", Axis::Pitch),
+];
+
+impl Axis {
+    fn name(self) -> &'static str {
+        AXES.iter()
+            .find(|&&(_, axis)| axis == self)
+            .map_or("", |&(name, _)| name)
+    }
+
+    /// Whether the axis rests at a position other than zero, so it cannot be detected as
+    /// pressed.
+    fn is_positional(self) -> bool {
+        matches!(self, Self::IrX | Self::IrY | Self::Roll | Self::Pitch)
+    }
+
+    /// Value of the axis in `input`, -1.0 to 1.0, or 0.0 to 1.0 for triggers.
+    fn value(self, input: &InputState) -> f32 {
+        let pad = input.pad.as_ref();
+        let wpad = input.wpad.as_ref();
+        let nunchuk = wpad.and_then(|wpad| match wpad.extension {
+            Extension::Nunchuk(nunchuk) => Some(nunchuk),
+            _ => None,
+        });
+        let classic = wpad.and_then(|wpad| match wpad.extension {
+            Extension::Classic(classic) => Some(classic),
+            _ => None,
+        });
+        let ir = wpad.map(|wpad| wpad.ir).filter(|ir| ir.valid);
+        let (width, height) = input.screen_size;
+
+        let value = match self {
+            Self::PadStickX => pad.map(|pad| f32::from(pad.stick_x) / STICK_RANGE),
+            Self::PadStickY => pad.map(|pad| f32::from(pad.stick_y) / STICK_RANGE),
+            Self::PadCStickX => pad.map(|pad| f32::from(pad.c_stick_x) / STICK_RANGE),
+            Self::PadCStickY => pad.map(|pad| f32::from(pad.c_stick_y) / STICK_RANGE),
+            Self::PadTriggerL => pad.map(|pad| f32::from(pad.trigger_l) / TRIGGER_RANGE),
+            Self::PadTriggerR => pad.map(|pad| f32::from(pad.trigger_r) / TRIGGER_RANGE),
+            Self::NunchukStickX => nunchuk.map(|nunchuk| nunchuk.stick.x),
+            Self::NunchukStickY => nunchuk.map(|nunchuk| nunchuk.stick.y),
+            Self::ClassicLeftStickX => classic.map(|classic| classic.left_stick.x),
+            Self::ClassicLeftStickY => classic.map(|classic| classic.left_stick.y),
+            Self::ClassicRightStickX => classic.map(|classic| classic.right_stick.x),
+            Self::ClassicRightStickY => classic.map(|classic| classic.right_stick.y),
+            Self::ClassicTriggerL => classic.map(|classic| classic.left_trigger),
+            Self::ClassicTriggerR => classic.map(|classic| classic.right_trigger),
+            Self::IrX => ir.map(|ir| ir.x / width * 2.0 - 1.0),
+            Self::IrY => ir.map(|ir| ir.y / height * 2.0 - 1.0),
+            Self::Roll => wpad.map(|wpad| wpad.orientation.roll / TILT_RANGE),
+            Self::Pitch => wpad.map(|wpad| wpad.orientation.pitch / TILT_RANGE),
+        };
+        value
+            .filter(|value| !value.is_nan())
+            .unwrap_or(0.0)
+            .clamp(-1.0, 1.0)
+    }
+}
+
+/// A controller input an action can be bound to.
+///
+/// Bindings are written as text like `pad.a`, `remote.two`, `nunchuk.z`, `classic.zl`,
+/// `pad.stick-x` or, for half an axis, `+nunchuk.stick-y` and `-nunchuk.stick-y`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Binding {
+    /// A GameCube controller button
+    Pad(PadButton),
+    /// A Wii Remote button
+    Remote(WPadButton),
+    /// A Nunchuk button, [`WPadButton::NUNCHUNK_C`] or [`WPadButton::NUNCHUNK_Z`]
+    Nunchuk(WPadButton),
+    /// A Classic Controller button, one of the `CLASSIC_` [`WPadButton`]s
+    Classic(WPadButton),
+    /// A whole axis
+    Axis(Axis),
+    /// An axis from its center to its positive end, to use it as a button
+    Positive(Axis),
+    /// An axis from its center to its negative end, counted as positive
+    Negative(Axis),
+}
+
+const BUTTONS: [(&str, Binding); 40] = [
+    ("pad.a", Binding::Pad(PadButton::A)),
+    ("pad.b", Binding::Pad(PadButton::B)),
+    ("pad.x", Binding::Pad(PadButton::X)),
+    ("pad.y", Binding::Pad(PadButton::Y)),
+    ("pad.z", Binding::Pad(PadButton::TRIGGER_Z)),
+    ("pad.l", Binding::Pad(PadButton::TRIGGER_L)),
+    ("pad.r", Binding::Pad(PadButton::TRIGGER_R)),
+    ("pad.start", Binding::Pad(PadButton::START)),
+    ("pad.up", Binding::Pad(PadButton::UP)),
+    ("pad.down", Binding::Pad(PadButton::DOWN)),
+    ("pad.left", Binding::Pad(PadButton::LEFT)),
+    ("pad.right", Binding::Pad(PadButton::RIGHT)),
+    ("remote.a", Binding::Remote(WPadButton::A)),
+    ("remote.b", Binding::Remote(WPadButton::B)),
+    ("remote.one", Binding::Remote(WPadButton::ONE)),
+    ("remote.two", Binding::Remote(WPadButton::TWO)),
+    ("remote.plus", Binding::Remote(WPadButton::PLUS)),
+    ("remote.minus", Binding::Remote(WPadButton::MINUS)),
+    ("remote.home", Binding::Remote(WPadButton::HOME)),
+    ("remote.up", Binding::Remote(WPadButton::UP)),
+    ("remote.down", Binding::Remote(WPadButton::DOWN)),
+    ("remote.left", Binding::Remote(WPadButton::LEFT)),
+    ("remote.right", Binding::Remote(WPadButton::RIGHT)),
+    ("nunchuk.c", Binding::Nunchuk(WPadButton::NUNCHUNK_C)),
+    ("nunchuk.z", Binding::Nunchuk(WPadButton::NUNCHUNK_Z)),
+    ("classic.a", Binding::Classic(WPadButton::CLASSIC_A)),
+    ("classic.b", Binding::Classic(WPadButton::CLASSIC_B)),
+    ("classic.x", Binding::Classic(WPadButton::CLASSIC_X)),
+    ("classic.y", Binding::Classic(WPadButton::CLASSIC_Y)),
+    ("classic.l", Binding::Classic(WPadButton::CLASSIC_FULL_L)),
+    ("classic.r", Binding::Classic(WPadButton::CLASSIC_FULL_R)),
+    ("classic.zl", Binding::Classic(WPadButton::CLASSIC_ZL)),
+    ("classic.zr", Binding::Classic(WPadButton::CLASSIC_ZR)),
+    ("classic.plus", Binding::Classic(WPadButton::CLASSIC_PLUS)),
+    ("classic.minus", Binding::Classic(WPadButton::CLASSIC_MINUS)),
+    ("classic.home", Binding::Classic(WPadButton::CLASSIC_HOME)),
+    ("classic.up", Binding::Classic(WPadButton::CLASSIC_UP)),
+    ("classic.down", Binding::Classic(WPadButton::CLASSIC_DOWN)),
+    ("classic.left", Binding::Classic(WPadButton::CLASSIC_LEFT)),
+    ("classic.right", Binding::Classic(WPadButton::CLASIC_RIGHT)),
+];
+
+impl Binding {
+    /// Value of the binding in `input`, 0.0 or 1.0 for buttons.
+    pub fn value(self, input: &InputState) -> f32 {
+        let pad = input.pad.as_ref();
+        let wpad = input.wpad.as_ref();
+        let held = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+        match self {
+            Self::Pad(button) => held(pad.is_some_and(|pad| pad.buttons.contains(button))),
+            Self::Remote(button) => held(wpad.is_some_and(|wpad| wpad.held.contains(button))),
+            Self::Nunchuk(button) => held(wpad.is_some_and(|wpad| {
+                matches!(wpad.extension, Extension::Nunchuk(_)) && wpad.held.contains(button)
+            })),
+            Self::Classic(button) => held(wpad.is_some_and(|wpad| {
+                matches!(wpad.extension, Extension::Classic(_)) && wpad.held.contains(button)
+            })),
+            Self::Axis(axis) => axis.value(input),
+            Self::Positive(axis) => axis.value(input).max(0.0),
+            Self::Negative(axis) => (-axis.value(input)).max(0.0),
+        }
+    }
+
+    /// The first button pressed or axis pushed past halfway in `input`, to rebind an action to
+    /// whatever the player presses.
+    ///
+    /// The pointer and tilt never count, they are never at rest.
+    pub fn detect(input: &InputState) -> Option<Self> {
+        let buttons = BUTTONS.iter().map(|&(_, binding)| binding);
+        let axes = AXES
+            .iter()
+            .filter(|(_, axis)| !axis.is_positional())
+            .flat_map(|&(_, axis)| [Self::Positive(axis), Self::Negative(axis)]);
+        buttons
+            .chain(axes)
+            .find(|binding| binding.value(input) >= PRESS_THRESHOLD)
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Axis(axis) => f.write_str(axis.name()),
+            Self::Positive(axis) => write!(f, "+{}", axis.name()),
+            Self::Negative(axis) => write!(f, "-{}", axis.name()),
+            button => match BUTTONS.iter().find(|(_, binding)| binding == button) {
+                Some((name, _)) => f.write_str(name),
+                None => match button {
+                    Self::Pad(button) => write!(f, "pad.{:#x}", button.bits()),
+                    Self::Remote(button) => write!(f, "remote.{:#x}", button.bits()),
+                    Self::Nunchuk(button) => write!(f, "nunchuk.{:#x}", button.bits()),
+                    Self::Classic(button) => write!(f, "classic.{:#x}", button.bits()),
+                    _ => unreachable!(),
+                },
+            },
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = InputError;
+
+    fn from_str(text: &str) -> core::result::Result<Self, InputError> {
+        let text = text.trim();
+        let axis = |name: &str| {
+            AXES.iter()
+                .find(|(axis, _)| *axis == name)
+                .map(|&(_, axis)| axis)
+        };
+        if let Some(name) = text.strip_prefix('+') {
+            return axis(name).map(Self::Positive).ok_or(InputError::Invalid);
+        }
+        if let Some(name) = text.strip_prefix('-') {
+            return axis(name).map(Self::Negative).ok_or(InputError::Invalid);
+        }
+        if let Some(axis) = axis(text) {
+            return Ok(Self::Axis(axis));
+        }
+        if let Some(&(_, binding)) = BUTTONS.iter().find(|(name, _)| *name == text) {
+            return Ok(binding);
+        }
+
+        // Buttons without a name are written as their bits.
+        let (device, bits) = text.split_once('.').ok_or(InputError::Invalid)?;
+        let bits = bits.strip_prefix("0x").ok_or(InputError::Invalid)?;
+        let bits = u32::from_str_radix(bits, 16).map_err(|_| InputError::Invalid)?;
+        match device {
+            "pad" => u16::try_from(bits)
+                .map(|bits| Self::Pad(PadButton::from_bits_retain(bits)))
+                .map_err(|_| InputError::Invalid),
+            "remote" => Ok(Self::Remote(WPadButton::from_bits_retain(bits))),
+            "nunchuk" => Ok(Self::Nunchuk(WPadButton::from_bits_retain(bits))),
+            "classic" => Ok(Self::Classic(WPadButton::from_bits_retain(bits))),
+            _ => Err(InputError::Invalid),
+        }
+    }
+}
+
+/// How an action turns the value of its bindings into its own.
+///
+/// Values inside the deadzone become 0.0, the rest is stretched back to the full range, bent
+/// by the exponent and scaled.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct AxisResponse {
+    deadzone: f32,
+    exponent: f32,
+    scale: f32,
+    invert: bool,
+}
+
+impl AxisResponse {
+    pub fn new() -> Self {
+        Self {
+            deadzone: 0.15,
+            exponent: 1.0,
+            scale: 1.0,
+            invert: false,
+        }
+    }
+
+    /// Values from 0.0 up to this are treated as 0.0, 0.15 by default.
+    #[must_use]
+    pub fn deadzone(mut self, deadzone: f32) -> Self {
+        self.deadzone = deadzone.clamp(0.0, 0.99);
+        self
+    }
+
+    /// Sensitivity curve, above 1.0 gives finer control near the center, 1.0 by default.
+    #[must_use]
+    pub fn exponent(mut self, exponent: f32) -> Self {
+        self.exponent = exponent.max(0.01);
+        self
+    }
+
+    /// Multiplier applied last, 1.0 by default.
+    #[must_use]
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Flip the sign of the value.
+    #[must_use]
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Shape `value`.
+    pub fn apply(&self, value: f32) -> f32 {
+        let magnitude = value.abs();
+        if magnitude <= self.deadzone {
+            return 0.0;
+        }
+        let magnitude = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        let value = magnitude.powf(self.exponent) * self.scale * value.signum();
+        if self.invert { -value } else { value }
+    }
+}
+
+impl Default for AxisResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What a player's controllers reported in one frame.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct InputState {
+    /// The GameCube controller, if one is connected
+    pub pad: Option<PadStatus>,
+    /// The Wii Remote, if one is connected
+    pub wpad: Option<WPadState>,
+    /// Screen size the IR pointer is reported in, see [`WPad::set_screen_resolution`]
+    pub screen_size: (f32, f32),
+}
+
+impl InputState {
+    /// Read the controllers on `port`, after [`Pad::update`] and [`WPad::update`].
+    pub fn read(port: ControllerPort) -> Self {
+        let wpad = WPad::new(port);
+        let (width, height) = wpad.screen_resolution();
+        Self {
+            pad: Pad::new(port).read().ok(),
+            wpad: Some(wpad.state()).filter(|state| state.connected),
+            screen_size: (width as f32, height as f32),
+        }
+    }
+}
+
+impl Default for InputState {
+    /// Nothing connected, with libogc's default 640x480 pointer resolution.
+    fn default() -> Self {
+        Self {
+            pad: None,
+            wpad: None,
+            screen_size: (640.0, 480.0),
+        }
+    }
+}
+
+/// Whether `name` is written and read back unchanged by [`ActionMap::save_bindings`] and
+/// [`ActionMap::load_bindings`].
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('#')
+        && name.trim() == name
+        && !name.contains(['=', ',', '\n', '\r'])
+}
+
+#[derive(Debug, Clone)]
+struct Action {
+    name: String,
+    bindings: Vec<Binding>,
+    response: AxisResponse,
+    value: f32,
+    held: bool,
+    was_held: bool,
+    held_frames: u32,
+}
+
+/// Named actions bound to one player's controllers.
+///
+/// Each action takes the strongest value among its bindings. Call [`ActionMap::update`] once
+/// per frame, the queries then describe that frame.
+///
+/// Action names have to survive [`ActionMap::save_bindings`], so they cannot be empty, contain
+/// `=`, `,` or line breaks, start with `#` or start or end with whitespace.
+///
+/// ```rust
+/// let mut actions = ActionMap::new(ControllerPort::One);
+/// actions.bind("jump", Binding::Pad(PadButton::A))?;
+/// actions.bind("jump", Binding::Remote(WPadButton::TWO))?;
+/// actions.bind("move", Binding::Axis(Axis::PadStickX))?;
+/// actions.bind("move", Binding::Axis(Axis::NunchukStickX))?;
+/// actions.set_response("move", AxisResponse::new().deadzone(0.2).exponent(2.0))?;
+///
+/// loop {
+///     Pad::update();
+///     WPad::update();
+///     actions.update();
+///     if actions.pressed("jump") {
+///         player.jump();
+///     }
+///     player.walk(actions.value("move"));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ActionMap {
+    port: ControllerPort,
+    actions: Vec<Action>,
+    repeat_delay: u32,
+    repeat_interval: u32,
+}
+
+impl ActionMap {
+    /// An empty map reading the controllers on `port`.
+    pub fn new(port: ControllerPort) -> Self {
+        Self {
+            port,
+            actions: Vec::new(),
+            repeat_delay: 30,
+            repeat_interval: 6,
+        }
+    }
+
+    /// The port the map reads.
+    pub fn port(&self) -> ControllerPort {
+        self.port
+    }
+
+    /// Set after how many frames held [`ActionMap::repeated`] starts firing, and every how
+    /// many frames after that. 30 and 6 by default.
+    pub fn set_repeat(&mut self, delay: u32, interval: u32) {
+        self.repeat_delay = delay;
+        self.repeat_interval = interval.max(1);
+    }
+
+    fn action(&self, name: &str) -> Option<&Action> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.actions.iter().position(|action| action.name == name)
+    }
+
+    /// The action `name`, created if needed, or [`InputError::Invalid`] if the name would not
+    /// load back from [`ActionMap::save_bindings`].
+    fn action_mut(&mut self, name: &str) -> Result<&mut Action> {
+        let index = match self.position(name) {
+            Some(index) => index,
+            None => {
+                if !is_valid_name(name) {
+                    return Err(InputError::Invalid.into());
+                }
+                self.actions.push(Action {
+                    name: name.to_string(),
+                    bindings: Vec::new(),
+                    response: AxisResponse::new(),
+                    value: 0.0,
+                    held: false,
+                    was_held: false,
+                    held_frames: 0,
+                });
+                self.actions.len() - 1
+            }
+        };
+        Ok(&mut self.actions[index])
+    }
+
+    /// Add `binding` to the action `name`, creating it if needed.
+    ///
+    /// New names that are not valid action names return [`InputError::Invalid`].
+    pub fn bind(&mut self, name: &str, binding: Binding) -> Result<()> {
+        let action = self.action_mut(name)?;
+        if !action.bindings.contains(&binding) {
+            action.bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    /// Remove `binding` from the action `name`.
+    pub fn unbind(&mut self, name: &str, binding: Binding) {
+        if let Some(index) = self.position(name) {
+            self.actions[index]
+                .bindings
+                .retain(|&bound| bound != binding);
+        }
+    }
+
+    /// Replace every binding of the action `name`, creating it if needed.
+    ///
+    /// New names that are not valid action names return [`InputError::Invalid`].
+    pub fn set_bindings(&mut self, name: &str, bindings: &[Binding]) -> Result<()> {
+        self.action_mut(name)?.bindings = bindings.to_vec();
+        Ok(())
+    }
+
+    /// The bindings of the action `name`.
+    pub fn bindings(&self, name: &str) -> &[Binding] {
+        self.action(name).map_or(&[], |action| &action.bindings)
+    }
+
+    /// Set how the action `name` shapes its value, creating it if needed.
+    ///
+    /// New names that are not valid action names return [`InputError::Invalid`].
+    pub fn set_response(&mut self, name: &str, response: AxisResponse) -> Result<()> {
+        self.action_mut(name)?.response = response;
+        Ok(())
+    }
+
+    /// Remove the action `name`.
+    pub fn remove(&mut self, name: &str) {
+        self.actions.retain(|action| action.name != name);
+    }
+
+    /// The names of every action.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|action| action.name.as_str())
+    }
+
+    /// Read the controllers and advance a frame.
+    pub fn update(&mut self) {
+        self.update_with(&InputState::read(self.port));
+    }
+
+    /// Advance a frame with `input` instead of reading the controllers.
+    pub fn update_with(&mut self, input: &InputState) {
+        for action in &mut self.actions {
+            let value = action
+                .bindings
+                .iter()
+                .map(|binding| binding.value(input))
+                .fold(0.0f32, |strongest, value| {
+                    if value.abs() > strongest.abs() {
+                        value
+                    } else {
+                        strongest
+                    }
+                });
+            action.value = action.response.apply(value);
+            action.was_held = action.held;
+            action.held = action.value.abs() >= PRESS_THRESHOLD;
+            action.held_frames = if action.held {
+                action.held_frames.saturating_add(1)
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Value of the action, 0.0 for unknown actions.
+    pub fn value(&self, name: &str) -> f32 {
+        self.action(name).map_or(0.0, |action| action.value)
+    }
+
+    /// Whether the action is held this frame.
+    pub fn held(&self, name: &str) -> bool {
+        self.action(name).is_some_and(|action| action.held)
+    }
+
+    /// Whether the action started being held this frame.
+    pub fn pressed(&self, name: &str) -> bool {
+        self.action(name)
+            .is_some_and(|action| action.held && !action.was_held)
+    }
+
+    /// Whether the action stopped being held this frame.
+    pub fn released(&self, name: &str) -> bool {
+        self.action(name)
+            .is_some_and(|action| !action.held && action.was_held)
+    }
+
+    /// Whether the action was pressed this frame or has been held long enough to repeat, as
+    /// for scrolling through a menu.
+    pub fn repeated(&self, name: &str) -> bool {
+        self.action(name).is_some_and(|action| {
+            let frames = action.held_frames;
+            frames == 1
+                || (frames > self.repeat_delay
+                    && (frames - self.repeat_delay - 1).is_multiple_of(self.repeat_interval))
+        })
+    }
+
+    /// Number of frames the action has been held, 0 when it is not.
+    pub fn held_frames(&self, name: &str) -> u32 {
+        self.action(name).map_or(0, |action| action.held_frames)
+    }
+
+    /// Write the bindings of every action as text, one `name = binding, binding` line per
+    /// action.
+    pub fn save_bindings(&self) -> String {
+        let mut text = String::new();
+        for action in &self.actions {
+            text.push_str(&action.name);
+            text.push_str(" =");
+            for (index, binding) in action.bindings.iter().enumerate() {
+                text.push_str(if index == 0 { " " } else { ", " });
+                text.push_str(&binding.to_string());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Replace the bindings of the actions in `text`, as written by
+    /// [`ActionMap::save_bindings`].
+    ///
+    /// Empty lines and lines starting with `#` are skipped. Nothing changes if any line fails
+    /// to parse, which returns [`InputError::Invalid`].
+    pub fn load_bindings(&mut self, text: &str) -> Result<()> {
+        let mut loaded = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, bindings) = line.split_once('=').ok_or(InputError::Invalid)?;
+            let bindings = bindings
+                .split(',')
+                .filter(|binding| !binding.trim().is_empty())
+                .map(Binding::from_str)
+                .collect::<core::result::Result<Vec<_>, _>>()?;
+            let name = name.trim();
+            if !is_valid_name(name) {
+                return Err(InputError::Invalid.into());
+            }
+            loaded.push((name, bindings));
+        }
+        for (name, bindings) in loaded {
+            self.set_bindings(name, &bindings)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn response() {
+        let response = AxisResponse::new();
+        assert_eq!(response.apply(0.1), 0.0);
+        assert_eq!(response.apply(-0.15), 0.0);
+        assert!(close(response.apply(0.575), 0.5));
+        assert!(close(response.apply(-1.0), -1.0));
+
+        let response = AxisResponse::new()
+            .deadzone(0.2)
+            .exponent(2.0)
+            .scale(3.0)
+            .invert(true);
+        assert!(close(response.apply(0.6), -0.75));
+        assert!(close(response.apply(-0.6), 0.75));
+        // Out of range values stop at the scale.
+        assert!(close(response.apply(2.0), -3.0));
+
+        assert!(close(AxisResponse::new().deadzone(2.0).apply(1.0), 1.0));
+        assert!(close(AxisResponse::new().deadzone(0.0).apply(0.3), 0.3));
+    }
+
+    #[test]
+    fn binding_text() {
+        let buttons = BUTTONS.iter().map(|&(_, binding)| binding);
+        let axes = AXES.iter().flat_map(|&(_, axis)| {
+            [
+                Binding::Axis(axis),
+                Binding::Positive(axis),
+                Binding::Negative(axis),
+            ]
+        });
+        let unnamed = [
+            Binding::Pad(PadButton::from_bits_retain(0x8000)),
+            Binding::Remote(WPadButton::from_bits_retain(0x8000)),
+            Binding::Nunchuk(WPadButton::A | WPadButton::B),
+            Binding::Classic(WPadButton::from_bits_retain(0)),
+        ];
+        for binding in buttons.chain(axes).chain(unnamed) {
+            let text = binding.to_string();
+            assert_eq!(text.parse(), Ok(binding), "{text}");
+        }
+
+        assert_eq!("  pad.a ".parse(), Ok(Binding::Pad(PadButton::A)));
+        assert_eq!("-remote.roll".parse(), Ok(Binding::Negative(Axis::Roll)));
+        for text in [
+            "",
+            "pad",
+            "pad.q",
+            "+pad.a",
+            "-",
+            "pad.0x10000",
+            "pad.8000",
+            "wheel.0x1",
+        ] {
+            assert_eq!(text.parse::<Binding>(), Err(InputError::Invalid), "{text}");
+        }
+    }
+
+    #[test]
+    fn names() {
+        let mut actions = ActionMap::new(ControllerPort::One);
+        for name in ["", "a=b", "a,b", "a\nb", "#a", " a", "a\t"] {
+            assert!(
+                actions.bind(name, Binding::Pad(PadButton::A)).is_err(),
+                "{name:?}"
+            );
+            assert!(actions.set_bindings(name, &[]).is_err(), "{name:?}");
+            assert!(
+                actions.set_response(name, AxisResponse::new()).is_err(),
+                "{name:?}"
+            );
+        }
+        assert_eq!(actions.actions().count(), 0);
+
+        actions.unbind("jump", Binding::Pad(PadButton::A));
+        assert_eq!(actions.actions().count(), 0);
+
+        actions
+            .bind("jump high", Binding::Pad(PadButton::A))
+            .unwrap();
+        actions
+            .bind("jump high", Binding::Pad(PadButton::B))
+            .unwrap();
+        actions.unbind("jump high", Binding::Pad(PadButton::A));
+        assert_eq!(actions.bindings("jump high"), [Binding::Pad(PadButton::B)]);
+
+        let mut loaded = ActionMap::new(ControllerPort::Two);
+        loaded.load_bindings(&actions.save_bindings()).unwrap();
+        assert_eq!(loaded.bindings("jump high"), [Binding::Pad(PadButton::B)]);
+        assert!(loaded.load_bindings("move = pad.a\na,b = pad.b").is_err());
+        assert_eq!(loaded.bindings("move"), []);
+    }
+}
@@ -70,6 +70,8 @@ impl From<Button> for PadButton {
     }
 }
 
+/// Buttons of a single controller type, see [`ActionMap`](super::ActionMap) to bind actions to
+/// any controller.
 pub struct Input {
     id: ControllerPort,
     ctrl_type: ControllerType,
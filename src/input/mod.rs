@@ -1,5 +1,8 @@
+pub mod actions;
 pub mod controller;
 pub mod pad;
+pub mod recording;
+pub mod speaker;
 pub mod wpad;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -11,6 +14,14 @@ pub enum ControllerPort {
     Four = 3,
 }
 
+impl ControllerPort {
+    /// Every port, in order.
+    pub const ALL: [Self; 4] = [Self::One, Self::Two, Self::Three, Self::Four];
+}
+
+pub use actions::*;
 pub use controller::*;
 pub use pad::*;
+pub use recording::*;
+pub use speaker::*;
 pub use wpad::*;
@@ -1,12 +1,14 @@
 use super::ControllerPort;
-use crate::ffi;
+use crate::{Result, error::InputError, ffi};
 use bitflags::bitflags;
+use ogc_formats::si;
 
 pub struct Pad {
     id: ControllerPort,
 }
 
 bitflags! {
+    #[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
     pub struct PadButton: u16 {
        const LEFT = 0x0001;
        const RIGHT = 0x0002;
@@ -23,6 +25,71 @@ bitflags! {
     }
 }
 
+/// Rumble motor commands for [`Pad::rumble`].
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum PadRumble {
+    Stop = ffi::PAD_MOTOR_STOP,
+    Rumble = ffi::PAD_MOTOR_RUMBLE,
+    /// Stop and brake the motor so it halts at once
+    HardStop = ffi::PAD_MOTOR_STOP_HARD,
+}
+
+/// What is plugged into a GameCube controller port, see [`Pad::kind`].
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum PadKind {
+    None,
+    /// A wired controller, which may lack a rumble motor
+    Wired {
+        rumble: bool,
+    },
+    /// A WaveBird receiver, with or without a controller paired
+    WaveBird,
+    /// Any other device, with its SI type
+    Other(u32),
+}
+
+impl From<si::Device> for PadKind {
+    fn from(device: si::Device) -> Self {
+        match device {
+            si::Device::Wired { rumble } => Self::Wired { rumble },
+            si::Device::WaveBird => Self::WaveBird,
+            si::Device::Other(kind) => Self::Other(kind),
+        }
+    }
+}
+
+/// Everything a GameCube controller reported, see [`Pad::read`].
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct PadStatus {
+    pub buttons: PadButton,
+    pub stick_x: i8,
+    pub stick_y: i8,
+    pub c_stick_x: i8,
+    pub c_stick_y: i8,
+    pub trigger_l: u8,
+    pub trigger_r: u8,
+    /// Analog A and B, 0 on controllers that do not report them
+    pub analog_a: u8,
+    pub analog_b: u8,
+}
+
+impl From<si::Status> for PadStatus {
+    fn from(status: si::Status) -> Self {
+        Self {
+            buttons: PadButton::from_bits_truncate(status.buttons),
+            stick_x: status.stick_x,
+            stick_y: status.stick_y,
+            c_stick_x: status.c_stick_x,
+            c_stick_y: status.c_stick_y,
+            trigger_l: status.trigger_l,
+            trigger_r: status.trigger_r,
+            analog_a: 0,
+            analog_b: 0,
+        }
+    }
+}
+
 impl Pad {
     pub fn new(id: ControllerPort) -> Self {
         Pad { id }
@@ -56,7 +123,7 @@ impl Pad {
     }
 
     pub fn stick_x(&self) -> i8 {
-        unsafe { ffi::PAD_StickY(self.id as i32) }
+        unsafe { ffi::PAD_StickX(self.id as i32) }
     }
 
     pub fn stick_y(&self) -> i8 {
@@ -79,6 +146,66 @@ impl Pad {
         unsafe { ffi::PAD_TriggerR(self.id as i32) }
     }
 
+    /// Read the controller now, independent of [`Pad::update`].
+    ///
+    /// This fails with [`InputError::NoController`] when nothing is plugged in and with
+    /// [`InputError::Transfer`] when the controller missed this read.
+    pub fn read(&self) -> Result<PadStatus> {
+        let mut status: [ffi::PADStatus; 4] = unsafe { core::mem::zeroed() };
+        unsafe { ffi::PAD_Read(status.as_mut_ptr()) };
+        let status = &status[self.id as usize];
+        if status.err != ffi::PAD_ERR_NONE as _ {
+            return Err(InputError::from_pad(status.err.into()).into());
+        }
+        Ok(PadStatus {
+            buttons: PadButton::from_bits_truncate(status.button),
+            stick_x: status.stickX,
+            stick_y: status.stickY,
+            c_stick_x: status.substickX,
+            c_stick_y: status.substickY,
+            trigger_l: status.triggerL,
+            trigger_r: status.triggerR,
+            analog_a: status.analogA,
+            analog_b: status.analogB,
+        })
+    }
+
+    /// Whether a controller is plugged in and answering.
+    pub fn is_connected(&self) -> bool {
+        !matches!(
+            self.read(),
+            Err(crate::OgcError::Input(InputError::NoController))
+        )
+    }
+
+    /// What is plugged into the port.
+    pub fn kind(&self) -> PadKind {
+        let kind = unsafe { ffi::SI_GetType(self.id as i32) };
+        if kind & ffi::SI_ERROR_NO_RESPONSE != 0 {
+            PadKind::None
+        } else if kind & ffi::SI_TYPE_MASK != ffi::SI_TYPE_GC {
+            PadKind::Other(kind)
+        } else if kind & ffi::SI_GC_WIRELESS != 0 {
+            PadKind::WaveBird
+        } else if kind & ffi::SI_GC_STANDARD != 0 {
+            PadKind::Wired {
+                rumble: kind & ffi::SI_GC_NOMOTOR == 0,
+            }
+        } else {
+            PadKind::Other(kind)
+        }
+    }
+
+    pub fn rumble(&self, rumble: PadRumble) {
+        unsafe { ffi::PAD_ControlMotor(self.id as i32, rumble as u32) };
+    }
+
+    /// Take the current stick and trigger positions as the new neutral ones, as holding
+    /// X, Y and Start does.
+    pub fn recalibrate(&self) {
+        unsafe { ffi::PAD_Recalibrate(ffi::PAD_CHAN0_BIT >> self.id as u32) };
+    }
+
     pub fn init() {
         unsafe { ffi::PAD_Init() };
     }
@@ -0,0 +1,786 @@
+//! Recording controller input and replaying it.
+//!
+//! The format is [`ogc_formats::recording`], which stores each port as a list of fields and
+//! only writes the fields that changed since the frame before. Floats are quantized, so a
+//! replay matches the recorded input to a fraction of a pixel or degree rather than exactly.
+//! That crate builds on the host, recordings can be read by tools on a PC with its `Decoder`.
+//!
+//! Nothing here touches the hardware apart from [`Snapshot::read`].
+
+use super::{
+    Accel, BalanceBoard, ClassicController, ControllerPort, Extension, GForce, Guitar, InputState,
+    Ir, IrDot, MotionPlus, Nunchuk, Orientation, PadButton, PadStatus, Stick, WPadButton,
+    WPadState,
+};
+use crate::{
+    OgcError, Result,
+    error::{FormatError, InputError},
+};
+use alloc::{boxed::Box, vec::Vec};
+use ogc_formats::recording::{self as format, FIELD_COUNT, FIELDS, Frame, PORTS};
+
+/// Version of the format written by [`Recorder`].
+pub const RECORDING_VERSION: u16 = format::VERSION;
+
+/// What every controller reported in one frame.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Snapshot {
+    /// Input of each port, indexed by [`ControllerPort`]
+    pub ports: [InputState; 4],
+}
+
+impl Snapshot {
+    /// Read every port, after [`Pad::update`](super::Pad::update) and
+    /// [`WPad::update`](super::WPad::update).
+    pub fn read() -> Self {
+        Self {
+            ports: ControllerPort::ALL.map(InputState::read),
+        }
+    }
+
+    /// The input of `port`.
+    pub fn port(&self, port: ControllerPort) -> &InputState {
+        &self.ports[port as usize]
+    }
+}
+
+/// Records snapshots in the format of [`ogc_formats::recording`].
+///
+/// ```rust
+/// let mut recorder = Recorder::new();
+/// // Record a minute at most, or until the player quits.
+/// while recorder.frames() < 60 * 60 {
+///     Pad::update();
+///     WPad::update();
+///     let snapshot = Snapshot::read();
+///     recorder.record(&snapshot);
+///     actions.update_with(snapshot.port(actions.port()));
+///     if actions.pressed("quit") {
+///         break;
+///     }
+/// }
+/// let recording = recorder.into_bytes();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    encoder: format::Encoder,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a frame.
+    pub fn record(&mut self, snapshot: &Snapshot) {
+        let mut frame = [[0; FIELD_COUNT]; PORTS];
+        for (fields, input) in frame.iter_mut().zip(&snapshot.ports) {
+            let mut writer = Writer { fields, index: 0 };
+            input.write(&mut writer);
+            debug_assert_eq!(writer.index, FIELD_COUNT);
+        }
+        self.encoder.push(&frame);
+    }
+
+    /// Number of frames recorded.
+    pub fn frames(&self) -> u32 {
+        self.encoder.frames()
+    }
+
+    /// The recording so far.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.encoder.as_bytes()
+    }
+
+    /// Finish recording.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.encoder.into_bytes()
+    }
+}
+
+/// Plays a recording back one snapshot per frame.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    decoder: format::Decoder<Vec<u8>>,
+}
+
+impl Replay {
+    /// Check the header of `recording` and start at its first frame.
+    ///
+    /// # Errors
+    ///
+    /// [`InputError::Recording`] with [`FormatError::InvalidMagic`] when this is not a
+    /// recording, or [`FormatError::Unsupported`] when it was made by another version of the
+    /// format.
+    pub fn new(recording: Vec<u8>) -> Result<Self> {
+        let decoder = format::Decoder::new(recording).map_err(invalid)?;
+        Ok(Self { decoder })
+    }
+
+    /// The next frame, `None` once the recording is over.
+    ///
+    /// # Errors
+    ///
+    /// [`InputError::Recording`] with [`FormatError::Truncated`] when the recording stops
+    /// partway through a frame, or [`FormatError::Invalid`] when a frame holds values no
+    /// recorder writes.
+    pub fn next_snapshot(&mut self) -> Result<Option<Snapshot>> {
+        let Some(frame) = self.decoder.next_frame().map_err(invalid)? else {
+            return Ok(None);
+        };
+        snapshot(frame).map(Some)
+    }
+
+    /// Number of frames played so far.
+    pub fn frame(&self) -> u32 {
+        self.decoder.frame()
+    }
+
+    /// Whether every frame was played.
+    pub fn is_finished(&self) -> bool {
+        self.decoder.is_finished()
+    }
+
+    /// Go back to the first frame.
+    pub fn rewind(&mut self) {
+        self.decoder.rewind();
+    }
+}
+
+fn snapshot(frame: &Frame) -> Result<Snapshot> {
+    let mut snapshot = Snapshot::default();
+    for (input, fields) in snapshot.ports.iter_mut().zip(frame) {
+        *input = InputState::read_fields(&mut Reader { fields, index: 0 })?;
+    }
+    Ok(snapshot)
+}
+
+/// Where a game's input comes from, the controllers or a recording.
+///
+/// ```rust
+/// let mut source = if attract_mode {
+///     InputSource::Replay(Box::new(Replay::new(demo)?))
+/// } else {
+///     InputSource::Live
+/// };
+/// loop {
+///     let snapshot = source.poll()?;
+///     actions.update_with(snapshot.port(actions.port()));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub enum InputSource {
+    /// Read the controllers
+    Live,
+    /// Play a recording back
+    Replay(Box<Replay>),
+}
+
+impl InputSource {
+    /// Input for the next frame.
+    ///
+    /// Live input updates [`Pad`](super::Pad) and [`WPad`](super::WPad) itself. Once a replay
+    /// is over every port reads as disconnected.
+    ///
+    /// # Errors
+    ///
+    /// The errors of [`Replay::next_snapshot`].
+    pub fn poll(&mut self) -> Result<Snapshot> {
+        match self {
+            Self::Live => {
+                super::Pad::update();
+                super::WPad::update();
+                Ok(Snapshot::read())
+            }
+            Self::Replay(replay) => Ok(replay.next_snapshot()?.unwrap_or_default()),
+        }
+    }
+
+    /// Whether a replay is over, always `false` for live input.
+    pub fn is_finished(&self) -> bool {
+        match self {
+            Self::Live => false,
+            Self::Replay(replay) => replay.is_finished(),
+        }
+    }
+}
+
+fn invalid(error: FormatError) -> OgcError {
+    InputError::Recording(error).into()
+}
+
+/// Fills the fields of a port in order.
+struct Writer<'a> {
+    fields: &'a mut [i32; FIELD_COUNT],
+    index: usize,
+}
+
+impl Writer<'_> {
+    fn int(&mut self, value: i32) {
+        self.fields[self.index] = value;
+        self.index += 1;
+    }
+
+    fn float(&mut self, value: f32) {
+        self.int(FIELDS[self.index].quantize(value));
+    }
+}
+
+/// Takes the fields of a port in order.
+struct Reader<'a> {
+    fields: &'a [i32; FIELD_COUNT],
+    index: usize,
+}
+
+impl Reader<'_> {
+    fn int<T: TryFrom<i32>>(&mut self) -> Result<T> {
+        let value = self.fields[self.index];
+        self.index += 1;
+        T::try_from(value).map_err(|_| invalid(FormatError::Invalid))
+    }
+
+    fn float(&mut self) -> f32 {
+        let value = FIELDS[self.index].value(self.fields[self.index]);
+        self.index += 1;
+        value
+    }
+}
+
+/// A value stored as one or more fields of a port.
+trait Fields: Sized {
+    fn write(&self, writer: &mut Writer<'_>);
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self>;
+}
+
+macro_rules! integer_fields {
+    ($($ty:ty),*) => {
+        $(impl Fields for $ty {
+            fn write(&self, writer: &mut Writer<'_>) {
+                writer.int(i32::from(*self));
+            }
+
+            fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+                reader.int()
+            }
+        })*
+    };
+}
+
+integer_fields!(u8, u16, i8, i16, i32);
+
+impl Fields for f32 {
+    fn write(&self, writer: &mut Writer<'_>) {
+        writer.float(*self);
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        Ok(reader.float())
+    }
+}
+
+impl Fields for bool {
+    fn write(&self, writer: &mut Writer<'_>) {
+        writer.int(i32::from(*self));
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        match reader.int::<i32>()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid(FormatError::Invalid)),
+        }
+    }
+}
+
+/// A flag followed by the fields of the value, all 0 when it is not set.
+impl<T: Fields + Default> Fields for Option<T> {
+    fn write(&self, writer: &mut Writer<'_>) {
+        self.is_some().write(writer);
+        match self {
+            Some(value) => value.write(writer),
+            None => T::default().write(writer),
+        }
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        let some = bool::read_fields(reader)?;
+        let value = T::read_fields(reader)?;
+        Ok(some.then_some(value))
+    }
+}
+
+impl Fields for PadButton {
+    fn write(&self, writer: &mut Writer<'_>) {
+        self.bits().write(writer);
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        u16::read_fields(reader).map(Self::from_bits_retain)
+    }
+}
+
+impl Fields for WPadButton {
+    fn write(&self, writer: &mut Writer<'_>) {
+        writer.int(self.bits().cast_signed());
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        reader
+            .int::<i32>()
+            .map(|bits| Self::from_bits_retain(bits.cast_unsigned()))
+    }
+}
+
+impl<T: Fields + Copy + Default, const N: usize> Fields for [T; N] {
+    fn write(&self, writer: &mut Writer<'_>) {
+        for value in self {
+            value.write(writer);
+        }
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        let mut values = [T::default(); N];
+        for value in &mut values {
+            *value = T::read_fields(reader)?;
+        }
+        Ok(values)
+    }
+}
+
+/// Store a struct field by field, in the order given.
+macro_rules! struct_fields {
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        impl Fields for $ty {
+            fn write(&self, writer: &mut Writer<'_>) {
+                $(self.$field.write(writer);)*
+            }
+
+            fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+                Ok(Self {
+                    $($field: Fields::read_fields(reader)?,)*
+                })
+            }
+        }
+    };
+}
+
+struct_fields!(PadStatus {
+    buttons,
+    stick_x,
+    stick_y,
+    c_stick_x,
+    c_stick_y,
+    trigger_l,
+    trigger_r,
+    analog_a,
+    analog_b,
+});
+struct_fields!(IrDot { x, y, size });
+struct_fields!(Ir {
+    valid,
+    x,
+    y,
+    smooth_valid,
+    smooth_x,
+    smooth_y,
+    angle,
+    distance,
+    z,
+    dots,
+});
+struct_fields!(Orientation { roll, pitch, yaw });
+struct_fields!(Accel { x, y, z });
+struct_fields!(GForce { x, y, z });
+struct_fields!(Stick {
+    x,
+    y,
+    angle,
+    magnitude,
+});
+struct_fields!(Nunchuk {
+    stick,
+    accel,
+    gforce,
+    orientation,
+});
+struct_fields!(ClassicController {
+    left_stick,
+    right_stick,
+    left_trigger,
+    right_trigger,
+});
+struct_fields!(Guitar { stick, whammy_bar });
+struct_fields!(BalanceBoard {
+    top_left,
+    top_right,
+    bottom_left,
+    bottom_right,
+    x,
+    y,
+});
+struct_fields!(MotionPlus {
+    rate_x,
+    rate_y,
+    rate_z,
+});
+struct_fields!(WPadState {
+    connected,
+    held,
+    down,
+    up,
+    ir,
+    orientation,
+    accel,
+    gforce,
+    battery_level,
+    extension,
+});
+
+/// The kind, then the fields of every extension, 0 for all but the one plugged in.
+impl Fields for Extension {
+    fn write(&self, writer: &mut Writer<'_>) {
+        let kind = match self {
+            Self::None => 0,
+            Self::Nunchuk(_) => 1,
+            Self::Classic(_) => 2,
+            Self::Guitar(_) => 3,
+            Self::BalanceBoard(_) => 4,
+            Self::MotionPlus(_) => 5,
+            Self::Unknown(_) => 6,
+        };
+        writer.int(kind);
+        writer.int(match self {
+            Self::Unknown(kind) => *kind,
+            _ => 0,
+        });
+        match self {
+            Self::Nunchuk(nunchuk) => *nunchuk,
+            _ => Nunchuk::default(),
+        }
+        .write(writer);
+        match self {
+            Self::Classic(classic) => *classic,
+            _ => ClassicController::default(),
+        }
+        .write(writer);
+        match self {
+            Self::Guitar(guitar) => *guitar,
+            _ => Guitar::default(),
+        }
+        .write(writer);
+        match self {
+            Self::BalanceBoard(board) => *board,
+            _ => BalanceBoard::default(),
+        }
+        .write(writer);
+        match self {
+            Self::MotionPlus(motion_plus) => *motion_plus,
+            _ => MotionPlus::default(),
+        }
+        .write(writer);
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        let kind = reader.int::<i32>()?;
+        let unknown = reader.int()?;
+        let nunchuk = Fields::read_fields(reader)?;
+        let classic = Fields::read_fields(reader)?;
+        let guitar = Fields::read_fields(reader)?;
+        let board = Fields::read_fields(reader)?;
+        let motion_plus = Fields::read_fields(reader)?;
+        Ok(match kind {
+            0 => Self::None,
+            1 => Self::Nunchuk(nunchuk),
+            2 => Self::Classic(classic),
+            3 => Self::Guitar(guitar),
+            4 => Self::BalanceBoard(board),
+            5 => Self::MotionPlus(motion_plus),
+            6 => Self::Unknown(unknown),
+            _ => return Err(invalid(FormatError::Invalid)),
+        })
+    }
+}
+
+/// Whether a controller and a remote are connected, the screen size, then the fields of both,
+/// 0 for a missing one.
+impl Fields for InputState {
+    fn write(&self, writer: &mut Writer<'_>) {
+        self.pad.is_some().write(writer);
+        self.wpad.is_some().write(writer);
+        self.screen_size.0.write(writer);
+        self.screen_size.1.write(writer);
+        self.pad.unwrap_or_default().write(writer);
+        self.wpad.unwrap_or_default().write(writer);
+    }
+
+    fn read_fields(reader: &mut Reader<'_>) -> Result<Self> {
+        let has_pad = bool::read_fields(reader)?;
+        let has_wpad = bool::read_fields(reader)?;
+        let screen_size = (f32::read_fields(reader)?, f32::read_fields(reader)?);
+        let pad = PadStatus::read_fields(reader)?;
+        let wpad = WPadState::read_fields(reader)?;
+        Ok(Self {
+            pad: has_pad.then_some(pad),
+            wpad: has_wpad.then_some(wpad),
+            screen_size,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use ogc_formats::recording::field;
+
+    /// Input using every field, with floats that quantize exactly.
+    fn snapshot() -> Snapshot {
+        let stick = Stick {
+            x: -0.5,
+            y: 0.25,
+            angle: 296.5,
+            magnitude: 0.5625,
+        };
+        let remote = WPadState {
+            connected: true,
+            held: WPadButton::A | WPadButton::from_bits_retain(0x8000_0000),
+            down: WPadButton::A,
+            up: WPadButton::B,
+            ir: Ir {
+                valid: true,
+                x: 320.5,
+                y: -12.25,
+                smooth_valid: true,
+                smooth_x: 319.75,
+                smooth_y: -11.0,
+                angle: -3.5,
+                distance: 210.0625,
+                z: 1.5,
+                dots: [
+                    Some(IrDot {
+                        x: 1023,
+                        y: 767,
+                        size: 15,
+                    }),
+                    None,
+                    Some(IrDot::default()),
+                    None,
+                ],
+            },
+            orientation: Orientation {
+                roll: 45.25,
+                pitch: -90.0,
+                yaw: 0.015625,
+            },
+            accel: Accel {
+                x: 512,
+                y: 0,
+                z: u16::MAX,
+            },
+            gforce: GForce {
+                x: 0.0,
+                y: -1.0,
+                z: 3.5,
+            },
+            battery_level: 200,
+            extension: Extension::Nunchuk(Nunchuk {
+                stick,
+                accel: Accel { x: 1, y: 2, z: 3 },
+                gforce: GForce {
+                    x: 0.5,
+                    y: 0.25,
+                    z: -0.125,
+                },
+                orientation: Orientation {
+                    roll: 1.0,
+                    pitch: 2.0,
+                    yaw: 3.0,
+                },
+            }),
+        };
+        let pad = PadStatus {
+            buttons: PadButton::A | PadButton::START,
+            stick_x: -128,
+            stick_y: 127,
+            c_stick_x: 5,
+            c_stick_y: -5,
+            trigger_l: 255,
+            trigger_r: 1,
+            analog_a: 2,
+            analog_b: 3,
+        };
+
+        let mut snapshot = Snapshot::default();
+        snapshot.ports[0].pad = Some(pad);
+        snapshot.ports[1].wpad = Some(remote);
+        snapshot.ports[1].screen_size = (1920.0, 1080.0);
+        snapshot.ports[2].wpad = Some(WPadState {
+            extension: Extension::Classic(ClassicController {
+                left_stick: stick,
+                right_stick: Stick::default(),
+                left_trigger: 1.0,
+                right_trigger: 0.5,
+            }),
+            ..remote
+        });
+        snapshot.ports[3].wpad = Some(WPadState {
+            extension: Extension::Unknown(-7),
+            ..WPadState::default()
+        });
+        snapshot
+    }
+
+    fn replay(snapshots: &[Snapshot]) -> Vec<Snapshot> {
+        let mut recorder = Recorder::new();
+        for snapshot in snapshots {
+            recorder.record(snapshot);
+        }
+        assert_eq!(recorder.frames(), snapshots.len() as u32);
+        let mut replay = Replay::new(recorder.into_bytes()).unwrap();
+        let mut replayed = Vec::new();
+        while let Some(snapshot) = replay.next_snapshot().unwrap() {
+            replayed.push(snapshot);
+        }
+        assert!(replay.is_finished());
+        replayed
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut snapshots = vec![Snapshot::default(), snapshot()];
+        let mut moved = snapshot();
+        moved.ports[0].pad.as_mut().unwrap().stick_x = 0;
+        let board = BalanceBoard {
+            top_left: 20.5,
+            top_right: 21.0,
+            bottom_left: 19.25,
+            bottom_right: 18.0,
+            x: 0.125,
+            y: -0.75,
+        };
+        moved.ports[3].wpad.as_mut().unwrap().extension = Extension::BalanceBoard(board);
+        snapshots.extend([moved, moved, snapshot()]);
+        moved.ports[3].wpad.as_mut().unwrap().extension = Extension::MotionPlus(MotionPlus {
+            rate_x: 8192,
+            rate_y: -1,
+            rate_z: i16::MAX,
+        });
+        moved.ports[2].wpad.as_mut().unwrap().extension = Extension::Guitar(Guitar {
+            stick: Stick::default(),
+            whammy_bar: 0.75,
+        });
+        moved.ports[1] = InputState::default();
+        snapshots.push(moved);
+        assert_eq!(replay(&snapshots), snapshots);
+
+        let mut replay = Replay::new(Recorder::new().into_bytes()).unwrap();
+        assert_eq!(replay.next_snapshot().unwrap(), None);
+    }
+
+    #[test]
+    fn quantized() {
+        let mut snapshot = Snapshot::default();
+        let mut remote = WPadState::default();
+        remote.ir.x = 123.456;
+        remote.orientation.roll = -33.333;
+        remote.gforce.z = f32::NAN;
+        snapshot.ports[0].wpad = Some(remote);
+        let remote = replay(&[snapshot])[0].ports[0].wpad.unwrap();
+        assert!((remote.ir.x - 123.456).abs() <= 0.5 / 16.0);
+        assert!((remote.orientation.roll + 33.333).abs() <= 0.5 / 64.0);
+        assert_eq!(remote.gforce.z, 0.0);
+    }
+
+    #[test]
+    fn layout() {
+        let mut recorder = Recorder::new();
+        recorder.record(&snapshot());
+        let mut decoder = format::Decoder::new(recorder.as_bytes()).unwrap();
+        let frame = decoder.next_frame().unwrap().unwrap();
+        let value = |port: usize, name| frame[port][field(name).unwrap()];
+        assert_eq!(value(0, "pad"), 1);
+        assert_eq!(value(0, "pad.stick-x"), -128);
+        assert_eq!(value(0, "pad.analog-b"), 3);
+        assert_eq!(value(1, "screen.width"), 1920 * 16);
+        assert_eq!(
+            value(1, "wpad.held"),
+            (WPadButton::A.bits() | 0x8000_0000) as i32
+        );
+        assert_eq!(value(1, "wpad.ir.dot-0.size"), 15);
+        assert_eq!(value(1, "wpad.ir.dot-1"), 0);
+        assert_eq!(value(1, "wpad.battery-level"), 200);
+        assert_eq!(value(1, "wpad.extension"), 1);
+        assert_eq!(value(1, "nunchuk.orientation.yaw"), 3 * 64);
+        assert_eq!(value(2, "classic.right-trigger"), 2048);
+        assert_eq!(value(3, "unknown.kind"), -7);
+    }
+
+    #[test]
+    fn compact() {
+        let snapshot = snapshot();
+        let mut recorder = Recorder::new();
+        recorder.record(&snapshot);
+        let len = recorder.as_bytes().len();
+        recorder.record(&snapshot);
+        assert_eq!(recorder.as_bytes().len(), len + 1);
+
+        // A stick moving by one step on one port is a byte for the ports, one for the count
+        // and two for the field.
+        let mut moved = snapshot;
+        moved.ports[0].pad.as_mut().unwrap().stick_y -= 1;
+        recorder.record(&moved);
+        assert_eq!(recorder.as_bytes().len(), len + 1 + 4);
+    }
+
+    #[test]
+    fn truncated() {
+        let snapshots = [snapshot(), Snapshot::default(), snapshot()];
+        let mut recorder = Recorder::new();
+        for snapshot in &snapshots {
+            recorder.record(snapshot);
+        }
+        let data = recorder.into_bytes();
+
+        for len in 0..format::HEADER_LEN {
+            assert_eq!(
+                Replay::new(data[..len].to_vec()).err(),
+                Some(invalid(FormatError::Truncated))
+            );
+        }
+        for len in format::HEADER_LEN..data.len() {
+            let mut replay = Replay::new(data[..len].to_vec()).unwrap();
+            let error = loop {
+                match replay.next_snapshot() {
+                    Ok(Some(snapshot)) => {
+                        assert_eq!(snapshot, snapshots[replay.frame() as usize - 1]);
+                    }
+                    Ok(None) => break None,
+                    Err(error) => break Some(error),
+                }
+            };
+            // Cuts between frames replay fewer frames, the rest fail partway through one.
+            match error {
+                Some(error) => assert_eq!(error, invalid(FormatError::Truncated)),
+                None => assert!(replay.frame() < snapshots.len() as u32),
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        let mut header = format::MAGIC.to_vec();
+        header.extend([0, 2, 0, 0]);
+        assert_eq!(
+            Replay::new(header).err(),
+            Some(invalid(FormatError::Unsupported))
+        );
+
+        // A frame setting `pad` to 2, then one setting an extension kind of 7.
+        for (name, value) in [("pad", 4), ("wpad.extension", 14)] {
+            let mut data = Recorder::new().into_bytes();
+            data.extend([0x01, 0x01, field(name).unwrap() as u8, value]);
+            let mut replay = Replay::new(data).unwrap();
+            assert_eq!(replay.next_snapshot(), Err(invalid(FormatError::Invalid)));
+        }
+    }
+}
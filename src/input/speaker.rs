@@ -0,0 +1,207 @@
+//! Streaming sound to the Wii Remote speaker.
+
+use super::{ControllerPort, wpad::wpad_result};
+use crate::{OgcError, Result, error::InputError, ffi};
+use alloc::{boxed::Box, collections::VecDeque, vec};
+use core::{ffi::c_void, ptr::NonNull, time::Duration};
+
+/// Bytes of ADPCM sent to the remote per report, 40 samples.
+const REPORT_BYTES: usize = 20;
+/// Time the speaker takes to play one report.
+const REPORT_PERIOD: Duration =
+    Duration::from_nanos(REPORT_BYTES as u64 * 2 * 1_000_000_000 / Speaker::SAMPLE_RATE as u64);
+
+/// Encoded sound waiting for the alarm to send it.
+struct Stream {
+    port: ControllerPort,
+    queue: VecDeque<u8>,
+}
+
+/// Sample format the Wii Remote speaker is set up for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpeakerFormat {
+    /// 4-bit Yamaha ADPCM, what libogc sets up.
+    Adpcm,
+    /// Signed 8-bit PCM, which libogc cannot set up.
+    Pcm8,
+}
+
+/// The speaker of a Wii Remote.
+///
+/// libogc sets the speaker up for 4-bit ADPCM at [`Speaker::SAMPLE_RATE`], samples pushed are
+/// encoded and sent from an alarm at the rate the speaker plays them. Dropping the speaker turns
+/// it off.
+///
+/// ```rust
+/// let mut speaker = Speaker::new(ControllerPort::One)?;
+/// speaker.push(&beep);
+/// ```
+pub struct Speaker {
+    stream: NonNull<Stream>,
+    alarm: ffi::syswd_t,
+    encoder: ffi::WPADEncStatus,
+    first: bool,
+    /// Sample left over from an odd length push, ADPCM packs two per byte.
+    pending: Option<i16>,
+}
+
+// SAFETY: the stream is only touched with interrupts disabled.
+unsafe impl Send for Speaker {}
+
+impl Speaker {
+    /// Rate the speaker plays samples at, in Hz.
+    pub const SAMPLE_RATE: u32 = 3000;
+
+    /// Turn on the speaker of the remote on `port`, which must be connected.
+    pub fn new(port: ControllerPort) -> Result<Self> {
+        Self::with_format(port, SpeakerFormat::Adpcm)
+    }
+
+    /// Turn on the speaker of the remote on `port` in `format`.
+    ///
+    /// [`SpeakerFormat::Pcm8`] returns [`InputError::Unsupported`]: libogc always configures the
+    /// speaker for ADPCM and `wiiuse/wpad.h` has no way to write its registers.
+    pub fn with_format(port: ControllerPort, format: SpeakerFormat) -> Result<Self> {
+        if format != SpeakerFormat::Adpcm {
+            return Err(InputError::Unsupported.into());
+        }
+        wpad_result(unsafe { ffi::WPAD_ControlSpeaker(port as i32, 1) })?;
+
+        let mut alarm = 0;
+        let ret = unsafe { ffi::SYS_CreateAlarm(&mut alarm) };
+        if ret < 0 {
+            unsafe { ffi::WPAD_ControlSpeaker(port as i32, 0) };
+            return Err(OgcError::System(ret));
+        }
+        let speaker = Self {
+            stream: NonNull::from(Box::leak(Box::new(Stream {
+                port,
+                queue: VecDeque::new(),
+            }))),
+            alarm,
+            // SAFETY: the encoder state is plain bytes, zero is the start state.
+            encoder: unsafe { core::mem::zeroed() },
+            first: true,
+            pending: None,
+        };
+
+        let period = ffi::timespec {
+            tv_sec: 0,
+            tv_nsec: REPORT_PERIOD.subsec_nanos() as _,
+        };
+        let ret = unsafe {
+            ffi::SYS_SetPeriodicAlarm(
+                alarm,
+                &period,
+                &period,
+                Some(alarm_callback),
+                speaker.stream.as_ptr().cast(),
+            )
+        };
+        if ret < 0 {
+            return Err(OgcError::System(ret));
+        }
+        Ok(speaker)
+    }
+
+    /// Queue mono samples at [`Speaker::SAMPLE_RATE`] to play after the ones already queued.
+    pub fn push(&mut self, samples: &[i16]) {
+        let mut pcm = vec![0; samples.len() + 1];
+        let mut len = 0;
+        for &sample in self.pending.take().iter().chain(samples) {
+            pcm[len] = sample;
+            len += 1;
+        }
+        if len % 2 == 1 {
+            len -= 1;
+            self.pending = Some(pcm[len]);
+        }
+        if len == 0 {
+            return;
+        }
+
+        let mut encoded = vec![0; len / 2];
+        let flag = if self.first {
+            ffi::WPAD_ENC_FIRST
+        } else {
+            ffi::WPAD_ENC_CONT
+        };
+        self.first = false;
+        unsafe {
+            ffi::WPAD_EncodeData(
+                &mut self.encoder,
+                flag,
+                pcm.as_ptr(),
+                len as i32,
+                encoded.as_mut_ptr(),
+            )
+        };
+        // Interrupts are disabled while the queue is locked, so it must not grow or free there.
+        let grow = self.lock(|stream| {
+            let queue = &mut stream.queue;
+            if queue.capacity() - queue.len() >= encoded.len() {
+                queue.extend(encoded.drain(..));
+                None
+            } else {
+                Some((queue.len() + encoded.len()).max(queue.capacity() * 2))
+            }
+        });
+        if let Some(capacity) = grow {
+            // The alarm only takes from the queue, so it still fits once locked again.
+            let mut queue = VecDeque::with_capacity(capacity);
+            self.lock(|stream| {
+                queue.extend(stream.queue.drain(..));
+                queue.extend(encoded.drain(..));
+                core::mem::swap(&mut queue, &mut stream.queue);
+            });
+        }
+    }
+
+    /// Number of samples waiting to be played.
+    pub fn queued(&self) -> usize {
+        self.lock(|stream| stream.queue.len()) * 2 + usize::from(self.pending.is_some())
+    }
+
+    /// Drop every queued sample.
+    pub fn clear(&mut self) {
+        self.pending = None;
+        self.lock(|stream| stream.queue.clear());
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut Stream) -> R) -> R {
+        // SAFETY: the alarm callback is the only other user of the stream.
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            let result = f(&mut *self.stream.as_ptr());
+            ffi::IRQ_Restore(level);
+            result
+        }
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::SYS_RemoveAlarm(self.alarm);
+            let stream = Box::from_raw(self.stream.as_ptr());
+            ffi::WPAD_ControlSpeaker(stream.port as i32, 0);
+        }
+    }
+}
+
+unsafe extern "C" fn alarm_callback(_alarm: ffi::syswd_t, arg: *mut c_void) {
+    if arg.is_null() {
+        return;
+    }
+    // SAFETY: `arg` is the stream of the speaker, which removes the alarm before freeing it.
+    let stream = unsafe { &mut *arg.cast::<Stream>() };
+    let mut report = [0u8; REPORT_BYTES];
+    let len = stream.queue.len().min(REPORT_BYTES);
+    if len == 0 {
+        return;
+    }
+    for (byte, data) in report.iter_mut().zip(stream.queue.drain(..len)) {
+        *byte = data;
+    }
+    unsafe { ffi::WPAD_SendStreamData(stream.port as i32, report.as_mut_ptr().cast(), len as u32) };
+}
@@ -1,13 +1,27 @@
 use super::ControllerPort;
-use crate::ffi;
+use crate::{Result, error::InputError, ffi};
 use alloc::boxed::Box;
 use bitflags::bitflags;
+use core::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use num_traits::Float;
 
+/// Pointer resolution of each port as `width << 16 | height`, libogc starts at 640x480.
+static SCREEN_RESOLUTION: [AtomicU32; 4] = [const { AtomicU32::new(640 << 16 | 480) }; 4];
+
+/// A Wii Remote on one of the controller ports.
+///
+/// libogc does not let the player LEDs or the IR camera sensitivity be changed: it lights the
+/// LED of the port when a remote connects and leaves the sensitivity at its own default, and
+/// `wiiuse/wpad.h` has no call for either.
 pub struct WPad {
     id: ControllerPort,
 }
 
 bitflags! {
+    #[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
     pub struct WPadButton: u32 {
         const TWO = 0x0001;
         const ONE = 0x0002;
@@ -63,6 +77,436 @@ pub enum WPadDataFormat {
     ButtonsAccelIR = ffi::WPAD_FMT_BTNS_ACC_IR,
 }
 
+/// Raw battery level of a full battery.
+const FULL_BATTERY: f32 = 200.0;
+
+/// A point of light seen by the IR camera.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct IrDot {
+    /// Raw X coordinate, 0 to 1023
+    pub x: i16,
+    /// Raw Y coordinate, 0 to 767
+    pub y: i16,
+    /// Size of the dot, 0 to 15
+    pub size: u8,
+}
+
+/// Where the Wii Remote points, from the sensor bar seen by its IR camera.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Ir {
+    /// Whether the pointer is on screen, `x` and `y` are stale otherwise
+    pub valid: bool,
+    /// Pointer position in screen pixels
+    pub x: f32,
+    pub y: f32,
+    /// Whether the smoothed position is valid
+    pub smooth_valid: bool,
+    /// Smoothed pointer position in screen pixels
+    pub smooth_x: f32,
+    pub smooth_y: f32,
+    /// Roll of the remote relative to the sensor bar, in degrees
+    pub angle: f32,
+    /// Distance between the two sensor bar dots, in camera pixels
+    pub distance: f32,
+    /// Distance from the sensor bar, in meters
+    pub z: f32,
+    /// The dots the camera sees
+    pub dots: [Option<IrDot>; 4],
+}
+
+impl Ir {
+    /// Number of dots the camera sees.
+    pub fn visible_dots(&self) -> usize {
+        self.dots.iter().flatten().count()
+    }
+}
+
+impl From<&ffi::ir_t> for Ir {
+    fn from(ir: &ffi::ir_t) -> Self {
+        Self {
+            valid: ir.valid != 0,
+            x: ir.x,
+            y: ir.y,
+            smooth_valid: ir.smooth_valid != 0,
+            smooth_x: ir.sx,
+            smooth_y: ir.sy,
+            angle: ir.angle,
+            distance: ir.distance,
+            z: ir.z,
+            dots: ir.dot.map(|dot| {
+                (dot.visible != 0).then_some(IrDot {
+                    x: dot.rx,
+                    y: dot.ry,
+                    size: dot.size,
+                })
+            }),
+        }
+    }
+}
+
+/// Orientation of a Wii Remote or Nunchuk, in degrees.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Orientation {
+    pub roll: f32,
+    pub pitch: f32,
+    /// Only known from the IR camera or MotionPlus
+    pub yaw: f32,
+}
+
+impl From<&ffi::orient_t> for Orientation {
+    fn from(orient: &ffi::orient_t) -> Self {
+        Self {
+            roll: orient.roll,
+            pitch: orient.pitch,
+            yaw: orient.yaw,
+        }
+    }
+}
+
+/// Raw accelerometer reading.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct Accel {
+    pub x: u16,
+    pub y: u16,
+    pub z: u16,
+}
+
+impl From<&ffi::vec3w_t> for Accel {
+    fn from(accel: &ffi::vec3w_t) -> Self {
+        Self {
+            x: accel.x,
+            y: accel.y,
+            z: accel.z,
+        }
+    }
+}
+
+/// Calibrated acceleration, in g.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct GForce {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl From<&ffi::gforce_t> for GForce {
+    fn from(gforce: &ffi::gforce_t) -> Self {
+        Self {
+            x: gforce.x,
+            y: gforce.y,
+            z: gforce.z,
+        }
+    }
+}
+
+/// Position of an analog stick.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Stick {
+    /// -1.0 (left) to 1.0 (right)
+    pub x: f32,
+    /// -1.0 (down) to 1.0 (up)
+    pub y: f32,
+    /// Angle clockwise from up, in degrees
+    pub angle: f32,
+    /// Distance from the center, 0.0 to 1.0
+    pub magnitude: f32,
+}
+
+impl From<&ffi::joystick_t> for Stick {
+    fn from(js: &ffi::joystick_t) -> Self {
+        // libogc leaves these NaN while the stick is centered.
+        let angle = if js.ang.is_nan() { 0.0 } else { js.ang };
+        let magnitude = if js.mag.is_nan() {
+            0.0
+        } else {
+            js.mag.min(1.0)
+        };
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Self {
+            x: magnitude * sin,
+            y: magnitude * cos,
+            angle,
+            magnitude,
+        }
+    }
+}
+
+/// Nunchuk state, its buttons are in [`WPadState::held`].
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Nunchuk {
+    pub stick: Stick,
+    pub accel: Accel,
+    pub gforce: GForce,
+    pub orientation: Orientation,
+}
+
+/// Classic Controller state, its buttons are in [`WPadState::held`].
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct ClassicController {
+    pub left_stick: Stick,
+    pub right_stick: Stick,
+    /// Analog triggers, 0.0 to 1.0
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+}
+
+/// Guitar Hero guitar state, its frets are in [`WPadState::held`].
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Guitar {
+    pub stick: Stick,
+    /// 0.0 (released) to 1.0 (pressed down)
+    pub whammy_bar: f32,
+}
+
+/// Wii Balance Board state.
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct BalanceBoard {
+    /// Weight on each sensor, in kilograms
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_left: f32,
+    pub bottom_right: f32,
+    /// Center of balance, -1.0 to 1.0 on each axis
+    pub x: f32,
+    pub y: f32,
+}
+
+impl BalanceBoard {
+    /// Total weight on the board, in kilograms.
+    pub fn weight(&self) -> f32 {
+        self.top_left + self.top_right + self.bottom_left + self.bottom_right
+    }
+}
+
+/// Wii MotionPlus state.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct MotionPlus {
+    /// Raw 14-bit rotation rates around each axis, about 8192 at rest
+    pub rate_x: i16,
+    pub rate_y: i16,
+    pub rate_z: i16,
+}
+
+/// The extension plugged into a Wii Remote.
+///
+/// libogc does not decode the Guitar Hero drums, they show up as [`Extension::Unknown`].
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub enum Extension {
+    #[default]
+    None,
+    Nunchuk(Nunchuk),
+    Classic(ClassicController),
+    Guitar(Guitar),
+    BalanceBoard(BalanceBoard),
+    MotionPlus(MotionPlus),
+    /// An extension libogc reports but does not decode, with its type
+    Unknown(i32),
+}
+
+impl From<&ffi::expansion_t> for Extension {
+    fn from(exp: &ffi::expansion_t) -> Self {
+        // SAFETY: the type says which field of the union libogc filled in.
+        unsafe {
+            let data = &exp.__bindgen_anon_1;
+            match exp.type_ as u32 {
+                ffi::EXP_NONE => Self::None,
+                ffi::EXP_NUNCHUK => {
+                    let nunchuk = data.nunchuk.as_ref();
+                    Self::Nunchuk(Nunchuk {
+                        stick: (&nunchuk.js).into(),
+                        accel: (&nunchuk.accel).into(),
+                        gforce: (&nunchuk.gforce).into(),
+                        orientation: (&nunchuk.orient).into(),
+                    })
+                }
+                ffi::EXP_CLASSIC => {
+                    let classic = data.classic.as_ref();
+                    Self::Classic(ClassicController {
+                        left_stick: (&classic.ljs).into(),
+                        right_stick: (&classic.rjs).into(),
+                        left_trigger: classic.l_shoulder,
+                        right_trigger: classic.r_shoulder,
+                    })
+                }
+                ffi::EXP_GUITAR_HERO_3 => {
+                    let guitar = data.gh3.as_ref();
+                    Self::Guitar(Guitar {
+                        stick: (&guitar.js).into(),
+                        whammy_bar: guitar.whammy_bar,
+                    })
+                }
+                ffi::EXP_WII_BOARD => {
+                    let board = data.wb.as_ref();
+                    Self::BalanceBoard(BalanceBoard {
+                        top_left: board.tl,
+                        top_right: board.tr,
+                        bottom_left: board.bl,
+                        bottom_right: board.br,
+                        x: board.x,
+                        y: board.y,
+                    })
+                }
+                ffi::EXP_MOTION_PLUS => {
+                    let mp = data.mp.as_ref();
+                    Self::MotionPlus(MotionPlus {
+                        rate_x: mp.rx,
+                        rate_y: mp.ry,
+                        rate_z: mp.rz,
+                    })
+                }
+                _ => Self::Unknown(exp.type_),
+            }
+        }
+    }
+}
+
+/// Everything a Wii Remote reported on the last [`WPad::update`].
+///
+/// What is filled in depends on the [`WPadDataFormat`], IR and accelerometer data need
+/// [`WPadDataFormat::ButtonsAccelIR`].
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct WPadState {
+    /// Whether the remote is connected and sent data
+    pub connected: bool,
+    pub held: WPadButton,
+    pub down: WPadButton,
+    pub up: WPadButton,
+    pub ir: Ir,
+    pub orientation: Orientation,
+    pub accel: Accel,
+    pub gforce: GForce,
+    /// Raw battery level, see [`WPadState::battery`]
+    pub battery_level: u8,
+    pub extension: Extension,
+}
+
+impl WPadState {
+    /// Battery charge, 0.0 to 1.0.
+    pub fn battery(&self) -> f32 {
+        (f32::from(self.battery_level) / FULL_BATTERY).min(1.0)
+    }
+}
+
+impl From<&ffi::WPADData> for WPadState {
+    fn from(data: &ffi::WPADData) -> Self {
+        Self {
+            connected: data.err == ffi::WPAD_ERR_NONE as _ && data.data_present != 0,
+            held: WPadButton::from_bits_truncate(data.btns_h),
+            down: WPadButton::from_bits_truncate(data.btns_d),
+            up: WPadButton::from_bits_truncate(data.btns_u),
+            ir: (&data.ir).into(),
+            orientation: (&data.orient).into(),
+            accel: (&data.accel).into(),
+            gforce: (&data.gforce).into(),
+            battery_level: data.battery_level,
+            extension: (&data.exp).into(),
+        }
+    }
+}
+
+/// What is plugged into a Wii Remote's extension port, as reported by [`WPad::probe`].
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ExtensionType {
+    None,
+    Nunchuk,
+    Classic,
+    Guitar,
+    BalanceBoard,
+    /// An extension libogc does not know, with its type
+    Unknown(u32),
+}
+
+impl From<u32> for ExtensionType {
+    fn from(kind: u32) -> Self {
+        match kind {
+            ffi::WPAD_EXP_NONE => Self::None,
+            ffi::WPAD_EXP_NUNCHUK => Self::Nunchuk,
+            ffi::WPAD_EXP_CLASSIC => Self::Classic,
+            ffi::WPAD_EXP_GUITARHERO3 => Self::Guitar,
+            ffi::WPAD_EXP_WIIBOARD => Self::BalanceBoard,
+            kind => Self::Unknown(kind),
+        }
+    }
+}
+
+/// A change in which Wii Remotes are connected, see [`WPadMonitor`].
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum WPadEvent {
+    Connected(ControllerPort),
+    Disconnected(ControllerPort),
+    /// An extension was plugged in or pulled out, this is the new one
+    ExtensionChanged(ControllerPort, ExtensionType),
+}
+
+/// Watches the Wii Remote ports for connections and extension changes.
+///
+/// Call [`WPadMonitor::poll`] once per frame after [`WPad::update`].
+///
+/// ```rust
+/// let mut monitor = WPadMonitor::new();
+/// loop {
+///     WPad::update();
+///     monitor.poll(|event| match event {
+///         WPadEvent::Connected(port) => println!("player {port:?} joined"),
+///         WPadEvent::Disconnected(port) => println!("player {port:?} left"),
+///         WPadEvent::ExtensionChanged(..) => {}
+///     });
+/// }
+/// ```
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WPadMonitor {
+    ports: [Option<ExtensionType>; 4],
+}
+
+impl WPadMonitor {
+    /// A monitor that sees every remote as disconnected, so the first poll reports the
+    /// connected ones.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Probe every port, calling `on_event` for each change since the last poll.
+    pub fn poll(&mut self, mut on_event: impl FnMut(WPadEvent)) {
+        for (port, last) in ControllerPort::ALL.into_iter().zip(&mut self.ports) {
+            let now = WPad::new(port).probe().ok();
+            match (*last, now) {
+                (None, Some(extension)) => {
+                    on_event(WPadEvent::Connected(port));
+                    if extension != ExtensionType::None {
+                        on_event(WPadEvent::ExtensionChanged(port, extension));
+                    }
+                }
+                (Some(_), None) => on_event(WPadEvent::Disconnected(port)),
+                (Some(old), Some(new)) if old != new => {
+                    on_event(WPadEvent::ExtensionChanged(port, new))
+                }
+                _ => {}
+            }
+            *last = now;
+        }
+    }
+
+    /// Whether the remote on `port` was connected at the last poll.
+    pub fn is_connected(&self, port: ControllerPort) -> bool {
+        self.ports[port as usize].is_some()
+    }
+
+    /// The ports with a remote connected at the last poll.
+    pub fn connected(&self) -> impl Iterator<Item = ControllerPort> + '_ {
+        ControllerPort::ALL
+            .into_iter()
+            .filter(|&port| self.is_connected(port))
+    }
+}
+
+pub(crate) fn wpad_result(code: i32) -> Result<()> {
+    if code < 0 {
+        Err(InputError::from_wpad(code).into())
+    } else {
+        Ok(())
+    }
+}
+
 impl WPad {
     pub fn new(id: ControllerPort) -> Self {
         WPad { id }
@@ -95,6 +539,14 @@ impl WPad {
         buttons.contains(button)
     }
 
+    /// Everything the remote reported on the last [`WPad::update`].
+    pub fn state(&self) -> WPadState {
+        // SAFETY: libogc returns null for a port that does not exist.
+        unsafe { ffi::WPAD_Data(self.id as i32).as_ref() }
+            .map(WPadState::from)
+            .unwrap_or_default()
+    }
+
     pub fn raw(&self) -> Box<ffi::WPADData> {
         unsafe { Box::new(*ffi::WPAD_Data(self.id as i32)) }
     }
@@ -127,6 +579,54 @@ impl WPad {
         unsafe { ffi::WPAD_SetMotionPlus(self.id as i32, enable_motion_plus as u8) };
     }
 
+    /// Whether the remote is connected and what is plugged into it.
+    pub fn probe(&self) -> Result<ExtensionType> {
+        let mut kind = 0;
+        wpad_result(unsafe { ffi::WPAD_Probe(self.id as i32, &mut kind) })?;
+        Ok(kind.into())
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.probe().is_ok()
+    }
+
+    pub fn set_rumble(&self, rumble: bool) -> Result<()> {
+        wpad_result(unsafe { ffi::WPAD_Rumble(self.id as i32, rumble.into()) })
+    }
+
+    /// Set the screen size the IR pointer position is scaled to, 640x480 by default.
+    ///
+    /// Sizes above 65535 pixels are [`InputError::Invalid`].
+    pub fn set_screen_resolution(&self, width: u32, height: u32) -> Result<()> {
+        if width > 0xFFFF || height > 0xFFFF {
+            return Err(InputError::Invalid.into());
+        }
+        wpad_result(unsafe { ffi::WPAD_SetVRes(self.id as i32, width, height) })?;
+        SCREEN_RESOLUTION[self.id as usize].store(width << 16 | height, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// The screen size last set with [`WPad::set_screen_resolution`].
+    pub fn screen_resolution(&self) -> (u32, u32) {
+        let resolution = SCREEN_RESOLUTION[self.id as usize].load(Ordering::Relaxed);
+        (resolution >> 16, resolution & 0xFFFF)
+    }
+
+    /// Raw battery level, see [`WPadState::battery`].
+    pub fn battery_level(&self) -> u8 {
+        unsafe { ffi::WPAD_BatteryLevel(self.id as i32) }
+    }
+
+    /// Disconnect the remote, which turns it off.
+    pub fn disconnect(&self) -> Result<()> {
+        wpad_result(unsafe { ffi::WPAD_Disconnect(self.id as i32) })
+    }
+
+    /// Turn every remote off after `timeout` without input.
+    pub fn set_idle_timeout(timeout: Duration) {
+        unsafe { ffi::WPAD_SetIdleTimeout(timeout.as_secs().try_into().unwrap_or(u32::MAX)) };
+    }
+
     pub fn init() {
         unsafe { ffi::WPAD_Init() };
     }
@@ -0,0 +1,665 @@
+//! The ``io`` module of ``ogc-rs``.
+//!
+//! Byte oriented [`Read`], [`Write`], [`Seek`] and [`BufRead`] traits modelled after
+//! ``std::io``, so parsers and protocol handlers can work with sockets, `IOS` files, the USB
+//! Gecko and in-memory buffers alike. Errors are [`OgcError`]s, end of file is a read of `0`
+//! bytes.
+//!
+//! With the ``embedded-io`` feature every type implementing these traits also implements the
+//! matching ``embedded_io`` traits.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt;
+
+use crate::{OgcError, Result, error::IoError};
+
+/// Size of the buffer used by [`BufReader::new`] and [`copy`].
+const DEFAULT_BUF_SIZE: usize = 4096;
+
+/// Position to seek to, see [`Seek::seek`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// Offset from the start
+    Start(u64),
+    /// Offset from the end
+    End(i64),
+    /// Offset from the current position
+    Current(i64),
+}
+
+/// A source of bytes.
+pub trait Read {
+    /// Read into `buf`, returning how many bytes were read.
+    ///
+    /// `0` means the end of the source was reached or `buf` is empty.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Fill all of `buf`.
+    ///
+    /// Fails with [`IoError::UnexpectedEof`] if the source ends first, how much of `buf` was
+    /// filled is unspecified then.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(IoError::UnexpectedEof.into()),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Append everything up to the end of the source to `buf`, returning how many bytes were
+    /// read.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        let mut chunk = [0u8; 512];
+        loop {
+            match self.read(&mut chunk)? {
+                0 => return Ok(buf.len() - start),
+                n => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+
+    /// Append everything up to the end of the source to `buf`, returning how many bytes were
+    /// read.
+    ///
+    /// Fails with [`IoError::InvalidData`] if it is not UTF-8, `buf` is left unchanged then.
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let len = self.read_to_end(&mut bytes)?;
+        buf.push_str(core::str::from_utf8(&bytes).map_err(|_| IoError::InvalidData)?);
+        Ok(len)
+    }
+
+    /// Borrow this reader, so adapters can be used without consuming it.
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+/// A sink for bytes.
+pub trait Write {
+    /// Write from `buf`, returning how many bytes were written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Make sure everything written so far reached its destination.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Write all of `buf`.
+    ///
+    /// Fails with [`IoError::WriteZero`] if the sink stops accepting bytes.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(IoError::WriteZero.into()),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Write formatted text, used by the `write!` macro.
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+        struct Adapter<'a, W: ?Sized> {
+            inner: &'a mut W,
+            error: Option<OgcError>,
+        }
+
+        impl<W: Write + ?Sized> fmt::Write for Adapter<'_, W> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.inner.write_all(s.as_bytes()).map_err(|err| {
+                    self.error = Some(err);
+                    fmt::Error
+                })
+            }
+        }
+
+        let mut adapter = Adapter {
+            inner: self,
+            error: None,
+        };
+        match fmt::write(&mut adapter, args) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(adapter.error.unwrap_or(IoError::InvalidData.into())),
+        }
+    }
+
+    /// Borrow this writer, so adapters can be used without consuming it.
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+/// A source or sink with a position that can be moved.
+pub trait Seek {
+    /// Move to `pos`, returning the new position from the start.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+    /// Move back to the start.
+    fn rewind(&mut self) -> Result<()> {
+        self.seek(SeekFrom::Start(0)).map(|_| ())
+    }
+
+    /// The current position from the start.
+    fn stream_position(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+/// A [`Read`] with an internal buffer, allowing lines to be read.
+pub trait BufRead: Read {
+    /// The buffered bytes, reading more if the buffer is empty.
+    ///
+    /// An empty slice means the end of the source was reached.
+    fn fill_buf(&mut self) -> Result<&[u8]>;
+
+    /// Mark `amt` bytes returned by [`BufRead::fill_buf`] as read.
+    fn consume(&mut self, amt: usize);
+
+    /// Append bytes to `buf` up to and including `byte` or the end of the source, returning how
+    /// many were read.
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut read = 0;
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(read);
+            }
+            let (done, used) = match available.iter().position(|&b| b == byte) {
+                Some(i) => (true, i + 1),
+                None => (false, available.len()),
+            };
+            buf.extend_from_slice(&available[..used]);
+            self.consume(used);
+            read += used;
+            if done {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Append a line including its `\n` to `buf`, returning how many bytes were read.
+    ///
+    /// Fails with [`IoError::InvalidData`] if the line is not UTF-8, `buf` is left unchanged
+    /// then.
+    fn read_line(&mut self, buf: &mut String) -> Result<usize> {
+        let mut bytes = Vec::new();
+        let len = self.read_until(b'\n', &mut bytes)?;
+        buf.push_str(core::str::from_utf8(&bytes).map_err(|_| IoError::InvalidData)?);
+        Ok(len)
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        (**self).seek(pos)
+    }
+}
+
+impl<B: BufRead + ?Sized> BufRead for &mut B {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        (**self).consume(amt);
+    }
+}
+
+impl<R: Read + ?Sized> Read for Box<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<W: Write + ?Sized> Write for Box<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len().min(self.len());
+        let (head, tail) = self.split_at(len);
+        buf[..len].copy_from_slice(head);
+        *self = tail;
+        Ok(len)
+    }
+}
+
+impl BufRead for &[u8] {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        *self = &self[amt.min(self.len())..];
+    }
+}
+
+impl Write for &mut [u8] {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let len = buf.len().min(self.len());
+        let (head, tail) = core::mem::take(self).split_at_mut(len);
+        head.copy_from_slice(&buf[..len]);
+        *self = tail;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// An in-memory buffer with a position, making it [`Seek`].
+///
+/// # Examples
+///
+/// ```rust
+/// let mut cursor = Cursor::new(Vec::new());
+/// write!(cursor, "score: {}", 100)?;
+/// cursor.rewind()?;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cursor<T> {
+    inner: T,
+    pos: u64,
+}
+
+impl<T> Cursor<T> {
+    /// Wrap `inner`, starting at position `0`.
+    pub fn new(inner: T) -> Self {
+        Self { inner, pos: 0 }
+    }
+
+    /// The current position.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Move to `pos`, which may be past the end.
+    pub fn set_position(&mut self, pos: u64) {
+        self.pos = pos;
+    }
+
+    /// The wrapped buffer.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// The wrapped buffer.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwrap the buffer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: AsRef<[u8]>> Cursor<T> {
+    /// The bytes after the current position.
+    fn remaining(&self) -> &[u8] {
+        let inner = self.inner.as_ref();
+        let start = usize::try_from(self.pos).map_or(inner.len(), |pos| pos.min(inner.len()));
+        &inner[start..]
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for Cursor<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.remaining().read(buf)?;
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<T: AsRef<[u8]>> BufRead for Cursor<T> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl<T: AsRef<[u8]>> Seek for Cursor<T> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let len = self.inner.as_ref().len() as u64;
+        self.pos = seek_offset(self.pos, len, pos)?;
+        Ok(self.pos)
+    }
+}
+
+impl Write for Cursor<&mut [u8]> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let start =
+            usize::try_from(self.pos).map_or(self.inner.len(), |pos| pos.min(self.inner.len()));
+        let len = (&mut self.inner[start..]).write(buf)?;
+        self.pos = (start + len) as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Cursor<Vec<u8>> {
+    /// Overwrite the bytes at the current position, growing the buffer as needed.
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let start = usize::try_from(self.pos).map_err(|_| IoError::InvalidSeek)?;
+        let end = start.checked_add(buf.len()).ok_or(IoError::InvalidSeek)?;
+        if self.inner.len() < end {
+            self.inner.resize(end, 0);
+        }
+        self.inner[start..end].copy_from_slice(buf);
+        self.pos = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The position `pos` refers to, given the current position and the length.
+///
+/// Fails with [`IoError::InvalidSeek`] if it would be before the start.
+pub(crate) fn seek_offset(current: u64, len: u64, pos: SeekFrom) -> Result<u64> {
+    let target = match pos {
+        SeekFrom::Start(offset) => Some(offset),
+        SeekFrom::End(offset) => len.checked_add_signed(offset),
+        SeekFrom::Current(offset) => current.checked_add_signed(offset),
+    };
+    target.ok_or(IoError::InvalidSeek.into())
+}
+
+/// Adds a buffer to any [`Read`], making it [`BufRead`].
+///
+/// Useful with sources where every read is a system call, such as sockets and `IOS` files.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut reader = BufReader::new(TcpStream::connect("192.168.1.2:4405")?);
+/// let mut line = String::new();
+/// reader.read_line(&mut line)?;
+/// ```
+pub struct BufReader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: Read> BufReader<R> {
+    /// Wrap `inner` with a 4 KiB buffer.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Wrap `inner` with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: alloc::vec![0; capacity.max(1)].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+}
+
+impl<R> BufReader<R> {
+    /// The wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// The wrapped reader, reading from it directly skips the buffered bytes.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// The bytes read from the wrapped reader but not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Unwrap the reader, dropping the buffered bytes.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Skip the buffer for reads at least as large as it.
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return self.inner.read(buf);
+        }
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: Read> BufRead for BufReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos == self.filled {
+            self.filled = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: Read + Seek> Seek for BufReader<R> {
+    /// Seek the wrapped reader, dropping the buffered bytes.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let pos = match pos {
+            SeekFrom::Current(offset) => {
+                SeekFrom::Current(offset - (self.filled - self.pos) as i64)
+            }
+            pos => pos,
+        };
+        self.pos = 0;
+        self.filled = 0;
+        self.inner.seek(pos)
+    }
+}
+
+/// Copy everything from `reader` to `writer`, returning how many bytes were copied.
+pub fn copy<R, W>(reader: &mut R, writer: &mut W) -> Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut buf = alloc::vec![0u8; DEFAULT_BUF_SIZE];
+    let mut copied = 0;
+    loop {
+        match reader.read(&mut buf)? {
+            0 => return Ok(copied),
+            n => {
+                writer.write_all(&buf[..n])?;
+                copied += n as u64;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+mod embedded {
+    use embedded_io::ErrorKind;
+
+    use alloc::vec::Vec;
+
+    use super::{BufReader, Cursor, SeekFrom};
+    use crate::{
+        OgcError, Result,
+        console::{Console, Gecko},
+        error::{Errno, IoError},
+        ios::{self, FileDescriptor},
+        network::{Socket, TcpStream, http::Response},
+    };
+
+    impl embedded_io::Error for OgcError {
+        fn kind(&self) -> ErrorKind {
+            match *self {
+                Self::Network(errno) => match errno {
+                    Errno::ECONNREFUSED => ErrorKind::ConnectionRefused,
+                    Errno::ECONNRESET => ErrorKind::ConnectionReset,
+                    Errno::ECONNABORTED => ErrorKind::ConnectionAborted,
+                    Errno::ENOTCONN => ErrorKind::NotConnected,
+                    Errno::EADDRINUSE => ErrorKind::AddrInUse,
+                    Errno::EADDRNOTAVAIL => ErrorKind::AddrNotAvailable,
+                    Errno::EPIPE => ErrorKind::BrokenPipe,
+                    Errno::EINVAL => ErrorKind::InvalidInput,
+                    Errno::ETIMEDOUT => ErrorKind::TimedOut,
+                    Errno::EINTR => ErrorKind::Interrupted,
+                    Errno::ENOMEM => ErrorKind::OutOfMemory,
+                    _ => ErrorKind::Other,
+                },
+                Self::Ios(ios::Error::NoEntry) => ErrorKind::NotFound,
+                Self::Ios(ios::Error::Invalid) => ErrorKind::InvalidInput,
+                Self::Ios(ios::Error::NoMemory | ios::Error::NoHeap) => ErrorKind::OutOfMemory,
+                Self::Io(IoError::WriteZero) => ErrorKind::WriteZero,
+                Self::Io(IoError::InvalidSeek) => ErrorKind::InvalidInput,
+                Self::Io(IoError::InvalidData) => ErrorKind::InvalidData,
+                Self::Alloc(_) => ErrorKind::OutOfMemory,
+                _ => ErrorKind::Other,
+            }
+        }
+    }
+
+    impl From<embedded_io::SeekFrom> for SeekFrom {
+        fn from(pos: embedded_io::SeekFrom) -> Self {
+            match pos {
+                embedded_io::SeekFrom::Start(offset) => Self::Start(offset),
+                embedded_io::SeekFrom::End(offset) => Self::End(offset),
+                embedded_io::SeekFrom::Current(offset) => Self::Current(offset),
+            }
+        }
+    }
+
+    impl From<SeekFrom> for embedded_io::SeekFrom {
+        fn from(pos: SeekFrom) -> Self {
+            match pos {
+                SeekFrom::Start(offset) => Self::Start(offset),
+                SeekFrom::End(offset) => Self::End(offset),
+                SeekFrom::Current(offset) => Self::Current(offset),
+            }
+        }
+    }
+
+    /// Implement the `embedded_io` traits listed after `=>` by forwarding to ours.
+    ///
+    /// The generics of the impls go in the leading brackets, `ErrorType` has to be listed once
+    /// per type.
+    macro_rules! embedded_io {
+        ($generics:tt $ty:ty => $($tr:ident),+) => {
+            $(embedded_io!(@$tr $generics $ty);)+
+        };
+        (@ErrorType [$($g:tt)*] $ty:ty) => {
+            impl<$($g)*> embedded_io::ErrorType for $ty {
+                type Error = OgcError;
+            }
+        };
+        (@Read [$($g:tt)*] $ty:ty) => {
+            impl<$($g)*> embedded_io::Read for $ty {
+                fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+                    super::Read::read(self, buf)
+                }
+            }
+        };
+        (@Write [$($g:tt)*] $ty:ty) => {
+            impl<$($g)*> embedded_io::Write for $ty {
+                fn write(&mut self, buf: &[u8]) -> Result<usize> {
+                    super::Write::write(self, buf)
+                }
+
+                fn flush(&mut self) -> Result<()> {
+                    super::Write::flush(self)
+                }
+            }
+        };
+        (@Seek [$($g:tt)*] $ty:ty) => {
+            impl<$($g)*> embedded_io::Seek for $ty {
+                fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64> {
+                    super::Seek::seek(self, pos.into())
+                }
+            }
+        };
+        (@BufRead [$($g:tt)*] $ty:ty) => {
+            impl<$($g)*> embedded_io::BufRead for $ty {
+                fn fill_buf(&mut self) -> Result<&[u8]> {
+                    super::BufRead::fill_buf(self)
+                }
+
+                fn consume(&mut self, amt: usize) {
+                    super::BufRead::consume(self, amt);
+                }
+            }
+        };
+    }
+
+    embedded_io!([] TcpStream => ErrorType, Read, Write);
+    embedded_io!(['a] &'a TcpStream => ErrorType, Read, Write);
+    embedded_io!([] Socket => ErrorType, Read, Write);
+    embedded_io!([] Response => ErrorType, Read);
+    embedded_io!([] FileDescriptor => ErrorType, Read, Write, Seek);
+    embedded_io!([] Console => ErrorType, Write);
+    embedded_io!([] Gecko => ErrorType, Read, Write);
+    embedded_io!([T] Cursor<T> => ErrorType);
+    embedded_io!([T: AsRef<[u8]>] Cursor<T> => Read, BufRead, Seek);
+    embedded_io!(['a] Cursor<&'a mut [u8]> => Write);
+    embedded_io!([] Cursor<Vec<u8>> => Write);
+    embedded_io!([R] BufReader<R> => ErrorType);
+    embedded_io!([R: super::Read] BufReader<R> => Read, BufRead);
+    embedded_io!([R: super::Read + super::Seek] BufReader<R> => Seek);
+}
@@ -20,6 +20,17 @@ pub mod fs;
 /// `/dev/es` device hellper functions.
 pub mod es;
 
+/// State Management IOS Device
+///
+/// `/dev/stm` device helper functions for power, reset and the disc slot LED.
+pub mod stm;
+
+/// Network IOS Devices
+///
+/// `/dev/net/ncd/manage` and `/dev/net/ip/top` helper functions for the network configuration
+/// and interface status.
+pub mod net;
+
 #[repr(u32)]
 /// Interprocess Control / IOS File Mode
 pub enum Mode {
@@ -71,7 +82,7 @@ impl TryFrom<u8> for Mode {
 }
 
 #[repr(i32)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 /// Interprocess Control / IOS Errors
 pub enum Error {
     /// A invalid argument was provided.
@@ -137,6 +148,8 @@ impl Display for Error {
     }
 }
 
+impl core::error::Error for Error {}
+
 /// Interprocess Control / IOS File Descriptor
 ///
 /// Represents either a device in the case of something under the `/dev/` file path or an actual
@@ -254,15 +267,63 @@ impl From<SeekMode> for i32 {
 /// See [`Error`]
 ///
 pub fn seek(fd: FileDescriptor, offset: i32, mode: SeekMode) -> Result<(), Error> {
+    seek_position(fd, offset, mode).map(|_| ())
+}
+
+/// Attempts to seek to a certain position within a file descriptor, returning the new position
+///
+/// Attempts to seek to `offset` from `mode` in `fd`
+///
+/// # Errors
+/// See [`Error`]
+///
+pub fn seek_position(fd: FileDescriptor, offset: i32, mode: SeekMode) -> Result<u32, Error> {
     match unsafe { ogc_sys::IOS_Seek(fd.0, offset, mode.into()) } {
         val if { val == -4 || val == -5 || val == -6 || val == -8 || val == -22 } => {
             Err(Error::try_from(val).map_err(|()| Error::UnknownErrorCode(val))?)
         }
-        val if { val >= 0 } => Ok(()),
+        val if { val >= 0 } => Ok(val.unsigned_abs()),
         val => Err(Error::UnknownErrorCode(val)),
     }
 }
 
+impl crate::io::Read for FileDescriptor {
+    fn read(&mut self, buf: &mut [u8]) -> crate::Result<usize> {
+        Ok(read(*self, buf)?.unsigned_abs() as usize)
+    }
+}
+
+impl crate::io::Write for FileDescriptor {
+    fn write(&mut self, buf: &[u8]) -> crate::Result<usize> {
+        Ok(write(*self, buf)?.unsigned_abs() as usize)
+    }
+
+    /// Writes go straight to `IOS`, there is nothing to flush.
+    fn flush(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl crate::io::Seek for FileDescriptor {
+    /// Offsets have to fit in an `i32`, anything else fails with
+    /// [`IoError::InvalidSeek`](crate::error::IoError::InvalidSeek).
+    fn seek(&mut self, pos: crate::io::SeekFrom) -> crate::Result<u64> {
+        let invalid = |_| crate::error::IoError::InvalidSeek;
+        let (offset, mode) = match pos {
+            crate::io::SeekFrom::Start(offset) => {
+                (i32::try_from(offset).map_err(invalid)?, SeekMode::Start)
+            }
+            crate::io::SeekFrom::End(offset) => {
+                (i32::try_from(offset).map_err(invalid)?, SeekMode::End)
+            }
+            crate::io::SeekFrom::Current(offset) => {
+                (i32::try_from(offset).map_err(invalid)?, SeekMode::Current)
+            }
+        };
+        Ok(u64::from(seek_position(*self, offset, mode)?))
+    }
+}
+
 /// Attempts to call an ioctl using a file descriptor with an in buffer and out buffer
 ///
 /// Attempts to call `ioctl` with `fd` using `buf_in` and `buf_out`
@@ -144,6 +144,22 @@ pub struct Attributes {
     attribute: u8,
 }
 
+impl Attributes {
+    /// Copy these attributes onto another `path`
+    /// # Errors
+    /// Returns [`ios::Error::FilePathLengthTooLong`] if `path` and its `NUL` terminator do not
+    /// fit in 64 bytes
+    pub fn with_path(mut self, path: &str) -> Result<Self, ios::Error> {
+        if path.len() >= 64 {
+            return Err(ios::Error::FilePathLengthTooLong);
+        }
+
+        self.path = [0u8; 64];
+        self.path[0..path.len()].copy_from_slice(path.as_bytes());
+        Ok(self)
+    }
+}
+
 /// Create a Directory using `params`
 /// # Errors
 /// See [`ios::Error`]
@@ -174,6 +190,29 @@ pub struct ReadDirectory {
     file_count: u32,
 }
 
+impl ReadDirectory {
+    /// Number of entries in the directory
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.file_count as usize / 13
+    }
+
+    /// Whether the directory has no entries
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Names of the entries in the directory
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.file_list_buf
+            .split(|&b| b == 0)
+            .filter(|name| !name.is_empty())
+            .take(self.len())
+            .filter_map(|name| core::str::from_utf8(name).ok())
+    }
+}
+
 //TODO: Find a way to avoid allocation
 /// Read the directory specified by `directory_path` reading up to `MAX_FILE_COUNT` entries
 /// # Errors
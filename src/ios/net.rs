@@ -0,0 +1,406 @@
+use core::{ffi::CStr, net::Ipv4Addr};
+
+use bitflags::bitflags;
+
+use crate::{
+    ios::{self, Mode},
+    utils::Buf32,
+};
+
+static DEV_NET_NCD_MANAGE: &CStr = c"/dev/net/ncd/manage";
+static DEV_NET_IP_TOP: &CStr = c"/dev/net/ip/top";
+
+/// Size of the network configuration as stored in `/shared2/sys/net/02/config.dat`.
+const CONFIG_SIZE: usize = 0x1B5C;
+/// Size of the header in front of the connection slots.
+const CONFIG_HEADER_SIZE: usize = 8;
+/// Size of a single connection slot.
+const CONNECTION_SIZE: usize = 0x91C;
+/// Number of connection slots.
+pub const CONNECTION_COUNT: usize = 3;
+
+/// Level passed with [`Ioctl::GetInterfaceOption`].
+const INTERFACE_LEVEL: u32 = 0xFFFE;
+/// Address, netmask and broadcast address of the interface.
+const OPTION_ADDRESS_TABLE: u32 = 0x4003;
+/// Routes of the `IP` stack.
+const OPTION_ROUTING_TABLE: u32 = 0x4006;
+/// DNS servers handed to the resolver.
+const OPTION_DNS_SERVERS: u32 = 0xB003;
+/// Size of a routing table entry: destination, netmask and gateway followed by 20 bytes that
+/// are not needed here.
+const ROUTE_SIZE: usize = 32;
+/// Most routes read from the routing table.
+const MAX_ROUTES: usize = 8;
+
+/// Network Supported Ioctls
+///
+/// [`Ioctl::GetInterfaceOption`] is only valid on `/dev/net/ip/top`, every other ioctl is issued
+/// on `/dev/net/ncd/manage`.
+pub enum Ioctl {
+    /// Get the Network Configuration in use
+    GetConfig,
+    /// Replace the Network Configuration in use without saving it
+    SetConfig,
+    /// Get the Link Status of the Interface
+    GetLinkStatus,
+    /// Get the MAC Address of the Wireless Interface
+    GetWirelessMacAddress,
+    /// Get an Interface Option from the `IP` stack
+    GetInterfaceOption,
+}
+
+impl From<Ioctl> for i32 {
+    fn from(value: Ioctl) -> Self {
+        match value {
+            Ioctl::GetConfig => 0x3,
+            Ioctl::SetConfig => 0x4,
+            Ioctl::GetLinkStatus => 0x7,
+            Ioctl::GetWirelessMacAddress => 0x8,
+            Ioctl::GetInterfaceOption => 0x1C,
+        }
+    }
+}
+
+/// Link Status of the Network Interface
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LinkStatus {
+    /// The interface is still being brought up
+    Busy,
+    /// No interface is configured
+    None,
+    /// The wired adapter is connected
+    Wired,
+    /// The wireless interface is not associated with an access point
+    WirelessDown,
+    /// The wireless interface is associated with an access point
+    WirelessUp,
+    /// A status code this module does not know about
+    Unknown(u32),
+}
+
+impl LinkStatus {
+    /// Whether a cable is plugged in or an access point was joined.
+    #[must_use]
+    pub fn is_up(self) -> bool {
+        matches!(self, Self::Wired | Self::WirelessUp)
+    }
+}
+
+impl From<u32> for LinkStatus {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::Busy,
+            2 => Self::None,
+            3 => Self::Wired,
+            4 => Self::WirelessDown,
+            5 => Self::WirelessUp,
+            val => Self::Unknown(val),
+        }
+    }
+}
+
+bitflags! {
+    /// Flags of a [`Connection`].
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct ConnectionFlags: u8 {
+        /// Use the wired adapter instead of the wireless interface
+        const WIRED = 0x01;
+        /// Get the DNS servers from DHCP
+        const DNS_DHCP = 0x02;
+        /// Get the address, netmask and gateway from DHCP
+        const IP_DHCP = 0x04;
+        /// Use a proxy
+        const PROXY = 0x10;
+        /// The connection test succeeded
+        const TESTED = 0x20;
+        /// This is the connection in use
+        const SELECTED = 0x80;
+    }
+}
+
+/// A connection slot of the network configuration.
+///
+/// Only the addressing part of the slot is exposed, the proxy and wireless settings are kept as
+/// they are when it is written back with [`Config::set_connection`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Connection {
+    /// Connection flags
+    pub flags: ConnectionFlags,
+    /// Address used when DHCP is off
+    pub address: Ipv4Addr,
+    /// Netmask used when DHCP is off
+    pub netmask: Ipv4Addr,
+    /// Gateway used when DHCP is off
+    pub gateway: Ipv4Addr,
+    /// DNS servers used when DNS from DHCP is off
+    pub dns_servers: [Ipv4Addr; 2],
+    /// MTU, `0` for the default
+    pub mtu: u16,
+}
+
+/// The network configuration used by `IOS`.
+///
+/// This is the configuration the system menu saves, [`set_config`] only changes the copy in use
+/// until the next reset.
+#[derive(Clone)]
+pub struct Config(Buf32);
+
+impl Config {
+    fn slot(&self, index: usize) -> Option<&[u8]> {
+        let start = CONFIG_HEADER_SIZE + index * CONNECTION_SIZE;
+        (index < CONNECTION_COUNT).then(|| &self.0.as_slice()[start..start + CONNECTION_SIZE])
+    }
+
+    fn slot_mut(&mut self, index: usize) -> Option<&mut [u8]> {
+        let start = CONFIG_HEADER_SIZE + index * CONNECTION_SIZE;
+        (index < CONNECTION_COUNT)
+            .then(|| &mut self.0.as_mut_slice()[start..start + CONNECTION_SIZE])
+    }
+
+    /// The index of the connection in use.
+    #[must_use]
+    pub fn selected(&self) -> Option<usize> {
+        (0..CONNECTION_COUNT).find(|&index| {
+            self.connection(index)
+                .is_some_and(|connection| connection.flags.contains(ConnectionFlags::SELECTED))
+        })
+    }
+
+    /// The connection in slot `index`, `None` if there is no such slot.
+    #[must_use]
+    pub fn connection(&self, index: usize) -> Option<Connection> {
+        let slot = self.slot(index)?;
+        let address = |offset: usize| {
+            Ipv4Addr::new(
+                slot[offset],
+                slot[offset + 1],
+                slot[offset + 2],
+                slot[offset + 3],
+            )
+        };
+
+        Some(Connection {
+            flags: ConnectionFlags::from_bits_retain(slot[0]),
+            address: address(4),
+            netmask: address(8),
+            gateway: address(12),
+            dns_servers: [address(16), address(20)],
+            mtu: u16::from_be_bytes([slot[26], slot[27]]),
+        })
+    }
+
+    /// Replace the addressing part of slot `index` with `connection`.
+    ///
+    /// Returns `false` if there is no such slot.
+    pub fn set_connection(&mut self, index: usize, connection: &Connection) -> bool {
+        let Some(slot) = self.slot_mut(index) else {
+            return false;
+        };
+
+        slot[0] = connection.flags.bits();
+        slot[4..8].copy_from_slice(&connection.address.octets());
+        slot[8..12].copy_from_slice(&connection.netmask.octets());
+        slot[12..16].copy_from_slice(&connection.gateway.octets());
+        slot[16..20].copy_from_slice(&connection.dns_servers[0].octets());
+        slot[20..24].copy_from_slice(&connection.dns_servers[1].octets());
+        slot[26..28].copy_from_slice(&connection.mtu.to_be_bytes());
+        true
+    }
+}
+
+/// Turn the result `NCD` writes next to its output into an error.
+fn check_result(result: [u8; 4]) -> Result<(), ios::Error> {
+    match i32::from_be_bytes(result) {
+        val if val >= 0 => Ok(()),
+        val => Err(ios::Error::try_from(val).unwrap_or(ios::Error::UnknownErrorCode(val))),
+    }
+}
+
+/// [`Ioctl::GetConfig`]
+///
+/// Get the network configuration in use
+/// # Errors
+/// See [`ios::Error`]
+pub fn get_config() -> Result<Config, ios::Error> {
+    let ncd = ios::open(DEV_NET_NCD_MANAGE, Mode::None)?;
+
+    let mut config = Buf32::new(CONFIG_SIZE);
+    let mut result = [0u8; 32];
+    let ret = ios::ioctlv::<0, 2, 2>(
+        ncd,
+        Ioctl::GetConfig,
+        &[],
+        &mut [&mut config.as_mut_slice()[..CONFIG_SIZE], &mut result],
+    );
+
+    let _ = ios::close(ncd);
+    ret?;
+    check_result([result[0], result[1], result[2], result[3]])?;
+
+    Ok(Config(config))
+}
+
+/// [`Ioctl::SetConfig`]
+///
+/// Replace the network configuration in use until the next reset, it is not saved to NAND.
+/// `IOS` reads it when the network is initialized.
+/// # Errors
+/// See [`ios::Error`]
+pub fn set_config(config: &Config) -> Result<(), ios::Error> {
+    let ncd = ios::open(DEV_NET_NCD_MANAGE, Mode::None)?;
+
+    let mut result = [0u8; 32];
+    let ret = ios::ioctlv::<1, 1, 2>(
+        ncd,
+        Ioctl::SetConfig,
+        &[&config.0.as_slice()[..CONFIG_SIZE]],
+        &mut [&mut result],
+    );
+
+    let _ = ios::close(ncd);
+    ret?;
+    check_result([result[0], result[1], result[2], result[3]])
+}
+
+/// [`Ioctl::GetLinkStatus`]
+///
+/// Get the link status of the interface in use
+/// # Errors
+/// See [`ios::Error`]
+pub fn get_link_status() -> Result<LinkStatus, ios::Error> {
+    let ncd = ios::open(DEV_NET_NCD_MANAGE, Mode::None)?;
+
+    let mut out_buf = [0u8; 32];
+    let ret = ios::ioctlv::<0, 1, 1>(ncd, Ioctl::GetLinkStatus, &[], &mut [&mut out_buf]);
+
+    let _ = ios::close(ncd);
+    ret?;
+    check_result([out_buf[0], out_buf[1], out_buf[2], out_buf[3]])?;
+
+    Ok(LinkStatus::from(u32::from_be_bytes([
+        out_buf[4], out_buf[5], out_buf[6], out_buf[7],
+    ])))
+}
+
+/// [`Ioctl::GetWirelessMacAddress`]
+///
+/// Get the MAC address of the wireless interface
+/// # Errors
+/// See [`ios::Error`]
+pub fn get_mac_address() -> Result<[u8; 6], ios::Error> {
+    let ncd = ios::open(DEV_NET_NCD_MANAGE, Mode::None)?;
+
+    let mut result = [0u8; 32];
+    let mut mac = [0u8; 6];
+    let ret = ios::ioctlv::<0, 2, 2>(
+        ncd,
+        Ioctl::GetWirelessMacAddress,
+        &[],
+        &mut [&mut result, &mut mac],
+    );
+
+    let _ = ios::close(ncd);
+    ret?;
+    check_result([result[0], result[1], result[2], result[3]])?;
+
+    Ok(mac)
+}
+
+/// [`Ioctl::GetInterfaceOption`]
+///
+/// Read interface option `option` into `buf`, returning how many bytes were written.
+/// # Errors
+/// See [`ios::Error`]
+fn get_interface_option(option: u32, buf: &mut [u8]) -> Result<usize, ios::Error> {
+    let mut request = [0u8; 8];
+    request[..4].copy_from_slice(&INTERFACE_LEVEL.to_be_bytes());
+    request[4..].copy_from_slice(&option.to_be_bytes());
+    let mut len = u32::try_from(buf.len())
+        .map_err(|_| ios::Error::BufferTooLong(buf.len()))?
+        .to_be_bytes();
+
+    let top = ios::open(DEV_NET_IP_TOP, Mode::None)?;
+
+    let ret = ios::ioctlv::<1, 2, 3>(
+        top,
+        Ioctl::GetInterfaceOption,
+        &[&request],
+        &mut [buf, &mut len],
+    );
+
+    let _ = ios::close(top);
+    ret?;
+
+    Ok(u32::from_be_bytes(len) as usize)
+}
+
+/// [`Ioctl::GetInterfaceOption`]
+///
+/// Get the address, netmask and broadcast address of the interface. The network must be
+/// initialized.
+/// # Errors
+/// See [`ios::Error`]
+pub fn get_address_table() -> Result<(Ipv4Addr, Ipv4Addr, Ipv4Addr), ios::Error> {
+    let mut table = [0u8; 12];
+    let len = get_interface_option(OPTION_ADDRESS_TABLE, &mut table)?;
+    if len < table.len() {
+        return Err(ios::Error::UnknownErrorCode(-1));
+    }
+
+    let address = |offset: usize| {
+        Ipv4Addr::new(
+            table[offset],
+            table[offset + 1],
+            table[offset + 2],
+            table[offset + 3],
+        )
+    };
+    Ok((address(0), address(4), address(8)))
+}
+
+/// [`Ioctl::GetInterfaceOption`]
+///
+/// Get the default gateway from the routing table of the `IP` stack, which also knows the one
+/// handed out by DHCP. `None` if there is no default route. The network must be initialized.
+/// # Errors
+/// See [`ios::Error`]
+pub fn get_default_gateway() -> Result<Option<Ipv4Addr>, ios::Error> {
+    let mut table = [0u8; ROUTE_SIZE * MAX_ROUTES];
+    let len = get_interface_option(OPTION_ROUTING_TABLE, &mut table)?.min(table.len());
+
+    let word = |route: &[u8], offset: usize| {
+        Ipv4Addr::new(
+            route[offset],
+            route[offset + 1],
+            route[offset + 2],
+            route[offset + 3],
+        )
+    };
+    Ok(table[..len]
+        .chunks_exact(ROUTE_SIZE)
+        .find(|route| word(route, 0).is_unspecified() && word(route, 4).is_unspecified())
+        .map(|route| word(route, 8))
+        .filter(|gateway| !gateway.is_unspecified()))
+}
+
+/// [`Ioctl::GetInterfaceOption`]
+///
+/// Get the DNS servers the `IP` stack resolves names with. The network must be initialized.
+/// # Errors
+/// See [`ios::Error`]
+pub fn get_dns_servers() -> Result<[Option<Ipv4Addr>; 2], ios::Error> {
+    let mut servers = [0u8; 8];
+    let len = get_interface_option(OPTION_DNS_SERVERS, &mut servers)?.min(servers.len());
+
+    let server = |offset: usize| {
+        let octets = [
+            servers[offset],
+            servers[offset + 1],
+            servers[offset + 2],
+            servers[offset + 3],
+        ];
+        (offset + 4 <= len && octets != [0; 4]).then(|| Ipv4Addr::from(octets))
+    };
+    Ok([server(0), server(4)])
+}
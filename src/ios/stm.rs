@@ -0,0 +1,322 @@
+use core::{
+    cell::UnsafeCell,
+    ffi::{CStr, c_void},
+    sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, Ordering},
+};
+
+use crate::ios::{self, Mode};
+
+static DEV_STM_IMMEDIATE: &CStr = c"/dev/stm/immediate";
+static DEV_STM_EVENTHOOK: &CStr = c"/dev/stm/eventhook";
+
+/// State Management Supported Ioctls
+///
+/// [`Ioctl::EventHook`] is only valid on `/dev/stm/eventhook`, every other ioctl is issued on
+/// `/dev/stm/immediate`.
+pub enum Ioctl {
+    /// Register an Event Hook
+    EventHook,
+    /// Hot Reset the System
+    HotReset,
+    /// Hot Reset the System for the Power Driver
+    HotResetForPowerDriver,
+    /// Shutdown the System into Standby
+    Shutdown,
+    /// Get Current Idle Mode
+    GetIdleMode,
+    /// Release the currently registered Event Hook
+    ReleaseEventHook,
+    /// Shutdown the System into Idle (`WiiConnect24` Standby)
+    Idle,
+    /// Wake the System up from Idle
+    Wakeup,
+    /// Force Video Interface Dimming
+    VideoDimming,
+    /// Flash the Disc Slot LED
+    LedFlash,
+    /// Set the Disc Slot LED Mode
+    LedMode,
+    /// Read State Management Version
+    ReadVersion,
+}
+
+impl From<Ioctl> for i32 {
+    fn from(value: Ioctl) -> Self {
+        match value {
+            Ioctl::EventHook => 0x1000,
+            Ioctl::HotReset => 0x2001,
+            Ioctl::HotResetForPowerDriver => 0x2002,
+            Ioctl::Shutdown => 0x2003,
+            Ioctl::GetIdleMode => 0x3001,
+            Ioctl::ReleaseEventHook => 0x3002,
+            Ioctl::Idle => 0x5001,
+            Ioctl::Wakeup => 0x5002,
+            Ioctl::VideoDimming => 0x5003,
+            Ioctl::LedFlash => 0x6001,
+            Ioctl::LedMode => 0x6002,
+            Ioctl::ReadVersion => 0x7001,
+        }
+    }
+}
+
+/// State Management Events
+///
+/// These are delivered to the hook registered with [`register_event_hook`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The front panel reset button was pressed
+    Reset,
+    /// The front panel power button was pressed
+    Power,
+    /// An event code this module does not know about
+    Unknown(u32),
+}
+
+impl From<u32> for Event {
+    fn from(value: u32) -> Self {
+        match value {
+            0x0002_0000 => Self::Reset,
+            0x0000_0800 => Self::Power,
+            val => Self::Unknown(val),
+        }
+    }
+}
+
+/// Disc Slot LED Modes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LedMode {
+    /// LED off
+    Off,
+    /// LED dimmed
+    Dim,
+    /// LED fully lit
+    Bright,
+}
+
+impl From<LedMode> for u32 {
+    fn from(value: LedMode) -> Self {
+        match value {
+            LedMode::Off => 0,
+            LedMode::Dim => 1,
+            LedMode::Bright => 2,
+        }
+    }
+}
+
+/// A 32 byte aligned buffer that `IOS` can write into while the event hook is pending.
+#[repr(C, align(32))]
+struct EventBuffer(UnsafeCell<[u32; 8]>);
+
+// SAFETY: The buffers are only touched by `IOS` while a hook is pending and by the hook handler
+// once `IOS` has replied, never concurrently.
+unsafe impl Sync for EventBuffer {}
+
+static EVENT_BUF_IN: EventBuffer = EventBuffer(UnsafeCell::new([0; 8]));
+static EVENT_BUF_OUT: EventBuffer = EventBuffer(UnsafeCell::new([0; 8]));
+
+/// `/dev/stm/immediate`, closed again when dropped so no error path leaks it.
+struct Immediate(ios::FileDescriptor);
+
+impl Immediate {
+    fn open() -> Result<Self, ios::Error> {
+        ios::open(DEV_STM_IMMEDIATE, Mode::None).map(Self)
+    }
+
+    fn ioctl(&self, ioctl: Ioctl, in_buf: &[u8], out_buf: &mut [u8]) -> Result<(), ios::Error> {
+        ios::ioctl(self.0, ioctl, in_buf, out_buf)
+    }
+}
+
+impl Drop for Immediate {
+    fn drop(&mut self) {
+        let _ = ios::close(self.0);
+    }
+}
+
+static EVENT_HOOK: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+static EVENT_HOOK_FD: AtomicI32 = AtomicI32::new(-1);
+static EVENT_HOOK_RELEASING: AtomicBool = AtomicBool::new(false);
+static LIBOGC_RELEASED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn event_hook_handler(result: i32, _usrdata: *mut c_void) -> i32 {
+    if result < 0 || EVENT_HOOK_RELEASING.load(Ordering::Acquire) {
+        return result;
+    }
+
+    // SAFETY: `IOS` has replied so it is done writing into the out buffer.
+    let event = unsafe { core::ptr::read_volatile(EVENT_BUF_OUT.0.get().cast::<u32>()) };
+
+    // Re-arm before dispatching so no event is missed while the hook runs.
+    let fd = EVENT_HOOK_FD.load(Ordering::Acquire);
+    if fd >= 0 {
+        let _ = arm_event_hook(fd);
+    }
+
+    let hook = EVENT_HOOK.load(Ordering::Acquire);
+    if !hook.is_null() {
+        // SAFETY: Only `fn(Event)` pointers are ever stored in `EVENT_HOOK`.
+        let hook = unsafe { core::mem::transmute::<*mut (), fn(Event)>(hook) };
+        hook(Event::from(event));
+    }
+
+    result
+}
+
+fn arm_event_hook(fd: i32) -> Result<(), ios::Error> {
+    match unsafe {
+        ogc_sys::IOS_IoctlAsync(
+            fd,
+            Ioctl::EventHook.into(),
+            EVENT_BUF_IN.0.get().cast(),
+            32,
+            EVENT_BUF_OUT.0.get().cast(),
+            32,
+            Some(event_hook_handler),
+            core::ptr::null_mut(),
+        )
+    } {
+        val if { val == -4 || val == -5 || val == -6 || val == -8 || val == -22 } => {
+            Err(ios::Error::try_from(val).map_err(|()| ios::Error::UnknownErrorCode(val))?)
+        }
+        val if { val >= 0 } => Ok(()),
+        val => Err(ios::Error::UnknownErrorCode(val)),
+    }
+}
+
+/// Register `hook` to be called on reset and power button presses.
+///
+/// Only one event hook can be registered with `IOS` at a time. The first call takes it over from
+/// libogc, after which [`System::set_reset_callback`](crate::system::System::set_reset_callback)
+/// and [`System::set_power_callback`](crate::system::System::set_power_callback) no longer fire.
+/// Calling this again replaces the previous hook.
+///
+/// `hook` is called from the IPC interrupt handler, so it should do as little as possible.
+/// # Errors
+/// See [`ios::Error`]
+pub fn register_event_hook(hook: fn(Event)) -> Result<(), ios::Error> {
+    EVENT_HOOK.store(hook as *mut (), Ordering::Release);
+
+    if EVENT_HOOK_FD.load(Ordering::Acquire) >= 0 {
+        return Ok(());
+    }
+
+    if !LIBOGC_RELEASED.swap(true, Ordering::AcqRel) {
+        // SAFETY: This releases libogc's own event hook and closes its `/dev/stm` handles.
+        let _ = unsafe { ogc_sys::__STM_Close() };
+    }
+
+    let eventhook = ios::open(DEV_STM_EVENTHOOK, Mode::None)?;
+    EVENT_HOOK_RELEASING.store(false, Ordering::Release);
+    EVENT_HOOK_FD.store(eventhook.0, Ordering::Release);
+
+    if let Err(err) = arm_event_hook(eventhook.0) {
+        EVENT_HOOK_FD.store(-1, Ordering::Release);
+        let _ = ios::close(eventhook);
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Release the event hook registered with [`register_event_hook`].
+/// # Errors
+/// See [`ios::Error`]
+pub fn release_event_hook() -> Result<(), ios::Error> {
+    let fd = EVENT_HOOK_FD.swap(-1, Ordering::AcqRel);
+    EVENT_HOOK.store(core::ptr::null_mut(), Ordering::Release);
+    if fd < 0 {
+        return Ok(());
+    }
+
+    EVENT_HOOK_RELEASING.store(true, Ordering::Release);
+
+    let eventhook = ios::FileDescriptor(fd);
+    let stm = match Immediate::open() {
+        Ok(stm) => stm,
+        Err(err) => {
+            let _ = ios::close(eventhook);
+            return Err(err);
+        }
+    };
+
+    let mut out_buf = [0u8; 32];
+    let res = stm.ioctl(Ioctl::ReleaseEventHook, &[0u8; 32], &mut out_buf);
+
+    let _ = ios::close(eventhook);
+    res
+}
+
+/// Shutdown the system into standby, turning the console off.
+///
+/// This skips any of libogc's cleanup, prefer
+/// [`System::reset_system`](crate::system::System::reset_system) when that matters.
+/// # Errors
+/// See [`ios::Error`]
+pub fn shutdown_to_standby() -> Result<!, ios::Error> {
+    let stm = Immediate::open()?;
+
+    let mut out_buf = [0u8; 32];
+    stm.ioctl(Ioctl::Shutdown, &[0u8; 32], &mut out_buf)?;
+
+    loop {}
+}
+
+/// Shutdown the system into idle mode (yellow LED, `WiiConnect24` stays active).
+/// # Errors
+/// See [`ios::Error`]
+pub fn shutdown_to_idle() -> Result<!, ios::Error> {
+    let stm = Immediate::open()?;
+
+    // Wake-up and LED configuration differs between early and later Hollywood revisions.
+    let config: u32 = if crate::system::System::get_hollywood_revision() < 2 {
+        0xFCA0_8280
+    } else {
+        0xFCE0_82C0
+    };
+    let mut in_buf = [0u8; 32];
+    in_buf[0..4].copy_from_slice(&config.to_be_bytes());
+
+    let mut out_buf = [0u8; 32];
+    stm.ioctl(Ioctl::Idle, &in_buf, &mut out_buf)?;
+
+    loop {}
+}
+
+/// Hot reset the system, this is the same as pressing the reset button.
+/// # Errors
+/// See [`ios::Error`]
+pub fn hot_reset() -> Result<!, ios::Error> {
+    let stm = Immediate::open()?;
+
+    let mut out_buf = [0u8; 32];
+    stm.ioctl(Ioctl::HotReset, &[0u8; 32], &mut out_buf)?;
+
+    loop {}
+}
+
+/// Set the disc slot LED to `mode`
+/// # Errors
+/// See [`ios::Error`]
+pub fn set_led_mode(mode: LedMode) -> Result<(), ios::Error> {
+    let stm = Immediate::open()?;
+
+    let mut in_buf = [0u8; 32];
+    in_buf[0..4].copy_from_slice(&u32::from(mode).to_be_bytes());
+
+    let mut out_buf = [0u8; 32];
+    stm.ioctl(Ioctl::LedMode, &in_buf, &mut out_buf)
+}
+
+/// Get the idle mode the system will shut down into
+/// # Errors
+/// See [`ios::Error`]
+pub fn get_idle_mode() -> Result<u32, ios::Error> {
+    let stm = Immediate::open()?;
+
+    let mut out_buf = [0u8; 32];
+    stm.ioctl(Ioctl::GetIdleMode, &[0u8; 32], &mut out_buf)?;
+
+    Ok(u32::from_be_bytes(
+        out_buf[0..4].try_into().map_err(|_| ios::Error::Invalid)?,
+    ))
+}
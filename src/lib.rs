@@ -5,13 +5,19 @@
 //! ``ogc-rs`` provides many features from libogc such as:
 //!
 //! * ``network``: Provides TCP networking for the Wii.
+//! * ``io``: Provides ``Read`` and ``Write`` traits shared by sockets, files and the USB Gecko.
 //! * ``audio``: Provides functions for audio on the Wii.
+//! * ``mixer``: Provides a software mixer for playing many sounds through one hardware voice.
+//! * ``music``: Provides a MOD, XM and MIDI player for ``ASND`` voices.
 //! * ``fs``: Provides functions for manipulating the filesystem on the Wii.
 //! * ``system``: Provides OS functions for the Wii.
+//! * ``sysconf``: Provides access to the console settings on the Wii.
 //! * ``console``: Provides console functions for the Wii.
 //! * ``input``: Provides an interface for reading input from devices on the Wii.
 //! * ``video``: Provides functions for video output on the Wii.
 //! * ``gx``: Provides an opengl-like interface for rendering on the Wii.
+//! * ``devserver``: Provides a remote debug server, behind the ``devserver`` feature.
+//! * ``vorbis``: Provides an Ogg Vorbis decoder in ``audio``, behind the ``vorbis`` feature.
 //!
 //! ``ogc-rs`` also provides runtime functions and an allocator for ``no_std``
 //! environments.
@@ -34,12 +40,21 @@ extern crate alloc;
 /// `IOS` subsystems
 pub mod ios;
 
+/// System Configuration
+///
+/// This module reads and writes the console settings stored in `SYSCONF`
+pub mod sysconf;
+
 // Custom Error Implementation
 pub mod error;
 pub use error::{OgcError, Result};
 
+// I/O Traits
+pub mod io;
+
 // Networking Implementation
 pub mod network;
+pub use network as net;
 
 // Audio Implementation
 pub mod audio;
@@ -78,6 +93,12 @@ pub mod asnd;
 // AESND Implmentation
 pub mod aesnd;
 
+// Software Mixer
+pub mod mixer;
+
+// Music Sequencer
+pub mod music;
+
 // Input Implementation
 pub mod input;
 
@@ -98,6 +119,10 @@ pub mod time;
 #[cfg(feature = "glam_compat")]
 pub mod glam_impl;
 
+// Remote Debug Server
+#[cfg(feature = "devserver")]
+pub mod devserver;
+
 // FFI
 cfg_if::cfg_if! {
     if #[cfg(feature = "ffi")] {
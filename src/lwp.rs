@@ -1,8 +1,11 @@
 //! The ``lwp`` module of ``ogc-rs``.
 //!
 //! This module implements a safe wrapper around thread based functions.
+//!
+//! Failures are reported as [`OgcError::Lwp`]. libogc returns `-1` for invalid handles, which
+//! shows up as [`Errno::EPERM`].
 
-use crate::ffi;
+use crate::{OgcError, Result, error::Errno, ffi};
 use core::ffi::c_void;
 
 /// A thread context handle.
@@ -17,6 +20,11 @@ impl Thread {
         Thread { handle }
     }
 
+    /// The raw handle of this thread.
+    pub fn id(&self) -> u32 {
+        self.handle
+    }
+
     /// Test whether this thread is suspended or not.
     pub fn is_suspended(&self) -> bool {
         unsafe { ffi::LWP_ThreadIsSuspended(self.handle) != 0 }
@@ -25,12 +33,12 @@ impl Thread {
     /// Suspend this thread.
     ///
     /// On success, returns `Ok(1)` if the thread was already suspended, or `Ok(0)` if it was
-    /// successfully suspended. Otherwise, returns an error code.
-    pub fn suspend(&self) -> Result<i32, i32> {
+    /// successfully suspended. Otherwise, returns [`OgcError::Lwp`].
+    pub fn suspend(&self) -> Result<i32> {
         let res = unsafe { ffi::LWP_SuspendThread(self.handle) };
 
         if res < 0 {
-            Err(res)
+            Err(OgcError::Lwp(Errno::from_return(res)))
         } else {
             Ok(res)
         }
@@ -39,12 +47,12 @@ impl Thread {
     /// Resume this thread.
     ///
     /// On success, returns `Ok(1)` if the thread was already resumed, or `Ok(0)` if it was
-    /// successfully resumed. Otherwise, returns an error code.
-    pub fn resume(&self) -> Result<i32, i32> {
+    /// successfully resumed. Otherwise, returns [`OgcError::Lwp`].
+    pub fn resume(&self) -> Result<i32> {
         let res = unsafe { ffi::LWP_ResumeThread(self.handle) };
 
         if res < 0 {
-            Err(res)
+            Err(OgcError::Lwp(Errno::from_return(res)))
         } else {
             Ok(res)
         }
@@ -56,14 +64,16 @@ impl Thread {
     }
 
     /// Join this thread.
-    pub fn join(&self) -> Result<*mut c_void, i32> {
+    pub fn join(&self) -> Result<*mut c_void> {
         let mut ret = core::mem::MaybeUninit::uninit();
         unsafe {
             let res = ffi::LWP_JoinThread(self.handle, ret.as_mut_ptr());
 
             if res < 0 {
-                Err(res)
+                Err(OgcError::Lwp(Errno::from_return(res)))
             } else {
+                #[cfg(feature = "devserver")]
+                crate::devserver::unregister_thread(self);
                 Ok(ret.assume_init())
             }
         }
@@ -120,7 +130,7 @@ impl Builder {
         self
     }
 
-    pub fn spawn(self, entry: EntryFn) -> Result<Thread, i32> {
+    pub fn spawn(self, entry: EntryFn) -> Result<Thread> {
         let mut thread = core::mem::MaybeUninit::uninit();
         unsafe {
             let res = ffi::LWP_CreateThread(
@@ -133,9 +143,12 @@ impl Builder {
             );
 
             if res < 0 {
-                Err(res)
+                Err(OgcError::Lwp(Errno::from_return(res)))
             } else {
-                Ok(Thread::new(thread.assume_init()))
+                let thread = Thread::new(thread.assume_init());
+                #[cfg(feature = "devserver")]
+                crate::devserver::register_thread("lwp", &thread);
+                Ok(thread)
             }
         }
     }
@@ -149,13 +162,13 @@ pub struct Queue {
 
 impl Queue {
     /// Initialize the thread synchronization queue.
-    pub fn new() -> Result<Self, i32> {
+    pub fn new() -> Result<Self> {
         let mut q = core::mem::MaybeUninit::uninit();
         unsafe {
             let res = ffi::LWP_InitQueue(q.as_mut_ptr());
 
             if res < 0 {
-                Err(res)
+                Err(OgcError::Lwp(Errno::from_return(res)))
             } else {
                 Ok(Queue {
                     handle: q.assume_init(),
@@ -164,6 +177,10 @@ impl Queue {
         }
     }
 
+    pub(crate) fn handle(&self) -> ffi::lwpq_t {
+        self.handle
+    }
+
     /// Removes all blocked threads from the thread synchronization queue and sets them back to
     /// running state.
     pub fn broadcast(&self) {
@@ -207,12 +224,12 @@ pub fn reschedule(prio: u8) {
 
 /// Pushes the current thread onto the given thread synchronization queue and sets the thread state
 /// to blocked.
-pub fn sleep(q: Queue) -> Result<(), i32> {
+pub fn sleep(q: Queue) -> Result<()> {
     unsafe {
         let res = ffi::LWP_ThreadSleep(q.handle);
 
         if res < 0 {
-            Err(res)
+            Err(OgcError::Lwp(Errno::from_return(res)))
         } else {
             Ok(())
         }
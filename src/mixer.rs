@@ -0,0 +1,30 @@
+//! The ``mixer`` module of ``ogc-rs``.
+//!
+//! A software mixer that plays any number of logical voices through a single hardware voice.
+//!
+//! [`Mixer`] itself is plain Rust and lives in ``ogc_formats::mixer`` so it is tested on the
+//! host: it pulls samples from [`SampleSource`]s, resamples them to
+//! [`SAMPLE_RATE`], applies volume, pan and fades and sums everything into interleaved 16-bit
+//! stereo. [`MixerOutput`] hands the result to `ASND` or `AESND`, and [`Prefetch`] runs slow
+//! decoders on their own thread so they never hold up the audio interrupt. [`InfiniteVoice`]
+//! loops a whole source on a hardware voice of its own instead.
+//!
+//! Sources of voices that finish or are stopped are not freed by the mixer itself, they are
+//! parked until [`Mixer::collect`] or the next [`MixerOutput::lock`] so the audio interrupt
+//! never frees memory.
+//!
+//! ```rust
+//! let mut mixer = Mixer::new(32);
+//! let id = mixer.play(PcmSource::new(SAMPLES, 1, 22050), PlayOptions::new().pan(-0.5));
+//!
+//! let output = MixerOutput::asnd(mixer, 0)?;
+//! output.lock(|mixer| mixer.fade_out(id.unwrap(), Duration::from_millis(500)));
+//! ```
+
+mod infinite;
+mod output;
+mod prefetch;
+pub use infinite::InfiniteVoice;
+pub use ogc_formats::mixer::{Mixer, PcmSource, PlayOptions, SAMPLE_RATE, SampleSource, VoiceId};
+pub use output::MixerOutput;
+pub use prefetch::Prefetch;
@@ -0,0 +1,95 @@
+//! Looping a whole source on an `ASND` voice.
+
+use alloc::vec::Vec;
+
+use super::SampleSource;
+use crate::{
+    OgcError, Result,
+    asnd::{Asnd, VoiceFormat, VoiceOptions},
+    error::AudioError,
+    ffi,
+    utils::Buf32,
+};
+
+/// `ASND` buffers hold a multiple of 32 bytes.
+const ALIGN_BYTES: usize = 32;
+
+/// A [`SampleSource`] looping forever on an `ASND` voice through
+/// [`Asnd::set_infinite_voice`].
+///
+/// The whole source is decoded into memory up front, from then on the hardware loops it without
+/// a gap and without any work on the CPU. This suits music loops and ambience, longer music that
+/// plays once is better streamed through a [`Prefetch`](super::Prefetch). Dropping it stops the
+/// voice.
+///
+/// # Examples
+///
+/// ```rust
+/// let music = Vorbis::new(File::open("sd:/music/title.ogg")?)?;
+/// let music = InfiniteVoice::new(music, 1)?;
+/// Asnd::change_volume_voice(music.voice(), 128, 128)?;
+/// ```
+pub struct InfiniteVoice {
+    voice: u32,
+    /// Played by the hardware until the voice is stopped
+    _buffer: Buf32,
+}
+
+impl InfiniteVoice {
+    /// Decode `source` to its end and loop it on `ASND` voice `voice`.
+    ///
+    /// `ASND` must already be initialized. Buffers have to be a whole number of 32 bytes, so the
+    /// last few frames are cut from the loop, at most 15 for mono and 7 for stereo. A source
+    /// shorter than that fails with [`AudioError::Invalid`], as does a voice above 15.
+    pub fn new<S: SampleSource>(mut source: S, voice: u32) -> Result<Self> {
+        if voice >= 16 {
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+        let format = match source.channels() {
+            1 => VoiceFormat::Mono16BitBe,
+            2 => VoiceFormat::Stereo16BitBe,
+            _ => return Err(OgcError::Audio(AudioError::Invalid)),
+        };
+
+        let mut samples = Vec::new();
+        let mut chunk = [0; 1024];
+        loop {
+            match source.read(&mut chunk) {
+                0 => break,
+                len => samples.extend_from_slice(&chunk[..len]),
+            }
+        }
+        let len = samples.len() * 2 / ALIGN_BYTES * ALIGN_BYTES;
+        if len == 0 {
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        let mut buffer = Buf32::new(len);
+        for (bytes, sample) in buffer.chunks_exact_mut(2).zip(&samples) {
+            bytes.copy_from_slice(&sample.to_be_bytes());
+        }
+        drop(samples);
+        unsafe { ffi::DCFlushRange(buffer.as_mut_ptr().cast(), len as u32) };
+
+        let options = VoiceOptions::new()
+            .voice(voice)
+            .format(format)
+            .pitch(source.sample_rate());
+        Asnd::set_infinite_voice(options, &mut buffer)?;
+        Ok(Self {
+            voice,
+            _buffer: buffer,
+        })
+    }
+
+    /// The `ASND` voice the source loops on, for changing its volume or pitch.
+    pub fn voice(&self) -> u32 {
+        self.voice
+    }
+}
+
+impl Drop for InfiniteVoice {
+    fn drop(&mut self) {
+        let _ = Asnd::stop_voice(self.voice);
+    }
+}
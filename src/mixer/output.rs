@@ -0,0 +1,275 @@
+//! Feeding a [`Mixer`] to the audio hardware.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{
+    cell::Cell,
+    ffi::c_void,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+use ogc_formats::mixer::PendingVoice;
+
+use super::{Mixer, PlayOptions, SAMPLE_RATE, SampleSource, VoiceId};
+use crate::{
+    OgcError, Result, asnd::VoiceFormat, error::AudioError, ffi, print, println, utils::Buf32,
+};
+
+/// Frames in each `ASND` buffer, about 21ms.
+const BUFFER_FRAMES: usize = 1024;
+const BUFFER_BYTES: usize = BUFFER_FRAMES * 4;
+
+/// State shared with the interrupt handler feeding the hardware.
+struct State {
+    mixer: Mixer,
+    scratch: Vec<i16>,
+    buffers: [Buf32; 2],
+    next: usize,
+}
+
+impl State {
+    /// Mix the next frames into `buffers[index]` as big endian samples.
+    fn fill(&mut self, index: usize) {
+        self.mixer.mix(&mut self.scratch);
+        let buffer = &mut self.buffers[index][..BUFFER_BYTES];
+        for (bytes, sample) in buffer.chunks_exact_mut(2).zip(&self.scratch) {
+            bytes.copy_from_slice(&sample.to_be_bytes());
+        }
+        flush(buffer);
+    }
+}
+
+fn flush(buffer: &[u8]) {
+    unsafe { ffi::DCFlushRange(buffer.as_ptr().cast_mut().cast(), buffer.len() as u32) };
+}
+
+/// The only `ASND` output, its callback does not get a user pointer.
+static ASND_STATE: AtomicPtr<State> = AtomicPtr::new(ptr::null_mut());
+
+enum Backend {
+    Asnd(u32),
+    Aesnd,
+}
+
+/// A [`Mixer`] playing on the audio hardware.
+///
+/// The mixer runs in interrupt context from then on, use [`MixerOutput::play`] to start voices
+/// and [`MixerOutput::lock`] to change them. Dropping the output stops it.
+pub struct MixerOutput {
+    state: NonNull<State>,
+    backend: Backend,
+    /// Room to take the parked sources of the mixer into, so they are freed outside of `lock`.
+    spare: Cell<Vec<Box<dyn SampleSource + Send>>>,
+}
+
+// SAFETY: the state is only touched with interrupts disabled.
+unsafe impl Send for MixerOutput {}
+
+impl MixerOutput {
+    fn spare(mixer: &Mixer) -> Cell<Vec<Box<dyn SampleSource + Send>>> {
+        Cell::new(Vec::with_capacity(mixer.parked_capacity()))
+    }
+
+    fn new_state(mixer: Mixer) -> NonNull<State> {
+        let state = Box::new(State {
+            mixer,
+            scratch: vec![0; BUFFER_FRAMES * 2],
+            buffers: [Buf32::new(BUFFER_BYTES), Buf32::new(BUFFER_BYTES)],
+            next: 0,
+        });
+        NonNull::from(Box::leak(state))
+    }
+
+    /// Stream `mixer` through `ASND` voice `voice` with two buffers.
+    ///
+    /// `ASND` must already be initialized. Only one `ASND` output can exist at a time, creating
+    /// a second one fails with [`AudioError::Invalid`].
+    pub fn asnd(mixer: Mixer, voice: u32) -> Result<Self> {
+        if voice >= 16 {
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        let spare = Self::spare(&mixer);
+        let state = Self::new_state(mixer);
+        if ASND_STATE
+            .compare_exchange(
+                ptr::null_mut(),
+                state.as_ptr(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            drop(unsafe { Box::from_raw(state.as_ptr()) });
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        let output = Self {
+            state,
+            backend: Backend::Asnd(voice),
+            spare,
+        };
+        // SAFETY: the voice is not playing yet so the callback cannot run.
+        let ret = unsafe {
+            let state = &mut *state.as_ptr();
+            state.fill(0);
+            state.next = 1;
+            ffi::ASND_SetVoice(
+                voice as i32,
+                VoiceFormat::Stereo16Bit.as_i32(),
+                SAMPLE_RATE as i32,
+                0,
+                state.buffers[0].as_mut_ptr().cast(),
+                BUFFER_BYTES as i32,
+                255,
+                255,
+                Some(asnd_callback),
+            )
+        };
+        if ret == ffi::SND_OK as _ {
+            Ok(output)
+        } else {
+            Err(OgcError::Audio(AudioError::from_asnd(ret)))
+        }
+    }
+
+    /// Add `mixer` to the output of `AESND` through its audio callback.
+    ///
+    /// `AESND` must already be initialized. This replaces any callback registered with
+    /// [`Aesnd::register_audio_callback`](crate::aesnd::Aesnd::register_audio_callback).
+    pub fn aesnd(mixer: Mixer) -> Self {
+        let spare = Self::spare(&mixer);
+        let state = Self::new_state(mixer);
+        unsafe {
+            ffi::AESND_RegisterAudioCallbackWithArg(Some(aesnd_callback), state.as_ptr().cast())
+        };
+        Self {
+            state,
+            backend: Backend::Aesnd,
+            spare,
+        }
+    }
+
+    /// Run `f` on the mixer with interrupts disabled.
+    ///
+    /// Audio stops being mixed while `f` runs, keep it short. Nothing may be allocated with
+    /// interrupts disabled, so start voices with [`MixerOutput::play`] rather than
+    /// [`Mixer::play`], which boxes the source. Sources of voices that ended are freed once `f`
+    /// returned and interrupts are enabled again.
+    ///
+    /// If more voices ended since the last call than the mixer had room for, the extra sources
+    /// were leaked. The room is doubled here and the leak is printed.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut Mixer) -> R) -> R {
+        let mut spare = self.spare.take();
+        let mut capacity = spare.capacity();
+        // SAFETY: the interrupt handler is the only other user of the state.
+        let (result, overflowed, leaked) = unsafe {
+            let level = ffi::IRQ_Disable();
+            let mixer = &mut (*self.state.as_ptr()).mixer;
+            let result = f(mixer);
+            let overflowed = mixer.swap_parked(&mut spare);
+            let leaked = mixer.leaked();
+            ffi::IRQ_Restore(level);
+            (result, overflowed, leaked)
+        };
+        spare.clear();
+        if overflowed {
+            capacity *= 2;
+            println!(
+                "mixer: {} sources leaked, parking up to {} now",
+                leaked, capacity
+            );
+        }
+        // The mixer holds the old spare now, the next swap needs at least as much room.
+        spare.reserve_exact(capacity);
+        self.spare.set(spare);
+        result
+    }
+
+    /// Start playing `source`, see [`Mixer::play`].
+    ///
+    /// The source is boxed and its first samples are read before interrupts are disabled, and
+    /// it is dropped after they are enabled again if no voice is free for it.
+    pub fn play<S: SampleSource + Send + 'static>(
+        &self,
+        source: S,
+        options: PlayOptions,
+    ) -> Option<VoiceId> {
+        let mut voice = Some(PendingVoice::new(source, &options));
+        self.lock(|mixer| mixer.start(&mut voice))
+    }
+
+    /// Stop the output and get the mixer back.
+    pub fn into_mixer(self) -> Mixer {
+        let mut output = core::mem::ManuallyDrop::new(self);
+        output.stop();
+        let state = unsafe { Box::from_raw(output.state.as_ptr()) };
+        state.mixer
+    }
+
+    fn stop(&mut self) {
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            match self.backend {
+                Backend::Asnd(voice) => {
+                    ffi::ASND_StopVoice(voice as i32);
+                    ASND_STATE.store(ptr::null_mut(), Ordering::Release);
+                }
+                Backend::Aesnd => ffi::AESND_RegisterAudioCallbackWithArg(None, ptr::null_mut()),
+            }
+            ffi::IRQ_Restore(level);
+        }
+    }
+}
+
+impl Drop for MixerOutput {
+    fn drop(&mut self) {
+        self.stop();
+        drop(unsafe { Box::from_raw(self.state.as_ptr()) });
+    }
+}
+
+unsafe extern "C" fn asnd_callback(voice: i32) {
+    let state = ASND_STATE.load(Ordering::Acquire);
+    if state.is_null() {
+        return;
+    }
+    // SAFETY: the state outlives the voice and user code only touches it with interrupts off.
+    let state = unsafe { &mut *state };
+    if unsafe { ffi::ASND_TestVoiceBufferReady(voice) } == 0 {
+        return;
+    }
+
+    let index = state.next;
+    state.fill(index);
+    state.next ^= 1;
+    unsafe {
+        ffi::ASND_AddVoice(
+            voice,
+            state.buffers[index].as_mut_ptr().cast(),
+            BUFFER_BYTES as i32,
+        )
+    };
+}
+
+unsafe extern "C" fn aesnd_callback(buffer: *mut c_void, len: u32, arg: *mut c_void) {
+    if buffer.is_null() || arg.is_null() {
+        return;
+    }
+    // SAFETY: `arg` is the state registered in `MixerOutput::aesnd`, which unregisters the
+    // callback before freeing it.
+    let state = unsafe { &mut *arg.cast::<State>() };
+    let buffer = unsafe { core::slice::from_raw_parts_mut(buffer.cast::<u8>(), len as usize) };
+
+    for chunk in buffer.chunks_mut(state.scratch.len() * 2) {
+        let scratch = &mut state.scratch[..chunk.len() / 2];
+        for (sample, bytes) in scratch.iter_mut().zip(chunk.chunks_exact(2)) {
+            *sample = i16::from_be_bytes([bytes[0], bytes[1]]);
+        }
+        state.mixer.mix_add(scratch);
+        for (bytes, sample) in chunk.chunks_exact_mut(2).zip(scratch.iter()) {
+            bytes.copy_from_slice(&sample.to_be_bytes());
+        }
+    }
+    flush(buffer);
+}
@@ -0,0 +1,240 @@
+//! Decoding a source ahead of time on its own thread.
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    ffi::c_void,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use super::SampleSource;
+use crate::{
+    Result, ffi,
+    lwp::{self, Queue},
+};
+
+/// Samples decoded at once by the thread.
+const CHUNK_SAMPLES: usize = 1024;
+/// `end` while the source has not ended.
+const NOT_ENDED: usize = usize::MAX;
+
+/// The ring buffer between the decoding thread and the reader.
+struct Shared {
+    samples: Box<[UnsafeCell<i16>]>,
+    /// Samples read so far, wrapping
+    read: AtomicUsize,
+    /// Samples written so far, wrapping
+    write: AtomicUsize,
+    /// Value of `write` where the source ended, [`NOT_ENDED`] if it did not
+    end: AtomicUsize,
+    running: AtomicBool,
+    underruns: AtomicU32,
+    /// Where the thread waits for free space
+    queue: Queue,
+}
+
+// SAFETY: the thread only writes the free part of the ring and the reader only reads the rest,
+// `read` and `write` hand samples over with release and acquire ordering.
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn mask(&self) -> usize {
+        self.samples.len() - 1
+    }
+
+    fn wake(&self) {
+        unsafe { ffi::LWP_ThreadSignal(self.queue.handle()) };
+    }
+
+    /// Sleep until `ready` holds or the prefetch is dropped.
+    fn wait(&self, ready: impl Fn(&Self) -> bool) {
+        // SAFETY: interrupts are disabled while checking so a wake up from an audio interrupt
+        // cannot come between the check and going to sleep.
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            while self.running.load(Ordering::Acquire) && !ready(self) {
+                ffi::LWP_ThreadSleep(self.queue.handle());
+            }
+            ffi::IRQ_Restore(level);
+        }
+    }
+}
+
+struct Worker {
+    source: Box<dyn SampleSource + Send>,
+    shared: Arc<Shared>,
+}
+
+impl Worker {
+    fn run(mut self) {
+        let shared = &*self.shared;
+        let capacity = shared.samples.len();
+        let channels = usize::from(self.source.channels());
+        let mut chunk = [0i16; CHUNK_SAMPLES];
+
+        let free = |shared: &Shared| {
+            let used = shared
+                .write
+                .load(Ordering::Relaxed)
+                .wrapping_sub(shared.read.load(Ordering::Acquire));
+            capacity - used
+        };
+
+        while shared.running.load(Ordering::Acquire) {
+            shared.wait(|shared| free(shared) >= channels);
+            if !shared.running.load(Ordering::Acquire) {
+                break;
+            }
+
+            let len = free(shared).min(CHUNK_SAMPLES) / channels * channels;
+            let read = self.source.read(&mut chunk[..len]);
+            let write = shared.write.load(Ordering::Relaxed);
+            if read == 0 {
+                shared.end.store(write, Ordering::Release);
+                break;
+            }
+            for (i, &sample) in chunk[..read].iter().enumerate() {
+                let slot = &shared.samples[write.wrapping_add(i) & shared.mask()];
+                unsafe { *slot.get() = sample };
+            }
+            shared
+                .write
+                .store(write.wrapping_add(read), Ordering::Release);
+        }
+    }
+}
+
+/// A [`SampleSource`] that decodes another one ahead of time on its own thread.
+///
+/// Decoders that are too slow to run in an audio interrupt, like
+/// [`Vorbis`](crate::audio::vorbis::Vorbis) or a [`StreamSource`](crate::audio::stream::StreamSource)
+/// reading from a file, can be played by a [`MixerOutput`](super::MixerOutput) through this.
+/// Reading never blocks, if the thread falls behind the missing samples are played as silence
+/// and counted by [`Prefetch::underruns`].
+///
+/// It cannot be rewound, loop music with an [`InfiniteVoice`](super::InfiniteVoice) instead.
+/// Dropping the prefetch tells the thread to stop without waiting for it, so it can happen in an
+/// audio interrupt. The thread drops the source once it noticed.
+pub struct Prefetch {
+    shared: Arc<Shared>,
+    sample_rate: u32,
+    channels: u8,
+}
+
+impl Prefetch {
+    /// Decode `source` on a new thread with `priority`, keeping up to `buffer` of audio ready.
+    pub fn new<S>(source: S, buffer: Duration, priority: u8) -> Result<Self>
+    where
+        S: SampleSource + Send + 'static,
+    {
+        let sample_rate = source.sample_rate();
+        let channels = source.channels();
+        let frames = buffer.as_millis() as usize * sample_rate as usize / 1000;
+        let capacity = (frames * usize::from(channels))
+            .max(2 * CHUNK_SAMPLES)
+            .next_power_of_two();
+
+        let shared = Arc::new(Shared {
+            samples: (0..capacity)
+                .map(|_| UnsafeCell::new(0))
+                .collect::<Vec<_>>()
+                .into(),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+            end: AtomicUsize::new(NOT_ENDED),
+            running: AtomicBool::new(true),
+            underruns: AtomicU32::new(0),
+            queue: Queue::new()?,
+        });
+
+        unsafe extern "C" fn entry(arg: *mut c_void) -> *mut c_void {
+            // SAFETY: `arg` was created from a `Box<Worker>` in `Prefetch::new`.
+            let worker = unsafe { Box::from_raw(arg.cast::<Worker>()) };
+            worker.run();
+            // Nobody joins the thread, see `Drop for Prefetch`.
+            #[cfg(feature = "devserver")]
+            crate::devserver::unregister_thread(&lwp::current());
+            core::ptr::null_mut()
+        }
+
+        let arg = Box::into_raw(Box::new(Worker {
+            source: Box::new(source),
+            shared: Arc::clone(&shared),
+        }));
+        let thread = lwp::Builder::new()
+            .arg(arg.cast())
+            .stack_size(16 * 1024)
+            .priority(priority)
+            .spawn(Some(entry));
+        match thread {
+            Ok(_) => Ok(Self {
+                shared,
+                sample_rate,
+                channels,
+            }),
+            Err(err) => {
+                // SAFETY: the thread was not created so `arg` is still owned here.
+                drop(unsafe { Box::from_raw(arg) });
+                Err(err)
+            }
+        }
+    }
+
+    /// Number of reads that found fewer samples ready than asked for.
+    pub fn underruns(&self) -> u32 {
+        self.shared.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Whether the source ended and every sample before its end was read.
+    pub fn is_finished(&self) -> bool {
+        self.shared.read.load(Ordering::Relaxed) == self.shared.end.load(Ordering::Acquire)
+    }
+}
+
+impl SampleSource for Prefetch {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        let shared = &*self.shared;
+        let read = shared.read.load(Ordering::Relaxed);
+        let end = shared.end.load(Ordering::Acquire);
+        let mut available = shared.write.load(Ordering::Acquire).wrapping_sub(read);
+        let ending = end != NOT_ENDED && end.wrapping_sub(read) <= available;
+        if ending {
+            available = end.wrapping_sub(read);
+            if available == 0 {
+                return 0;
+            }
+        }
+
+        let len = available.min(buf.len());
+        for (i, sample) in buf[..len].iter_mut().enumerate() {
+            // SAFETY: the thread does not write samples that were not read yet.
+            *sample = unsafe { *shared.samples[read.wrapping_add(i) & shared.mask()].get() };
+        }
+        shared.read.store(read.wrapping_add(len), Ordering::Release);
+        shared.wake();
+
+        if len < buf.len() && !ending {
+            shared.underruns.fetch_add(1, Ordering::Relaxed);
+            buf[len..].fill(0);
+            return buf.len();
+        }
+        len
+    }
+}
+
+impl Drop for Prefetch {
+    fn drop(&mut self) {
+        // Joining could block in an audio interrupt, the thread exits on its own.
+        self.shared.running.store(false, Ordering::Release);
+        self.shared.wake();
+    }
+}
@@ -0,0 +1,271 @@
+#![warn(missing_docs)]
+#![warn(clippy::pedantic)]
+
+use super::serial_interface::{
+    CommuicationStatus, INPUT_OUTPUT_BUFFER, InputBufferHigh, InputBufferLow, OutputBuffer,
+    PollingRegister, Status,
+};
+use crate::{
+    Result,
+    error::InputError,
+    input::{ControllerPort, PadKind, PadRumble, PadStatus},
+    time::Instant,
+};
+use core::time::Duration;
+use ogc_formats::si::{self, NEEDS_ORIGIN};
+
+pub use ogc_formats::si::Origin;
+
+/// Ask a device what it is, answered with 3 bytes.
+const COMMAND_IDENTIFY: u8 = 0x00;
+/// Read buttons, sticks and triggers, answered with 8 bytes.
+const COMMAND_POLL: u8 = 0x40;
+/// Read the neutral stick and trigger positions, answered with 10 bytes.
+const COMMAND_ORIGIN: u8 = 0x41;
+/// Take the current positions as the neutral ones, answered like [`COMMAND_ORIGIN`].
+const COMMAND_RECALIBRATE: u8 = 0x42;
+/// Analog mode of poll responses: full sticks and triggers, no analog A and B.
+const ANALOG_MODE: u32 = 3;
+
+/// Scanlines between polls and polls per frame, the rate libogc uses by default.
+const LINES_PER_POLL: u32 = 246;
+const POLLS_PER_FRAME: u32 = 2;
+/// Size of the shared buffer commands and answers go through.
+const MAX_TRANSFER: usize = 128;
+/// Longest a transfer may take, the longest possible one takes about 10 ms.
+const TRANSFER_TIMEOUT: Duration = Duration::from_millis(100);
+
+fn no_response(port: ControllerPort, status: Status) -> bool {
+    match port {
+        ControllerPort::One => status.channel_0_no_response_error(),
+        ControllerPort::Two => status.channel_1_no_response_error(),
+        ControllerPort::Three => status.channel_2_no_response_error(),
+        ControllerPort::Four => status.channel_3_no_response_error(),
+    }
+}
+
+fn transfer_error(port: ControllerPort) -> crate::OgcError {
+    if no_response(port, Status::read()) {
+        InputError::NoController.into()
+    } else {
+        InputError::Transfer.into()
+    }
+}
+
+/// Send `command` to the device on `port` and wait for its answer.
+///
+/// This goes through the shared [`INPUT_OUTPUT_BUFFER`], so polling keeps running alongside.
+///
+/// # Errors
+///
+/// [`InputError::Invalid`] when `command` or the answer is empty or longer than 128 bytes,
+/// [`InputError::NoController`] when nothing answers, [`InputError::Transfer`] on any other
+/// communication error or when the transfer does not finish.
+#[allow(clippy::cast_possible_truncation)]
+pub fn transfer<const N: usize>(port: ControllerPort, command: &[u8]) -> Result<[u8; N]> {
+    if !(1..=MAX_TRANSFER).contains(&command.len()) || !(1..=MAX_TRANSFER).contains(&N) {
+        return Err(InputError::Invalid.into());
+    }
+
+    for (index, chunk) in command.chunks(4).enumerate() {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        INPUT_OUTPUT_BUFFER
+            .index(index)
+            .write(u32::from_be_bytes(word));
+    }
+
+    // Lengths of 128 bytes are written as 0.
+    CommuicationStatus::read()
+        .with_channel(port as u32)
+        .with_output_length(command.len() as u32 & 0x7F)
+        .with_input_length(N as u32 & 0x7F)
+        .with_communication_error(true)
+        .with_transfer_complete_interrupt(true)
+        .with_transfer_start(true)
+        .write();
+    let start = Instant::now();
+    let status = loop {
+        let status = CommuicationStatus::read();
+        if !status.transfer_start() {
+            break status;
+        }
+        if start.elapsed() > TRANSFER_TIMEOUT {
+            return Err(InputError::Transfer.into());
+        }
+    };
+    if status.communication_error() {
+        return Err(transfer_error(port));
+    }
+
+    let mut response = [0; N];
+    for (index, chunk) in response.chunks_mut(4).enumerate() {
+        let word = INPUT_OUTPUT_BUFFER.index(index).read().to_be_bytes();
+        chunk.copy_from_slice(&word[..chunk.len()]);
+    }
+    Ok(response)
+}
+
+/// Ask the device on `port` what it is.
+///
+/// # Errors
+///
+/// The errors of [`transfer`].
+pub fn identify(port: ControllerPort) -> Result<PadKind> {
+    transfer(port, &[COMMAND_IDENTIFY]).map(|response| si::decode_identify(response).into())
+}
+
+/// A Gamecube controller read straight from the serial interface registers.
+///
+/// The serial interface polls the controller on its own each frame, [`Controller::poll`] reads
+/// the latest answer. This does not go through libogc, so do not mix it with
+/// [`Pad`](crate::input::Pad) on the same port.
+///
+/// ```rust
+/// let mut controller = Controller::new(ControllerPort::One)?;
+/// let status = controller.poll()?;
+/// if status.buttons.contains(PadButton::A) {
+///     controller.rumble(PadRumble::Rumble);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Controller {
+    port: ControllerPort,
+    kind: PadKind,
+    origin: Origin,
+    rumble: PadRumble,
+}
+
+impl Controller {
+    /// Identify the device on `port`, read its origin and start polling it.
+    ///
+    /// # Errors
+    ///
+    /// [`InputError::NoController`] when nothing or something other than a controller is
+    /// plugged in, and the errors of [`transfer`].
+    pub fn new(port: ControllerPort) -> Result<Self> {
+        let kind = identify(port)?;
+        if !matches!(kind, PadKind::Wired { .. } | PadKind::WaveBird) {
+            return Err(InputError::NoController.into());
+        }
+        let mut controller = Self {
+            port,
+            kind,
+            origin: Origin::default(),
+            rumble: PadRumble::Stop,
+        };
+        controller.read_origin()?;
+        controller.send_poll_command();
+
+        controller.set_polling(true);
+        Ok(controller)
+    }
+
+    /// What kind of controller this is.
+    #[must_use]
+    pub fn kind(&self) -> PadKind {
+        self.kind
+    }
+
+    /// The neutral positions sticks and triggers are reported relative to.
+    #[must_use]
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    /// Read the latest answer to the hardware poll.
+    ///
+    /// # Errors
+    ///
+    /// [`InputError::NoController`] when the controller was unplugged, [`InputError::Transfer`]
+    /// when the last poll failed.
+    pub fn poll(&mut self) -> Result<PadStatus> {
+        let (high, low) = match self.port {
+            ControllerPort::One => (InputBufferHigh::read_zero(), InputBufferLow::read_zero()),
+            ControllerPort::Two => (InputBufferHigh::read_one(), InputBufferLow::read_one()),
+            ControllerPort::Three => (InputBufferHigh::read_two(), InputBufferLow::read_two()),
+            ControllerPort::Four => (InputBufferHigh::read_three(), InputBufferLow::read_three()),
+        };
+        if high.error_status() {
+            return Err(transfer_error(self.port));
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let byte = |value: u32| value as u8;
+        let response = [
+            byte(high.input_zero()),
+            byte(high.input_one()),
+            byte(high.input_two()),
+            byte(high.input_three()),
+            byte(low.input_four()),
+            byte(low.input_five()),
+            byte(low.input_six()),
+            byte(low.input_seven()),
+        ];
+        if response[0] & NEEDS_ORIGIN != 0 {
+            self.read_origin()?;
+        }
+        Ok(si::decode_poll(response, &self.origin).into())
+    }
+
+    /// Start or stop the rumble motor, from the next poll on.
+    pub fn rumble(&mut self, rumble: PadRumble) {
+        self.rumble = rumble;
+        self.send_poll_command();
+    }
+
+    /// Take the current stick and trigger positions as the new neutral ones.
+    ///
+    /// # Errors
+    ///
+    /// The errors of [`transfer`].
+    pub fn recalibrate(&mut self) -> Result<()> {
+        self.origin = Origin::from_response(transfer(self.port, &[COMMAND_RECALIBRATE])?);
+        Ok(())
+    }
+
+    fn read_origin(&mut self) -> Result<()> {
+        self.origin = Origin::from_response(transfer(self.port, &[COMMAND_ORIGIN])?);
+        Ok(())
+    }
+
+    fn set_polling(&self, enable: bool) {
+        let mut polling = PollingRegister::read();
+        if polling.polls_per_frame() == 0 {
+            polling = polling
+                .with_lines_per_poll(LINES_PER_POLL)
+                .with_polls_per_frame(POLLS_PER_FRAME);
+        }
+        match self.port {
+            ControllerPort::One => polling.with_channel_0_enable(enable),
+            ControllerPort::Two => polling.with_channel_1_enable(enable),
+            ControllerPort::Three => polling.with_channel_2_enable(enable),
+            ControllerPort::Four => polling.with_channel_3_enable(enable),
+        }
+        .write();
+    }
+
+    /// Set the command the hardware polls with, which carries the rumble state.
+    fn send_poll_command(&self) {
+        let command = OutputBuffer::new()
+            .with_command_opcode(COMMAND_POLL.into())
+            .with_output_zero(ANALOG_MODE)
+            .with_output_one(self.rumble as u32);
+        match self.port {
+            ControllerPort::One => command.write_zero(),
+            ControllerPort::Two => command.write_one(),
+            ControllerPort::Three => command.write_two(),
+            ControllerPort::Four => command.write_three(),
+        }
+        Status::new().with_output_buffer_write(true).write();
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        if self.rumble != PadRumble::Stop {
+            self.rumble(PadRumble::Stop);
+        }
+        self.set_polling(false);
+    }
+}
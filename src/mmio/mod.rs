@@ -11,6 +11,12 @@ pub mod vi;
 /// with the serial interface command buffers.
 pub mod serial_interface;
 
+/// Gamecube Controller Driver
+///
+/// This identifies, polls and rumbles Gamecube controllers through the serial interface
+/// registers, without libogc. Responses decode into the same types as [`crate::input::Pad`].
+pub mod controller;
+
 /// Command Processor Inteface Helper Types and MMIO
 ///
 /// This is used to interact with the Graphics Fifo. This is needed to properly intitalize the GX
@@ -1,10 +1,45 @@
-use alloc::sync::Arc;
-use libc::c_void;
+//! MP3 playback through `libmad` and `ASND`.
+//!
+//! [`MP3Player::play`] streams from any [`Read`] source, so files on SD or NAND never have to be
+//! loaded whole. It decodes on its own thread and plays the PCM on the first unused `ASND`
+//! voice, or the one set with [`PlaybackOptions::voice`].
 
-use crate::{asnd::Asnd, ffi};
+use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
+use core::{
+    cell::UnsafeCell,
+    ffi::c_void,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    OgcError, Result,
+    asnd::{Asnd, VoiceFormat},
+    error::{AudioError, FormatError},
+    ffi,
+    io::{Cursor, Read},
+    lwp::{self, Queue, Thread},
+    utils::Buf32,
+};
+
+/// Units of `mad_timer_t::fraction` in a second.
+const TIMER_RESOLUTION: u64 = 352_800_000;
+/// Most samples per channel in a frame.
+const MAX_FRAME_SAMPLES: usize = 1152;
+/// Buffers of decoded frames, two of them are handed to `ASND` at any time.
+const BUFFERS: usize = 4;
+/// Bytes read from the source at once.
+const INPUT_BYTES: usize = 8 * 1024;
+/// Silence played while the decoder is behind, about 5ms.
+const SILENCE_BYTES: usize = 1024;
+/// Priority of the decoding thread, the same as the `libogc` player.
+const DECODER_PRIORITY: u8 = 80;
 
 pub struct MP3Player {
     asnd: Arc<Asnd>,
+    volume: u8,
 }
 
 impl MP3Player {
@@ -14,10 +49,18 @@ impl MP3Player {
         }
         Self {
             asnd: Arc::new(asnd),
+            volume: 255,
         }
     }
 
-    pub fn play_buffer(&mut self, buffer: &[u8]) {
+    /// Play an MP3 held in memory with the `libogc` player.
+    ///
+    /// # Safety
+    ///
+    /// The player keeps reading from `buffer` after this returns, it must stay alive until
+    /// playback ended or [`MP3Player::stop`] was called.
+    #[deprecated(note = "use `MP3Player::play_owned`, which owns the buffer")]
+    pub unsafe fn play_buffer(&mut self, buffer: &[u8]) {
         unsafe {
             ffi::MP3Player_PlayBuffer(
                 buffer.as_ptr().cast::<c_void>(),
@@ -27,11 +70,118 @@ impl MP3Player {
         }
     }
 
+    /// Play an MP3 held in memory, the returned [`Playback`] owns `buffer`.
+    pub fn play_owned<B>(&mut self, buffer: B) -> Result<Playback<'_>>
+    where
+        B: AsRef<[u8]> + Send + 'static,
+    {
+        self.play(Cursor::new(buffer))
+    }
+
+    /// Stream an MP3 from `source`, stopping whatever is playing.
+    pub fn play<R>(&mut self, source: R) -> Result<Playback<'_>>
+    where
+        R: Read + Send + 'static,
+    {
+        self.play_with(source, PlaybackOptions::new())
+    }
+
+    /// Stream an MP3 from `source` with a filter or end callback.
+    ///
+    /// `source` is read from the decoding thread and owned by the returned [`Playback`],
+    /// dropping it stops playback. Reading stops at the first error, see [`Playback::error`].
+    /// Only one stream can play at a time, starting a second one fails with
+    /// [`AudioError::Invalid`], as does a voice that is not below `16`. Without
+    /// [`PlaybackOptions::voice`] it fails with [`AudioError::NoFreeVoice`] if every voice is in
+    /// use.
+    pub fn play_with<R>(&mut self, source: R, options: PlaybackOptions) -> Result<Playback<'_>>
+    where
+        R: Read + Send + 'static,
+    {
+        unsafe { ffi::MP3Player_Stop() };
+        let voice = match options.voice {
+            Some(voice) if voice < 16 => voice,
+            Some(_) => return Err(OgcError::Audio(AudioError::Invalid)),
+            None => Asnd::get_first_unused_voice()?,
+        };
+
+        let shared = Arc::new(Shared {
+            slots: core::array::from_fn(|_| Slot {
+                data: UnsafeCell::new(Buf32::new(MAX_FRAME_SAMPLES * 4)),
+                len: AtomicUsize::new(0),
+                rate: AtomicU32::new(0),
+            }),
+            silence: Buf32::new(SILENCE_BYTES),
+            written: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            rate: AtomicU32::new(0),
+            voice,
+            volume: self.volume,
+            running: AtomicBool::new(true),
+            ended: AtomicBool::new(false),
+            error: UnsafeCell::new(None),
+            elapsed_ms: AtomicU32::new(0),
+            queue: Queue::new()?,
+        });
+        if CURRENT
+            .compare_exchange(
+                ptr::null_mut(),
+                Arc::as_ptr(&shared).cast_mut(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        unsafe extern "C" fn entry(arg: *mut c_void) -> *mut c_void {
+            // SAFETY: `arg` was created from a `Box<Decoder>` in `MP3Player::play_with`.
+            let decoder = unsafe { Box::from_raw(arg.cast::<Decoder>()) };
+            decoder.run();
+            ptr::null_mut()
+        }
+
+        let arg = Box::into_raw(Box::new(Decoder {
+            source: Box::new(source),
+            filter: options.filter,
+            on_end: options.on_end,
+            shared: Arc::clone(&shared),
+            mad: Mad::new(),
+            input: vec![0; INPUT_BYTES + ffi::MAD_BUFFER_GUARD as usize].into(),
+            draining: false,
+            pcm: vec![0; MAX_FRAME_SAMPLES * 2],
+            seconds: 0,
+            fraction: 0,
+        }));
+        let thread = lwp::Builder::new()
+            .arg(arg.cast())
+            .stack_size(32 * 1024)
+            .priority(DECODER_PRIORITY)
+            .spawn(Some(entry));
+        match thread {
+            Ok(thread) => Ok(Playback {
+                shared,
+                thread,
+                paused: false,
+                _player: PhantomData,
+            }),
+            Err(err) => {
+                CURRENT.store(ptr::null_mut(), Ordering::Release);
+                // SAFETY: the thread was not created so `arg` is still owned here.
+                drop(unsafe { Box::from_raw(arg) });
+                Err(err)
+            }
+        }
+    }
+
     pub fn is_playing(&self) -> bool {
         unsafe { ffi::MP3Player_IsPlaying() }
     }
 
+    /// Set the volume from `0` to `255` of the `libogc` player and of streams started after this.
     pub fn volume(&mut self, volume: u32) {
+        self.volume = volume.min(255) as u8;
         unsafe { ffi::MP3Player_Volume(volume) }
     }
 
@@ -39,3 +189,522 @@ impl MP3Player {
         unsafe { ffi::MP3Player_Stop() }
     }
 }
+
+type FilterFn = dyn FnMut(&mut Frame<'_>) + Send;
+type EndFn = dyn FnOnce() + Send;
+
+/// Options for [`MP3Player::play_with`].
+#[derive(Default)]
+pub struct PlaybackOptions {
+    voice: Option<u32>,
+    filter: Option<Box<FilterFn>>,
+    on_end: Option<Box<EndFn>>,
+}
+
+impl PlaybackOptions {
+    /// The first unused voice, no filter and no end callback.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Play on `ASND` voice `voice` rather than the first unused one.
+    #[must_use]
+    pub fn voice(mut self, voice: u32) -> Self {
+        self.voice = Some(voice);
+        self
+    }
+
+    /// Run `filter` on the PCM of every decoded frame before it is played.
+    ///
+    /// The filter runs on the decoding thread, a slow filter makes the output fall behind.
+    #[must_use]
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: FnMut(&mut Frame<'_>) + Send + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Call `on_end` from the decoding thread once the source runs out or fails.
+    ///
+    /// The frames that were already decoded are still playing at that point.
+    #[must_use]
+    pub fn on_end<F>(mut self, on_end: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.on_end = Some(Box::new(on_end));
+        self
+    }
+}
+
+/// A decoded MP3 frame handed to [`PlaybackOptions::filter`].
+pub struct Frame<'a> {
+    sample_rate: u32,
+    channels: usize,
+    samples: &'a mut [i16],
+}
+
+impl Frame<'_> {
+    /// Sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of channels, `1` or `2`.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Number of samples in each channel.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// The PCM samples, interleaved if the frame is stereo.
+    pub fn samples_mut(&mut self) -> &mut [i16] {
+        self.samples
+    }
+
+    /// Multiply every sample of `channel` by `gain`.
+    ///
+    /// # Panics
+    ///
+    /// If `channel` is not below [`Frame::channels`].
+    pub fn scale(&mut self, channel: usize, gain: f32) {
+        assert!(channel < self.channels);
+        // 16.16 fixed point keeps the multiply in 64 bits.
+        let gain = (gain * 65536.0) as i64;
+        for sample in self.samples.iter_mut().skip(channel).step_by(self.channels) {
+            *sample = ((i64::from(*sample) * gain) >> 16)
+                .clamp(i64::from(i16::MIN), i64::from(i16::MAX)) as i16;
+        }
+    }
+}
+
+/// A buffer of decoded stereo samples for `ASND`.
+struct Slot {
+    data: UnsafeCell<Buf32>,
+    len: AtomicUsize,
+    rate: AtomicU32,
+}
+
+/// State the decoding thread shares with `ASND` and the main thread.
+struct Shared {
+    slots: [Slot; BUFFERS],
+    silence: Buf32,
+    /// Slots filled by the decoder so far, wrapping
+    written: AtomicUsize,
+    /// Slots handed to `ASND` so far, wrapping
+    queued: AtomicUsize,
+    /// Pitch the voice plays at
+    rate: AtomicU32,
+    voice: u32,
+    volume: u8,
+    running: AtomicBool,
+    ended: AtomicBool,
+    /// Written once by the decoding thread before `ended` is set
+    error: UnsafeCell<Option<OgcError>>,
+    elapsed_ms: AtomicU32,
+    /// Where the decoding thread waits for a free slot
+    queue: Queue,
+}
+
+// SAFETY: the decoder only writes slots `ASND` is done with, `written` and `queued` hand them
+// over with release and acquire ordering. `error` is only read after `ended` is set, it is never
+// written again then.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn wake(&self) {
+        self.queue.signal();
+    }
+
+    /// Whether the decoder may fill the next slot.
+    ///
+    /// The last two slots handed to `ASND` may still be playing.
+    fn has_free_slot(&self) -> bool {
+        let used = self
+            .written
+            .load(Ordering::Relaxed)
+            .wrapping_sub(self.queued.load(Ordering::Acquire));
+        used < BUFFERS - 2
+    }
+
+    /// Sleep until a slot is free or playback is stopped.
+    fn wait(&self) {
+        // SAFETY: interrupts are disabled while checking so a wake up from the voice callback
+        // cannot come between the check and going to sleep.
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            while self.running.load(Ordering::Acquire) && !self.has_free_slot() {
+                ffi::LWP_ThreadSleep(self.queue.handle());
+            }
+            ffi::IRQ_Restore(level);
+        }
+    }
+
+    /// Start the voice with the first slot.
+    fn start(&self) -> Result<()> {
+        let slot = &self.slots[0];
+        let rate = slot.rate.load(Ordering::Relaxed);
+        self.rate.store(rate, Ordering::Relaxed);
+        self.queued.store(1, Ordering::Release);
+        let ret = unsafe {
+            ffi::ASND_SetVoice(
+                self.voice as i32,
+                VoiceFormat::Stereo16Bit.as_i32(),
+                rate as i32,
+                0,
+                (*slot.data.get()).as_mut_ptr().cast(),
+                slot.len.load(Ordering::Relaxed) as i32,
+                self.volume.into(),
+                self.volume.into(),
+                Some(voice_callback),
+            )
+        };
+        if ret == ffi::SND_OK as _ {
+            Ok(())
+        } else {
+            Err(OgcError::Audio(AudioError::from_asnd(ret)))
+        }
+    }
+
+    /// Hand the next decoded slot to `ASND`, or silence if the decoder fell behind.
+    fn queue_next(&self, voice: i32) {
+        let queued = self.queued.load(Ordering::Relaxed);
+        if queued == self.written.load(Ordering::Acquire) {
+            if !self.ended.load(Ordering::Acquire) {
+                unsafe {
+                    ffi::ASND_AddVoice(
+                        voice,
+                        self.silence.as_ptr().cast_mut().cast(),
+                        SILENCE_BYTES as i32,
+                    )
+                };
+            }
+            return;
+        }
+
+        let slot = &self.slots[queued % BUFFERS];
+        let rate = slot.rate.load(Ordering::Relaxed);
+        if self.rate.swap(rate, Ordering::Relaxed) != rate {
+            unsafe { ffi::ASND_ChangePitchVoice(voice, rate as i32) };
+        }
+        unsafe {
+            ffi::ASND_AddVoice(
+                voice,
+                (*slot.data.get()).as_mut_ptr().cast(),
+                slot.len.load(Ordering::Relaxed) as i32,
+            )
+        };
+        self.queued.store(queued.wrapping_add(1), Ordering::Release);
+        self.wake();
+    }
+}
+
+/// The playing stream, `ASND` voice callbacks do not get a user pointer.
+static CURRENT: AtomicPtr<Shared> = AtomicPtr::new(ptr::null_mut());
+
+unsafe extern "C" fn voice_callback(voice: i32) {
+    let shared = CURRENT.load(Ordering::Acquire);
+    if shared.is_null() || unsafe { ffi::ASND_TestVoiceBufferReady(voice) } == 0 {
+        return;
+    }
+    // SAFETY: `Playback` clears `CURRENT` with interrupts disabled before releasing the state.
+    unsafe { &*shared }.queue_next(voice);
+}
+
+/// The `libmad` decoder state.
+struct Mad {
+    stream: ffi::mad_stream,
+    frame: ffi::mad_frame,
+    synth: ffi::mad_synth,
+}
+
+impl Mad {
+    fn new() -> Box<Self> {
+        let mut mad = Box::<Self>::new_uninit();
+        let mad_ptr = mad.as_mut_ptr();
+        // SAFETY: the init functions set every field.
+        unsafe {
+            ffi::mad_stream_init(&raw mut (*mad_ptr).stream);
+            ffi::mad_frame_init(&raw mut (*mad_ptr).frame);
+            ffi::mad_synth_init(&raw mut (*mad_ptr).synth);
+            mad.assume_init()
+        }
+    }
+}
+
+impl Drop for Mad {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::mad_frame_finish(&mut self.frame);
+            ffi::mad_stream_finish(&mut self.stream);
+        }
+    }
+}
+
+/// Convert a `libmad` sample with 28 fractional bits to 16 bits.
+fn to_i16(sample: ffi::mad_fixed_t) -> i16 {
+    ((i64::from(sample) + (1 << 12)) >> 13).clamp(i64::from(i16::MIN), i64::from(i16::MAX)) as i16
+}
+
+/// State only the decoding thread touches.
+struct Decoder {
+    source: Box<dyn Read + Send>,
+    filter: Option<Box<FilterFn>>,
+    on_end: Option<Box<EndFn>>,
+    shared: Arc<Shared>,
+    mad: Box<Mad>,
+    /// Data `libmad` decodes from, it must not move while the stream points into it
+    input: Box<[u8]>,
+    /// Whether the source ended and the guard bytes after its last frame were added
+    draining: bool,
+    /// The current frame as 16-bit samples
+    pcm: Vec<i16>,
+    seconds: u32,
+    fraction: u64,
+}
+
+impl Decoder {
+    fn run(mut self) {
+        let result = self.play();
+        // SAFETY: nobody reads `error` before `ended` is set.
+        unsafe { *self.shared.error.get() = result.err() };
+        self.shared.ended.store(true, Ordering::Release);
+        if let Some(on_end) = self.on_end.take() {
+            on_end();
+        }
+    }
+
+    fn play(&mut self) -> Result<()> {
+        let shared = Arc::clone(&self.shared);
+        let mut started = false;
+        let result = loop {
+            shared.wait();
+            if !shared.running.load(Ordering::Acquire) {
+                break Ok(());
+            }
+            match self.decode() {
+                Ok(true) => {}
+                Ok(false) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+            self.write_frame();
+            // Two frames are ready before starting, so the first callback has one to queue.
+            if !started && shared.written.load(Ordering::Relaxed) == 2 {
+                shared.start()?;
+                started = true;
+            }
+        };
+        if !started && shared.written.load(Ordering::Relaxed) > 0 {
+            shared.start()?;
+        }
+        result
+    }
+
+    /// Move the undecoded rest of the input to the front and read more after it.
+    ///
+    /// Returns `false` once the source ended and everything in it was decoded.
+    fn refill(&mut self) -> Result<bool> {
+        if self.draining {
+            return Ok(false);
+        }
+        let stream = &mut self.mad.stream;
+        let rest = if stream.next_frame.is_null() {
+            0
+        } else {
+            // SAFETY: `next_frame` points into `input` up to `bufend`.
+            let offset = unsafe { stream.next_frame.offset_from(self.input.as_ptr()) } as usize;
+            let rest = unsafe { stream.bufend.offset_from(stream.next_frame) } as usize;
+            self.input.copy_within(offset..offset + rest, 0);
+            rest
+        };
+
+        let read = self.source.read(&mut self.input[rest..INPUT_BYTES])?;
+        let len = if read == 0 {
+            // `libmad` needs zeroes after the last frame to decode it.
+            let end = rest + ffi::MAD_BUFFER_GUARD as usize;
+            self.input[rest..end].fill(0);
+            self.draining = true;
+            end
+        } else {
+            rest + read
+        };
+        unsafe { ffi::mad_stream_buffer(stream, self.input.as_ptr(), len as _) };
+        stream.error = ffi::MAD_ERROR_NONE;
+        Ok(true)
+    }
+
+    /// Decode the next frame into `mad.synth`, returning `false` at the end of the source.
+    fn decode(&mut self) -> Result<bool> {
+        loop {
+            let stream = &self.mad.stream;
+            if (stream.buffer.is_null() || stream.error == ffi::MAD_ERROR_BUFLEN)
+                && !self.refill()?
+            {
+                return Ok(false);
+            }
+
+            let mad = &mut *self.mad;
+            if unsafe { ffi::mad_frame_decode(&mut mad.frame, &mut mad.stream) } == 0 {
+                unsafe { ffi::mad_synth_frame(&mut mad.synth, &mad.frame) };
+                return Ok(true);
+            }
+            // Recoverable errors skip a broken frame.
+            let error = mad.stream.error;
+            if error != ffi::MAD_ERROR_BUFLEN && error & 0xff00 == 0 {
+                return Err(OgcError::Audio(AudioError::Format(FormatError::Invalid)));
+            }
+        }
+    }
+
+    /// Filter the synthesized frame and write it to the next free slot.
+    fn write_frame(&mut self) {
+        let duration = self.mad.frame.header.duration;
+        self.seconds = self.seconds.wrapping_add(duration.seconds as u32);
+        self.fraction += u64::from(duration.fraction);
+        self.seconds = self
+            .seconds
+            .wrapping_add((self.fraction / TIMER_RESOLUTION) as u32);
+        self.fraction %= TIMER_RESOLUTION;
+        let ms = self
+            .seconds
+            .wrapping_mul(1000)
+            .wrapping_add((self.fraction * 1000 / TIMER_RESOLUTION) as u32);
+        self.shared.elapsed_ms.store(ms, Ordering::Relaxed);
+
+        let pcm = &self.mad.synth.pcm;
+        let channels = usize::from(pcm.channels).clamp(1, 2);
+        let frames = usize::from(pcm.length).min(MAX_FRAME_SAMPLES);
+        let samples = &mut self.pcm[..frames * channels];
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = to_i16(pcm.samples[i % channels][i / channels]);
+        }
+        if let Some(filter) = self.filter.as_mut() {
+            filter(&mut Frame {
+                sample_rate: pcm.samplerate,
+                channels,
+                samples,
+            });
+        }
+
+        let shared = &*self.shared;
+        let written = shared.written.load(Ordering::Relaxed);
+        let slot = &shared.slots[written % BUFFERS];
+        // SAFETY: `ASND` is done with the slot, see `Shared::has_free_slot`.
+        let data = unsafe { &mut *slot.data.get() };
+        let len = (frames * 4).next_multiple_of(32);
+        for (bytes, frame) in data[..len]
+            .chunks_exact_mut(4)
+            .zip(samples.chunks(channels))
+        {
+            bytes[..2].copy_from_slice(&frame[0].to_be_bytes());
+            bytes[2..].copy_from_slice(&frame[channels - 1].to_be_bytes());
+        }
+        data[frames * 4..len].fill(0);
+        unsafe { ffi::DCFlushRange(data.as_mut_ptr().cast(), len as u32) };
+        slot.len.store(len, Ordering::Relaxed);
+        slot.rate.store(pcm.samplerate, Ordering::Relaxed);
+        shared
+            .written
+            .store(written.wrapping_add(1), Ordering::Release);
+    }
+}
+
+/// An MP3 streaming from a [`Read`] source, see [`MP3Player::play`].
+///
+/// Dropping it stops playback and drops the source.
+///
+/// # Examples
+///
+/// ```rust
+/// let file = File::open("sd:/music/title.mp3")?;
+/// let playback = player.play_with(file, PlaybackOptions::new().on_end(|| println!("done")))?;
+/// while playback.is_playing() {
+///     println!("{:?}", playback.elapsed());
+///     Video::wait_vsync();
+/// }
+/// ```
+pub struct Playback<'a> {
+    shared: Arc<Shared>,
+    thread: Thread,
+    paused: bool,
+    _player: PhantomData<&'a mut MP3Player>,
+}
+
+impl Playback<'_> {
+    /// The `ASND` voice the stream plays on.
+    pub fn voice(&self) -> u32 {
+        self.shared.voice
+    }
+
+    /// Pause the output, decoding stops once the buffers are full.
+    pub fn pause(&mut self) -> Result<()> {
+        Asnd::pause_voice(self.shared.voice, true)?;
+        self.paused = true;
+        Ok(())
+    }
+
+    /// Continue after [`Playback::pause`].
+    pub fn resume(&mut self) -> Result<()> {
+        Asnd::pause_voice(self.shared.voice, false)?;
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Whether playback is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether the stream is still playing, this stays `true` while paused.
+    pub fn is_playing(&self) -> bool {
+        !self.is_finished() || Asnd::status_voice(self.shared.voice).is_ok()
+    }
+
+    /// Whether the source has run out or failed.
+    ///
+    /// The frames that were already decoded may still be playing, see [`Playback::is_playing`].
+    pub fn is_finished(&self) -> bool {
+        self.shared.ended.load(Ordering::Acquire)
+    }
+
+    /// The error that stopped reading the source, if any.
+    pub fn error(&self) -> Option<OgcError> {
+        if self.is_finished() {
+            // SAFETY: `error` is not written again once `ended` is set.
+            unsafe { *self.shared.error.get() }
+        } else {
+            None
+        }
+    }
+
+    /// Length of the audio decoded so far.
+    ///
+    /// This runs ahead of what is heard by the few frames waiting in the output buffers.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.shared.elapsed_ms.load(Ordering::Relaxed).into())
+    }
+
+    /// Stop playback.
+    pub fn stop(self) {}
+}
+
+impl Drop for Playback<'_> {
+    fn drop(&mut self) {
+        // The thread may still start the voice, so it has to finish first.
+        self.shared.running.store(false, Ordering::Release);
+        self.shared.wake();
+        let _ = self.thread.join();
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            ffi::ASND_StopVoice(self.shared.voice as i32);
+            CURRENT.store(ptr::null_mut(), Ordering::Release);
+            ffi::IRQ_Restore(level);
+        }
+    }
+}
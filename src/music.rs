@@ -0,0 +1,528 @@
+//! The ``music`` module of ``ogc-rs``.
+//!
+//! Sequenced music: ProTracker MOD and FastTracker II XM modules, and standard MIDI files played
+//! with a [`SampleBank`].
+//!
+//! A [`Song`] is played by a [`Sequencer`], which works out every tick what each voice should be
+//! playing. The sequencer is plain Rust: [`MusicPlayer`] plays it on `ASND` voices, and
+//! [`Renderer`] mixes it to PCM in software, through a [`Mixer`](crate::mixer::Mixer) or on the
+//! host.
+//!
+//! ```rust
+//! let mut sequencer = Sequencer::new(Song::from_xm(include_bytes!("title.xm"))?);
+//! sequencer.set_looping(true);
+//! sequencer.set_tick_callback(|position| {
+//!     if let Position::Tracker { row: 0, .. } = position {
+//!         BEAT.store(true, Ordering::Relaxed);
+//!     }
+//! });
+//!
+//! let player = MusicPlayer::new(sequencer, 0)?;
+//! player.lock(|sequencer| sequencer.set_muted(3, true));
+//! ```
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::{ops::RangeInclusive, time::Duration};
+
+use num_traits::Float;
+
+use crate::error::FormatError;
+
+mod midi;
+mod player;
+mod protracker;
+mod render;
+mod tracker;
+mod xm;
+
+pub use midi::SampleBank;
+pub use player::MusicPlayer;
+pub use render::Renderer;
+
+/// Sample rate that plays a sample at its own pitch on C-4, the Amiga rate of the same note.
+const BASE_RATE: f32 = 8363.0;
+/// MIDI note number of C-4, the note samples are tuned to.
+const MIDDLE_C: u8 = 60;
+/// Full volume of an instrument fading out after its key was released.
+const FADE_FULL: i32 = 32768;
+
+/// A sample played by an [`Instrument`].
+///
+/// Samples loaded from modules keep their tuning and loop points, samples for a
+/// [`SampleBank`] are built with [`Sample::new`].
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub(crate) data: Vec<i16>,
+    /// Start and end of the loop, ping-pong loops are unrolled into forward ones
+    pub(crate) loop_range: Option<(u32, u32)>,
+    /// Default volume, `0..=64`
+    pub(crate) volume: u8,
+    /// Default panning, `0..=255`, `None` keeps the panning of the channel
+    pub(crate) panning: Option<u8>,
+    /// Tuning in semitones, relative to [`BASE_RATE`] on C-4
+    pub(crate) pitch: f32,
+}
+
+impl Sample {
+    /// A mono sample that plays at `sample_rate` Hz on middle C.
+    pub fn new(data: Vec<i16>, sample_rate: u32) -> Self {
+        Self {
+            data,
+            loop_range: None,
+            volume: 64,
+            panning: None,
+            pitch: 12.0 * (sample_rate as f32 / BASE_RATE).log2(),
+        }
+    }
+
+    /// Loop the samples in `start..end` until the note is released.
+    #[must_use]
+    pub fn looping(mut self, start: u32, end: u32) -> Self {
+        let end = end.min(self.data.len() as u32);
+        self.loop_range = (start < end).then_some((start, end));
+        self
+    }
+
+    /// Play at the sample rate on MIDI note `note` instead of middle C.
+    #[must_use]
+    pub fn root_note(mut self, note: u8) -> Self {
+        self.pitch += f32::from(MIDDLE_C) - f32::from(note);
+        self
+    }
+
+    /// Volume from `0.0` to `1.0`.
+    #[must_use]
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = (volume.clamp(0.0, 1.0) * 64.0) as u8;
+        self
+    }
+
+    /// Number of samples.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Turn a ping-pong loop over `start..end` into a forward one.
+    pub(crate) fn unroll_ping_pong(&mut self, start: u32, end: u32) {
+        let (start, end) = (start as usize, end as usize);
+        self.data.truncate(end);
+        let back: Vec<i16> = self.data[start + 1..end - 1]
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+        self.data.extend_from_slice(&back);
+        self.loop_range = Some((start as u32, self.data.len() as u32));
+    }
+}
+
+/// A volume or panning envelope, as in XM instruments.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Envelope {
+    /// Ticks and values, `0..=64`, with increasing ticks
+    pub(crate) points: Vec<(u16, u8)>,
+    /// Tick the envelope holds at while the key is down
+    pub(crate) sustain: Option<u16>,
+    /// Ticks the envelope loops between
+    pub(crate) loop_range: Option<(u16, u16)>,
+}
+
+impl Envelope {
+    /// Value at `tick`, from `0.0` to `1.0`.
+    pub(crate) fn value(&self, tick: u16) -> f32 {
+        let Some(&(_, first)) = self.points.first() else {
+            return 1.0;
+        };
+        let mut value = f32::from(first);
+        for pair in self.points.windows(2) {
+            let [(x0, y0), (x1, y1)] = [pair[0], pair[1]];
+            if tick >= x1 {
+                value = f32::from(y1);
+            } else if tick >= x0 && x1 > x0 {
+                let t = f32::from(tick - x0) / f32::from(x1 - x0);
+                value = f32::from(y0) + (f32::from(y1) - f32::from(y0)) * t;
+                break;
+            }
+        }
+        value / 64.0
+    }
+
+    /// The tick after `tick`.
+    pub(crate) fn advance(&self, tick: u16, key_on: bool) -> u16 {
+        if key_on && self.sustain == Some(tick) {
+            return tick;
+        }
+        if let Some((start, end)) = self.loop_range
+            && tick >= end
+        {
+            return start;
+        }
+        tick.saturating_add(1).min(self.end())
+    }
+
+    /// Tick of the last point.
+    pub(crate) fn end(&self) -> u16 {
+        self.points.last().map_or(0, |&(tick, _)| tick)
+    }
+}
+
+/// Samples and envelopes played for the notes of one instrument.
+#[derive(Clone, Debug)]
+pub struct Instrument {
+    pub(crate) samples: Vec<Sample>,
+    /// Index into `samples` for each MIDI note number
+    pub(crate) note_map: [u8; 128],
+    pub(crate) volume_envelope: Option<Envelope>,
+    pub(crate) panning_envelope: Option<Envelope>,
+    /// Subtracted from [`FADE_FULL`] every tick once the key was released
+    pub(crate) fadeout: u16,
+}
+
+impl Instrument {
+    /// An instrument playing `sample` for every note.
+    pub fn new(sample: Sample) -> Self {
+        Self {
+            samples: vec![sample],
+            note_map: [0; 128],
+            volume_envelope: None,
+            panning_envelope: None,
+            fadeout: 0,
+        }
+    }
+
+    /// Play `sample` for the MIDI notes in `keys` instead.
+    #[must_use]
+    pub fn key_range(mut self, keys: RangeInclusive<u8>, sample: Sample) -> Self {
+        let index = self.samples.len().min(u8::MAX.into()) as u8;
+        self.samples.push(sample);
+        for key in keys.filter(|&key| key < 128) {
+            self.note_map[usize::from(key)] = index;
+        }
+        self
+    }
+
+    /// Fade out over `release` after a note is released instead of stopping looped samples.
+    #[must_use]
+    pub fn release(mut self, release: Duration) -> Self {
+        let ticks = (release.as_nanos() / u128::from(midi::ENVELOPE_TICK_NS)).max(1);
+        self.fadeout = (FADE_FULL as u128).div_ceil(ticks).min(u16::MAX.into()) as u16;
+        self
+    }
+
+    pub(crate) fn sample_for(&self, note: u8) -> Option<(usize, &Sample)> {
+        let index = usize::from(*self.note_map.get(usize::from(note))?);
+        self.samples.get(index).map(|sample| (index, sample))
+    }
+}
+
+pub(crate) enum Kind {
+    Tracker(tracker::Module),
+    Midi(Box<midi::Sequence>),
+}
+
+/// A piece of music that a [`Sequencer`] can play.
+pub struct Song {
+    name: String,
+    pub(crate) instruments: Vec<Instrument>,
+    pub(crate) kind: Kind,
+}
+
+impl Song {
+    /// Load a ProTracker compatible MOD file with 4 to 32 channels.
+    ///
+    /// Old 15 sample Soundtracker modules are not supported.
+    pub fn from_mod(file: &[u8]) -> Result<Self, FormatError> {
+        protracker::parse(file)
+    }
+
+    /// Load a FastTracker II XM file.
+    pub fn from_xm(file: &[u8]) -> Result<Self, FormatError> {
+        xm::parse(file)
+    }
+
+    /// Load a standard MIDI file of format 0 or 1, played with the instruments of `bank`.
+    pub fn from_midi(file: &[u8], bank: SampleBank) -> Result<Self, FormatError> {
+        midi::parse(file, bank)
+    }
+
+    /// Title stored in the file, empty if there is none.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of channels: those of a module or the 16 MIDI channels.
+    pub fn channels(&self) -> usize {
+        match &self.kind {
+            Kind::Tracker(module) => module.channels,
+            Kind::Midi(_) => midi::CHANNELS,
+        }
+    }
+
+    /// Number of voices needed to play the song: one per channel of a module or the polyphony
+    /// of a MIDI song.
+    pub fn voices(&self) -> usize {
+        match &self.kind {
+            Kind::Tracker(module) => module.channels,
+            Kind::Midi(sequence) => sequence.voices,
+        }
+    }
+
+    pub(crate) fn new(name: &[u8], instruments: Vec<Instrument>, kind: Kind) -> Self {
+        let name = String::from_utf8_lossy(name);
+        Self {
+            name: name.trim_end_matches(['\0', ' ']).into(),
+            instruments,
+            kind,
+        }
+    }
+
+    pub(crate) fn sample(&self, (instrument, sample): (u16, u16)) -> Option<&Sample> {
+        self.instruments
+            .get(usize::from(instrument))?
+            .samples
+            .get(usize::from(sample))
+    }
+}
+
+/// Where a [`Sequencer`] is in its song.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Position {
+    /// A position in a MOD or XM module.
+    Tracker {
+        /// Index into the order list
+        order: usize,
+        /// Pattern played at `order`
+        pattern: usize,
+        row: usize,
+        /// Tick within the row
+        tick: u32,
+    },
+    /// A position in a MIDI song.
+    Midi {
+        /// MIDI ticks since the start
+        tick: u64,
+    },
+}
+
+/// What a voice plays after a tick of the [`Sequencer`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Voice {
+    /// Instrument and sample, `None` when the voice is silent
+    pub(crate) sample: Option<(u16, u16)>,
+    /// Sample offset to start from if the sample was started on this tick
+    pub(crate) start: Option<u32>,
+    /// Playback rate in Hz
+    pub(crate) frequency: f32,
+    /// Volume from `0.0` to `1.0`
+    pub(crate) volume: f32,
+    /// Panning from `-1.0` (left) to `1.0` (right)
+    pub(crate) pan: f32,
+    /// Channel of the song playing on the voice
+    pub(crate) channel: u8,
+}
+
+enum Engine {
+    Tracker(tracker::Tracker),
+    Midi(midi::Synth),
+}
+
+/// Plays a [`Song`], tick by tick.
+///
+/// The sequencer does not make any sound itself, it is driven by a [`MusicPlayer`] or a
+/// [`Renderer`].
+pub struct Sequencer {
+    song: Song,
+    engine: Engine,
+    voices: Vec<Voice>,
+    muted: Vec<bool>,
+    looping: bool,
+    tempo: f32,
+    volume: f32,
+    /// Volume of each voice so that a few of them at full volume do not clip
+    mix_gain: f32,
+    finished: bool,
+    tick_callback: Option<Box<dyn FnMut(Position) + Send>>,
+}
+
+impl Sequencer {
+    /// Start at the beginning of `song`.
+    pub fn new(song: Song) -> Self {
+        let engine = match &song.kind {
+            Kind::Tracker(module) => Engine::Tracker(tracker::Tracker::new(module)),
+            Kind::Midi(sequence) => Engine::Midi(midi::Synth::new(sequence)),
+        };
+        let voices = song.voices();
+        Self {
+            voices: vec![Voice::default(); voices],
+            muted: vec![false; song.channels()],
+            mix_gain: 1.0 / (voices.max(1) as f32).sqrt(),
+            song,
+            engine,
+            looping: false,
+            tempo: 1.0,
+            volume: 1.0,
+            finished: false,
+            tick_callback: None,
+        }
+    }
+
+    /// The song being played.
+    pub fn song(&self) -> &Song {
+        &self.song
+    }
+
+    /// Number of voices needed to play the song, see [`Song::voices`].
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Whether the song starts over once it ends.
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Start the song over once it ends instead of stopping.
+    ///
+    /// Modules loop when they run past their last order or jump back to an order they already
+    /// played.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Speed the song is played at, relative to its own tempo.
+    pub fn tempo(&self) -> f32 {
+        self.tempo
+    }
+
+    /// Play at `tempo` times the song's own tempo, changing it does not change the pitch.
+    pub fn set_tempo(&mut self, tempo: f32) {
+        self.tempo = tempo.max(0.01);
+    }
+
+    /// Volume applied to every voice.
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    /// Set the volume applied to every voice.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.max(0.0);
+    }
+
+    /// Whether `channel` is muted.
+    pub fn is_muted(&self, channel: usize) -> bool {
+        self.muted.get(channel).copied().unwrap_or(false)
+    }
+
+    /// Silence `channel` of the song, see [`Song::channels`].
+    ///
+    /// A muted channel keeps playing silently, so unmuting it picks up where it would be.
+    pub fn set_muted(&mut self, channel: usize, muted: bool) {
+        if let Some(slot) = self.muted.get_mut(channel) {
+            *slot = muted;
+        }
+    }
+
+    /// Call `callback` after every tick with the position that was just played.
+    ///
+    /// A [`MusicPlayer`] calls it from an interrupt handler, keep it short.
+    pub fn set_tick_callback(&mut self, callback: impl FnMut(Position) + Send + 'static) {
+        self.tick_callback = Some(Box::new(callback));
+    }
+
+    /// Stop calling the tick callback.
+    pub fn clear_tick_callback(&mut self) {
+        self.tick_callback = None;
+    }
+
+    /// Position of the last tick played.
+    pub fn position(&self) -> Position {
+        match &self.engine {
+            Engine::Tracker(tracker) => tracker.position(),
+            Engine::Midi(synth) => synth.position(),
+        }
+    }
+
+    /// Whether the song ended, which never happens while looping.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Go back to the start of the song.
+    pub fn restart(&mut self) {
+        match (&mut self.engine, &self.song.kind) {
+            (Engine::Tracker(tracker), Kind::Tracker(module)) => {
+                *tracker = tracker::Tracker::new(module)
+            }
+            (Engine::Midi(synth), Kind::Midi(sequence)) => *synth = midi::Synth::new(sequence),
+            _ => unreachable!(),
+        }
+        self.voices.fill(Voice::default());
+        self.finished = false;
+    }
+
+    /// Time until the next tick at the current tempo.
+    pub fn tick_duration(&self) -> Duration {
+        let nanos = match (&self.engine, &self.song.kind) {
+            (Engine::Tracker(tracker), _) => tracker.tick_nanos(),
+            (Engine::Midi(synth), Kind::Midi(sequence)) => synth.tick_nanos(sequence),
+            _ => unreachable!(),
+        };
+        Duration::from_nanos((nanos as f32 / self.tempo) as u64)
+    }
+
+    /// What every voice plays after the last tick.
+    pub(crate) fn voices(&self) -> &[Voice] {
+        &self.voices
+    }
+
+    /// Play the next tick.
+    pub(crate) fn tick(&mut self) {
+        if self.finished {
+            return;
+        }
+        for voice in &mut self.voices {
+            voice.start = None;
+        }
+
+        let playing = match (&mut self.engine, &self.song.kind) {
+            (Engine::Tracker(tracker), Kind::Tracker(module)) => tracker.tick(
+                module,
+                &self.song.instruments,
+                &mut self.voices,
+                self.looping,
+            ),
+            (Engine::Midi(synth), Kind::Midi(sequence)) => synth.tick(
+                sequence,
+                &self.song.instruments,
+                &mut self.voices,
+                self.looping,
+            ),
+            _ => unreachable!(),
+        };
+        if !playing {
+            self.finished = true;
+            self.voices.fill(Voice::default());
+            return;
+        }
+
+        let gain = self.volume * self.mix_gain;
+        for voice in &mut self.voices {
+            if self.muted.get(usize::from(voice.channel)) == Some(&true) {
+                voice.volume = 0.0;
+            } else {
+                voice.volume *= gain;
+            }
+        }
+
+        let position = self.position();
+        if let Some(callback) = &mut self.tick_callback {
+            callback(position);
+        }
+    }
+}
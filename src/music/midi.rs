@@ -0,0 +1,712 @@
+//! Standard MIDI files played with sampled instruments.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use num_traits::Float;
+
+use super::{FADE_FULL, Instrument, Kind, MIDDLE_C, Position, Sample, Song, Voice};
+use crate::error::FormatError;
+
+/// Number of MIDI channels.
+pub(crate) const CHANNELS: usize = 16;
+/// Channel that plays drums in General MIDI.
+const DRUM_CHANNEL: u8 = 9;
+/// Envelopes and fade outs advance at the 50 Hz of a tracker at 125 BPM.
+pub(crate) const ENVELOPE_TICK_NS: u64 = 20_000_000;
+/// Tempo until the file sets one, 120 BPM.
+const DEFAULT_TEMPO: u32 = 500_000;
+/// Range of pitch bends, in semitones.
+const BEND_RANGE: f32 = 2.0;
+
+/// Instruments used to play MIDI files, for [`Song::from_midi`].
+///
+/// Programs without an instrument and drum keys without a sample are silent.
+///
+/// ```rust
+/// let bank = SampleBank::new()
+///     .program(0, Instrument::new(Sample::new(piano, 22050).looping(4410, 22050)))
+///     .drum(36, Sample::new(kick, 22050));
+/// let song = Song::from_midi(include_bytes!("theme.mid"), bank)?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct SampleBank {
+    programs: Vec<Option<Instrument>>,
+    drums: Vec<(u8, Sample)>,
+    polyphony: usize,
+}
+
+impl Default for SampleBank {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SampleBank {
+    /// An empty bank playing up to 16 notes at once.
+    pub fn new() -> Self {
+        Self {
+            programs: vec![None; 128],
+            drums: Vec::new(),
+            polyphony: 16,
+        }
+    }
+
+    /// Play `instrument` for General MIDI program `program`, `0..=127`.
+    #[must_use]
+    pub fn program(mut self, program: u8, instrument: Instrument) -> Self {
+        if let Some(slot) = self.programs.get_mut(usize::from(program)) {
+            *slot = Some(instrument);
+        }
+        self
+    }
+
+    /// Play `sample` for key `key` on the drum channel, at its own pitch.
+    #[must_use]
+    pub fn drum(mut self, key: u8, sample: Sample) -> Self {
+        self.drums.retain(|&(other, _)| other != key);
+        self.drums.push((key, sample));
+        self
+    }
+
+    /// Play at most `voices` notes at once, stealing the oldest ones.
+    ///
+    /// A [`MusicPlayer`](super::MusicPlayer) needs one `ASND` voice for each.
+    #[must_use]
+    pub fn polyphony(mut self, voices: usize) -> Self {
+        self.polyphony = voices.max(1);
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Event {
+    NoteOn {
+        channel: u8,
+        key: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        key: u8,
+    },
+    Controller {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    Program {
+        channel: u8,
+        program: u8,
+    },
+    PitchBend {
+        channel: u8,
+        value: i16,
+    },
+    /// Microseconds per quarter note
+    Tempo(u32),
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Timing {
+    TicksPerQuarter(u16),
+    /// Ticks of a SMPTE time code
+    Nanos(u64),
+}
+
+/// The events of every track of a MIDI file, merged in order.
+pub(crate) struct Sequence {
+    /// Events and the tick they happen on
+    events: Vec<(u64, Event)>,
+    /// Tick the longest track ends on
+    length: u64,
+    timing: Timing,
+    /// Instrument of each program
+    programs: [Option<u16>; 128],
+    /// Instrument holding the drum samples
+    drums: Option<u16>,
+    pub(crate) voices: usize,
+}
+
+/// Reads the fields of a track chunk.
+struct TrackReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl TrackReader<'_> {
+    fn byte(&mut self) -> Result<u8, FormatError> {
+        let byte = *self.data.get(self.offset).ok_or(FormatError::Truncated)?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    /// A variable length quantity of up to four bytes.
+    fn quantity(&mut self) -> Result<u32, FormatError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | u32::from(byte & 0x7F);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(FormatError::Invalid)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&[u8], FormatError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or(FormatError::Truncated)?;
+        self.offset += len;
+        Ok(bytes)
+    }
+}
+
+/// Parse a track into `events`, returning the tick it ends on.
+fn parse_track(data: &[u8], events: &mut Vec<(u64, Event)>) -> Result<u64, FormatError> {
+    let mut reader = TrackReader { data, offset: 0 };
+    let mut tick = 0u64;
+    let mut running = None;
+    while reader.offset < data.len() {
+        tick += u64::from(reader.quantity()?);
+        let mut status = reader.byte()?;
+        let first = if status & 0x80 == 0 {
+            // Running status repeats the last channel message.
+            let data = status;
+            status = running.ok_or(FormatError::Invalid)?;
+            data
+        } else {
+            match status {
+                0xFF => {
+                    let kind = reader.byte()?;
+                    let len = reader.quantity()? as usize;
+                    let data = reader.bytes(len)?;
+                    match kind {
+                        0x2F => return Ok(tick),
+                        0x51 if len == 3 => {
+                            let tempo = u32::from_be_bytes([0, data[0], data[1], data[2]]);
+                            events.push((tick, Event::Tempo(tempo.max(1))));
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                0xF0 | 0xF7 => {
+                    let len = reader.quantity()? as usize;
+                    reader.bytes(len)?;
+                    continue;
+                }
+                0xF1..=0xFE => return Err(FormatError::Invalid),
+                _ => {
+                    running = Some(status);
+                    reader.byte()?
+                }
+            }
+        };
+
+        let channel = status & 0xF;
+        let event = match status >> 4 {
+            0x8 => {
+                reader.byte()?;
+                Event::NoteOff {
+                    channel,
+                    key: first,
+                }
+            }
+            0x9 => match reader.byte()? {
+                0 => Event::NoteOff {
+                    channel,
+                    key: first,
+                },
+                velocity => Event::NoteOn {
+                    channel,
+                    key: first,
+                    velocity,
+                },
+            },
+            0xB => Event::Controller {
+                channel,
+                controller: first,
+                value: reader.byte()?,
+            },
+            0xC => Event::Program {
+                channel,
+                program: first,
+            },
+            0xE => {
+                let value = (i16::from(reader.byte()?) << 7 | i16::from(first)) - 0x2000;
+                Event::PitchBend { channel, value }
+            }
+            0xA => {
+                reader.byte()?;
+                continue;
+            }
+            _ => continue,
+        };
+        events.push((tick, event));
+    }
+    Ok(tick)
+}
+
+pub(super) fn parse(file: &[u8], bank: SampleBank) -> Result<Song, FormatError> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    while offset + 8 <= file.len() {
+        let id = &file[offset..offset + 4];
+        let len = u32::from_be_bytes(file[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body = offset + 8;
+        let data = file.get(body..body + len).ok_or(FormatError::Truncated)?;
+        chunks.push((id, data));
+        offset = body + len;
+    }
+
+    let Some(&(_, header)) = chunks.first().filter(|(id, _)| id == b"MThd") else {
+        return Err(FormatError::InvalidMagic);
+    };
+    if header.len() < 6 {
+        return Err(FormatError::Truncated);
+    }
+    let format = u16::from_be_bytes([header[0], header[1]]);
+    let division = u16::from_be_bytes([header[4], header[5]]);
+    if format > 1 {
+        return Err(FormatError::Unsupported);
+    }
+    let timing = if division & 0x8000 != 0 {
+        let frames = u64::from(((division >> 8) as i8).unsigned_abs());
+        let ticks = u64::from(division & 0xFF);
+        if frames == 0 || ticks == 0 {
+            return Err(FormatError::Invalid);
+        }
+        // 29 stands for 29.97 frames per second.
+        let nanos = if frames == 29 {
+            1_001_000_000 / 30
+        } else {
+            1_000_000_000 / frames
+        };
+        Timing::Nanos(nanos / ticks)
+    } else if division == 0 {
+        return Err(FormatError::Invalid);
+    } else {
+        Timing::TicksPerQuarter(division)
+    };
+
+    let mut events = Vec::new();
+    let mut length = 0;
+    for &(_, data) in chunks.iter().filter(|(id, _)| id == b"MTrk") {
+        length = length.max(parse_track(data, &mut events)?);
+    }
+    // The sort is stable, so events on the same tick keep the order of their tracks.
+    events.sort_by_key(|&(tick, _)| tick);
+
+    let mut instruments = Vec::new();
+    let mut programs = [None; 128];
+    for (program, instrument) in bank.programs.into_iter().enumerate() {
+        if let Some(instrument) = instrument {
+            programs[program] = Some(instruments.len() as u16);
+            instruments.push(instrument);
+        }
+    }
+    let mut drums = None;
+    if !bank.drums.is_empty() {
+        let mut kit = Instrument {
+            samples: Vec::with_capacity(bank.drums.len()),
+            note_map: [u8::MAX; 128],
+            volume_envelope: None,
+            panning_envelope: None,
+            fadeout: 0,
+        };
+        for (key, sample) in bank.drums {
+            if let Some(slot) = kit.note_map.get_mut(usize::from(key)) {
+                *slot = kit.samples.len() as u8;
+                kit.samples.push(sample);
+            }
+        }
+        drums = Some(instruments.len() as u16);
+        instruments.push(kit);
+    }
+
+    let sequence = Sequence {
+        events,
+        length,
+        timing,
+        programs,
+        drums,
+        voices: bank.polyphony,
+    };
+    Ok(Song::new(&[], instruments, Kind::Midi(Box::new(sequence))))
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Channel {
+    program: u8,
+    /// Controller 7
+    volume: u8,
+    /// Controller 11
+    expression: u8,
+    /// Controller 10
+    pan: u8,
+    /// In semitones
+    bend: f32,
+    /// Controller 64
+    sustain: bool,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self {
+            program: 0,
+            volume: 100,
+            expression: 127,
+            pan: 64,
+            bend: 0.0,
+            sustain: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Note {
+    channel: u8,
+    key: u8,
+    instrument: u16,
+    sample: u16,
+    velocity: u8,
+    /// Whether the key is down
+    held: bool,
+    /// Whether the key was released while the sustain pedal was down
+    sustained: bool,
+    /// Volume left while fading out after the release, up to [`FADE_FULL`]
+    fade: i32,
+    envelope: u16,
+    /// Nanoseconds until a sample that does not loop ends
+    remaining: Option<u64>,
+    /// When the note started, to steal the oldest
+    started: u64,
+    start: bool,
+}
+
+impl Note {
+    fn released(&self) -> bool {
+        !self.held && !self.sustained
+    }
+}
+
+/// Plays the events of a [`Sequence`].
+pub(crate) struct Synth {
+    /// Next event to play
+    next: usize,
+    tick: u64,
+    /// Microseconds per quarter note
+    tempo: u32,
+    channels: [Channel; CHANNELS],
+    notes: Vec<Option<Note>>,
+    /// Nanoseconds since the envelopes last advanced
+    envelope_time: u64,
+    notes_started: u64,
+}
+
+impl Synth {
+    pub(crate) fn new(sequence: &Sequence) -> Self {
+        Self {
+            next: 0,
+            tick: 0,
+            tempo: DEFAULT_TEMPO,
+            channels: [Channel::default(); CHANNELS],
+            notes: vec![None; sequence.voices],
+            envelope_time: 0,
+            notes_started: 0,
+        }
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        Position::Midi {
+            tick: self.tick.saturating_sub(1),
+        }
+    }
+
+    pub(crate) fn tick_nanos(&self, sequence: &Sequence) -> u64 {
+        match sequence.timing {
+            Timing::TicksPerQuarter(ticks) => u64::from(self.tempo) * 1000 / u64::from(ticks),
+            Timing::Nanos(nanos) => nanos,
+        }
+    }
+
+    /// Play one tick, returning `false` once the song ended.
+    pub(crate) fn tick(
+        &mut self,
+        sequence: &Sequence,
+        instruments: &[Instrument],
+        voices: &mut [Voice],
+        looping: bool,
+    ) -> bool {
+        if self.next >= sequence.events.len() && self.tick >= sequence.length {
+            if !looping || sequence.events.is_empty() {
+                return false;
+            }
+            let notes = core::mem::take(&mut self.notes);
+            *self = Self::new(sequence);
+            self.notes = notes;
+            for note in self.notes.iter_mut().flatten() {
+                note.held = false;
+                note.sustained = false;
+            }
+            self.free_released(instruments);
+        }
+
+        while let Some(&(tick, event)) = sequence.events.get(self.next) {
+            if tick > self.tick {
+                break;
+            }
+            self.next += 1;
+            self.event(sequence, instruments, event);
+        }
+
+        let length = self.tick_nanos(sequence);
+        self.envelope_time += length;
+        while self.envelope_time >= ENVELOPE_TICK_NS {
+            self.envelope_time -= ENVELOPE_TICK_NS;
+            self.advance_envelopes(instruments);
+        }
+        for slot in &mut self.notes {
+            if let Some(note) = slot
+                && let Some(remaining) = &mut note.remaining
+            {
+                *remaining = remaining.saturating_sub(length);
+                if *remaining == 0 {
+                    *slot = None;
+                }
+            }
+        }
+
+        for (slot, voice) in self.notes.iter_mut().zip(voices) {
+            *voice = match slot {
+                Some(note) => self.channels[usize::from(note.channel)].output(note, instruments),
+                None => Voice::default(),
+            };
+        }
+        self.tick += 1;
+        true
+    }
+
+    fn event(&mut self, sequence: &Sequence, instruments: &[Instrument], event: Event) {
+        match event {
+            Event::NoteOn {
+                channel,
+                key,
+                velocity,
+            } => {
+                self.note_off(channel, key, instruments);
+                self.note_on(sequence, instruments, channel, key, velocity);
+            }
+            Event::NoteOff { channel, key } => self.note_off(channel, key, instruments),
+            Event::Program { channel, program } => {
+                self.channels[usize::from(channel)].program = program;
+            }
+            Event::PitchBend { channel, value } => {
+                self.channels[usize::from(channel)].bend = f32::from(value) / 8192.0 * BEND_RANGE;
+            }
+            Event::Controller {
+                channel,
+                controller,
+                value,
+            } => {
+                let state = &mut self.channels[usize::from(channel)];
+                match controller {
+                    7 => state.volume = value,
+                    10 => state.pan = value,
+                    11 => state.expression = value,
+                    64 => {
+                        state.sustain = value >= 64;
+                        if !state.sustain {
+                            for note in self.notes.iter_mut().flatten() {
+                                if note.channel == channel {
+                                    note.sustained = false;
+                                }
+                            }
+                            self.free_released(instruments);
+                        }
+                    }
+                    // All sound off stops right away, all notes off releases.
+                    120 => {
+                        for slot in &mut self.notes {
+                            if slot.is_some_and(|note| note.channel == channel) {
+                                *slot = None;
+                            }
+                        }
+                    }
+                    121 => {
+                        *state = Channel {
+                            program: state.program,
+                            ..Channel::default()
+                        }
+                    }
+                    123 => {
+                        for note in self.notes.iter_mut().flatten() {
+                            if note.channel == channel {
+                                note.held = false;
+                                note.sustained = false;
+                            }
+                        }
+                        self.free_released(instruments);
+                    }
+                    _ => {}
+                }
+            }
+            Event::Tempo(tempo) => self.tempo = tempo,
+        }
+    }
+
+    fn note_on(
+        &mut self,
+        sequence: &Sequence,
+        instruments: &[Instrument],
+        channel: u8,
+        key: u8,
+        velocity: u8,
+    ) {
+        let instrument = if channel == DRUM_CHANNEL {
+            sequence.drums
+        } else {
+            sequence.programs[usize::from(self.channels[usize::from(channel)].program)]
+        };
+        let Some(instrument) = instrument else {
+            return;
+        };
+        let Some((sample_index, sample)) = instruments
+            .get(usize::from(instrument))
+            .and_then(|instrument| instrument.sample_for(key))
+        else {
+            return;
+        };
+
+        // Take a free voice, else the oldest released note, else the oldest note.
+        let slot = self.notes.iter().position(Option::is_none).or_else(|| {
+            let oldest = |released: bool| {
+                self.notes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, note)| note.is_some_and(|note| note.released() || !released))
+                    .min_by_key(|(_, note)| note.map_or(0, |note| note.started))
+                    .map(|(index, _)| index)
+            };
+            oldest(true).or_else(|| oldest(false))
+        });
+        let Some(slot) = slot else {
+            return;
+        };
+
+        let remaining = match sample.loop_range {
+            Some(_) => None,
+            None => {
+                let frequency = self.channels[usize::from(channel)].frequency(channel, key, sample);
+                Some((sample.len() as f32 / frequency * 1e9) as u64)
+            }
+        };
+        self.notes_started += 1;
+        self.notes[slot] = Some(Note {
+            channel,
+            key,
+            instrument,
+            sample: sample_index as u16,
+            velocity,
+            held: true,
+            sustained: false,
+            fade: FADE_FULL,
+            envelope: 0,
+            remaining,
+            started: self.notes_started,
+            start: true,
+        });
+    }
+
+    fn note_off(&mut self, channel: u8, key: u8, instruments: &[Instrument]) {
+        let sustain = self.channels[usize::from(channel)].sustain;
+        for note in self.notes.iter_mut().flatten() {
+            if note.channel == channel && note.key == key && note.held {
+                note.held = false;
+                note.sustained = sustain;
+            }
+        }
+        self.free_released(instruments);
+    }
+
+    /// Stop released notes of looped samples that have nothing to fade out with.
+    fn free_released(&mut self, instruments: &[Instrument]) {
+        for slot in &mut self.notes {
+            let Some(note) = slot else {
+                continue;
+            };
+            let instrument = &instruments[usize::from(note.instrument)];
+            if note.released()
+                && note.remaining.is_none()
+                && instrument.volume_envelope.is_none()
+                && instrument.fadeout == 0
+            {
+                *slot = None;
+            }
+        }
+    }
+
+    fn advance_envelopes(&mut self, instruments: &[Instrument]) {
+        for slot in &mut self.notes {
+            let Some(note) = slot else {
+                continue;
+            };
+            let instrument = &instruments[usize::from(note.instrument)];
+            if let Some(envelope) = &instrument.volume_envelope {
+                note.envelope = envelope.advance(note.envelope, !note.released());
+            }
+            if note.released() {
+                note.fade -= i32::from(instrument.fadeout);
+                let silent = instrument.volume_envelope.as_ref().is_some_and(|envelope| {
+                    note.envelope == envelope.end() && envelope.value(note.envelope) == 0.0
+                });
+                if note.fade <= 0 || silent {
+                    *slot = None;
+                }
+            }
+        }
+    }
+}
+
+impl Channel {
+    fn frequency(&self, channel: u8, key: u8, sample: &Sample) -> f32 {
+        // Drums play at the pitch of their sample whatever the key.
+        let key = if channel == DRUM_CHANNEL {
+            MIDDLE_C
+        } else {
+            key
+        };
+        let semitones = f32::from(key) - f32::from(MIDDLE_C) + sample.pitch + self.bend;
+        super::BASE_RATE * (semitones / 12.0).exp2()
+    }
+
+    fn output(&self, note: &mut Note, instruments: &[Instrument]) -> Voice {
+        let instrument = &instruments[usize::from(note.instrument)];
+        let sample = &instrument.samples[usize::from(note.sample)];
+        let envelope = instrument
+            .volume_envelope
+            .as_ref()
+            .map_or(1.0, |envelope| envelope.value(note.envelope));
+        let level = |value: u8| {
+            let value = f32::from(value) / 127.0;
+            value * value
+        };
+        let volume = level(note.velocity) * level(self.volume) * f32::from(self.expression) / 127.0
+            * f32::from(sample.volume)
+            / 64.0
+            * envelope
+            * note.fade.max(0) as f32
+            / FADE_FULL as f32;
+
+        Voice {
+            sample: Some((note.instrument, note.sample)),
+            start: core::mem::take(&mut note.start).then_some(0),
+            frequency: self.frequency(note.channel, note.key, sample),
+            volume,
+            pan: ((f32::from(self.pan) - 64.0) / 63.0).clamp(-1.0, 1.0),
+            channel: note.channel,
+        }
+    }
+}
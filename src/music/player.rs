@@ -0,0 +1,408 @@
+//! Playing a [`Sequencer`] on `ASND` voices.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{
+    ffi::c_void,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, Ordering},
+    time::Duration,
+};
+
+use super::{Sample, Sequencer};
+use crate::{
+    OgcError, Result,
+    asnd::{Asnd, VoiceFormat, VoiceOptions},
+    error::AudioError,
+    ffi,
+    utils::Buf32,
+};
+
+/// Highest pitch `ASND` plays at, in Hz.
+const MAX_PITCH: u32 = 144_000;
+/// Period of the alarm that runs the sequencer, the timing resolution of the song.
+const ALARM_PERIOD: Duration = Duration::from_millis(1);
+/// `ASND` buffers hold a multiple of 32 bytes, 16 samples.
+const ALIGN_SAMPLES: usize = 16;
+/// Loops shorter than this are repeated until they fill a whole number of 32 byte blocks,
+/// longer ones are cut to one.
+const MAX_REPEATED_LOOP: usize = 4096;
+
+fn timespec(duration: Duration) -> ffi::timespec {
+    ffi::timespec {
+        tv_sec: duration.as_secs() as _,
+        tv_nsec: duration.subsec_nanos() as _,
+    }
+}
+
+fn to_buffer(samples: &[i16]) -> Option<Buf32> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut buffer = Buf32::new(samples.len() * 2);
+    buffer.fill(0);
+    for (bytes, sample) in buffer.chunks_exact_mut(2).zip(samples) {
+        bytes.copy_from_slice(&sample.to_be_bytes());
+    }
+    unsafe { ffi::DCFlushRange(buffer.as_mut_ptr().cast(), buffer.len() as u32) };
+    Some(buffer)
+}
+
+/// A sample laid out for `ASND`.
+///
+/// `ASND` voices can only loop a whole buffer, so a looped sample is split into the part up to
+/// its loop, queued once, and the loop, queued again each time it starts playing.
+struct Prepared {
+    head: Option<Buf32>,
+    looped: Option<Buf32>,
+}
+
+impl Prepared {
+    fn new(sample: &Sample) -> Self {
+        let data = &sample.data;
+        let Some((start, end)) = sample.loop_range else {
+            return Self {
+                head: to_buffer(data),
+                looped: None,
+            };
+        };
+
+        let (start, end) = (start as usize, end as usize);
+        let length = end - start;
+        // The head runs into the loop up to a whole block, the loop buffer picks up from there.
+        let head_length = start.next_multiple_of(ALIGN_SAMPLES);
+        let looped_at = |index: usize| data[start + index % length];
+        let head: Vec<i16> = data[..start]
+            .iter()
+            .copied()
+            .chain((0..head_length - start).map(looped_at))
+            .collect();
+        let phase = head_length - start;
+        let loop_length = if length < MAX_REPEATED_LOOP {
+            length * (ALIGN_SAMPLES / gcd(length, ALIGN_SAMPLES))
+        } else {
+            length / ALIGN_SAMPLES * ALIGN_SAMPLES
+        };
+        Self {
+            head: to_buffer(&head),
+            looped: to_buffer(
+                &(0..loop_length)
+                    .map(|index| looped_at(phase + index))
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// What a hardware voice was last told to play.
+#[derive(Copy, Clone, Debug, Default)]
+struct Hardware {
+    sample: Option<(u16, u16)>,
+    pitch: u32,
+    volume: (u8, u8),
+}
+
+/// State shared with the alarm and voice callbacks.
+struct State {
+    sequencer: Sequencer,
+    first_voice: u32,
+    samples: Vec<Vec<Prepared>>,
+    hardware: Vec<Hardware>,
+    /// Nanoseconds until the next tick
+    until_tick: i64,
+    paused: bool,
+}
+
+fn prepared_mut(
+    samples: &mut [Vec<Prepared>],
+    (instrument, sample): (u16, u16),
+) -> Option<&mut Prepared> {
+    samples
+        .get_mut(usize::from(instrument))?
+        .get_mut(usize::from(sample))
+}
+
+impl State {
+    /// Send what the sequencer plays after its last tick to the voices.
+    fn update_voices(&mut self) {
+        for (index, voice) in self.sequencer.voices().iter().enumerate() {
+            let number = self.first_voice + index as u32;
+            let pitch = (voice.frequency as u32).clamp(1, MAX_PITCH);
+            let pan = voice.pan.clamp(-1.0, 1.0);
+            let level = |gain: f32| (voice.volume * gain * 255.0).clamp(0.0, 255.0) as u8;
+            let volume = (level((1.0 - pan).min(1.0)), level((1.0 + pan).min(1.0)));
+
+            let hardware = self.hardware[index];
+            if let (Some(start), Some(sample)) = (voice.start, voice.sample) {
+                let started = prepared_mut(&mut self.samples, sample)
+                    .is_some_and(|prepared| start_voice(number, prepared, start, pitch, volume));
+                self.hardware[index] = Hardware {
+                    sample: started.then_some(sample),
+                    pitch,
+                    volume,
+                };
+            } else if voice.sample.is_none() {
+                if hardware.sample.is_some() {
+                    let _ = Asnd::stop_voice(number);
+                    self.hardware[index].sample = None;
+                }
+            } else if hardware.sample.is_some() {
+                if hardware.pitch != pitch {
+                    let _ = Asnd::change_pitch_voice(number, pitch);
+                }
+                if hardware.volume != volume {
+                    let _ = Asnd::change_volume_voice(number, volume.0, volume.1);
+                }
+                self.hardware[index].pitch = pitch;
+                self.hardware[index].volume = volume;
+            }
+        }
+    }
+
+    fn stop_voices(&mut self) {
+        for (index, hardware) in self.hardware.iter_mut().enumerate() {
+            if hardware.sample.take().is_some() {
+                let _ = Asnd::stop_voice(self.first_voice + index as u32);
+            }
+        }
+    }
+}
+
+/// Start `prepared` on voice `number` from sample `start`, returning whether it plays.
+fn start_voice(
+    number: u32,
+    prepared: &mut Prepared,
+    start: u32,
+    pitch: u32,
+    volume: (u8, u8),
+) -> bool {
+    // Buffers have to start on a 32 byte boundary.
+    let offset = start as usize / ALIGN_SAMPLES * ALIGN_SAMPLES * 2;
+    let first = match prepared.head.as_deref_mut() {
+        Some(head) if offset < head.len() => &mut head[offset..],
+        _ => match prepared.looped.as_deref_mut() {
+            Some(looped) => looped,
+            None => return false,
+        },
+    };
+
+    let options = VoiceOptions::new()
+        .voice(number)
+        .format(VoiceFormat::Mono16BitBe)
+        .pitch(pitch)
+        .volume_left(volume.0)
+        .volume_right(volume.1)
+        .callback(Some(voice_callback));
+    if Asnd::set_voice(options, first).is_err() {
+        return false;
+    }
+    if let Some(looped) = prepared.looped.as_deref_mut() {
+        let _ = Asnd::add_voice(number, looped);
+    }
+    true
+}
+
+/// The only player, `ASND` voice callbacks do not get a user pointer.
+static PLAYER: AtomicPtr<State> = AtomicPtr::new(ptr::null_mut());
+
+/// A [`Sequencer`] playing on `ASND` voices.
+///
+/// Each voice of the song gets its own hardware voice, so the mixing is done by the DSP. The
+/// sequencer runs from a 1 ms alarm from then on, use [`MusicPlayer::lock`] to change it.
+/// Dropping the player stops it.
+pub struct MusicPlayer {
+    state: NonNull<State>,
+    alarm: ffi::syswd_t,
+}
+
+// SAFETY: the state is only touched with interrupts disabled.
+unsafe impl Send for MusicPlayer {}
+
+impl MusicPlayer {
+    /// Play `sequencer` on the `ASND` voices from `first_voice` on.
+    ///
+    /// The song needs [`Sequencer::voice_count`] voices. `ASND` must already be initialized.
+    /// Only one player can exist at a time, creating a second one fails with
+    /// [`AudioError::Invalid`], as does a song that needs more voices than are left.
+    ///
+    /// Every sample is copied into buffers laid out for `ASND` first.
+    pub fn new(sequencer: Sequencer, first_voice: u32) -> Result<Self> {
+        let voices = sequencer.voice_count();
+        if first_voice as usize + voices > 16 {
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        let samples = sequencer
+            .song()
+            .instruments
+            .iter()
+            .map(|instrument| instrument.samples.iter().map(Prepared::new).collect())
+            .collect();
+        let state = NonNull::from(Box::leak(Box::new(State {
+            sequencer,
+            first_voice,
+            samples,
+            hardware: vec![Hardware::default(); voices],
+            until_tick: 0,
+            paused: false,
+        })));
+        if PLAYER
+            .compare_exchange(
+                ptr::null_mut(),
+                state.as_ptr(),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            drop(unsafe { Box::from_raw(state.as_ptr()) });
+            return Err(OgcError::Audio(AudioError::Invalid));
+        }
+
+        let mut alarm = 0;
+        let ret = unsafe { ffi::SYS_CreateAlarm(&mut alarm) };
+        if ret < 0 {
+            PLAYER.store(ptr::null_mut(), Ordering::Release);
+            drop(unsafe { Box::from_raw(state.as_ptr()) });
+            return Err(OgcError::System(ret));
+        }
+        let player = Self { state, alarm };
+
+        let period = timespec(ALARM_PERIOD);
+        let ret = unsafe {
+            ffi::SYS_SetPeriodicAlarm(
+                alarm,
+                &period,
+                &period,
+                Some(alarm_callback),
+                state.as_ptr().cast(),
+            )
+        };
+        if ret < 0 {
+            return Err(OgcError::System(ret));
+        }
+        Ok(player)
+    }
+
+    /// Run `f` on the sequencer with interrupts disabled.
+    ///
+    /// The song does not advance while `f` runs, keep it short.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut Sequencer) -> R) -> R {
+        // SAFETY: the alarm and voice callbacks are the only other users of the state.
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            let result = f(&mut (*self.state.as_ptr()).sequencer);
+            ffi::IRQ_Restore(level);
+            result
+        }
+    }
+
+    /// Pause the song and its voices.
+    pub fn pause(&mut self) {
+        self.set_paused(true);
+    }
+
+    /// Resume the song after [`MusicPlayer::pause`].
+    pub fn resume(&mut self) {
+        self.set_paused(false);
+    }
+
+    /// Whether the song is paused.
+    pub fn is_paused(&self) -> bool {
+        unsafe { (*self.state.as_ptr()).paused }
+    }
+
+    /// Whether the song ended, see [`Sequencer::is_finished`].
+    pub fn is_finished(&self) -> bool {
+        self.lock(|sequencer| sequencer.is_finished())
+    }
+
+    /// Stop playing and get the sequencer back.
+    pub fn into_sequencer(self) -> Sequencer {
+        let mut player = core::mem::ManuallyDrop::new(self);
+        player.stop();
+        let state = unsafe { Box::from_raw(player.state.as_ptr()) };
+        state.sequencer
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            let state = &mut *self.state.as_ptr();
+            state.paused = paused;
+            for index in 0..state.hardware.len() {
+                let _ = Asnd::pause_voice(state.first_voice + index as u32, paused);
+            }
+            ffi::IRQ_Restore(level);
+        }
+    }
+
+    fn stop(&mut self) {
+        unsafe {
+            ffi::SYS_RemoveAlarm(self.alarm);
+            let level = ffi::IRQ_Disable();
+            let state = &mut *self.state.as_ptr();
+            state.stop_voices();
+            PLAYER.store(ptr::null_mut(), Ordering::Release);
+            ffi::IRQ_Restore(level);
+        }
+    }
+}
+
+impl Drop for MusicPlayer {
+    fn drop(&mut self) {
+        self.stop();
+        drop(unsafe { Box::from_raw(self.state.as_ptr()) });
+    }
+}
+
+unsafe extern "C" fn alarm_callback(_alarm: ffi::syswd_t, arg: *mut c_void) {
+    if arg.is_null() {
+        return;
+    }
+    // SAFETY: `arg` is the state of the player, which removes the alarm before freeing it.
+    let state = unsafe { &mut *arg.cast::<State>() };
+    if state.paused || state.sequencer.is_finished() {
+        return;
+    }
+
+    state.until_tick -= ALARM_PERIOD.as_nanos() as i64;
+    while state.until_tick <= 0 {
+        state.sequencer.tick();
+        if state.sequencer.is_finished() {
+            state.stop_voices();
+            return;
+        }
+        state.update_voices();
+        state.until_tick += state.sequencer.tick_duration().as_nanos().max(1) as i64;
+    }
+}
+
+unsafe extern "C" fn voice_callback(voice: i32) {
+    let state = PLAYER.load(Ordering::Acquire);
+    if state.is_null() {
+        return;
+    }
+    // SAFETY: the state outlives the voices and user code only touches it with interrupts off.
+    let state = unsafe { &mut *state };
+    let voice = voice as u32;
+    let Some(&hardware) = state
+        .hardware
+        .get(voice.wrapping_sub(state.first_voice) as usize)
+    else {
+        return;
+    };
+    let looped = hardware
+        .sample
+        .and_then(|sample| prepared_mut(&mut state.samples, sample))
+        .and_then(|prepared| prepared.looped.as_deref_mut());
+    // Queue the loop again whenever the voice starts playing its last buffer.
+    if let Some(looped) = looped
+        && Asnd::test_voice_buffer_ready(voice)
+    {
+        let _ = Asnd::add_voice(voice, looped);
+    }
+}
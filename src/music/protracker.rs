@@ -0,0 +1,136 @@
+//! ProTracker MOD files.
+
+use alloc::vec::Vec;
+
+use num_traits::Float;
+
+use super::{
+    Instrument, Kind, Sample, Song,
+    tracker::{Cell, Module, Pattern},
+};
+use crate::error::FormatError;
+
+const SAMPLES: usize = 31;
+const SAMPLE_HEADER: usize = 30;
+const ORDERS: usize = 950;
+const SIGNATURE: usize = 1080;
+const PATTERNS: usize = 1084;
+const ROWS: usize = 64;
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, FormatError> {
+    let bytes = data.get(offset..offset + 2).ok_or(FormatError::Truncated)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Number of channels given by the signature at offset 1080.
+fn channels(signature: &[u8]) -> Option<usize> {
+    match signature {
+        b"M.K." | b"M!K!" | b"M&K!" | b"FLT4" | b"4CHN" | b"N.T." => Some(4),
+        b"OCTA" | b"CD81" => Some(8),
+        [digit, b'C', b'H', b'N'] if digit.is_ascii_digit() => Some(usize::from(digit - b'0')),
+        [tens, ones, b'C', b'H' | b'N'] if tens.is_ascii_digit() && ones.is_ascii_digit() => {
+            Some(usize::from((tens - b'0') * 10 + ones - b'0'))
+        }
+        _ => None,
+    }
+    .filter(|channels| (1..=32).contains(channels))
+}
+
+/// Note number of an Amiga period, or `0` for none.
+fn period_to_note(period: u16) -> u8 {
+    if period == 0 {
+        return 0;
+    }
+    let semitones = 12.0 * (428.0 / f32::from(period)).log2();
+    (49.0 + semitones.round()).clamp(1.0, 96.0) as u8
+}
+
+pub(super) fn parse(file: &[u8]) -> Result<Song, FormatError> {
+    let signature = file
+        .get(SIGNATURE..PATTERNS)
+        .ok_or(FormatError::Truncated)?;
+    let channels = channels(signature).ok_or(FormatError::InvalidMagic)?;
+
+    let length = usize::from(file[ORDERS]).clamp(1, 128);
+    let restart = usize::from(file[ORDERS + 1]);
+    let orders = file[ORDERS + 2..ORDERS + 2 + 128].to_vec();
+    // Every pattern in the table is stored, even past the song length.
+    let pattern_count = usize::from(*orders.iter().max().unwrap_or(&0)) + 1;
+
+    let pattern_size = ROWS * channels * 4;
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for index in 0..pattern_count {
+        let start = PATTERNS + index * pattern_size;
+        let data = file
+            .get(start..start + pattern_size)
+            .ok_or(FormatError::Truncated)?;
+        let cells = data
+            .chunks_exact(4)
+            .map(|bytes| Cell {
+                note: period_to_note(u16::from_be_bytes([bytes[0] & 0xF, bytes[1]])),
+                instrument: (bytes[0] & 0xF0) | bytes[2] >> 4,
+                volume: 0,
+                effect: bytes[2] & 0xF,
+                param: bytes[3],
+            })
+            .collect();
+        patterns.push(Pattern { rows: ROWS, cells });
+    }
+
+    let mut offset = PATTERNS + pattern_count * pattern_size;
+    let mut instruments = Vec::with_capacity(SAMPLES);
+    for index in 0..SAMPLES {
+        let header = 20 + index * SAMPLE_HEADER;
+        let length = usize::from(read_u16(file, header + 22)?) * 2;
+        let finetune = ((file[header + 24] & 0xF) << 4) as i8 >> 4;
+        let volume = file[header + 25].min(64);
+        let mut loop_start = u32::from(read_u16(file, header + 26)?) * 2;
+        let loop_length = u32::from(read_u16(file, header + 28)?) * 2;
+
+        // Files are often cut short in the middle of the last sample.
+        let end = file.len().min(offset + length);
+        let data: Vec<i16> = file[offset.min(end)..end]
+            .iter()
+            .map(|&byte| i16::from(byte as i8) << 8)
+            .collect();
+        offset += length;
+
+        // Some trackers saved the loop start in bytes rather than words.
+        if loop_start + loop_length > data.len() as u32 {
+            loop_start /= 2;
+        }
+        let mut sample = Sample {
+            data,
+            loop_range: None,
+            volume,
+            panning: None,
+            pitch: f32::from(finetune) / 8.0,
+        };
+        if loop_length > 2 {
+            sample = sample.looping(loop_start, loop_start + loop_length);
+        }
+        instruments.push(Instrument::new(sample));
+    }
+
+    let module = Module {
+        channels,
+        orders: orders[..length].to_vec(),
+        restart: if restart < length { restart } else { 0 },
+        patterns,
+        speed: 6,
+        bpm: 125,
+        linear: false,
+        amiga_limits: channels == 4,
+        // Amiga channels go left, right, right, left.
+        panning: (0..channels)
+            .map(|channel| {
+                if matches!(channel % 4, 0 | 3) {
+                    0x30
+                } else {
+                    0xD0
+                }
+            })
+            .collect(),
+    };
+    Ok(Song::new(&file[..20], instruments, Kind::Tracker(module)))
+}
@@ -0,0 +1,354 @@
+//! Mixing a [`Sequencer`] to PCM in software.
+
+use alloc::{vec, vec::Vec};
+
+use super::{Sequencer, Voice};
+use crate::mixer::SampleSource;
+
+const FRAC_BITS: u32 = 32;
+/// Frames mixed at once, bounds the stack used by [`Renderer::render`].
+const CHUNK_FRAMES: usize = 256;
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Channel {
+    sample: Option<(u16, u16)>,
+    /// Position in the sample, in 32.32 fixed point
+    position: u64,
+    /// Samples to advance per output frame, in 32.32 fixed point
+    step: u64,
+    gains: [f32; 2],
+}
+
+impl Channel {
+    fn update(&mut self, voice: &Voice, sample_rate: u32) {
+        if let Some(start) = voice.start {
+            self.sample = voice.sample;
+            self.position = u64::from(start) << FRAC_BITS;
+        }
+        if voice.sample.is_none() {
+            self.sample = None;
+        }
+        self.step = ((f64::from(voice.frequency) / f64::from(sample_rate))
+            * (1u64 << FRAC_BITS) as f64) as u64;
+        let pan = voice.pan.clamp(-1.0, 1.0);
+        self.gains = [
+            voice.volume * (1.0 - pan).min(1.0),
+            voice.volume * (1.0 + pan).min(1.0),
+        ];
+    }
+}
+
+/// Mixes a [`Sequencer`] into interleaved 16-bit stereo.
+///
+/// This plays a song without `ASND` voices, as a [`SampleSource`] for a
+/// [`Mixer`](crate::mixer::Mixer), and renders songs off the console.
+///
+/// ```rust
+/// let mut renderer = Renderer::new(Sequencer::new(Song::from_mod(MOD)?), 48000);
+/// let mut pcm = vec![0; 48000 * 2];
+/// let len = renderer.render(&mut pcm);
+/// ```
+pub struct Renderer {
+    sequencer: Sequencer,
+    sample_rate: u32,
+    channels: Vec<Channel>,
+    /// Frames left until the next tick
+    frames_left: u64,
+    /// Fraction of a frame carried over between ticks, in frames times 10^9
+    remainder: u64,
+}
+
+impl Renderer {
+    /// Mix `sequencer` at `sample_rate` Hz.
+    pub fn new(sequencer: Sequencer, sample_rate: u32) -> Self {
+        Self {
+            channels: vec![Channel::default(); sequencer.voice_count()],
+            sequencer,
+            sample_rate: sample_rate.max(1),
+            frames_left: 0,
+            remainder: 0,
+        }
+    }
+
+    /// The sequencer being played.
+    pub fn sequencer(&self) -> &Sequencer {
+        &self.sequencer
+    }
+
+    /// The sequencer being played, to change its tempo or mute channels.
+    pub fn sequencer_mut(&mut self) -> &mut Sequencer {
+        &mut self.sequencer
+    }
+
+    /// Return the sequencer.
+    pub fn into_sequencer(self) -> Sequencer {
+        self.sequencer
+    }
+
+    /// Fill `out` with interleaved stereo samples, returning how many were written.
+    ///
+    /// Fewer samples than asked for are only written once the song ended.
+    pub fn render(&mut self, out: &mut [i16]) -> usize {
+        let frames = out.len() / 2;
+        let mut done = 0;
+        while done < frames {
+            if self.frames_left == 0 && !self.next_tick() {
+                break;
+            }
+            let len = (frames - done)
+                .min(self.frames_left as usize)
+                .min(CHUNK_FRAMES);
+            self.mix(&mut out[done * 2..(done + len) * 2]);
+            self.frames_left -= len as u64;
+            done += len;
+        }
+        done * 2
+    }
+
+    /// Play the next tick of the sequencer, returning `false` if the song ended.
+    fn next_tick(&mut self) -> bool {
+        self.sequencer.tick();
+        if self.sequencer.is_finished() {
+            return false;
+        }
+        for (channel, voice) in self.channels.iter_mut().zip(self.sequencer.voices()) {
+            channel.update(voice, self.sample_rate);
+        }
+
+        let length = self.sequencer.tick_duration().as_nanos() as u64;
+        let budget = self.remainder + length * u64::from(self.sample_rate);
+        self.frames_left = budget / NANOS_PER_SECOND;
+        self.remainder = budget % NANOS_PER_SECOND;
+        true
+    }
+
+    fn mix(&mut self, out: &mut [i16]) {
+        let song = self.sequencer.song();
+        let mut acc = [0.0f32; CHUNK_FRAMES * 2];
+        let acc = &mut acc[..out.len()];
+        for channel in &mut self.channels {
+            let Some(sample) = channel.sample.and_then(|id| song.sample(id)) else {
+                continue;
+            };
+            let data = &sample.data;
+            let (loop_start, end) = match sample.loop_range {
+                Some((start, end)) => (Some(u64::from(start)), u64::from(end)),
+                None => (None, data.len() as u64),
+            };
+
+            for frame in acc.chunks_exact_mut(2) {
+                let mut index = channel.position >> FRAC_BITS;
+                if index >= end {
+                    let Some(loop_start) = loop_start else {
+                        channel.sample = None;
+                        break;
+                    };
+                    let length = end - loop_start;
+                    index = loop_start + (index - loop_start) % length;
+                    channel.position = index << FRAC_BITS | (channel.position & 0xFFFF_FFFF);
+                }
+                let next = match index + 1 {
+                    next if next < end => Some(next),
+                    _ => loop_start,
+                };
+
+                let current = f32::from(data[index as usize]);
+                let next = next.map_or(0.0, |next| f32::from(data[next as usize]));
+                let t = (channel.position & 0xFFFF_FFFF) as f32 / (1u64 << FRAC_BITS) as f32;
+                let value = current + (next - current) * t;
+                frame[0] += value * channel.gains[0];
+                frame[1] += value * channel.gains[1];
+                channel.position += channel.step;
+            }
+        }
+        for (out, &value) in out.iter_mut().zip(acc.iter()) {
+            *out = value.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+        }
+    }
+}
+
+impl SampleSource for Renderer {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        2
+    }
+
+    fn read(&mut self, buf: &mut [i16]) -> usize {
+        self.render(buf)
+    }
+
+    fn rewind(&mut self) -> bool {
+        self.sequencer.restart();
+        self.channels.fill(Channel::default());
+        self.frames_left = 0;
+        self.remainder = 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::music::{Instrument, Position, Sample, SampleBank, Song, xm::tests::square};
+
+    const RATE: u32 = 48000;
+
+    /// Frequency of the left channel, from its rising zero crossings.
+    fn frequency(pcm: &[i16]) -> f32 {
+        let left: Vec<i16> = pcm.chunks_exact(2).map(|frame| frame[0]).collect();
+        let rising: Vec<usize> = (1..left.len())
+            .filter(|&i| left[i - 1] <= 0 && left[i] > 0)
+            .collect();
+        let (first, last) = (rising[0], rising[rising.len() - 1]);
+        (rising.len() - 1) as f32 * RATE as f32 / (last - first) as f32
+    }
+
+    /// Render until the song ends, returning the number of frames.
+    fn frames_to_end(renderer: &mut Renderer) -> usize {
+        let mut pcm = vec![0; RATE as usize * 2];
+        let mut total = 0;
+        loop {
+            let len = renderer.render(&mut pcm);
+            total += len / 2;
+            if len < pcm.len() {
+                return total;
+            }
+        }
+    }
+
+    /// A 4 channel MOD playing a square wave with 32 samples per period on C-3 for one pattern.
+    fn build_mod() -> Vec<u8> {
+        let mut file = vec![0; 1084];
+        file[..4].copy_from_slice(b"test");
+        let data = square(32, 64);
+        let sample = &mut file[20..50];
+        sample[22..24].copy_from_slice(&(data.len() as u16 / 2).to_be_bytes());
+        sample[25] = 64;
+        sample[28..30].copy_from_slice(&(data.len() as u16 / 2).to_be_bytes());
+        file[950] = 1;
+        file[951] = 127;
+        file[1080..1084].copy_from_slice(b"M.K.");
+
+        let mut pattern = vec![0; 64 * 4 * 4];
+        // Period 428 with instrument 1
+        pattern[..3].copy_from_slice(&[0x01, 0xAC, 0x10]);
+        file.extend(pattern);
+        file.extend(data.iter().map(|&sample| (sample >> 8) as u8));
+        file
+    }
+
+    /// A type 0 MIDI file playing A4 for a quarter note, then resting for another.
+    fn build_midi() -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend(b"MThd");
+        file.extend(6u32.to_be_bytes());
+        for value in [0u16, 1, 96] {
+            file.extend(value.to_be_bytes());
+        }
+        let track = [
+            0x00, 0x90, 69, 100, 0x60, 0x80, 69, 0, 0x60, 0xFF, 0x2F, 0x00,
+        ];
+        file.extend(b"MTrk");
+        file.extend((track.len() as u32).to_be_bytes());
+        file.extend(track);
+        file
+    }
+
+    #[test]
+    fn protracker() {
+        let song = Song::from_mod(&build_mod()).unwrap();
+        assert_eq!(song.name(), "test");
+        assert_eq!(song.channels(), 4);
+        let mut renderer = Renderer::new(Sequencer::new(song), RATE);
+
+        let mut pcm = vec![0; RATE as usize * 2];
+        assert_eq!(renderer.render(&mut pcm), pcm.len());
+        let frequency = frequency(&pcm);
+        assert!((frequency - 8363.0 / 32.0).abs() < 2.0, "{frequency}");
+
+        // 64 rows at speed 6 and 125 BPM last 7.68 seconds.
+        let seconds = (RATE as usize + frames_to_end(&mut renderer)) as f32 / RATE as f32;
+        assert!((seconds - 7.68).abs() < 0.02, "{seconds}");
+        assert!(renderer.sequencer().is_finished());
+    }
+
+    #[test]
+    fn looping() {
+        let mut sequencer = Sequencer::new(Song::from_mod(&build_mod()).unwrap());
+        sequencer.set_looping(true);
+        let rows = Arc::new(AtomicUsize::new(0));
+        let counter = rows.clone();
+        sequencer.set_tick_callback(move |position| {
+            if let Position::Tracker { tick: 0, .. } = position {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        let mut renderer = Renderer::new(sequencer, RATE);
+
+        let mut pcm = vec![0; RATE as usize * 2 * 9];
+        assert_eq!(renderer.render(&mut pcm), pcm.len());
+        assert!(rows.load(Ordering::Relaxed) > 64);
+        assert!(
+            pcm[RATE as usize * 2 * 8..]
+                .iter()
+                .any(|&sample| sample != 0)
+        );
+
+        assert!(renderer.rewind());
+        assert!(!renderer.sequencer().is_finished());
+    }
+
+    #[test]
+    fn mute_and_tempo() {
+        let mut sequencer = Sequencer::new(Song::from_mod(&build_mod()).unwrap());
+        sequencer.set_muted(0, true);
+        let mut renderer = Renderer::new(sequencer, RATE);
+        let mut pcm = vec![0; RATE as usize / 10 * 2];
+        renderer.render(&mut pcm);
+        assert!(pcm.iter().all(|&sample| sample == 0));
+
+        // Tick lengths at 125 BPM are 20 ms.
+        let sequencer = renderer.sequencer_mut();
+        sequencer.set_tempo(2.0);
+        let duration = sequencer.tick_duration().as_secs_f32();
+        assert!((duration - 0.01).abs() < 0.0005, "{duration}");
+    }
+
+    #[test]
+    fn xm() {
+        let song = Song::from_xm(&crate::music::xm::tests::build(0, 4096)).unwrap();
+        let mut renderer = Renderer::new(Sequencer::new(song), RATE);
+        let mut pcm = vec![0; RATE as usize];
+        assert_eq!(renderer.render(&mut pcm), pcm.len());
+        let frequency = frequency(&pcm);
+        assert!((frequency - 8363.0 / 32.0).abs() < 2.0, "{frequency}");
+    }
+
+    #[test]
+    fn midi() {
+        // A period of 100 samples at 44100 Hz is 441 Hz on the root note.
+        let sample = Sample::new(square(100, 1), 44100)
+            .looping(0, 100)
+            .root_note(69);
+        let bank = SampleBank::new().program(0, Instrument::new(sample));
+        let song = Song::from_midi(&build_midi(), bank).unwrap();
+        assert_eq!(song.channels(), 16);
+        let mut renderer = Renderer::new(Sequencer::new(song), RATE);
+
+        let mut pcm = vec![0; RATE as usize];
+        assert_eq!(renderer.render(&mut pcm), pcm.len());
+        let frequency = frequency(&pcm);
+        assert!((frequency - 441.0).abs() < 2.0, "{frequency}");
+
+        // Two quarter notes at the default 120 BPM last a second.
+        let seconds = (RATE as usize / 2 + frames_to_end(&mut renderer)) as f32 / RATE as f32;
+        assert!((seconds - 1.0).abs() < 0.03, "{seconds}");
+    }
+}
@@ -0,0 +1,778 @@
+//! Playing the patterns of MOD and XM modules.
+
+use alloc::{vec, vec::Vec};
+
+use num_traits::Float;
+
+use super::{FADE_FULL, Instrument, Position, Voice};
+
+/// Note value of a key off.
+pub(crate) const KEY_OFF: u8 = 97;
+/// Highest note, B-7.
+pub(crate) const LAST_NOTE: u8 = 96;
+/// Note number of C-4.
+const C4: f32 = 49.0;
+/// Linear period of C-4.
+const LINEAR_C4: f32 = 4608.0;
+/// Amiga period of C-4, four times the ProTracker one.
+const AMIGA_C4: f32 = 1712.0;
+/// Range of ProTracker periods, four times the Amiga ones.
+const AMIGA_LIMITS: (f32, f32) = (113.0 * 4.0, 856.0 * 4.0);
+
+/// Effect numbers, `0x10` and up are the letters `G` to `Z` of XM.
+pub(crate) mod effect {
+    pub(crate) const ARPEGGIO: u8 = 0x0;
+    pub(crate) const PORTA_UP: u8 = 0x1;
+    pub(crate) const PORTA_DOWN: u8 = 0x2;
+    pub(crate) const TONE_PORTA: u8 = 0x3;
+    pub(crate) const VIBRATO: u8 = 0x4;
+    pub(crate) const TONE_PORTA_VOLUME_SLIDE: u8 = 0x5;
+    pub(crate) const VIBRATO_VOLUME_SLIDE: u8 = 0x6;
+    pub(crate) const TREMOLO: u8 = 0x7;
+    pub(crate) const SET_PANNING: u8 = 0x8;
+    pub(crate) const SAMPLE_OFFSET: u8 = 0x9;
+    pub(crate) const VOLUME_SLIDE: u8 = 0xA;
+    pub(crate) const POSITION_JUMP: u8 = 0xB;
+    pub(crate) const SET_VOLUME: u8 = 0xC;
+    pub(crate) const PATTERN_BREAK: u8 = 0xD;
+    pub(crate) const EXTENDED: u8 = 0xE;
+    pub(crate) const SET_SPEED: u8 = 0xF;
+    pub(crate) const GLOBAL_VOLUME: u8 = 0x10;
+    pub(crate) const GLOBAL_VOLUME_SLIDE: u8 = 0x11;
+    pub(crate) const KEY_OFF: u8 = 0x14;
+    pub(crate) const ENVELOPE_POSITION: u8 = 0x15;
+    pub(crate) const PANNING_SLIDE: u8 = 0x19;
+    pub(crate) const MULTI_RETRIGGER: u8 = 0x1B;
+    pub(crate) const EXTRA_FINE_PORTA: u8 = 0x21;
+}
+
+/// Half a period of the vibrato and tremolo sine, as in ProTracker.
+const SINE: [u8; 32] = [
+    0, 24, 49, 74, 97, 120, 141, 161, 180, 197, 212, 224, 235, 244, 250, 253, 255, 253, 250, 244,
+    235, 224, 212, 197, 180, 161, 141, 120, 97, 74, 49, 24,
+];
+
+/// One note of a pattern, as in XM.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Cell {
+    /// `1..=96` from C-0, [`KEY_OFF`], or `0` for none
+    pub(crate) note: u8,
+    /// `1` and up, or `0` for none
+    pub(crate) instrument: u8,
+    /// XM volume column
+    pub(crate) volume: u8,
+    pub(crate) effect: u8,
+    pub(crate) param: u8,
+}
+
+pub(crate) struct Pattern {
+    pub(crate) rows: usize,
+    /// `rows` rows of one cell per channel
+    pub(crate) cells: Vec<Cell>,
+}
+
+pub(crate) struct Module {
+    pub(crate) channels: usize,
+    /// Patterns to play, in order
+    pub(crate) orders: Vec<u8>,
+    /// Order to go back to after the last one
+    pub(crate) restart: usize,
+    pub(crate) patterns: Vec<Pattern>,
+    /// Initial ticks per row
+    pub(crate) speed: u8,
+    /// Initial tempo, a tick lasts 2.5 / `bpm` seconds
+    pub(crate) bpm: u8,
+    /// Whether periods are linear in pitch rather than Amiga periods
+    pub(crate) linear: bool,
+    /// Whether slides stop at the ProTracker period range
+    pub(crate) amiga_limits: bool,
+    /// Initial panning of each channel
+    pub(crate) panning: Vec<u8>,
+}
+
+impl Module {
+    fn pattern(&self, order: usize) -> Option<&Pattern> {
+        self.patterns.get(usize::from(*self.orders.get(order)?))
+    }
+
+    /// Number of rows played at `order`, patterns that do not exist play as 64 empty rows.
+    fn rows(&self, order: usize) -> usize {
+        self.pattern(order).map_or(64, |pattern| pattern.rows)
+    }
+
+    fn cell(&self, order: usize, row: usize, channel: usize) -> Cell {
+        self.pattern(order)
+            .and_then(|pattern| pattern.cells.get(row * self.channels + channel))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn note_period(&self, note: u8, pitch: f32) -> f32 {
+        let semitones = f32::from(note) - C4 + pitch;
+        if self.linear {
+            LINEAR_C4 - semitones * 64.0
+        } else {
+            AMIGA_C4 * (-semitones / 12.0).exp2()
+        }
+    }
+
+    fn frequency(&self, period: f32) -> f32 {
+        if self.linear {
+            super::BASE_RATE * ((LINEAR_C4 - period) / 768.0).exp2()
+        } else {
+            super::BASE_RATE * AMIGA_C4 / period
+        }
+    }
+
+    fn clamp_period(&self, period: f32) -> f32 {
+        if self.amiga_limits {
+            period.clamp(AMIGA_LIMITS.0, AMIGA_LIMITS.1)
+        } else {
+            period.clamp(1.0, 32000.0)
+        }
+    }
+}
+
+fn waveform(wave: u8, position: u8) -> i32 {
+    let position = position & 63;
+    let value = match wave & 3 {
+        1 => 255 - i32::from(position) * 8,
+        2 => 255,
+        _ => i32::from(SINE[usize::from(position & 31)]),
+    };
+    // The ramp already goes negative, the others are mirrored.
+    if wave & 3 != 1 && position >= 32 {
+        -value
+    } else {
+        value
+    }
+}
+
+#[derive(Default)]
+struct Channel {
+    instrument: Option<usize>,
+    sample: Option<usize>,
+    /// Period of the note, moved by slides
+    period: f32,
+    /// Period tone portamento slides towards
+    target: f32,
+    /// `0..=64`
+    volume: i32,
+    /// `0..=255`
+    panning: i32,
+    key_on: bool,
+    /// Volume left while fading out after a key off, up to [`FADE_FULL`]
+    fade: i32,
+    volume_envelope: u16,
+    panning_envelope: u16,
+    cell: Cell,
+    /// Cell waiting for a note delay
+    delayed: Option<Cell>,
+
+    porta_up: u8,
+    porta_down: u8,
+    fine_porta_up: u8,
+    fine_porta_down: u8,
+    extra_fine_porta_up: u8,
+    extra_fine_porta_down: u8,
+    tone_porta: u8,
+    vibrato_speed: u8,
+    vibrato_depth: u8,
+    vibrato_position: u8,
+    vibrato_wave: u8,
+    tremolo_speed: u8,
+    tremolo_depth: u8,
+    tremolo_position: u8,
+    tremolo_wave: u8,
+    volume_slide: u8,
+    fine_volume_up: u8,
+    fine_volume_down: u8,
+    global_volume_slide: u8,
+    panning_slide: u8,
+    offset: u8,
+    retrigger: u8,
+    retrigger_ticks: u8,
+    loop_row: usize,
+    loop_count: u8,
+
+    /// Vibrato applied on this tick only
+    period_offset: f32,
+    /// Tremolo applied on this tick only
+    volume_offset: i32,
+    /// Arpeggio applied on this tick only, in semitones
+    arpeggio: u8,
+    /// Sample offset to start from on this tick
+    trigger: Option<u32>,
+}
+
+impl Channel {
+    fn slide_volume(&mut self, param: u8) {
+        let (up, down) = (i32::from(param >> 4), i32::from(param & 0xF));
+        self.volume = if up > 0 {
+            self.volume + up
+        } else {
+            self.volume - down
+        }
+        .clamp(0, 64);
+    }
+
+    fn slide_period(&mut self, module: &Module, delta: f32) {
+        self.period = module.clamp_period(self.period + delta);
+    }
+
+    fn slide_to_target(&mut self) {
+        let speed = f32::from(self.tone_porta) * 4.0;
+        if self.period < self.target {
+            self.period = (self.period + speed).min(self.target);
+        } else {
+            self.period = (self.period - speed).max(self.target);
+        }
+    }
+
+    fn vibrato(&mut self) {
+        let value = waveform(self.vibrato_wave, self.vibrato_position);
+        self.period_offset = (value * i32::from(self.vibrato_depth)) as f32 / 32.0;
+        self.vibrato_position = self.vibrato_position.wrapping_add(self.vibrato_speed);
+    }
+
+    fn tremolo(&mut self) {
+        let value = waveform(self.tremolo_wave, self.tremolo_position);
+        self.volume_offset = value * i32::from(self.tremolo_depth) / 64;
+        self.tremolo_position = self.tremolo_position.wrapping_add(self.tremolo_speed);
+    }
+
+    fn key_off(&mut self, instruments: &[Instrument]) {
+        self.key_on = false;
+        let instrument = self.instrument.and_then(|index| instruments.get(index));
+        if instrument.is_none_or(|instrument| instrument.volume_envelope.is_none()) {
+            self.volume = 0;
+        }
+    }
+}
+
+/// Plays the patterns of a [`Module`].
+pub(crate) struct Tracker {
+    channels: Vec<Channel>,
+    order: usize,
+    row: usize,
+    /// Ticks since the start of the row
+    tick: u32,
+    speed: u32,
+    bpm: u32,
+    /// `0..=64`
+    global_volume: i32,
+    /// Rows left to repeat for a pattern delay
+    pattern_delay: u32,
+    /// Order a position jump goes to
+    jump: Option<usize>,
+    /// Row a pattern break goes to
+    break_row: Option<usize>,
+    /// Row a pattern loop goes back to
+    loop_row: Option<usize>,
+    visited: Vec<bool>,
+    ended: bool,
+    /// Position of the last tick played
+    played: Position,
+}
+
+impl Tracker {
+    pub(crate) fn new(module: &Module) -> Self {
+        let channels = module
+            .panning
+            .iter()
+            .map(|&panning| Channel {
+                panning: i32::from(panning),
+                fade: FADE_FULL,
+                ..Channel::default()
+            })
+            .collect();
+        let mut visited = vec![false; module.orders.len()];
+        if let Some(first) = visited.first_mut() {
+            *first = true;
+        }
+        Self {
+            channels,
+            order: 0,
+            row: 0,
+            tick: 0,
+            speed: u32::from(module.speed.max(1)),
+            bpm: u32::from(module.bpm.max(32)),
+            global_volume: 64,
+            pattern_delay: 0,
+            jump: None,
+            break_row: None,
+            loop_row: None,
+            visited,
+            ended: module.orders.is_empty(),
+            played: Position::Tracker {
+                order: 0,
+                pattern: module.orders.first().copied().unwrap_or(0).into(),
+                row: 0,
+                tick: 0,
+            },
+        }
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.played
+    }
+
+    pub(crate) fn tick_nanos(&self) -> u64 {
+        2_500_000_000 / u64::from(self.bpm)
+    }
+
+    /// Play one tick, returning `false` once the song ended.
+    pub(crate) fn tick(
+        &mut self,
+        module: &Module,
+        instruments: &[Instrument],
+        voices: &mut [Voice],
+        looping: bool,
+    ) -> bool {
+        if self.ended {
+            return false;
+        }
+
+        let tick = self.tick % self.speed;
+        for index in 0..self.channels.len() {
+            let channel = &mut self.channels[index];
+            channel.period_offset = 0.0;
+            channel.volume_offset = 0;
+            channel.arpeggio = 0;
+            if self.tick == 0 {
+                let cell = module.cell(self.order, self.row, index);
+                self.start_row(module, instruments, index, cell);
+            } else {
+                self.continue_row(module, instruments, index, tick);
+            }
+        }
+
+        for (index, (channel, voice)) in self.channels.iter_mut().zip(voices).enumerate() {
+            *voice = Self::output(module, instruments, channel, self.global_volume);
+            voice.channel = index as u8;
+        }
+
+        self.played = Position::Tracker {
+            order: self.order,
+            pattern: module.orders[self.order].into(),
+            row: self.row,
+            tick: self.tick,
+        };
+        self.advance(module, looping);
+        true
+    }
+
+    /// Process the cell of `index` on the first tick of a row.
+    fn start_row(&mut self, module: &Module, instruments: &[Instrument], index: usize, cell: Cell) {
+        let channel = &mut self.channels[index];
+        channel.cell = cell;
+        channel.delayed = None;
+        let (x, y) = (cell.param >> 4, cell.param & 0xF);
+        if cell.effect == effect::EXTENDED && x == 0xD && y > 0 {
+            channel.delayed = Some(cell);
+        } else {
+            Self::start_note(module, instruments, channel, cell);
+        }
+
+        match cell.effect {
+            effect::PORTA_UP if cell.param > 0 => channel.porta_up = cell.param,
+            effect::PORTA_DOWN if cell.param > 0 => channel.porta_down = cell.param,
+            effect::TONE_PORTA if cell.param > 0 => channel.tone_porta = cell.param,
+            effect::VIBRATO => {
+                if x > 0 {
+                    channel.vibrato_speed = x;
+                }
+                if y > 0 {
+                    channel.vibrato_depth = y;
+                }
+            }
+            effect::TREMOLO => {
+                if x > 0 {
+                    channel.tremolo_speed = x;
+                }
+                if y > 0 {
+                    channel.tremolo_depth = y;
+                }
+            }
+            effect::TONE_PORTA_VOLUME_SLIDE
+            | effect::VIBRATO_VOLUME_SLIDE
+            | effect::VOLUME_SLIDE
+                if cell.param > 0 =>
+            {
+                channel.volume_slide = cell.param;
+            }
+            effect::SET_PANNING => channel.panning = i32::from(cell.param),
+            effect::SAMPLE_OFFSET => {
+                if cell.param > 0 {
+                    channel.offset = cell.param;
+                }
+                if channel.trigger.is_some() {
+                    channel.trigger = Some(u32::from(channel.offset) * 256);
+                }
+            }
+            effect::POSITION_JUMP => {
+                self.jump = Some(usize::from(cell.param));
+                self.break_row.get_or_insert(0);
+            }
+            effect::SET_VOLUME => channel.volume = i32::from(cell.param.min(64)),
+            effect::PATTERN_BREAK => self.break_row = Some(usize::from(x * 10 + y)),
+            effect::EXTENDED => match x {
+                0x1 => {
+                    if y > 0 {
+                        channel.fine_porta_up = y;
+                    }
+                    let delta = f32::from(channel.fine_porta_up) * 4.0;
+                    channel.slide_period(module, -delta);
+                }
+                0x2 => {
+                    if y > 0 {
+                        channel.fine_porta_down = y;
+                    }
+                    let delta = f32::from(channel.fine_porta_down) * 4.0;
+                    channel.slide_period(module, delta);
+                }
+                0x4 => channel.vibrato_wave = y,
+                0x6 if y == 0 => channel.loop_row = self.row,
+                0x6 => {
+                    if channel.loop_count == 0 {
+                        channel.loop_count = y;
+                        self.loop_row = Some(channel.loop_row);
+                    } else {
+                        channel.loop_count -= 1;
+                        if channel.loop_count > 0 {
+                            self.loop_row = Some(channel.loop_row);
+                        }
+                    }
+                }
+                0x7 => channel.tremolo_wave = y,
+                0x8 => channel.panning = i32::from(y) * 17,
+                0xA => {
+                    if y > 0 {
+                        channel.fine_volume_up = y;
+                    }
+                    channel.volume = (channel.volume + i32::from(channel.fine_volume_up)).min(64);
+                }
+                0xB => {
+                    if y > 0 {
+                        channel.fine_volume_down = y;
+                    }
+                    channel.volume = (channel.volume - i32::from(channel.fine_volume_down)).max(0);
+                }
+                0xC if y == 0 => channel.volume = 0,
+                0xE if self.pattern_delay == 0 => self.pattern_delay = u32::from(y),
+                _ => {}
+            },
+            effect::SET_SPEED => match cell.param {
+                0 => {}
+                1..=31 => self.speed = u32::from(cell.param),
+                _ => self.bpm = u32::from(cell.param),
+            },
+            effect::GLOBAL_VOLUME => self.global_volume = i32::from(cell.param.min(64)),
+            effect::GLOBAL_VOLUME_SLIDE if cell.param > 0 => {
+                channel.global_volume_slide = cell.param
+            }
+            effect::KEY_OFF if cell.param == 0 => channel.key_off(instruments),
+            effect::ENVELOPE_POSITION => {
+                channel.volume_envelope = u16::from(cell.param);
+                channel.panning_envelope = u16::from(cell.param);
+            }
+            effect::PANNING_SLIDE if cell.param > 0 => channel.panning_slide = cell.param,
+            effect::MULTI_RETRIGGER => {
+                if x > 0 || y > 0 {
+                    channel.retrigger = (if x > 0 { x } else { channel.retrigger >> 4 }) << 4
+                        | if y > 0 { y } else { channel.retrigger & 0xF };
+                }
+            }
+            effect::EXTRA_FINE_PORTA => match x {
+                1 => {
+                    if y > 0 {
+                        channel.extra_fine_porta_up = y;
+                    }
+                    let delta = f32::from(channel.extra_fine_porta_up);
+                    channel.slide_period(module, -delta);
+                }
+                2 => {
+                    if y > 0 {
+                        channel.extra_fine_porta_down = y;
+                    }
+                    let delta = f32::from(channel.extra_fine_porta_down);
+                    channel.slide_period(module, delta);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        let (kind, value) = (cell.volume >> 4, cell.volume & 0xF);
+        match kind {
+            0x8 => channel.volume = (channel.volume - i32::from(value)).max(0),
+            0x9 => channel.volume = (channel.volume + i32::from(value)).min(64),
+            0xA if value > 0 => channel.vibrato_speed = value,
+            0xB if value > 0 => channel.vibrato_depth = value,
+            0xF if value > 0 => channel.tone_porta = value << 4,
+            _ => {}
+        }
+    }
+
+    /// Start the note and instrument of `cell`, along with the volume column settings.
+    fn start_note(module: &Module, instruments: &[Instrument], channel: &mut Channel, cell: Cell) {
+        let porta = matches!(
+            cell.effect,
+            effect::TONE_PORTA | effect::TONE_PORTA_VOLUME_SLIDE
+        ) || cell.volume >> 4 == 0xF;
+
+        if cell.instrument > 0 {
+            channel.instrument = Some(usize::from(cell.instrument - 1));
+        }
+        let instrument = channel.instrument.and_then(|index| instruments.get(index));
+
+        if cell.note == KEY_OFF {
+            channel.key_off(instruments);
+        } else if (1..=LAST_NOTE).contains(&cell.note) {
+            match instrument.and_then(|instrument| instrument.sample_for(cell.note + 11)) {
+                Some((_, sample)) if porta && channel.sample.is_some() => {
+                    channel.target = module.note_period(cell.note, sample.pitch);
+                }
+                Some((index, sample)) => {
+                    channel.sample = Some(index);
+                    channel.period = module.note_period(cell.note, sample.pitch);
+                    channel.target = channel.period;
+                    channel.trigger = Some(0);
+                    if channel.vibrato_wave < 4 {
+                        channel.vibrato_position = 0;
+                    }
+                    if channel.tremolo_wave < 4 {
+                        channel.tremolo_position = 0;
+                    }
+                }
+                None => channel.sample = None,
+            }
+        }
+
+        if cell.instrument > 0 && cell.note != KEY_OFF {
+            let sample = instrument
+                .zip(channel.sample)
+                .and_then(|(instrument, index)| instrument.samples.get(index));
+            if let Some(sample) = sample {
+                channel.volume = i32::from(sample.volume.min(64));
+                if let Some(panning) = sample.panning {
+                    channel.panning = i32::from(panning);
+                }
+            }
+            channel.key_on = true;
+            channel.fade = FADE_FULL;
+            channel.volume_envelope = 0;
+            channel.panning_envelope = 0;
+            channel.retrigger_ticks = 0;
+        }
+
+        match cell.volume {
+            0x10..=0x50 => channel.volume = i32::from(cell.volume - 0x10),
+            0xC0..=0xCF => channel.panning = i32::from(cell.volume & 0xF) << 4,
+            _ => {}
+        }
+    }
+
+    /// Process the cell of `index` on the other ticks of a row.
+    fn continue_row(
+        &mut self,
+        module: &Module,
+        instruments: &[Instrument],
+        index: usize,
+        tick: u32,
+    ) {
+        let channel = &mut self.channels[index];
+        let cell = channel.cell;
+        let (x, y) = (cell.param >> 4, cell.param & 0xF);
+        match cell.effect {
+            effect::ARPEGGIO if cell.param > 0 => {
+                channel.arpeggio = [0, x, y][tick as usize % 3];
+            }
+            effect::PORTA_UP => {
+                let delta = f32::from(channel.porta_up) * 4.0;
+                channel.slide_period(module, -delta);
+            }
+            effect::PORTA_DOWN => {
+                let delta = f32::from(channel.porta_down) * 4.0;
+                channel.slide_period(module, delta);
+            }
+            effect::TONE_PORTA => channel.slide_to_target(),
+            effect::VIBRATO => channel.vibrato(),
+            effect::TONE_PORTA_VOLUME_SLIDE => {
+                channel.slide_to_target();
+                channel.slide_volume(channel.volume_slide);
+            }
+            effect::VIBRATO_VOLUME_SLIDE => {
+                channel.vibrato();
+                channel.slide_volume(channel.volume_slide);
+            }
+            effect::TREMOLO => channel.tremolo(),
+            effect::VOLUME_SLIDE => channel.slide_volume(channel.volume_slide),
+            effect::EXTENDED => match x {
+                0x9 if y > 0 && tick.is_multiple_of(u32::from(y)) => channel.trigger = Some(0),
+                0xC if tick == u32::from(y) => channel.volume = 0,
+                0xD if tick == u32::from(y) => {
+                    if let Some(cell) = channel.delayed.take() {
+                        Self::start_note(module, instruments, channel, cell);
+                    }
+                }
+                _ => {}
+            },
+            effect::GLOBAL_VOLUME_SLIDE => {
+                let param = channel.global_volume_slide;
+                let (up, down) = (i32::from(param >> 4), i32::from(param & 0xF));
+                let volume = if up > 0 {
+                    self.global_volume + up
+                } else {
+                    self.global_volume - down
+                };
+                self.global_volume = volume.clamp(0, 64);
+            }
+            effect::KEY_OFF if tick == u32::from(cell.param) => channel.key_off(instruments),
+            effect::PANNING_SLIDE => {
+                let param = channel.panning_slide;
+                let (right, left) = (i32::from(param >> 4), i32::from(param & 0xF));
+                let panning = if right > 0 {
+                    channel.panning + right
+                } else {
+                    channel.panning - left
+                };
+                channel.panning = panning.clamp(0, 255);
+            }
+            effect::MULTI_RETRIGGER => {
+                let interval = channel.retrigger & 0xF;
+                channel.retrigger_ticks += 1;
+                if interval > 0 && channel.retrigger_ticks >= interval {
+                    channel.retrigger_ticks = 0;
+                    channel.trigger = Some(0);
+                    let volume = channel.volume;
+                    channel.volume = match channel.retrigger >> 4 {
+                        0x1..=0x5 => volume - (1 << ((channel.retrigger >> 4) - 1)),
+                        0x6 => volume * 2 / 3,
+                        0x7 => volume / 2,
+                        0x9..=0xD => volume + (1 << ((channel.retrigger >> 4) - 9)),
+                        0xE => volume * 3 / 2,
+                        0xF => volume * 2,
+                        _ => volume,
+                    }
+                    .clamp(0, 64);
+                }
+            }
+            _ => {}
+        }
+
+        let value = cell.volume & 0xF;
+        match cell.volume >> 4 {
+            0x6 => channel.volume = (channel.volume - i32::from(value)).max(0),
+            0x7 => channel.volume = (channel.volume + i32::from(value)).min(64),
+            0xB => channel.vibrato(),
+            0xD => channel.panning = (channel.panning - i32::from(value)).max(0),
+            0xE => channel.panning = (channel.panning + i32::from(value)).min(255),
+            0xF => channel.slide_to_target(),
+            _ => {}
+        }
+    }
+
+    /// Run the envelopes of `channel` and work out what its voice plays.
+    fn output(
+        module: &Module,
+        instruments: &[Instrument],
+        channel: &mut Channel,
+        global_volume: i32,
+    ) -> Voice {
+        let start = channel.trigger.take();
+        let Some((instrument_index, instrument)) = channel
+            .instrument
+            .and_then(|index| instruments.get(index).map(|instrument| (index, instrument)))
+        else {
+            return Voice::default();
+        };
+        let Some(sample) = channel.sample else {
+            return Voice::default();
+        };
+
+        let mut envelope_volume = 1.0;
+        if let Some(envelope) = &instrument.volume_envelope {
+            envelope_volume = envelope.value(channel.volume_envelope);
+            channel.volume_envelope = envelope.advance(channel.volume_envelope, channel.key_on);
+            if !channel.key_on {
+                channel.fade = (channel.fade - i32::from(instrument.fadeout)).max(0);
+            }
+        }
+        let mut panning = channel.panning as f32;
+        if let Some(envelope) = &instrument.panning_envelope {
+            let value = envelope.value(channel.panning_envelope) * 64.0 - 32.0;
+            panning += value * (128.0 - (panning - 128.0).abs()) / 32.0;
+            channel.panning_envelope = envelope.advance(channel.panning_envelope, channel.key_on);
+        }
+
+        let volume = (channel.volume + channel.volume_offset).clamp(0, 64) as f32 / 64.0;
+        let fade = channel.fade as f32 / FADE_FULL as f32;
+        let period = module.clamp_period(channel.period + channel.period_offset);
+        let arpeggio = (f32::from(channel.arpeggio) / 12.0).exp2();
+        Voice {
+            sample: Some((instrument_index as u16, sample as u16)),
+            start,
+            frequency: module.frequency(period) * arpeggio,
+            volume: volume * envelope_volume * fade * global_volume as f32 / 64.0,
+            pan: ((panning.clamp(0.0, 255.0) - 128.0) / 128.0).max(-1.0),
+            channel: 0,
+        }
+    }
+
+    /// Move on to the next tick, and row if this one is done.
+    fn advance(&mut self, module: &Module, looping: bool) {
+        self.tick += 1;
+        if self.tick < self.speed * (1 + self.pattern_delay) {
+            return;
+        }
+        self.tick = 0;
+        self.pattern_delay = 0;
+
+        let mut order = self.order;
+        let mut row = self.row + 1;
+        let mut looped = false;
+        if let Some(loop_row) = self.loop_row.take() {
+            row = loop_row;
+            self.jump = None;
+            self.break_row = None;
+        } else if let Some(break_row) = self.break_row.take() {
+            order = self.jump.take().unwrap_or(self.order + 1);
+            row = break_row;
+            looped = order < self.order || (order == self.order && row <= self.row);
+            if row >= module.rows(order) {
+                row = 0;
+            }
+        } else if row >= module.rows(order) {
+            order += 1;
+            row = 0;
+        }
+
+        if order >= module.orders.len() {
+            order = if module.restart < module.orders.len() {
+                module.restart
+            } else {
+                0
+            };
+            looped = true;
+        }
+        if order != self.order {
+            for channel in &mut self.channels {
+                channel.loop_row = 0;
+                channel.loop_count = 0;
+            }
+        }
+        if order != self.order || looped {
+            looped |= self.visited[order];
+            if looped {
+                if !looping {
+                    self.ended = true;
+                }
+                self.visited.fill(false);
+            }
+            self.visited[order] = true;
+        }
+        self.order = order;
+        self.row = row;
+    }
+}
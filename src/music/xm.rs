@@ -0,0 +1,398 @@
+//! FastTracker II XM files.
+
+use alloc::{vec, vec::Vec};
+
+use super::{
+    Envelope, Instrument, Kind, Sample, Song,
+    tracker::{Cell, KEY_OFF, Module, Pattern},
+};
+use crate::error::FormatError;
+
+const MAGIC: &[u8] = b"Extended Module: ";
+/// Offset of the header size, which counts from there.
+const HEADER: usize = 60;
+/// Reserved byte of a sample header marking ModPlug ADPCM samples.
+const ADPCM: u8 = 0xAD;
+/// Sample type flag of 16-bit samples.
+const SIXTEEN_BIT: u8 = 0x10;
+/// Envelope flags.
+const ENVELOPE_ON: u8 = 1;
+const ENVELOPE_SUSTAIN: u8 = 2;
+const ENVELOPE_LOOP: u8 = 4;
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, FormatError> {
+    let bytes = data
+        .get(offset..)
+        .and_then(|data| data.get(..2))
+        .ok_or(FormatError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, FormatError> {
+    let bytes = data
+        .get(offset..)
+        .and_then(|data| data.get(..4))
+        .ok_or(FormatError::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, FormatError> {
+    data.get(offset).copied().ok_or(FormatError::Truncated)
+}
+
+/// Add sizes and offsets read from the file, which may be anything.
+fn add(a: usize, b: usize) -> Result<usize, FormatError> {
+    a.checked_add(b).ok_or(FormatError::Invalid)
+}
+
+pub(super) fn parse(file: &[u8]) -> Result<Song, FormatError> {
+    if file.len() < HEADER + 20 {
+        return Err(FormatError::Truncated);
+    }
+    if !file.starts_with(MAGIC) {
+        return Err(FormatError::InvalidMagic);
+    }
+    if read_u16(file, 58)? < 0x0104 {
+        return Err(FormatError::Unsupported);
+    }
+
+    let header_size = read_u32(file, HEADER)? as usize;
+    let length = usize::from(read_u16(file, 64)?).min(256);
+    let restart = usize::from(read_u16(file, 66)?);
+    let channels = usize::from(read_u16(file, 68)?);
+    let pattern_count = usize::from(read_u16(file, 70)?);
+    let instrument_count = usize::from(read_u16(file, 72)?);
+    let flags = read_u16(file, 74)?;
+    let speed = read_u16(file, 76)?;
+    let bpm = read_u16(file, 78)?;
+    if !(1..=32).contains(&channels) || pattern_count > 256 || instrument_count > 128 {
+        return Err(FormatError::Invalid);
+    }
+    let orders = file
+        .get(80..80 + length)
+        .ok_or(FormatError::Truncated)?
+        .to_vec();
+
+    let mut offset = add(HEADER, header_size)?;
+    let mut patterns = Vec::with_capacity(pattern_count);
+    for _ in 0..pattern_count {
+        let (pattern, size) = parse_pattern(file, offset, channels)?;
+        patterns.push(pattern);
+        offset = add(offset, size)?;
+    }
+
+    let mut instruments = Vec::with_capacity(instrument_count);
+    for _ in 0..instrument_count {
+        let (instrument, size) = parse_instrument(file, offset)?;
+        instruments.push(instrument);
+        offset = add(offset, size)?;
+    }
+
+    let module = Module {
+        channels,
+        orders,
+        restart,
+        patterns,
+        speed: speed.clamp(1, 31) as u8,
+        bpm: bpm.clamp(32, 255) as u8,
+        linear: flags & 1 != 0,
+        amiga_limits: false,
+        panning: vec![0x80; channels],
+    };
+    Ok(Song::new(&file[17..37], instruments, Kind::Tracker(module)))
+}
+
+/// Parse the pattern at `offset`, returning it with its size in the file.
+fn parse_pattern(
+    file: &[u8],
+    offset: usize,
+    channels: usize,
+) -> Result<(Pattern, usize), FormatError> {
+    let header = read_u32(file, offset)? as usize;
+    let rows = usize::from(read_u16(file, offset + 5)?);
+    let packed = usize::from(read_u16(file, offset + 7)?);
+    let start = add(offset, header)?;
+    let mut data = file
+        .get(start..add(start, packed)?)
+        .ok_or(FormatError::Truncated)?
+        .iter()
+        .copied();
+
+    let rows = if rows == 0 { 64 } else { rows };
+    let mut cells = vec![Cell::default(); rows * channels];
+    if packed > 0 {
+        for cell in &mut cells {
+            let Some(first) = data.next() else {
+                break;
+            };
+            // Packed cells start with a byte saying which fields follow.
+            let fields = if first & 0x80 != 0 { first } else { 0x1E };
+            let mut field = |bit: u8| {
+                if fields & bit != 0 {
+                    data.next().unwrap_or(0)
+                } else {
+                    0
+                }
+            };
+            let note = if first & 0x80 != 0 { field(1) } else { first };
+            *cell = Cell {
+                note: if note > KEY_OFF { 0 } else { note },
+                instrument: field(2),
+                volume: field(4),
+                effect: field(8),
+                param: field(16),
+            };
+        }
+    }
+    Ok((Pattern { rows, cells }, add(header, packed)?))
+}
+
+fn parse_envelope(
+    header: &[u8],
+    points: usize,
+    count: u8,
+    sustain: u8,
+    range: (u8, u8),
+    flags: u8,
+) -> Option<Envelope> {
+    if flags & ENVELOPE_ON == 0 || count == 0 {
+        return None;
+    }
+    let points: Vec<(u16, u8)> = header
+        .get(points..points + 48)?
+        .chunks_exact(4)
+        .take(usize::from(count.min(12)))
+        .map(|point| {
+            let tick = u16::from_le_bytes([point[0], point[1]]);
+            let value = u16::from_le_bytes([point[2], point[3]]).min(64) as u8;
+            (tick, value)
+        })
+        .collect();
+    let tick = |index: u8| points.get(usize::from(index)).map(|&(tick, _)| tick);
+    Some(Envelope {
+        sustain: if flags & ENVELOPE_SUSTAIN != 0 {
+            tick(sustain)
+        } else {
+            None
+        },
+        loop_range: if flags & ENVELOPE_LOOP != 0 {
+            tick(range.0).zip(tick(range.1))
+        } else {
+            None
+        },
+        points,
+    })
+}
+
+/// Parse the instrument at `offset` and its samples, returning it with its size in the file.
+fn parse_instrument(file: &[u8], offset: usize) -> Result<(Instrument, usize), FormatError> {
+    let header_size = read_u32(file, offset)? as usize;
+    let sample_count = usize::from(read_u16(file, offset + 27)?);
+    let mut instrument = Instrument {
+        samples: Vec::with_capacity(sample_count),
+        note_map: [0; 128],
+        volume_envelope: None,
+        panning_envelope: None,
+        fadeout: 0,
+    };
+    if sample_count == 0 {
+        return Ok((instrument, header_size));
+    }
+
+    let header = file
+        .get(offset..offset + 243)
+        .ok_or(FormatError::Truncated)?;
+    let sample_header_size = read_u32(header, 29)? as usize;
+    // XM notes 1 to 96 are MIDI notes 12 to 107, the notes outside use the nearest sample.
+    for (note, sample) in instrument.note_map.iter_mut().enumerate() {
+        let index = note.clamp(12, 107) - 12;
+        *sample = header[33 + index];
+    }
+    instrument.volume_envelope = parse_envelope(
+        header,
+        129,
+        header[225],
+        header[227],
+        (header[228], header[229]),
+        header[233],
+    );
+    instrument.panning_envelope = parse_envelope(
+        header,
+        177,
+        header[226],
+        header[230],
+        (header[231], header[232]),
+        header[234],
+    );
+    instrument.fadeout = read_u16(header, 239)?;
+
+    let mut headers = add(offset, header_size)?;
+    let mut data = add(
+        headers,
+        sample_count
+            .checked_mul(sample_header_size)
+            .ok_or(FormatError::Invalid)?,
+    )?;
+    for _ in 0..sample_count {
+        let length = read_u32(file, headers)? as usize;
+        let mut loop_start = read_u32(file, headers + 4)?;
+        let mut loop_length = read_u32(file, headers + 8)?;
+        let volume = read_u8(file, headers + 12)?.min(64);
+        let finetune = read_u8(file, headers + 13)? as i8;
+        let kind = read_u8(file, headers + 14)?;
+        let panning = read_u8(file, headers + 15)?;
+        let relative_note = read_u8(file, headers + 16)? as i8;
+        if read_u8(file, headers + 17)? == ADPCM {
+            return Err(FormatError::Unsupported);
+        }
+
+        let bytes = file
+            .get(data..add(data, length)?)
+            .ok_or(FormatError::Truncated)?;
+        // Samples are stored as differences from the previous one.
+        let samples: Vec<i16> = if kind & SIXTEEN_BIT != 0 {
+            loop_start /= 2;
+            loop_length /= 2;
+            let mut last = 0i16;
+            bytes
+                .chunks_exact(2)
+                .map(|pair| {
+                    last = last.wrapping_add(i16::from_le_bytes([pair[0], pair[1]]));
+                    last
+                })
+                .collect()
+        } else {
+            let mut last = 0i8;
+            bytes
+                .iter()
+                .map(|&delta| {
+                    last = last.wrapping_add(delta as i8);
+                    i16::from(last) << 8
+                })
+                .collect()
+        };
+
+        let mut sample = Sample {
+            data: samples,
+            loop_range: None,
+            volume,
+            panning: Some(panning),
+            pitch: f32::from(relative_note) + f32::from(finetune) / 128.0,
+        };
+        let loop_end = loop_start
+            .checked_add(loop_length)
+            .ok_or(FormatError::Invalid)?
+            .min(sample.data.len() as u32);
+        match kind & 3 {
+            1 => sample = sample.looping(loop_start, loop_end),
+            2 if loop_end > loop_start.saturating_add(2) => {
+                sample.unroll_ping_pong(loop_start, loop_end)
+            }
+            _ => {}
+        }
+        instrument.samples.push(sample);
+
+        headers = add(headers, sample_header_size)?;
+        data += length;
+    }
+    Ok((instrument, data - offset))
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// A square wave with `count` periods of `period` samples.
+    pub(in crate::music) fn square(period: usize, count: usize) -> Vec<i16> {
+        (0..period * count)
+            .map(|i| {
+                if i % period < period / 2 {
+                    10000
+                } else {
+                    -10000
+                }
+            })
+            .collect()
+    }
+
+    /// A two channel, 16 row song playing a looping 16-bit square wave with 32 samples per
+    /// period on C-4, with the loop given in bytes.
+    pub(in crate::music) fn build(loop_start: u32, loop_length: u32) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend(MAGIC);
+        let mut name = [b' '; 20];
+        name[..2].copy_from_slice(b"xm");
+        file.extend(name);
+        file.push(0x1A);
+        file.extend([0; 20]);
+        file.extend(0x0104u16.to_le_bytes());
+        file.extend(276u32.to_le_bytes());
+        // Length, restart, channels, patterns, instruments, linear periods, speed, BPM
+        for value in [1u16, 0, 2, 1, 1, 1, 6, 125] {
+            file.extend(value.to_le_bytes());
+        }
+        file.extend([0; 256]);
+
+        let mut packed = vec![0x83, 49, 1];
+        packed.extend([0x80; 16 * 2 - 1]);
+        file.extend(9u32.to_le_bytes());
+        file.push(0);
+        file.extend(16u16.to_le_bytes());
+        file.extend((packed.len() as u16).to_le_bytes());
+        file.extend(packed);
+
+        let mut instrument = [0; 263];
+        instrument[..4].copy_from_slice(&263u32.to_le_bytes());
+        instrument[27..29].copy_from_slice(&1u16.to_le_bytes());
+        instrument[29..33].copy_from_slice(&40u32.to_le_bytes());
+        file.extend(instrument);
+
+        let data = square(32, 64);
+        let mut header = [0; 40];
+        header[..4].copy_from_slice(&((data.len() * 2) as u32).to_le_bytes());
+        header[4..8].copy_from_slice(&loop_start.to_le_bytes());
+        header[8..12].copy_from_slice(&loop_length.to_le_bytes());
+        header[12] = 64;
+        header[14] = SIXTEEN_BIT | 1;
+        header[15] = 0x80;
+        file.extend(header);
+        let mut last = 0i16;
+        for &sample in &data {
+            file.extend(sample.wrapping_sub(last).to_le_bytes());
+            last = sample;
+        }
+        file
+    }
+
+    #[test]
+    fn parse() {
+        let song = super::parse(&build(0, 4096)).unwrap();
+        assert_eq!(song.name(), "xm");
+        assert_eq!(song.channels(), 2);
+        let sample = song.sample((0, 0)).unwrap();
+        assert_eq!(sample.len(), 2048);
+        assert_eq!(sample.loop_range, Some((0, 2048)));
+    }
+
+    #[test]
+    fn overflow() {
+        // Loops of 16-bit samples are halved and can't overflow, 8-bit ones can.
+        let mut file = build(0xFFFF_FF00, 0x200);
+        let kind = file.len() - 4096 - 40 + 14;
+        file[kind] = 1;
+        assert_eq!(super::parse(&file).err(), Some(FormatError::Invalid));
+
+        // A header size pointing past the end of the address space
+        let mut file = build(0, 4096);
+        file[HEADER..HEADER + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(super::parse(&file).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let file = build(0, 4096);
+        for len in [0, HEADER + 20, 336, 400, file.len() - 1] {
+            assert!(super::parse(&file[..len]).is_err(), "{len}");
+        }
+    }
+}
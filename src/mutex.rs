@@ -9,7 +9,7 @@ use crate::ffi;
 /// An enumeration of possible errors associated with a `LockResult` which can
 /// occur while trying to call a method on a Mutex.
 #[non_exhaustive]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(i32)]
 pub enum LockError {
     /// The lock could not be acquired at this time because the operation would
@@ -19,6 +19,17 @@ pub enum LockError {
     Unknown = -1,
 }
 
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WouldBlock => write!(f, "the lock could not be acquired without blocking"),
+            Self::Unknown => write!(f, "the lock operation failed"),
+        }
+    }
+}
+
+impl core::error::Error for LockError {}
+
 /// A type alias for the result of a lock method which can error.
 pub type LockResult<T> = Result<T, LockError>;
 
@@ -4,21 +4,34 @@
 
 #![allow(clippy::bad_bit_mask)]
 
-use crate::{OgcError, Result, ffi};
-use alloc::{
-    boxed::Box,
-    format,
-    string::{String, ToString},
-    vec::Vec,
-};
+use crate::{OgcError, Result, error::Errno, ffi};
+use alloc::{boxed::Box, ffi::CString, string::String, vec::Vec};
 
 use bitflags::bitflags;
 use core::{
     ffi::{CStr, c_void},
+    net::{Ipv4Addr, SocketAddrV4},
     slice,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::Duration,
 };
 use num_enum::IntoPrimitive;
 
+mod addr;
+mod config;
+pub mod dns;
+pub mod http;
+mod poll;
+mod tcp;
+mod udp;
+
+pub use crate::ios::net::LinkStatus;
+pub use addr::{ToSocketAddrs, lookup_host};
+pub use config::{Connecting, InitStatus, InterfaceConfig, StaticIp};
+pub use poll::{Event, Events, Interest, Poll, Token};
+pub use tcp::{Incoming, TcpListener, TcpStream};
+pub use udp::UdpSocket;
+
 bitflags! {
     /// Optional flags for sockets.
     pub struct SocketFlags: i32 {
@@ -207,24 +220,30 @@ impl From<SocketAddress> for *mut ffi::sockaddr {
 pub struct HostInformation {
     /// The official name of the host.
     pub name: String,
-    /// A NULL-terminated array of alternate names.
+    /// Alternate names of the host.
     pub aliases: Vec<String>,
     /// The type of address being returned.
     pub address_type: core::ffi::c_short,
     /// The length, in bytes, of each address.
     pub length: core::ffi::c_short,
-    /// A NULL-terminated list of addresses for the host.
-    pub address_list: Vec<String>,
+    /// The addresses of the host.
+    pub address_list: Vec<Ipv4Addr>,
+}
+
+/// Copy `s` into a NUL-terminated string for libogc.
+fn to_c_string(s: &str) -> Result<CString> {
+    CString::new(s).map_err(|_| OgcError::Network(Errno::EINVAL))
 }
 
 /// This function converts the specified string in the Internet standard dot notation
 /// to an integer value suitable for use as an Internet address.
 /// The converted address will be in Network Byte Order.
 pub fn dot_to_nbo(dot: &str) -> Result<IPV4Address> {
-    let r = unsafe { ffi::inet_addr(dot.as_ptr()) };
+    let dot = to_c_string(dot)?;
+    let r = unsafe { ffi::inet_addr(dot.as_ptr().cast()) };
 
     if r == 0 {
-        Err(OgcError::Network("network dot_to_nbo failed".to_string()))
+        Err(OgcError::Network(Errno::EINVAL))
     } else {
         Ok(IPV4Address { address: r })
     }
@@ -236,11 +255,14 @@ pub fn dot_to_nbo(dot: &str) -> Result<IPV4Address> {
 /// to a network address, and stores the address in the structure provided.
 /// The converted address will be in Network Byte Order.
 pub fn dot_to_net_addr(dot: &str, addr: &mut IPV4Address) -> Result<()> {
-    let r = unsafe { ffi::inet_aton(dot.as_ptr(), &mut addr.into()) };
+    let dot = to_c_string(dot)?;
+    let mut raw: ffi::in_addr = addr.into();
+    let r = unsafe { ffi::inet_aton(dot.as_ptr().cast(), &mut raw) };
 
     if r < 0 {
-        Err(OgcError::Network(format!("network dot_to_net_addr: {r}")))
+        Err(OgcError::Network(Errno::from_return(r)))
     } else {
+        addr.address = raw.s_addr;
         Ok(())
     }
 }
@@ -253,49 +275,70 @@ pub fn addr_to_dot(addr: &mut IPV4Address) -> Result<String> {
 
     if let Ok(r) = r_res {
         if r.is_empty() {
-            Err(OgcError::Network("addr_to_dot empty".to_string()))
+            Err(OgcError::Network(Errno::EINVAL))
         } else {
             Ok(r.into())
         }
     } else {
-        Err(OgcError::Network(
-            "addr_to_dot string conversion failed".to_string(),
-        ))
+        Err(OgcError::Network(Errno::EINVAL))
     }
 }
 
 /// This function returns a structure of type ``HostInformation`` for the given host name.
 /// Here ``addr_string`` is either a hostname, or an IPv4 address in standard dot notation.
+///
+/// This uses the resolver built into `IOS`, see [`dns::Resolver`] for one that talks to a DNS
+/// server directly.
 pub fn get_host_by_name(addr_string: &str) -> Result<HostInformation> {
+    let addr_string = to_c_string(addr_string)?;
+    let r = unsafe { ffi::net_gethostbyname(addr_string.as_ptr().cast()) };
+
+    if r.is_null() {
+        return Err(OgcError::Network(Errno::EHOSTUNREACH));
+    }
+
+    // SAFETY: libogc returned a valid `hostent` whose lists are NULL-terminated arrays.
     unsafe {
-        let r = ffi::net_gethostbyname(addr_string.as_ptr());
+        let host = &*r;
+
+        let mut aliases = Vec::new();
+        if !host.h_aliases.is_null() {
+            let mut alias = host.h_aliases;
+            while !(*alias).is_null() {
+                aliases.push(
+                    CStr::from_ptr((*alias).cast_const().cast())
+                        .to_string_lossy()
+                        .into_owned(),
+                );
+                alias = alias.add(1);
+            }
+        }
 
-        if r.is_null() {
-            Err(OgcError::Network("host provided doesnt exist".to_string()))
-        } else {
-            // TODO: Replace this with another method.
-            let arr_to_str = |p: *mut *mut u8| -> Vec<String> {
-                let slice = slice::from_raw_parts(p, 2);
-                slice
-                    .iter()
-                    .map(|x: &*mut u8| {
-                        let r = slice::from_raw_parts(*x, 1);
-                        String::from_utf8(r.to_vec()).unwrap()
-                    })
-                    .collect()
-            };
-
-            Ok(HostInformation {
-                name: CStr::from_ptr((*r).h_name.cast_const().cast())
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-                aliases: arr_to_str((*r).h_aliases),
-                address_type: (*r).h_addrtype,
-                length: (*r).h_length,
-                address_list: arr_to_str((*r).h_addr_list),
-            })
+        let mut address_list = Vec::new();
+        if !host.h_addr_list.is_null() && host.h_length == 4 {
+            let mut addr = host.h_addr_list;
+            while !(*addr).is_null() {
+                let octets = slice::from_raw_parts((*addr).cast_const().cast::<u8>(), 4);
+                address_list.push(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]));
+                addr = addr.add(1);
+            }
         }
+
+        let name = if host.h_name.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(host.h_name.cast_const().cast())
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        Ok(HostInformation {
+            name,
+            aliases,
+            address_type: host.h_addrtype,
+            length: host.h_length,
+            address_list,
+        })
     }
 }
 
@@ -313,7 +356,7 @@ impl Network {
         let r = unsafe { ffi::net_init() };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network init: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(Self)
         }
@@ -324,7 +367,7 @@ impl Network {
         let r = unsafe { ffi::net_socket(domain.into(), socket_type.into(), 0) };
 
         if r == INVALID_SOCKET {
-            Err(OgcError::Network(format!("network socket creation: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(Socket(r))
         }
@@ -343,7 +386,7 @@ impl Socket {
         let r = unsafe { ffi::net_connect(self.0, socket_addr.into(), address_length) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network socket connect: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(())
         }
@@ -354,7 +397,7 @@ impl Socket {
         let r = unsafe { ffi::net_bind(self.0, socket_addr.into(), address_length) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network socket bind: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(())
         }
@@ -365,7 +408,7 @@ impl Socket {
         let r = unsafe { ffi::net_listen(self.0, backlog) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network socket listen: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(())
         }
@@ -377,7 +420,7 @@ impl Socket {
         let r = unsafe { ffi::net_accept(self.0, socket_addr.into(), address_length) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network socket accept: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(r)
         }
@@ -388,7 +431,7 @@ impl Socket {
         let r = unsafe { ffi::net_write(descriptor, buffer.as_ptr() as *const c_void, count) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network writing failure: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(r)
         }
@@ -400,7 +443,7 @@ impl Socket {
             unsafe { ffi::net_send(descriptor, buffer.as_ptr() as *const c_void, length, flags) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network sending failure: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(r)
         }
@@ -411,7 +454,7 @@ impl Socket {
         let r = unsafe { ffi::net_read(descriptor, buffer.as_ptr() as *mut c_void, count) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network reading failure: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(r)
         }
@@ -422,7 +465,7 @@ impl Socket {
         let r = unsafe { ffi::net_recv(descriptor, buffer.as_ptr() as *mut c_void, length, flags) };
 
         if r < 0 {
-            Err(OgcError::Network(format!("network recieve failure: {r}")))
+            Err(OgcError::Network(Errno::from_return(r)))
         } else {
             Ok(r)
         }
@@ -438,3 +481,307 @@ impl Drop for Socket {
         }
     }
 }
+
+/// Access to the raw descriptor of a socket.
+pub trait AsRawSocket {
+    /// The raw descriptor as returned by ``net_socket``.
+    fn as_raw_socket(&self) -> i32;
+}
+
+impl AsRawSocket for Socket {
+    fn as_raw_socket(&self) -> i32 {
+        self.0
+    }
+}
+
+/// Possible values for the `how` argument of `shutdown`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shutdown {
+    /// Further receives are disallowed.
+    Read,
+    /// Further sends are disallowed.
+    Write,
+    /// Both sends and receives are disallowed.
+    Both,
+}
+
+impl From<Shutdown> for u32 {
+    fn from(value: Shutdown) -> Self {
+        match value {
+            Shutdown::Read => 0,
+            Shutdown::Write => 1,
+            Shutdown::Both => 2,
+        }
+    }
+}
+
+/// File control commands used by ``net_fcntl``.
+const F_GETFL: u32 = 3;
+const F_SETFL: u32 = 4;
+/// Non blocking flag as `IOS` understands it, not the newlib [`O_NONBLOCK`].
+const IOS_O_NONBLOCK: u32 = 0x04;
+
+/// Size of ``sockaddr_in``, passed as the address length.
+const SOCKADDR_IN_LEN: u32 = core::mem::size_of::<ffi::sockaddr_in>() as u32;
+
+/// Turn a negative libogc return code into an error.
+fn check(r: i32) -> Result<i32> {
+    if r < 0 {
+        Err(OgcError::Network(Errno::from_return(r)))
+    } else {
+        Ok(r)
+    }
+}
+
+/// Convert a ``SocketAddrV4`` into a ``sockaddr_in``.
+fn to_sockaddr(addr: SocketAddrV4) -> ffi::sockaddr_in {
+    // SAFETY: `sockaddr_in` is plain old data, all zeroes is a valid value.
+    let mut raw: ffi::sockaddr_in = unsafe { core::mem::zeroed() };
+    raw.sin_len = SOCKADDR_IN_LEN as u8;
+    raw.sin_family = ProtocolFamily::AfInet as u8;
+    raw.sin_port = addr.port().to_be();
+    raw.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+    raw
+}
+
+/// Convert a ``sockaddr_in`` back into a ``SocketAddrV4``.
+fn from_sockaddr(raw: &ffi::sockaddr_in) -> SocketAddrV4 {
+    SocketAddrV4::new(
+        Ipv4Addr::from(raw.sin_addr.s_addr.to_ne_bytes()),
+        u16::from_be(raw.sin_port),
+    )
+}
+
+/// Timeout shared between threads, stored in milliseconds with `0` meaning none.
+struct Timeout(AtomicU32);
+
+impl Timeout {
+    const fn new() -> Self {
+        Self(AtomicU32::new(0))
+    }
+
+    fn set(&self, timeout: Option<Duration>) -> Result<()> {
+        let millis = match timeout {
+            Some(dur) if dur.is_zero() => return Err(OgcError::Network(Errno::EINVAL)),
+            // Round up so tiny timeouts do not turn into "no timeout".
+            Some(dur) => u32::try_from(dur.as_millis().max(1)).unwrap_or(u32::MAX),
+            None => 0,
+        };
+        self.0.store(millis, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn get(&self) -> Option<Duration> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Some(Duration::from_millis(millis.into())),
+        }
+    }
+}
+
+impl Socket {
+    /// Create a new IPv4 socket of `socket_type`.
+    fn new_inet(socket_type: SocketType) -> Result<Self> {
+        let fd = check(unsafe {
+            ffi::net_socket(ProtocolFamily::AfInet.into(), socket_type.into(), 0)
+        })?;
+        Ok(Socket(fd))
+    }
+
+    /// Connect to `addr`.
+    fn connect_v4(&self, addr: SocketAddrV4) -> Result<()> {
+        let mut raw = to_sockaddr(addr);
+        check(unsafe { ffi::net_connect(self.0, (&raw mut raw).cast(), SOCKADDR_IN_LEN) })?;
+        Ok(())
+    }
+
+    /// Bind to `addr`.
+    fn bind_v4(&self, addr: SocketAddrV4) -> Result<()> {
+        let mut raw = to_sockaddr(addr);
+        check(unsafe { ffi::net_bind(self.0, (&raw mut raw).cast(), SOCKADDR_IN_LEN) })?;
+        Ok(())
+    }
+
+    /// Accept a connection, returning the new socket and the address of the peer.
+    fn accept_v4(&self) -> Result<(Socket, SocketAddrV4)> {
+        // SAFETY: `sockaddr_in` is plain old data, all zeroes is a valid value.
+        let mut raw: ffi::sockaddr_in = unsafe { core::mem::zeroed() };
+        let mut len = SOCKADDR_IN_LEN;
+        let fd = check(unsafe { ffi::net_accept(self.0, (&raw mut raw).cast(), &mut len) })?;
+        Ok((Socket(fd), from_sockaddr(&raw)))
+    }
+
+    /// The address the socket is bound to, with the port `IOS` picked when bound to port 0.
+    fn local_addr_v4(&self) -> Result<SocketAddrV4> {
+        // SAFETY: `sockaddr_in` is plain old data, all zeroes is a valid value.
+        let mut raw: ffi::sockaddr_in = unsafe { core::mem::zeroed() };
+        let mut len = SOCKADDR_IN_LEN;
+        check(unsafe { ffi::net_getsockname(self.0, (&raw mut raw).cast(), &mut len) })?;
+        Ok(from_sockaddr(&raw))
+    }
+
+    /// Set an integer socket option.
+    fn set_option(&self, level: u32, option: u32, value: i32) -> Result<()> {
+        check(unsafe {
+            ffi::net_setsockopt(
+                self.0,
+                level,
+                option,
+                (&raw const value).cast(),
+                core::mem::size_of::<i32>() as u32,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Set or clear the non blocking flag.
+    fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        let flags = check(unsafe { ffi::net_fcntl(self.0, F_GETFL, 0) })? as u32;
+        let flags = if nonblocking {
+            flags | IOS_O_NONBLOCK
+        } else {
+            flags & !IOS_O_NONBLOCK
+        };
+        check(unsafe { ffi::net_fcntl(self.0, F_SETFL, flags) })?;
+        Ok(())
+    }
+
+    /// Wait until `events` are ready, failing with ``ETIMEDOUT`` once `timeout` passes.
+    ///
+    /// Does nothing without a timeout, the following call blocks on its own.
+    fn wait(&self, events: PollBits, timeout: Option<Duration>) -> Result<()> {
+        let Some(timeout) = timeout else {
+            return Ok(());
+        };
+
+        let mut sd = ffi::pollsd {
+            socket: self.0,
+            events: events.bits(),
+            revents: 0,
+        };
+        let millis = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        match check(unsafe { ffi::net_poll(&mut sd, 1, millis) })? {
+            0 => Err(OgcError::Network(Errno::ETIMEDOUT)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Shut down part of a full duplex connection.
+    fn shutdown(&self, how: Shutdown) -> Result<()> {
+        check(unsafe { ffi::net_shutdown(self.0, how.into()) })?;
+        Ok(())
+    }
+
+    /// Receive into `buf`, returning the length and the sender's address.
+    fn recv_from_v4(&self, buf: &mut [u8], flags: u32) -> Result<(usize, SocketAddrV4)> {
+        // SAFETY: `sockaddr_in` is plain old data, all zeroes is a valid value.
+        let mut raw: ffi::sockaddr_in = unsafe { core::mem::zeroed() };
+        let mut len = SOCKADDR_IN_LEN;
+        let r = check(unsafe {
+            ffi::net_recvfrom(
+                self.0,
+                buf.as_mut_ptr().cast(),
+                buf_len(buf.len()),
+                flags,
+                (&raw mut raw).cast(),
+                &mut len,
+            )
+        })?;
+        Ok((r as usize, from_sockaddr(&raw)))
+    }
+
+    /// Send `buf` to `addr`, returning how many bytes were sent.
+    fn send_to_v4(&self, buf: &[u8], addr: SocketAddrV4) -> Result<usize> {
+        let mut raw = to_sockaddr(addr);
+        let r = check(unsafe {
+            ffi::net_sendto(
+                self.0,
+                buf.as_ptr().cast(),
+                buf_len(buf.len()),
+                0,
+                (&raw mut raw).cast(),
+                SOCKADDR_IN_LEN,
+            )
+        })?;
+        Ok(r as usize)
+    }
+
+    /// Receive into `buf` from the connected peer.
+    fn recv_raw(&self, buf: &mut [u8], flags: u32) -> Result<usize> {
+        let r = check(unsafe {
+            ffi::net_recv(self.0, buf.as_mut_ptr().cast(), buf_len(buf.len()), flags)
+        })?;
+        Ok(r as usize)
+    }
+
+    /// Send `buf` to the connected peer.
+    fn send_raw(&self, buf: &[u8], flags: u32) -> Result<usize> {
+        let r = check(unsafe {
+            ffi::net_send(self.0, buf.as_ptr().cast(), buf_len(buf.len()), flags)
+        })?;
+        Ok(r as usize)
+    }
+}
+
+impl crate::io::Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.recv_raw(buf, 0)
+    }
+}
+
+impl crate::io::Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.send_raw(buf, 0)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Clamp a buffer length to what libogc can take in one call.
+fn buf_len(len: usize) -> i32 {
+    i32::try_from(len).unwrap_or(i32::MAX)
+}
+
+/// Socket state shared by ``TcpStream``, ``TcpListener`` and ``UdpSocket``.
+struct SocketState {
+    socket: Socket,
+    read_timeout: Timeout,
+    write_timeout: Timeout,
+    nonblocking: AtomicBool,
+}
+
+impl SocketState {
+    fn new(socket: Socket) -> Self {
+        Self {
+            socket,
+            read_timeout: Timeout::new(),
+            write_timeout: Timeout::new(),
+            nonblocking: AtomicBool::new(false),
+        }
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.socket.set_nonblocking(nonblocking)?;
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Wait for the socket to become readable unless it is nonblocking.
+    fn wait_readable(&self) -> Result<()> {
+        if self.nonblocking.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.socket.wait(PollBits::POLLIN, self.read_timeout.get())
+    }
+
+    /// Wait for the socket to become writable unless it is nonblocking.
+    fn wait_writable(&self) -> Result<()> {
+        if self.nonblocking.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.socket
+            .wait(PollBits::POLLOUT, self.write_timeout.get())
+    }
+}
@@ -0,0 +1,139 @@
+//! Conversion of host names and addresses into socket addresses.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::net::{Ipv4Addr, SocketAddrV4};
+
+use super::{dns, get_host_by_name};
+use crate::{OgcError, Result, error::Errno};
+
+/// A value that can be turned into one or more [`SocketAddrV4`]s, like ``std::net::ToSocketAddrs``.
+///
+/// Strings are either `"host:port"` or a `(host, port)` pair, where the host is an IPv4 address
+/// in dot notation or a name looked up with [`lookup_host`].
+///
+/// # Examples
+///
+/// ```rust
+/// let stream = TcpStream::connect("example.com:80")?;
+/// let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+/// ```
+pub trait ToSocketAddrs {
+    /// Iterator over the socket addresses.
+    type Iter: Iterator<Item = SocketAddrV4>;
+
+    /// Resolve `self` into socket addresses.
+    ///
+    /// Fails with [`Errno::EINVAL`] if `self` cannot be parsed and with
+    /// [`Errno::EHOSTUNREACH`] if a host name could not be resolved.
+    fn to_socket_addrs(&self) -> Result<Self::Iter>;
+}
+
+impl ToSocketAddrs for SocketAddrV4 {
+    type Iter = core::option::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        Ok(Some(*self).into_iter())
+    }
+}
+
+impl ToSocketAddrs for (Ipv4Addr, u16) {
+    type Iter = core::option::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        SocketAddrV4::new(self.0, self.1).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for (&str, u16) {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        let (host, port) = *self;
+        let addrs = lookup_host(host)?
+            .into_iter()
+            .map(|ip| SocketAddrV4::new(ip, port))
+            .collect::<Vec<_>>();
+        Ok(addrs.into_iter())
+    }
+}
+
+impl ToSocketAddrs for (String, u16) {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        (self.0.as_str(), self.1).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for str {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        if let Ok(addr) = self.parse::<SocketAddrV4>() {
+            return Ok(vec![addr].into_iter());
+        }
+
+        let (host, port) = self
+            .rsplit_once(':')
+            .ok_or(OgcError::Network(Errno::EINVAL))?;
+        let port = port
+            .parse::<u16>()
+            .map_err(|_| OgcError::Network(Errno::EINVAL))?;
+        (host, port).to_socket_addrs()
+    }
+}
+
+impl ToSocketAddrs for String {
+    type Iter = vec::IntoIter<SocketAddrV4>;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        self.as_str().to_socket_addrs()
+    }
+}
+
+impl<T: ToSocketAddrs + ?Sized> ToSocketAddrs for &T {
+    type Iter = T::Iter;
+
+    fn to_socket_addrs(&self) -> Result<Self::Iter> {
+        (**self).to_socket_addrs()
+    }
+}
+
+/// The IPv4 addresses of `host`, which may also be an address in dot notation.
+///
+/// The `IOS` resolver is asked first, if it fails and a server was set with
+/// [`dns::set_fallback_server`] that server is queried directly.
+pub fn lookup_host(host: &str) -> Result<Vec<Ipv4Addr>> {
+    if let Ok(ip) = host.parse::<Ipv4Addr>() {
+        return Ok(vec![ip]);
+    }
+    if host.is_empty() {
+        return Err(OgcError::Network(Errno::EINVAL));
+    }
+
+    let err = match get_host_by_name(host) {
+        Ok(info) if !info.address_list.is_empty() => return Ok(info.address_list),
+        Ok(_) => OgcError::Network(Errno::EHOSTUNREACH),
+        Err(err) => err,
+    };
+
+    match dns::fallback_server() {
+        Some(server) => dns::Resolver::new(server).resolve(host),
+        None => Err(err),
+    }
+}
+
+/// Call `f` with every address of `addr`, returning the first success or the last error.
+pub(super) fn each_addr<A, T>(addr: A, mut f: impl FnMut(SocketAddrV4) -> Result<T>) -> Result<T>
+where
+    A: ToSocketAddrs,
+{
+    let mut last = OgcError::Network(Errno::EINVAL);
+    for addr in addr.to_socket_addrs()? {
+        match f(addr) {
+            Ok(value) => return Ok(value),
+            Err(err) => last = err,
+        }
+    }
+    Err(last)
+}
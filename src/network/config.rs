@@ -0,0 +1,328 @@
+//! Interface configuration, link state and asynchronous initialization.
+
+use alloc::vec::Vec;
+use core::{
+    cell::UnsafeCell,
+    ffi::c_void,
+    future::Future,
+    net::Ipv4Addr,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    task::{Context, Poll, Waker},
+};
+
+use super::Network;
+use crate::{
+    OgcError, Result,
+    error::Errno,
+    ffi,
+    ios::net::{self as ncd, ConnectionFlags, LinkStatus},
+};
+
+/// Queue threads waiting in [`Connecting::wait`] sleep on, created on first use.
+static INIT_QUEUE: AtomicU32 = AtomicU32::new(0);
+static INIT_QUEUE_READY: AtomicBool = AtomicBool::new(false);
+
+/// The waker of the task last polling a [`Connecting`], woken by the completion callback.
+struct WakerSlot(UnsafeCell<Option<Waker>>);
+
+// SAFETY: the slot is only touched with interrupts disabled.
+unsafe impl Sync for WakerSlot {}
+
+static INIT_WAKER: WakerSlot = WakerSlot(UnsafeCell::new(None));
+
+/// Put `waker` in [`INIT_WAKER`], returning the one it replaces so it is dropped with
+/// interrupts enabled.
+fn swap_init_waker(mut waker: Option<Waker>) -> Option<Waker> {
+    // SAFETY: interrupts are disabled, so `init_done` cannot run while the slot is changed.
+    unsafe {
+        let level = ffi::IRQ_Disable();
+        core::ptr::swap(INIT_WAKER.0.get(), &mut waker);
+        ffi::IRQ_Restore(level);
+    }
+    waker
+}
+
+/// How the interface came up, as returned by [`Network::config`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterfaceConfig {
+    /// Address of the console
+    pub address: Ipv4Addr,
+    /// Netmask of the local network
+    pub netmask: Ipv4Addr,
+    /// Default gateway from the routing table, or the configured one if `IOS` does not report
+    /// its routes
+    pub gateway: Option<Ipv4Addr>,
+    /// DNS servers used by [`get_host_by_name`](super::get_host_by_name)
+    pub dns_servers: Vec<Ipv4Addr>,
+    /// MAC address of the wireless interface
+    pub mac_address: [u8; 6],
+    /// Whether the address was handed out by DHCP
+    pub dhcp: bool,
+}
+
+/// A manually configured address for [`Network::set_static_ip`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StaticIp {
+    /// Address of the console
+    pub address: Ipv4Addr,
+    /// Netmask of the local network
+    pub netmask: Ipv4Addr,
+    /// Default gateway
+    pub gateway: Ipv4Addr,
+    /// Primary and secondary DNS servers, `None` keeps asking DHCP for them
+    pub dns_servers: Option<[Ipv4Addr; 2]>,
+}
+
+/// State of the networking service, see [`Network::status`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InitStatus {
+    /// The network was never initialized or was shut down
+    Down,
+    /// Initialization is in progress
+    Connecting,
+    /// The network is up
+    Up,
+    /// Initialization failed
+    Failed(Errno),
+}
+
+impl InitStatus {
+    fn from_return(ret: i32) -> Self {
+        if ret >= 0 {
+            return Self::Up;
+        }
+        match Errno::from_return(ret) {
+            Errno::EBUSY => Self::Connecting,
+            Errno::ENETDOWN => Self::Down,
+            errno => Self::Failed(errno),
+        }
+    }
+
+    /// The result of initialization, `None` while it is in progress.
+    fn result(self) -> Option<Result<Network>> {
+        match self {
+            Self::Up => Some(Ok(Network)),
+            Self::Connecting => None,
+            Self::Down => Some(Err(OgcError::Network(Errno::ENETDOWN))),
+            Self::Failed(errno) => Some(Err(OgcError::Network(errno))),
+        }
+    }
+}
+
+/// An initialization started with [`Network::init_async`].
+///
+/// Poll it with [`Connecting::status`] from a frame loop, block on it with
+/// [`Connecting::wait`] or `.await` it. The future is woken once by the completion callback,
+/// which runs in interrupt context, so the waker of the executor must be safe to call there.
+/// Only the task that polled last is woken.
+#[derive(Debug)]
+#[must_use = "initialization keeps running but its result is lost"]
+pub struct Connecting(());
+
+impl Connecting {
+    /// The current state without blocking.
+    pub fn status(&self) -> InitStatus {
+        Network::status()
+    }
+
+    /// The result of initialization, `None` while it is still in progress.
+    pub fn try_finish(&self) -> Option<Result<Network>> {
+        self.status().result()
+    }
+
+    /// Block the current thread until initialization is done.
+    pub fn wait(self) -> Result<Network> {
+        let queue = init_queue()?;
+        // SAFETY: interrupts are disabled while checking the status so the completion callback
+        // cannot broadcast between the check and going to sleep.
+        unsafe {
+            let level = ffi::IRQ_Disable();
+            while Network::status() == InitStatus::Connecting {
+                ffi::LWP_ThreadSleep(queue);
+            }
+            ffi::IRQ_Restore(level);
+        }
+        Network::status()
+            .result()
+            .unwrap_or(Err(OgcError::Network(Errno::EBUSY)))
+    }
+}
+
+impl Future for Connecting {
+    type Output = Result<Network>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Register before checking so finishing in between still wakes the task.
+        drop(swap_init_waker(Some(cx.waker().clone())));
+        match self.try_finish() {
+            Some(result) => {
+                drop(swap_init_waker(None));
+                Poll::Ready(result)
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+fn init_queue() -> Result<ffi::lwpq_t> {
+    // SAFETY: interrupts are disabled so the queue is only created once.
+    unsafe {
+        let level = ffi::IRQ_Disable();
+        if !INIT_QUEUE_READY.load(Ordering::Acquire) {
+            let mut queue = 0;
+            let res = ffi::LWP_InitQueue(&mut queue);
+            if res < 0 {
+                ffi::IRQ_Restore(level);
+                return Err(OgcError::Lwp(Errno::from_return(res)));
+            }
+            INIT_QUEUE.store(queue, Ordering::Relaxed);
+            INIT_QUEUE_READY.store(true, Ordering::Release);
+        }
+        ffi::IRQ_Restore(level);
+    }
+    Ok(INIT_QUEUE.load(Ordering::Relaxed))
+}
+
+unsafe extern "C" fn init_done(result: i32, usrdata: *mut c_void) -> i32 {
+    if INIT_QUEUE_READY.load(Ordering::Acquire) {
+        unsafe { ffi::LWP_ThreadBroadcast(INIT_QUEUE.load(Ordering::Relaxed)) };
+    }
+    // SAFETY: this runs in interrupt context, the slot is only changed with interrupts
+    // disabled. The waker stays in the slot so nothing is freed here.
+    if let Some(waker) = unsafe { &*INIT_WAKER.0.get() } {
+        waker.wake_by_ref();
+    }
+
+    if !usrdata.is_null() {
+        // SAFETY: Only `fn(Result<Network>)` pointers are passed as `usrdata`.
+        let callback = unsafe { core::mem::transmute::<*mut c_void, fn(Result<Network>)>(usrdata) };
+        callback(if result < 0 {
+            Err(OgcError::Network(Errno::from_return(result)))
+        } else {
+            Ok(Network)
+        });
+    }
+
+    result
+}
+
+fn start_init(usrdata: *mut c_void) -> Result<Connecting> {
+    init_queue()?;
+    let r = unsafe { ffi::net_init_async(Some(init_done), usrdata) };
+
+    if r < 0 {
+        Err(OgcError::Network(Errno::from_return(r)))
+    } else {
+        Ok(Connecting(()))
+    }
+}
+
+impl Network {
+    /// Start initializing the networking service in the background.
+    ///
+    /// Bringing the interface up and waiting for DHCP can take several seconds, this returns
+    /// right away so a "connecting..." screen can keep drawing.
+    pub fn init_async() -> Result<Connecting> {
+        start_init(core::ptr::null_mut())
+    }
+
+    /// Like [`Network::init_async`] but also calls `callback` with the result once
+    /// initialization is done.
+    ///
+    /// The callback runs in interrupt context, it must not block or allocate.
+    pub fn init_async_with(callback: fn(Result<Network>)) -> Result<Connecting> {
+        start_init(callback as *mut c_void)
+    }
+
+    /// The state of the networking service.
+    pub fn status() -> InitStatus {
+        InitStatus::from_return(unsafe { ffi::net_get_status() })
+    }
+
+    /// The link status of the interface in use.
+    ///
+    /// This does not need the networking service, it can tell whether a cable is plugged in or
+    /// an access point was joined before initializing.
+    pub fn link_status() -> Result<LinkStatus> {
+        Ok(ncd::get_link_status()?)
+    }
+
+    /// Use `ip` instead of DHCP, or go back to DHCP with `None`.
+    ///
+    /// This changes the connection selected in the system settings until the next reset, it is
+    /// never saved. It must be called before [`Network::init`] or [`Network::init_async`].
+    pub fn set_static_ip(ip: Option<StaticIp>) -> Result<()> {
+        let mut config = ncd::get_config()?;
+        let index = config
+            .selected()
+            .ok_or(OgcError::Network(Errno::ENETUNREACH))?;
+        let Some(mut connection) = config.connection(index) else {
+            return Err(OgcError::Network(Errno::ENETUNREACH));
+        };
+
+        match ip {
+            Some(ip) => {
+                connection.flags.remove(ConnectionFlags::IP_DHCP);
+                connection.address = ip.address;
+                connection.netmask = ip.netmask;
+                connection.gateway = ip.gateway;
+                match ip.dns_servers {
+                    Some(servers) => {
+                        connection.flags.remove(ConnectionFlags::DNS_DHCP);
+                        connection.dns_servers = servers;
+                    }
+                    None => connection.flags.insert(ConnectionFlags::DNS_DHCP),
+                }
+            }
+            None => connection
+                .flags
+                .insert(ConnectionFlags::IP_DHCP | ConnectionFlags::DNS_DHCP),
+        }
+
+        config.set_connection(index, &connection);
+        Ok(ncd::set_config(&config)?)
+    }
+
+    /// Initialize the networking service with a manually configured address.
+    ///
+    /// See [`Network::set_static_ip`].
+    pub fn init_static(ip: StaticIp) -> Result<Self> {
+        Self::set_static_ip(Some(ip))?;
+        Self::init()
+    }
+
+    /// How the interface is configured.
+    ///
+    /// Fails with [`Errno::ENETDOWN`] until initialization succeeded.
+    pub fn config() -> Result<InterfaceConfig> {
+        if Self::status() != InitStatus::Up {
+            return Err(OgcError::Network(Errno::ENETDOWN));
+        }
+
+        let (address, netmask, _broadcast) = ncd::get_address_table()?;
+        let dns_servers = ncd::get_dns_servers()?.into_iter().flatten().collect();
+        let mac_address = ncd::get_mac_address()?;
+
+        let config = ncd::get_config()?;
+        let connection = config.selected().and_then(|index| config.connection(index));
+        let dhcp = connection.is_none_or(|c| c.flags.contains(ConnectionFlags::IP_DHCP));
+        // The routing table also knows the gateway handed out by DHCP.
+        let gateway = match ncd::get_default_gateway() {
+            Ok(gateway) => gateway,
+            Err(_) => connection
+                .filter(|_| !dhcp)
+                .map(|c| c.gateway)
+                .filter(|gateway| !gateway.is_unspecified()),
+        };
+
+        Ok(InterfaceConfig {
+            address,
+            netmask,
+            gateway,
+            dns_servers,
+            mac_address,
+            dhcp,
+        })
+    }
+}
@@ -0,0 +1,133 @@
+//! A minimal DNS client that queries a server over UDP.
+//!
+//! [`get_host_by_name`](super::get_host_by_name) goes through the resolver built into `IOS`,
+//! which only knows the server handed out by DHCP. [`Resolver`] talks to any server directly and
+//! is used by [`ToSocketAddrs`](super::ToSocketAddrs) as a fallback once a server was set with
+//! [`set_fallback_server`].
+//!
+//! The messages are built and parsed by [`ogc_formats::dns`], which is tested on the host against
+//! a stub server.
+
+use alloc::vec::Vec;
+use core::{
+    net::{Ipv4Addr, SocketAddrV4},
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use super::{HostInformation, ProtocolFamily, UdpSocket};
+use crate::{OgcError, Result};
+
+pub use ogc_formats::dns::{DnsError, Host, Transport, build_query, parse_response};
+
+/// The port DNS servers listen on.
+pub const DNS_PORT: u16 = 53;
+
+/// Fallback server as returned by [`Ipv4Addr::to_bits`], `0` meaning none.
+static FALLBACK: AtomicU32 = AtomicU32::new(0);
+static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+/// Set the server [`ToSocketAddrs`](super::ToSocketAddrs) asks when the `IOS` resolver fails,
+/// `None` disables the fallback.
+pub fn set_fallback_server(server: Option<Ipv4Addr>) {
+    FALLBACK.store(server.map_or(0, Ipv4Addr::to_bits), Ordering::Relaxed);
+}
+
+/// The server set with [`set_fallback_server`].
+pub fn fallback_server() -> Option<Ipv4Addr> {
+    match FALLBACK.load(Ordering::Relaxed) {
+        0 => None,
+        bits => Some(Ipv4Addr::from_bits(bits)),
+    }
+}
+
+/// A new query id, mixing a counter with the current time so ids are hard to guess.
+fn next_id() -> u16 {
+    let count = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let now = crate::time::now().subsec_nanos();
+    (count.wrapping_mul(0x9E37) ^ now ^ (now >> 16)) as u16
+}
+
+/// Resolves host names by sending `A` queries to a DNS server.
+///
+/// # Examples
+///
+/// ```rust
+/// let resolver = Resolver::new(Ipv4Addr::new(1, 1, 1, 1));
+/// let addresses = resolver.resolve("example.com")?;
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Resolver {
+    server: SocketAddrV4,
+    timeout: Duration,
+    attempts: u8,
+}
+
+impl Resolver {
+    /// Create a resolver asking `server` on the standard port.
+    pub fn new(server: Ipv4Addr) -> Self {
+        Self::with_address(SocketAddrV4::new(server, DNS_PORT))
+    }
+
+    /// Create a resolver asking the server at `server`.
+    pub fn with_address(server: SocketAddrV4) -> Self {
+        Self {
+            server,
+            timeout: Duration::from_secs(2),
+            attempts: 3,
+        }
+    }
+
+    /// Set how long to wait for each response, two seconds by default.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how many queries are sent before giving up, three by default.
+    #[must_use]
+    pub fn attempts(mut self, attempts: u8) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// The addresses of `name`.
+    ///
+    /// Fails with [`Errno::EHOSTUNREACH`](crate::error::Errno::EHOSTUNREACH) if the name does
+    /// not exist or has no addresses and with the error of the last read, usually
+    /// [`Errno::ETIMEDOUT`](crate::error::Errno::ETIMEDOUT), if the server never answered.
+    pub fn resolve(&self, name: &str) -> Result<Vec<Ipv4Addr>> {
+        self.lookup(name).map(|host| host.address_list)
+    }
+
+    /// The addresses, canonical name and aliases of `name`.
+    ///
+    /// Fails like [`Resolver::resolve`].
+    pub fn lookup(&self, name: &str) -> Result<HostInformation> {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        socket.connect(self.server)?;
+
+        let host = ogc_formats::dns::resolve(&socket, name, self.attempts, next_id)?;
+        Ok(HostInformation {
+            name: host.name,
+            aliases: host.aliases,
+            address_type: ProtocolFamily::AfInet as core::ffi::c_short,
+            length: 4,
+            address_list: host.addresses,
+        })
+    }
+}
+
+impl Transport for UdpSocket {
+    type Error = OgcError;
+
+    fn send(&self, buf: &[u8]) -> Result<usize> {
+        UdpSocket::send(self, buf)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        UdpSocket::recv(self, buf)
+    }
+}
@@ -0,0 +1,430 @@
+//! A minimal HTTP/1.1 client over [`TcpStream`].
+//!
+//! Only plain `http://` URLs are supported. Every request opens its own connection and asks the
+//! server to close it once the response is complete. Host names are resolved with
+//! [`ToSocketAddrs`](super::ToSocketAddrs), so [`dns::set_fallback_server`](super::dns::set_fallback_server)
+//! applies here as well.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let mut response = http::Request::get("http://example.com/scores")?
+//!     .header("Accept", "application/json")
+//!     .timeout(Duration::from_secs(5))
+//!     .send()?;
+//! if response.is_success() {
+//!     let body = response.read_to_end()?;
+//! }
+//! ```
+
+use alloc::{format, string::String, vec::Vec};
+use core::time::Duration;
+
+use ogc_formats::http;
+
+use super::{TcpStream, addr::each_addr};
+use crate::{
+    OgcError, Result,
+    error::{Errno, HttpError},
+    io,
+};
+
+pub use ogc_formats::http::Method;
+
+/// How many redirects are followed by default.
+const MAX_REDIRECTS: u8 = 5;
+
+/// The parts of an `http://` URL needed to make a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Url {
+    host: String,
+    port: u16,
+    /// Path and query, always starting with `/`.
+    path: String,
+}
+
+impl Url {
+    fn parse(url: &str) -> Result<Self> {
+        let (scheme, rest) = url.split_once("://").ok_or(HttpError::InvalidUrl)?;
+        if !scheme.eq_ignore_ascii_case("http") {
+            return Err(HttpError::UnsupportedScheme.into());
+        }
+
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        if authority.contains('@') {
+            return Err(HttpError::InvalidUrl.into());
+        }
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| HttpError::InvalidUrl)?),
+            None => (authority, 80),
+        };
+        if host.is_empty() || path.contains([' ', '\r', '\n']) {
+            return Err(HttpError::InvalidUrl.into());
+        }
+
+        let path = if path.starts_with('/') {
+            String::from(path)
+        } else {
+            format!("/{path}")
+        };
+
+        Ok(Self {
+            host: String::from(host),
+            port,
+            path,
+        })
+    }
+
+    /// Resolve the `Location` of a redirect against this URL.
+    fn join(&self, location: &str) -> Result<Self> {
+        if location.contains("://") {
+            return Self::parse(location);
+        }
+        if let Some(rest) = location.strip_prefix("//") {
+            return Self::parse(&format!("http://{rest}"));
+        }
+        if location.contains([' ', '\r', '\n']) {
+            return Err(HttpError::InvalidUrl.into());
+        }
+
+        let path = if location.starts_with('/') {
+            String::from(location)
+        } else {
+            let base = self.path.split('?').next().unwrap_or_default();
+            let dir = &base[..=base.rfind('/').unwrap_or_default()];
+            format!("{dir}{location}")
+        };
+
+        Ok(Self {
+            host: self.host.clone(),
+            port: self.port,
+            path,
+        })
+    }
+
+    /// The value of the `Host` header.
+    fn host_header(&self) -> String {
+        if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// An HTTP request, built up with chained calls and sent with [`Request::send`].
+#[derive(Clone, Debug)]
+pub struct Request {
+    method: Method,
+    url: Url,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    timeout: Option<Duration>,
+    max_redirects: u8,
+}
+
+impl Request {
+    /// Create a request for `url`.
+    ///
+    /// Fails with [`HttpError::InvalidUrl`] or [`HttpError::UnsupportedScheme`] if `url` is not
+    /// an `http://` URL.
+    pub fn new(method: Method, url: &str) -> Result<Self> {
+        Ok(Self {
+            method,
+            url: Url::parse(url)?,
+            headers: Vec::new(),
+            body: Vec::new(),
+            timeout: None,
+            max_redirects: MAX_REDIRECTS,
+        })
+    }
+
+    /// Create a `GET` request for `url`.
+    pub fn get(url: &str) -> Result<Self> {
+        Self::new(Method::Get, url)
+    }
+
+    /// Create a `POST` request for `url`.
+    pub fn post(url: &str) -> Result<Self> {
+        Self::new(Method::Post, url)
+    }
+
+    /// Create a `HEAD` request for `url`.
+    pub fn head(url: &str) -> Result<Self> {
+        Self::new(Method::Head, url)
+    }
+
+    /// Add a header.
+    ///
+    /// `Host`, `User-Agent` and `Connection` are only sent with their default values if they were
+    /// not added here. `Content-Length` is always derived from the body.
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    /// Set the request body.
+    #[must_use]
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set how long connecting and every single read or write may take, `None` waits forever.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set how many redirects are followed, five by default.
+    ///
+    /// With `0` redirect responses are returned as they are, otherwise running out of redirects
+    /// fails with [`HttpError::TooManyRedirects`].
+    #[must_use]
+    pub fn max_redirects(mut self, max_redirects: u8) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Send the request and read the response head, the body is read from the [`Response`].
+    ///
+    /// Fails with [`Errno::EINVAL`] if a header contains a line break.
+    pub fn send(self) -> Result<Response> {
+        let invalid = |s: &str| s.contains(['\r', '\n']);
+        if self
+            .headers
+            .iter()
+            .any(|(name, value)| name.is_empty() || invalid(name) || invalid(value))
+        {
+            return Err(OgcError::Network(Errno::EINVAL));
+        }
+
+        let mut method = self.method;
+        let mut url = self.url;
+        let mut body = self.body;
+        let mut redirects = 0;
+
+        loop {
+            let stream = connect(&url, self.timeout)?;
+            write_request(&stream, method, &url, &self.headers, &body)?;
+            let response = Response::read_from(stream, method)?;
+
+            let location = match response.status() {
+                301 | 302 | 303 | 307 | 308 if self.max_redirects > 0 => {
+                    response.header("Location")
+                }
+                _ => None,
+            };
+            let Some(location) = location else {
+                return Ok(response);
+            };
+
+            if redirects == self.max_redirects {
+                return Err(HttpError::TooManyRedirects.into());
+            }
+            redirects += 1;
+
+            url = url.join(location)?;
+            // Like browsers, turn a redirected `POST` into a `GET` unless asked to repeat it.
+            if response.status() == 303
+                || (matches!(response.status(), 301 | 302) && method == Method::Post)
+            {
+                if method != Method::Head {
+                    method = Method::Get;
+                }
+                body.clear();
+            }
+        }
+    }
+}
+
+/// Send a `GET` request for `url`.
+pub fn get(url: &str) -> Result<Response> {
+    Request::get(url)?.send()
+}
+
+/// Send a `HEAD` request for `url`.
+pub fn head(url: &str) -> Result<Response> {
+    Request::head(url)?.send()
+}
+
+/// Send a `POST` request with `body` to `url`.
+pub fn post(url: &str, body: impl Into<Vec<u8>>) -> Result<Response> {
+    Request::post(url)?.body(body).send()
+}
+
+fn connect(url: &Url, timeout: Option<Duration>) -> Result<TcpStream> {
+    each_addr((url.host.as_str(), url.port), |addr| {
+        let stream = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(addr, timeout)?,
+            None => TcpStream::connect(addr)?,
+        };
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        Ok(stream)
+    })
+}
+
+fn write_request(
+    stream: &TcpStream,
+    method: Method,
+    url: &Url,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> Result<()> {
+    let has = |name: &str| headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name));
+
+    let mut head = format!("{} {} HTTP/1.1\r\n", method.as_str(), url.path);
+    if !has("Host") {
+        head += &format!("Host: {}\r\n", url.host_header());
+    }
+    if !has("User-Agent") {
+        head += "User-Agent: ogc-rs\r\n";
+    }
+    if !has("Connection") {
+        head += "Connection: close\r\n";
+    }
+    if !body.is_empty() || method == Method::Post {
+        head += &format!("Content-Length: {}\r\n", body.len());
+    }
+    for (name, value) in headers {
+        if !name.eq_ignore_ascii_case("Content-Length") {
+            head += &format!("{name}: {value}\r\n");
+        }
+    }
+    head += "\r\n";
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)
+}
+
+/// Adapts an [`io::Read`] to the reader [`http::Response`] is generic over.
+struct Reader<R>(R);
+
+impl<R: io::Read> http::Read for Reader<R> {
+    type Error = OgcError;
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+/// The response to a [`Request`].
+///
+/// The head is read by [`Request::send`], the body is read as it arrives with
+/// [`Response::read`]. Parsing is done by [`ogc_formats::http::Response`], which is tested on
+/// the host.
+pub struct Response<R = TcpStream>(http::Response<Reader<R>>);
+
+impl<R: io::Read> Response<R> {
+    /// Read the response head from `reader`, leaving the body to be read from the [`Response`].
+    ///
+    /// `method` is the method of the request this answers, responses to `HEAD` have no body.
+    /// Useful to speak HTTP over something other than a [`TcpStream`].
+    pub fn read_from(reader: R, method: Method) -> Result<Self> {
+        http::Response::read_from(Reader(reader), method).map(Self)
+    }
+
+    /// The status code.
+    pub fn status(&self) -> u16 {
+        self.0.status()
+    }
+
+    /// The reason phrase following the status code.
+    pub fn reason(&self) -> &str {
+        self.0.reason()
+    }
+
+    /// Whether the status code is in the `2xx` range.
+    pub fn is_success(&self) -> bool {
+        self.0.is_success()
+    }
+
+    /// Every header in the order they were received.
+    pub fn headers(&self) -> &[(String, String)] {
+        self.0.headers()
+    }
+
+    /// The value of the first header called `name`, compared case insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.0.header(name)
+    }
+
+    /// The length of the body if the server sent it up front.
+    pub fn content_length(&self) -> Option<u64> {
+        self.0.content_length()
+    }
+
+    /// Read part of the body into `buf`, returning how many bytes were read. `0` means the body
+    /// is complete.
+    ///
+    /// Fails with [`HttpError::UnexpectedEof`] if the connection closes before the body is
+    /// complete.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf)
+    }
+
+    /// Read the rest of the body into a `Vec`.
+    pub fn read_to_end(&mut self) -> Result<Vec<u8>> {
+        self.0.read_to_end()
+    }
+
+    /// Stream the rest of the body into `sink` piece by piece, returning the number of bytes
+    /// copied.
+    ///
+    /// Useful for large downloads that are written straight to storage.
+    pub fn copy_to<F>(&mut self, sink: F) -> Result<u64>
+    where
+        F: FnMut(&[u8]) -> Result<()>,
+    {
+        self.0.copy_to(sink)
+    }
+}
+
+impl<R: io::Read> io::Read for Response<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        Response::read(self, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls() {
+        let url = Url::parse("HTTP://example.com:8080/a/b?c=d#frag").unwrap();
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.path, "/a/b?c=d");
+        assert_eq!(url.host_header(), "example.com:8080");
+        assert_eq!(Url::parse("http://example.com").unwrap().path, "/");
+        assert_eq!(Url::parse("http://example.com?q").unwrap().path, "/?q");
+
+        assert_eq!(url.join("e").unwrap().path, "/a/e");
+        assert_eq!(url.join("/e").unwrap().path, "/e");
+        assert_eq!(url.join("//other/e").unwrap().host, "other");
+        assert_eq!(url.join("http://other:81/").unwrap().port, 81);
+
+        assert_eq!(
+            Url::parse("https://example.com"),
+            Err(OgcError::Http(HttpError::UnsupportedScheme))
+        );
+        for url in [
+            "example.com",
+            "http://",
+            "http://user@host/",
+            "http://host:port/",
+            "http://host/a b",
+        ] {
+            assert_eq!(Url::parse(url), Err(OgcError::Http(HttpError::InvalidUrl)));
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Readiness polling for many sockets at once, backed by ``net_poll``.
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use super::{AsRawSocket, PollBits, check};
+use crate::{OgcError, Result, error::Errno, ffi};
+
+/// Identifies a registered socket in the [`Events`] returned by [`Poll::poll`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Token(pub usize);
+
+bitflags::bitflags! {
+    /// Which readiness events to watch a socket for.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub struct Interest: u32 {
+        /// Data can be read or a connection can be accepted.
+        const READABLE = 0x0001;
+        /// Data can be written or a pending connect finished.
+        const WRITABLE = 0x0004;
+    }
+}
+
+/// A single readiness event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    token: Token,
+    revents: u32,
+}
+
+impl Event {
+    /// The token the socket was registered with.
+    pub fn token(&self) -> Token {
+        self.token
+    }
+
+    /// Data can be read or a connection can be accepted.
+    pub fn is_readable(&self) -> bool {
+        self.revents & (PollBits::POLLIN.bits() | PollBits::POLLPRI.bits()) != 0
+    }
+
+    /// Data can be written or a pending connect finished.
+    pub fn is_writable(&self) -> bool {
+        self.revents & PollBits::POLLOUT.bits() != 0
+    }
+
+    /// The socket has an error or is not a valid socket.
+    pub fn is_error(&self) -> bool {
+        self.revents & (PollBits::POLLERR.bits() | PollBits::POLLNVAL.bits()) != 0
+    }
+
+    /// The peer hung up.
+    pub fn is_hangup(&self) -> bool {
+        self.revents & PollBits::POLLHUP.bits() != 0
+    }
+}
+
+/// A collection of readiness events filled in by [`Poll::poll`].
+#[derive(Clone, Debug, Default)]
+pub struct Events {
+    events: Vec<Event>,
+}
+
+impl Events {
+    /// Create an empty event list with room for `capacity` events.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            events: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Iterate over the ready events.
+    pub fn iter(&self) -> core::slice::Iter<'_, Event> {
+        self.events.iter()
+    }
+
+    /// Whether no events are ready.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Remove every event.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl<'a> IntoIterator for &'a Events {
+    type Item = &'a Event;
+    type IntoIter = core::slice::Iter<'a, Event>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A registry of sockets to wait on from a single thread.
+///
+/// Sockets are not owned by the registry, deregister a socket before dropping it.
+///
+/// # Examples
+///
+/// ```rust
+/// const SERVER: Token = Token(0);
+/// const CONSOLE: Token = Token(1);
+///
+/// let mut poll = Poll::new();
+/// poll.register(&server, SERVER, Interest::READABLE)?;
+/// poll.register(&console, CONSOLE, Interest::READABLE)?;
+///
+/// let mut events = Events::with_capacity(8);
+/// loop {
+///     poll.poll(&mut events, Some(Duration::from_millis(16)))?;
+///     for event in &events {
+///         match event.token() {
+///             SERVER => { /* accept */ }
+///             CONSOLE => { /* read */ }
+///             _ => {}
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Poll {
+    sockets: Vec<ffi::pollsd>,
+    tokens: Vec<Token>,
+}
+
+impl Poll {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn position(&self, socket: i32) -> Option<usize> {
+        self.sockets.iter().position(|sd| sd.socket == socket)
+    }
+
+    /// Start watching `socket` for `interest`, reporting it as `token`.
+    ///
+    /// Registering the same socket twice fails with [`Errno::EEXIST`].
+    pub fn register<S: AsRawSocket>(
+        &mut self,
+        socket: &S,
+        token: Token,
+        interest: Interest,
+    ) -> Result<()> {
+        let socket = socket.as_raw_socket();
+        if self.position(socket).is_some() {
+            return Err(OgcError::Network(Errno::EEXIST));
+        }
+
+        self.sockets.push(ffi::pollsd {
+            socket,
+            events: interest.bits(),
+            revents: 0,
+        });
+        self.tokens.push(token);
+        Ok(())
+    }
+
+    /// Change the token and interest of an already registered `socket`.
+    ///
+    /// Fails with [`Errno::ENOENT`] if `socket` is not registered.
+    pub fn reregister<S: AsRawSocket>(
+        &mut self,
+        socket: &S,
+        token: Token,
+        interest: Interest,
+    ) -> Result<()> {
+        let index = self
+            .position(socket.as_raw_socket())
+            .ok_or(OgcError::Network(Errno::ENOENT))?;
+        self.sockets[index].events = interest.bits();
+        self.tokens[index] = token;
+        Ok(())
+    }
+
+    /// Stop watching `socket`.
+    ///
+    /// Fails with [`Errno::ENOENT`] if `socket` is not registered.
+    pub fn deregister<S: AsRawSocket>(&mut self, socket: &S) -> Result<()> {
+        let index = self
+            .position(socket.as_raw_socket())
+            .ok_or(OgcError::Network(Errno::ENOENT))?;
+        self.sockets.swap_remove(index);
+        self.tokens.swap_remove(index);
+        Ok(())
+    }
+
+    /// Wait until at least one registered socket is ready or `timeout` passes, `None` waits
+    /// forever.
+    ///
+    /// `events` is cleared and then filled with every ready socket. Returns the number of ready
+    /// sockets, `0` meaning the timeout passed.
+    pub fn poll(&mut self, events: &mut Events, timeout: Option<Duration>) -> Result<usize> {
+        events.clear();
+        if self.sockets.is_empty() {
+            return Err(OgcError::Network(Errno::EINVAL));
+        }
+
+        let millis = timeout.map_or(-1, |timeout| {
+            i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX)
+        });
+        for sd in &mut self.sockets {
+            sd.revents = 0;
+        }
+
+        let count =
+            i32::try_from(self.sockets.len()).map_err(|_| OgcError::Network(Errno::EINVAL))?;
+        let ready = check(unsafe { ffi::net_poll(self.sockets.as_mut_ptr(), count, millis) })?;
+
+        events.events.extend(
+            self.sockets
+                .iter()
+                .zip(&self.tokens)
+                .filter(|(sd, _)| sd.revents != 0)
+                .map(|(sd, &token)| Event {
+                    token,
+                    revents: sd.revents,
+                }),
+        );
+
+        Ok(ready as usize)
+    }
+}
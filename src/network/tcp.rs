@@ -0,0 +1,321 @@
+//! TCP sockets modelled after ``std::net``.
+
+use core::{
+    net::SocketAddrV4,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use super::{
+    AsRawSocket, IPPROTO_TCP, PollBits, Shutdown, Socket, SocketState, SocketType, TCP_NODELAY,
+    ToSocketAddrs, addr::each_addr,
+};
+use crate::{OgcError, Result, error::Errno, io};
+
+/// A TCP stream between a local and a remote socket.
+///
+/// The connection is closed when the value is dropped. [`Network::init`](super::Network::init)
+/// must have succeeded before any stream can be created.
+///
+/// # Examples
+///
+/// ```rust
+/// let stream = TcpStream::connect("192.168.1.2:4405")?;
+/// stream.set_nodelay(true)?;
+/// stream.write_all(b"hello")?;
+/// ```
+pub struct TcpStream {
+    state: SocketState,
+    peer: SocketAddrV4,
+    nodelay: AtomicBool,
+}
+
+impl AsRawSocket for TcpStream {
+    fn as_raw_socket(&self) -> i32 {
+        self.state.socket.0
+    }
+}
+
+impl TcpStream {
+    fn from_socket(socket: Socket, peer: SocketAddrV4) -> Self {
+        Self {
+            state: SocketState::new(socket),
+            peer,
+            nodelay: AtomicBool::new(false),
+        }
+    }
+
+    /// Open a TCP connection to `addr`.
+    ///
+    /// If `addr` resolves to several addresses each one is tried in turn, the error of the last
+    /// one is returned if none succeed.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        each_addr(addr, |addr| {
+            let socket = Socket::new_inet(SocketType::SockStream)?;
+            socket.connect_v4(addr)?;
+            Ok(Self::from_socket(socket, addr))
+        })
+    }
+
+    /// Open a TCP connection to `addr`, giving up once `timeout` has passed.
+    ///
+    /// A zero `timeout` is rejected with [`Errno::EINVAL`].
+    pub fn connect_timeout(addr: SocketAddrV4, timeout: Duration) -> Result<Self> {
+        if timeout.is_zero() {
+            return Err(OgcError::Network(Errno::EINVAL));
+        }
+
+        let socket = Socket::new_inet(SocketType::SockStream)?;
+        socket.set_nonblocking(true)?;
+        match socket.connect_v4(addr) {
+            Ok(()) => {}
+            Err(OgcError::Network(Errno::EINPROGRESS | Errno::EALREADY)) => {
+                socket.wait(PollBits::POLLOUT, Some(timeout))?;
+                // Connecting again reports whether the pending connection succeeded.
+                match socket.connect_v4(addr) {
+                    Ok(()) | Err(OgcError::Network(Errno::EISCONN)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Err(err) => return Err(err),
+        }
+        socket.set_nonblocking(false)?;
+
+        Ok(Self::from_socket(socket, addr))
+    }
+
+    /// The address of the remote end of this connection.
+    pub fn peer_addr(&self) -> SocketAddrV4 {
+        self.peer
+    }
+
+    /// Read into `buf`, returning how many bytes were read. `0` means the peer closed the
+    /// connection.
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        self.state.wait_readable()?;
+        self.state.socket.recv_raw(buf, 0)
+    }
+
+    /// Read exactly `buf.len()` bytes.
+    ///
+    /// Fails with [`Errno::ECONNRESET`] if the connection closes first.
+    pub fn read_exact(&self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(OgcError::Network(Errno::ECONNRESET)),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Read into `buf` without removing the data from the receive queue.
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize> {
+        self.state.wait_readable()?;
+        self.state.socket.recv_raw(buf, MSG_PEEK)
+    }
+
+    /// Write `buf`, returning how many bytes were written.
+    pub fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.state.wait_writable()?;
+        self.state.socket.send_raw(buf, 0)
+    }
+
+    /// Write all of `buf`.
+    pub fn write_all(&self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(OgcError::Network(Errno::ECONNRESET)),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Shut down the read half, write half or both halves of this connection.
+    pub fn shutdown(&self, how: Shutdown) -> Result<()> {
+        self.state.socket.shutdown(how)
+    }
+
+    /// Disable or enable Nagle's algorithm.
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<()> {
+        self.state
+            .socket
+            .set_option(IPPROTO_TCP, TCP_NODELAY, i32::from(nodelay))?;
+        self.nodelay.store(nodelay, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Whether Nagle's algorithm was disabled with [`TcpStream::set_nodelay`].
+    pub fn nodelay(&self) -> bool {
+        self.nodelay.load(Ordering::Relaxed)
+    }
+
+    /// Move this stream into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode calls that would block fail with [`Errno::EAGAIN`] and timeouts are
+    /// ignored.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.state.set_nonblocking(nonblocking)
+    }
+
+    /// Set how long reads wait for data, `None` waits forever.
+    ///
+    /// Reads that time out fail with [`Errno::ETIMEDOUT`]. A zero duration is rejected with
+    /// [`Errno::EINVAL`].
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.state.read_timeout.set(timeout)
+    }
+
+    /// The current read timeout.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.state.read_timeout.get()
+    }
+
+    /// Set how long writes wait for buffer space, `None` waits forever.
+    ///
+    /// Writes that time out fail with [`Errno::ETIMEDOUT`]. A zero duration is rejected with
+    /// [`Errno::EINVAL`].
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.state.write_timeout.set(timeout)
+    }
+
+    /// The current write timeout.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.state.write_timeout.get()
+    }
+}
+
+impl io::Read for TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        TcpStream::read(self, buf)
+    }
+}
+
+impl io::Write for TcpStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        TcpStream::write(self, buf)
+    }
+
+    /// Data is handed to `IOS` as it is written, there is nothing to flush.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Read for &TcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        TcpStream::read(self, buf)
+    }
+}
+
+impl io::Write for &TcpStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        TcpStream::write(self, buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Receive flag to look at data without consuming it.
+const MSG_PEEK: u32 = 0x02;
+
+/// A TCP socket server, listening for connections.
+///
+/// The socket stops listening when the value is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 80))?;
+/// for stream in listener.incoming() {
+///     let stream = stream?;
+///     stream.write_all(b"HTTP/1.0 204 No Content\r\n\r\n")?;
+/// }
+/// ```
+pub struct TcpListener {
+    state: SocketState,
+}
+
+impl AsRawSocket for TcpListener {
+    fn as_raw_socket(&self) -> i32 {
+        self.state.socket.0
+    }
+}
+
+impl TcpListener {
+    /// Default length of the pending connection queue.
+    const BACKLOG: u32 = 8;
+
+    /// Create a listener bound to `addr`.
+    ///
+    /// If `addr` resolves to several addresses the first one that can be bound is used.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        each_addr(addr, |addr| {
+            let socket = Socket::new_inet(SocketType::SockStream)?;
+            socket.bind_v4(addr)?;
+            socket.listen(Self::BACKLOG)?;
+            Ok(Self {
+                state: SocketState::new(socket),
+            })
+        })
+    }
+
+    /// The address this listener is bound to.
+    ///
+    /// After binding port 0 this holds the port that was picked.
+    pub fn local_addr(&self) -> Result<SocketAddrV4> {
+        self.state.socket.local_addr_v4()
+    }
+
+    /// Accept a new connection, returning the stream and the address of the peer.
+    ///
+    /// This waits for at most the read timeout set with [`TcpListener::set_timeout`].
+    pub fn accept(&self) -> Result<(TcpStream, SocketAddrV4)> {
+        self.state.wait_readable()?;
+        let (socket, peer) = self.state.socket.accept_v4()?;
+        Ok((TcpStream::from_socket(socket, peer), peer))
+    }
+
+    /// An iterator over the connections being received on this listener.
+    ///
+    /// The iterator never returns `None`.
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming { listener: self }
+    }
+
+    /// Move this listener into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode [`TcpListener::accept`] fails with [`Errno::EAGAIN`] when no connection
+    /// is pending.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.state.set_nonblocking(nonblocking)
+    }
+
+    /// Set how long [`TcpListener::accept`] waits for a connection, `None` waits forever.
+    ///
+    /// A zero duration is rejected with [`Errno::EINVAL`].
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.state.read_timeout.set(timeout)
+    }
+
+    /// The current accept timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.state.read_timeout.get()
+    }
+}
+
+/// An iterator that infinitely accepts connections on a [`TcpListener`].
+pub struct Incoming<'a> {
+    listener: &'a TcpListener,
+}
+
+impl Iterator for Incoming<'_> {
+    type Item = Result<TcpStream>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.listener.accept().map(|(stream, _)| stream))
+    }
+}
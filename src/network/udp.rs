@@ -0,0 +1,169 @@
+//! UDP sockets modelled after ``std::net``.
+
+use core::{
+    net::SocketAddrV4,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use super::{
+    AsRawSocket, SOL_SOCKET, Socket, SocketFlags, SocketState, SocketType, ToSocketAddrs,
+    addr::each_addr,
+};
+use crate::{OgcError, Result, error::Errno};
+
+/// Marks `UdpSocket::peer_port` as set.
+const PEER_SET: u32 = 1 << 16;
+
+/// A UDP socket.
+///
+/// The socket is closed when the value is dropped. [`Network::init`](super::Network::init) must
+/// have succeeded before any socket can be created.
+///
+/// # Examples
+///
+/// ```rust
+/// let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+/// socket.send_to(b"ping", "192.168.1.2:9000")?;
+/// let mut buf = [0u8; 512];
+/// let (len, from) = socket.recv_from(&mut buf)?;
+/// ```
+pub struct UdpSocket {
+    state: SocketState,
+    /// Address of the peer set by [`UdpSocket::connect`].
+    peer_ip: AtomicU32,
+    /// Port of the peer set by [`UdpSocket::connect`] with [`PEER_SET`] or'd in once connected.
+    peer_port: AtomicU32,
+}
+
+impl AsRawSocket for UdpSocket {
+    fn as_raw_socket(&self) -> i32 {
+        self.state.socket.0
+    }
+}
+
+impl UdpSocket {
+    /// Create a UDP socket bound to `addr`.
+    ///
+    /// If `addr` resolves to several addresses the first one that can be bound is used.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        each_addr(addr, |addr| {
+            let socket = Socket::new_inet(SocketType::SockDgram)?;
+            socket.bind_v4(addr)?;
+            Ok(Self {
+                state: SocketState::new(socket),
+                peer_ip: AtomicU32::new(0),
+                peer_port: AtomicU32::new(0),
+            })
+        })
+    }
+
+    /// The address this socket is bound to.
+    ///
+    /// After binding port 0 this holds the port that was picked.
+    pub fn local_addr(&self) -> Result<SocketAddrV4> {
+        self.state.socket.local_addr_v4()
+    }
+
+    /// Receive a single datagram, returning its length and the address it came from.
+    ///
+    /// If `buf` is too small the rest of the datagram is discarded.
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddrV4)> {
+        self.state.wait_readable()?;
+        self.state.socket.recv_from_v4(buf, 0)
+    }
+
+    /// Send `buf` as a single datagram to `addr`, returning how many bytes were sent.
+    ///
+    /// Only the first address `addr` resolves to is used, it fails with [`Errno::EINVAL`] if
+    /// there is none.
+    pub fn send_to<A: ToSocketAddrs>(&self, buf: &[u8], addr: A) -> Result<usize> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or(OgcError::Network(Errno::EINVAL))?;
+        self.state.wait_writable()?;
+        self.state.socket.send_to_v4(buf, addr)
+    }
+
+    /// Set the default destination for [`UdpSocket::send`] and only receive datagrams from
+    /// `addr`.
+    ///
+    /// If `addr` resolves to several addresses the first one that can be connected to is used.
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> Result<()> {
+        each_addr(addr, |addr| {
+            self.state.socket.connect_v4(addr)?;
+            self.peer_ip.store(addr.ip().to_bits(), Ordering::Relaxed);
+            self.peer_port
+                .store(u32::from(addr.port()) | PEER_SET, Ordering::Release);
+            Ok(())
+        })
+    }
+
+    /// The address set with [`UdpSocket::connect`].
+    pub fn peer_addr(&self) -> Result<SocketAddrV4> {
+        let port = self.peer_port.load(Ordering::Acquire);
+        if port & PEER_SET == 0 {
+            return Err(OgcError::Network(Errno::ENOTCONN));
+        }
+        Ok(SocketAddrV4::new(
+            self.peer_ip.load(Ordering::Relaxed).into(),
+            port as u16,
+        ))
+    }
+
+    /// Receive a single datagram from the connected peer.
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.state.wait_readable()?;
+        self.state.socket.recv_raw(buf, 0)
+    }
+
+    /// Send `buf` to the connected peer.
+    pub fn send(&self, buf: &[u8]) -> Result<usize> {
+        self.state.wait_writable()?;
+        self.state.socket.send_raw(buf, 0)
+    }
+
+    /// Allow or disallow sending to broadcast addresses.
+    pub fn set_broadcast(&self, broadcast: bool) -> Result<()> {
+        self.state.socket.set_option(
+            SOL_SOCKET,
+            SocketFlags::SO_BROADCAST.bits() as u32,
+            i32::from(broadcast),
+        )
+    }
+
+    /// Move this socket into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode calls that would block fail with [`Errno::EAGAIN`] and timeouts are
+    /// ignored.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.state.set_nonblocking(nonblocking)
+    }
+
+    /// Set how long receives wait for a datagram, `None` waits forever.
+    ///
+    /// Receives that time out fail with [`Errno::ETIMEDOUT`]. A zero duration is rejected with
+    /// [`Errno::EINVAL`].
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.state.read_timeout.set(timeout)
+    }
+
+    /// The current read timeout.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.state.read_timeout.get()
+    }
+
+    /// Set how long sends wait for buffer space, `None` waits forever.
+    ///
+    /// Sends that time out fail with [`Errno::ETIMEDOUT`]. A zero duration is rejected with
+    /// [`Errno::EINVAL`].
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.state.write_timeout.set(timeout)
+    }
+
+    /// The current write timeout.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.state.write_timeout.get()
+    }
+}
@@ -0,0 +1,924 @@
+//! The ``sysconf`` module of ``ogc-rs``.
+//!
+//! This module reads and writes the system configuration stored on the NAND at
+//! `/shared2/sys/SYSCONF`.
+//!
+//! [`SysConf::parse`] and [`SysConf::to_bytes`] only work on byte slices so the format can be
+//! inspected anywhere, [`read`] and [`SysConf::write`] go through `IOS`.
+//!
+//! The [`setting`] module handles the encrypted `setting.txt` that holds the console region,
+//! model and serial number.
+
+use alloc::{string::String, vec::Vec};
+use core::{ffi::CStr, fmt::Display};
+
+use crate::{
+    ios::{self, Mode, fs},
+    utils::Buf32,
+};
+
+pub mod setting;
+
+static SYSCONF_PATH: &CStr = c"/shared2/sys/SYSCONF";
+static SYSCONF_TMP_PATH: &CStr = c"/tmp/SYSCONF";
+
+/// Size of the `SYSCONF` file, it never grows or shrinks.
+pub const SYSCONF_SIZE: usize = 0x4000;
+
+const HEADER_MAGIC: &[u8; 4] = b"SCv0";
+const FOOTER_MAGIC: &[u8; 4] = b"SCed";
+const FOOTER_OFFSET: usize = SYSCONF_SIZE - 4;
+
+/// System Configuration Errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// An `IOS` call failed.
+    Ios(ios::Error),
+    /// The header or footer magic did not match.
+    BadMagic,
+    /// The data ended in the middle of the item table or an item.
+    Truncated,
+    /// An item used a type id that is not known.
+    UnknownType(u8),
+    /// An item name was not valid ASCII or was longer than 32 bytes.
+    InvalidName,
+    /// The item exists with a different type than the one provided.
+    TypeMismatch,
+    /// An item value was outside of the range it is allowed to be in.
+    InvalidValue,
+    /// The contents no longer fit into the file.
+    Full,
+}
+
+impl From<ios::Error> for Error {
+    fn from(value: ios::Error) -> Self {
+        Self::Ios(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Ios(err) => write!(f, "IOS error: {err}"),
+            Self::BadMagic => write!(f, "The SYSCONF header or footer magic was wrong"),
+            Self::Truncated => write!(f, "The SYSCONF data ended unexpectedly"),
+            Self::UnknownType(val) => write!(f, "The SYSCONF item type was unknown {val}"),
+            Self::InvalidName => write!(f, "The SYSCONF item name was invalid"),
+            Self::TypeMismatch => write!(f, "The SYSCONF item has a different type"),
+            Self::InvalidValue => write!(f, "The SYSCONF item value was out of range"),
+            Self::Full => write!(f, "The SYSCONF items do not fit in the file"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Ios(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A single `SYSCONF` value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    /// Up to 65536 bytes
+    BigArray(Vec<u8>),
+    /// Up to 256 bytes
+    SmallArray(Vec<u8>),
+    /// 8 bit value
+    Byte(u8),
+    /// 16 bit value
+    Short(u16),
+    /// 32 bit value
+    Long(u32),
+    /// 64 bit value
+    LongLong(u64),
+    /// Boolean value
+    Bool(bool),
+}
+
+impl Value {
+    fn type_id(&self) -> u8 {
+        match self {
+            Self::BigArray(_) => 1,
+            Self::SmallArray(_) => 2,
+            Self::Byte(_) => 3,
+            Self::Short(_) => 4,
+            Self::Long(_) => 5,
+            Self::LongLong(_) => 6,
+            Self::Bool(_) => 7,
+        }
+    }
+
+    fn encoded_len(&self) -> usize {
+        match self {
+            Self::BigArray(data) => 2 + data.len(),
+            Self::SmallArray(data) => 1 + data.len(),
+            Self::Byte(_) | Self::Bool(_) => 1,
+            Self::Short(_) => 2,
+            Self::Long(_) => 4,
+            Self::LongLong(_) => 8,
+        }
+    }
+
+    fn encode(&self, buf: &mut [u8]) -> Result<(), Error> {
+        match self {
+            Self::BigArray(data) => {
+                let len = u16::try_from(data.len().checked_sub(1).ok_or(Error::InvalidValue)?)
+                    .map_err(|_| Error::InvalidValue)?;
+                buf[0..2].copy_from_slice(&len.to_be_bytes());
+                buf[2..2 + data.len()].copy_from_slice(data);
+            }
+            Self::SmallArray(data) => {
+                buf[0] = u8::try_from(data.len().checked_sub(1).ok_or(Error::InvalidValue)?)
+                    .map_err(|_| Error::InvalidValue)?;
+                buf[1..=data.len()].copy_from_slice(data);
+            }
+            Self::Byte(val) => buf[0] = *val,
+            Self::Short(val) => buf[0..2].copy_from_slice(&val.to_be_bytes()),
+            Self::Long(val) => buf[0..4].copy_from_slice(&val.to_be_bytes()),
+            Self::LongLong(val) => buf[0..8].copy_from_slice(&val.to_be_bytes()),
+            Self::Bool(val) => buf[0] = u8::from(*val),
+        }
+        Ok(())
+    }
+
+    fn decode(type_id: u8, buf: &[u8]) -> Result<Self, Error> {
+        let get = |range: core::ops::Range<usize>| buf.get(range).ok_or(Error::Truncated);
+        match type_id {
+            1 => {
+                let len = usize::from(u16::from_be_bytes(
+                    get(0..2)?.try_into().map_err(|_| Error::Truncated)?,
+                )) + 1;
+                Ok(Self::BigArray(get(2..2 + len)?.to_vec()))
+            }
+            2 => {
+                let len = usize::from(get(0..1)?[0]) + 1;
+                Ok(Self::SmallArray(get(1..1 + len)?.to_vec()))
+            }
+            3 => Ok(Self::Byte(get(0..1)?[0])),
+            4 => Ok(Self::Short(u16::from_be_bytes(
+                get(0..2)?.try_into().map_err(|_| Error::Truncated)?,
+            ))),
+            5 => Ok(Self::Long(u32::from_be_bytes(
+                get(0..4)?.try_into().map_err(|_| Error::Truncated)?,
+            ))),
+            6 => Ok(Self::LongLong(u64::from_be_bytes(
+                get(0..8)?.try_into().map_err(|_| Error::Truncated)?,
+            ))),
+            7 => Ok(Self::Bool(get(0..1)?[0] != 0)),
+            val => Err(Error::UnknownType(val)),
+        }
+    }
+}
+
+/// A named `SYSCONF` item
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Item {
+    name: String,
+    value: Value,
+}
+
+impl Item {
+    /// Item name, for example `IPL.LNG`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Item value
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+/// System Menu Language
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Language {
+    /// Japanese
+    Japanese,
+    /// English
+    English,
+    /// German
+    German,
+    /// French
+    French,
+    /// Spanish
+    Spanish,
+    /// Italian
+    Italian,
+    /// Dutch
+    Dutch,
+    /// Simplified Chinese
+    SimplifiedChinese,
+    /// Traditional Chinese
+    TraditionalChinese,
+    /// Korean
+    Korean,
+}
+
+impl TryFrom<u8> for Language {
+    type Error = Error;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Japanese),
+            1 => Ok(Self::English),
+            2 => Ok(Self::German),
+            3 => Ok(Self::French),
+            4 => Ok(Self::Spanish),
+            5 => Ok(Self::Italian),
+            6 => Ok(Self::Dutch),
+            7 => Ok(Self::SimplifiedChinese),
+            8 => Ok(Self::TraditionalChinese),
+            9 => Ok(Self::Korean),
+            _ => Err(Error::InvalidValue),
+        }
+    }
+}
+
+impl From<Language> for u8 {
+    fn from(value: Language) -> Self {
+        match value {
+            Language::Japanese => 0,
+            Language::English => 1,
+            Language::German => 2,
+            Language::French => 3,
+            Language::Spanish => 4,
+            Language::Italian => 5,
+            Language::Dutch => 6,
+            Language::SimplifiedChinese => 7,
+            Language::TraditionalChinese => 8,
+            Language::Korean => 9,
+        }
+    }
+}
+
+/// Display Aspect Ratio
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AspectRatio {
+    /// 4:3
+    Standard,
+    /// 16:9
+    Widescreen,
+}
+
+/// Sensor Bar Position
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SensorBarPosition {
+    /// Below the TV
+    Bottom,
+    /// Above the TV
+    Top,
+}
+
+/// Bluetooth device address and name of a paired remote
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PairedDevice {
+    /// Bluetooth device address
+    pub address: [u8; 6],
+    /// Device name, `NUL` padded
+    pub name: [u8; 0x40],
+}
+
+impl PairedDevice {
+    /// Device name up to the first `NUL`
+    pub fn name(&self) -> &[u8] {
+        let len = self
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.name.len());
+        &self.name[..len]
+    }
+}
+
+const PAIRED_DEVICE_SIZE: usize = 0x46;
+const DINF_SIZE: usize = 0x461;
+
+/// Paired remotes stored in `BT.DINF`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PairedDevices {
+    /// Remotes that were paired with the 1 + 2 button sync
+    pub registered: Vec<PairedDevice>,
+    /// The six temporarily connected slots, four remotes followed by two unknown entries
+    pub active: [PairedDevice; 6],
+}
+
+impl PairedDevices {
+    /// Most registered remotes `BT.DINF` can hold
+    pub const MAX_REGISTERED: usize = 10;
+
+    fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != DINF_SIZE {
+            return Err(Error::InvalidValue);
+        }
+
+        let device = |index: usize| {
+            let offset = 1 + index * PAIRED_DEVICE_SIZE;
+            PairedDevice {
+                address: data[offset..offset + 6].try_into().unwrap_or([0; 6]),
+                name: data[offset + 6..offset + PAIRED_DEVICE_SIZE]
+                    .try_into()
+                    .unwrap_or([0; 0x40]),
+            }
+        };
+
+        let count = usize::from(data[0]).min(Self::MAX_REGISTERED);
+        Ok(Self {
+            registered: (0..count).map(device).collect(),
+            active: core::array::from_fn(|i| device(Self::MAX_REGISTERED + i)),
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        if self.registered.len() > Self::MAX_REGISTERED {
+            return Err(Error::InvalidValue);
+        }
+
+        let mut data = alloc::vec![0u8; DINF_SIZE];
+        data[0] = u8::try_from(self.registered.len()).map_err(|_| Error::InvalidValue)?;
+        let slots = self.registered.iter().enumerate().chain(
+            self.active
+                .iter()
+                .enumerate()
+                .map(|(index, device)| (Self::MAX_REGISTERED + index, device)),
+        );
+        for (index, device) in slots {
+            let offset = 1 + index * PAIRED_DEVICE_SIZE;
+            data[offset..offset + 6].copy_from_slice(&device.address);
+            data[offset + 6..offset + PAIRED_DEVICE_SIZE].copy_from_slice(&device.name);
+        }
+        Ok(data)
+    }
+}
+
+const PARENTAL_CONTROLS_SIZE: usize = 0x4A;
+
+/// Parental Control settings stored in `IPL.PC`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParentalControls {
+    /// Whether parental controls are enabled
+    pub enabled: bool,
+    /// Rating organization index
+    pub organization: u8,
+    /// Highest allowed rating
+    pub rating: u8,
+    /// Secret question index
+    pub question: u8,
+    /// Four digit PIN as ASCII
+    pub pin: [u8; 4],
+    /// Secret answer, at most 32 UTF-16 code units
+    pub answer: String,
+}
+
+impl ParentalControls {
+    fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != PARENTAL_CONTROLS_SIZE {
+            return Err(Error::InvalidValue);
+        }
+
+        let answer = data[8..]
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .take_while(|&unit| unit != 0);
+
+        Ok(Self {
+            enabled: data[0] & 0x80 != 0,
+            organization: data[1],
+            rating: data[2],
+            question: data[3],
+            pin: data[4..8].try_into().map_err(|_| Error::InvalidValue)?,
+            answer: char::decode_utf16(answer)
+                .collect::<Result<String, _>>()
+                .map_err(|_| Error::InvalidValue)?,
+        })
+    }
+
+    fn to_bytes(&self, previous: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut data = alloc::vec![0u8; PARENTAL_CONTROLS_SIZE];
+        // Keep the flag bits this module does not know about.
+        if let Some(&flags) = previous.first() {
+            data[0] = flags & 0x7F;
+        }
+        if self.enabled {
+            data[0] |= 0x80;
+        }
+        data[1] = self.organization;
+        data[2] = self.rating;
+        data[3] = self.question;
+        data[4..8].copy_from_slice(&self.pin);
+
+        // Leave room for the terminating `NUL`.
+        let answer = &mut data[8..PARENTAL_CONTROLS_SIZE - 2];
+        let mut units = self.answer.encode_utf16();
+        for unit in answer.chunks_exact_mut(2) {
+            match units.next() {
+                Some(val) => unit.copy_from_slice(&val.to_be_bytes()),
+                None => return Ok(data),
+            }
+        }
+
+        if units.next().is_some() {
+            return Err(Error::InvalidValue);
+        }
+        Ok(data)
+    }
+}
+
+/// Parsed `SYSCONF` contents
+///
+/// Items keep the order and values they were parsed with, so items this module does not know
+/// about are written back untouched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SysConf {
+    items: Vec<Item>,
+}
+
+impl SysConf {
+    /// Parse `SYSCONF` file contents
+    /// # Errors
+    /// See [`Error`]
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < SYSCONF_SIZE {
+            return Err(Error::Truncated);
+        }
+        if &data[0..4] != HEADER_MAGIC || &data[FOOTER_OFFSET..SYSCONF_SIZE] != FOOTER_MAGIC {
+            return Err(Error::BadMagic);
+        }
+
+        let count = usize::from(u16::from_be_bytes([data[4], data[5]]));
+        let mut items = Vec::with_capacity(count);
+        for index in 0..count {
+            let entry = 6 + index * 2;
+            let offset = usize::from(u16::from_be_bytes(
+                data.get(entry..entry + 2)
+                    .ok_or(Error::Truncated)?
+                    .try_into()
+                    .map_err(|_| Error::Truncated)?,
+            ));
+
+            let header = *data.get(offset).ok_or(Error::Truncated)?;
+            let name_len = usize::from(header & 0x1F) + 1;
+            let name = data
+                .get(offset + 1..offset + 1 + name_len)
+                .ok_or(Error::Truncated)?;
+            if !name.is_ascii() {
+                return Err(Error::InvalidName);
+            }
+
+            let value = Value::decode(
+                header >> 5,
+                data.get(offset + 1 + name_len..FOOTER_OFFSET)
+                    .ok_or(Error::Truncated)?,
+            )?;
+
+            items.push(Item {
+                name: String::from_utf8(name.to_vec()).map_err(|_| Error::InvalidName)?,
+                value,
+            });
+        }
+
+        Ok(Self { items })
+    }
+
+    /// Serialize back into `SYSCONF` file contents, always [`SYSCONF_SIZE`] bytes long
+    /// # Errors
+    /// See [`Error`]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut data = alloc::vec![0u8; SYSCONF_SIZE];
+        data[0..4].copy_from_slice(HEADER_MAGIC);
+        data[4..6].copy_from_slice(
+            &u16::try_from(self.items.len())
+                .map_err(|_| Error::Full)?
+                .to_be_bytes(),
+        );
+
+        // The offset table has one extra entry pointing past the last item.
+        let mut offset = 6 + (self.items.len() + 1) * 2;
+        for (index, item) in self.items.iter().enumerate() {
+            let entry = 6 + index * 2;
+            if entry + 2 > FOOTER_OFFSET {
+                return Err(Error::Full);
+            }
+            data[entry..entry + 2].copy_from_slice(
+                &u16::try_from(offset)
+                    .map_err(|_| Error::Full)?
+                    .to_be_bytes(),
+            );
+
+            let name = item.name.as_bytes();
+            let end = offset + 1 + name.len() + item.value.encoded_len();
+            if end > FOOTER_OFFSET {
+                return Err(Error::Full);
+            }
+
+            let name_len = u8::try_from(name.len() - 1).map_err(|_| Error::InvalidName)?;
+            data[offset] = item.value.type_id() << 5 | name_len;
+            data[offset + 1..=offset + name.len()].copy_from_slice(name);
+            item.value.encode(&mut data[offset + 1 + name.len()..end])?;
+            offset = end;
+        }
+
+        let entry = 6 + self.items.len() * 2;
+        data[entry..entry + 2].copy_from_slice(
+            &u16::try_from(offset)
+                .map_err(|_| Error::Full)?
+                .to_be_bytes(),
+        );
+        data[FOOTER_OFFSET..SYSCONF_SIZE].copy_from_slice(FOOTER_MAGIC);
+
+        Ok(data)
+    }
+
+    /// Iterate over every item in file order
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter()
+    }
+
+    /// Get the value of the item called `name`
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.items
+            .iter()
+            .find(|item| item.name == name)
+            .map(|item| &item.value)
+    }
+
+    /// Set the item called `name` to `value`, adding it if it does not exist yet
+    /// # Errors
+    /// Returns [`Error::TypeMismatch`] if the item exists with a different type and
+    /// [`Error::InvalidName`] if `name` is empty, longer than 32 bytes or not ASCII
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), Error> {
+        if name.is_empty() || name.len() > 32 || !name.is_ascii() {
+            return Err(Error::InvalidName);
+        }
+
+        match self.items.iter_mut().find(|item| item.name == name) {
+            Some(item) if item.value.type_id() != value.type_id() => Err(Error::TypeMismatch),
+            Some(item) => {
+                item.value = value;
+                Ok(())
+            }
+            None => {
+                self.items.push(Item {
+                    name: String::from(name),
+                    value,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove the item called `name`, returning its value
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        let index = self.items.iter().position(|item| item.name == name)?;
+        Some(self.items.remove(index).value)
+    }
+
+    fn get_byte(&self, name: &str) -> Option<u8> {
+        match self.get(name)? {
+            Value::Byte(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    fn get_array(&self, name: &str) -> Option<&[u8]> {
+        match self.get(name)? {
+            Value::BigArray(data) | Value::SmallArray(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// System menu language from `IPL.LNG`
+    pub fn language(&self) -> Option<Language> {
+        Language::try_from(self.get_byte("IPL.LNG")?).ok()
+    }
+
+    /// Set the system menu language
+    /// # Errors
+    /// See [`SysConf::set`]
+    pub fn set_language(&mut self, language: Language) -> Result<(), Error> {
+        self.set("IPL.LNG", Value::Byte(language.into()))
+    }
+
+    /// Display aspect ratio from `IPL.AR`
+    pub fn aspect_ratio(&self) -> Option<AspectRatio> {
+        match self.get_byte("IPL.AR")? {
+            0 => Some(AspectRatio::Standard),
+            _ => Some(AspectRatio::Widescreen),
+        }
+    }
+
+    /// Set the display aspect ratio
+    /// # Errors
+    /// See [`SysConf::set`]
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: AspectRatio) -> Result<(), Error> {
+        let val = match aspect_ratio {
+            AspectRatio::Standard => 0,
+            AspectRatio::Widescreen => 1,
+        };
+        self.set("IPL.AR", Value::Byte(val))
+    }
+
+    /// Whether progressive scan (480p) is enabled, from `IPL.PGS`
+    pub fn progressive_scan(&self) -> Option<bool> {
+        Some(self.get_byte("IPL.PGS")? != 0)
+    }
+
+    /// Enable or disable progressive scan
+    /// # Errors
+    /// See [`SysConf::set`]
+    pub fn set_progressive_scan(&mut self, enabled: bool) -> Result<(), Error> {
+        self.set("IPL.PGS", Value::Byte(enabled.into()))
+    }
+
+    /// Whether PAL60 (EURGB60) is enabled, from `IPL.E60`
+    pub fn pal60(&self) -> Option<bool> {
+        Some(self.get_byte("IPL.E60")? != 0)
+    }
+
+    /// Enable or disable PAL60
+    /// # Errors
+    /// See [`SysConf::set`]
+    pub fn set_pal60(&mut self, enabled: bool) -> Result<(), Error> {
+        self.set("IPL.E60", Value::Byte(enabled.into()))
+    }
+
+    /// Sensor bar position from `BT.BAR`
+    pub fn sensor_bar_position(&self) -> Option<SensorBarPosition> {
+        match self.get_byte("BT.BAR")? {
+            0 => Some(SensorBarPosition::Bottom),
+            _ => Some(SensorBarPosition::Top),
+        }
+    }
+
+    /// Set the sensor bar position
+    /// # Errors
+    /// See [`SysConf::set`]
+    pub fn set_sensor_bar_position(&mut self, position: SensorBarPosition) -> Result<(), Error> {
+        let val = match position {
+            SensorBarPosition::Bottom => 0,
+            SensorBarPosition::Top => 1,
+        };
+        self.set("BT.BAR", Value::Byte(val))
+    }
+
+    /// Sensor bar sensitivity from `BT.SENS`, between 1 and 5
+    pub fn sensor_bar_sensitivity(&self) -> Option<u32> {
+        match self.get("BT.SENS")? {
+            Value::Long(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /// Set the sensor bar sensitivity
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `sensitivity` is not between 1 and 5, otherwise see
+    /// [`SysConf::set`]
+    pub fn set_sensor_bar_sensitivity(&mut self, sensitivity: u32) -> Result<(), Error> {
+        if !(1..=5).contains(&sensitivity) {
+            return Err(Error::InvalidValue);
+        }
+        self.set("BT.SENS", Value::Long(sensitivity))
+    }
+
+    /// Paired remotes from `BT.DINF`
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `BT.DINF` is not the expected size
+    pub fn paired_devices(&self) -> Option<Result<PairedDevices, Error>> {
+        self.get_array("BT.DINF").map(PairedDevices::parse)
+    }
+
+    /// Replace the paired remotes
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if there are more than [`PairedDevices::MAX_REGISTERED`]
+    /// registered remotes, otherwise see [`SysConf::set`]
+    pub fn set_paired_devices(&mut self, devices: &PairedDevices) -> Result<(), Error> {
+        self.set("BT.DINF", Value::BigArray(devices.to_bytes()?))
+    }
+
+    /// Parental controls from `IPL.PC`
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if `IPL.PC` is not the expected size or the answer is not
+    /// valid UTF-16
+    pub fn parental_controls(&self) -> Option<Result<ParentalControls, Error>> {
+        self.get_array("IPL.PC").map(ParentalControls::parse)
+    }
+
+    /// Replace the parental control settings
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if the answer is longer than 32 UTF-16 code units,
+    /// otherwise see [`SysConf::set`]
+    pub fn set_parental_controls(&mut self, controls: &ParentalControls) -> Result<(), Error> {
+        let data = controls.to_bytes(self.get_array("IPL.PC").unwrap_or(&[]))?;
+        self.set("IPL.PC", Value::SmallArray(data))
+    }
+
+    /// Write the configuration back to the NAND.
+    ///
+    /// The new contents are written to `/tmp/SYSCONF` first and then renamed over the original,
+    /// so the file is never left half written.
+    /// # Errors
+    /// See [`Error`]
+    pub fn write(&self) -> Result<(), Error> {
+        Ok(replace_file(
+            SYSCONF_PATH,
+            SYSCONF_TMP_PATH,
+            &self.to_bytes()?,
+        )?)
+    }
+}
+
+/// Write `data` to `tmp_path` and rename it over `path`.
+///
+/// `IOS` only renames files when both paths end in the same file name.
+fn replace_file(path: &CStr, tmp_path: &CStr, data: &[u8]) -> Result<(), ios::Error> {
+    let mut buf = Buf32::new(data.len());
+    buf[..data.len()].copy_from_slice(data);
+
+    let tmp = tmp_path.to_str().map_err(|_| ios::Error::Invalid)?;
+    let path = path.to_str().map_err(|_| ios::Error::Invalid)?;
+
+    let attributes = fs::get_attributes(path)?;
+    let _ = fs::delete(tmp);
+    fs::create_file(attributes.with_path(tmp)?)?;
+
+    let res = write_file(tmp_path, &buf[..data.len()]).and_then(|()| fs::rename(tmp, path));
+    if res.is_err() {
+        let _ = fs::delete(tmp);
+    }
+    res
+}
+
+fn write_file(path: &CStr, data: &[u8]) -> Result<(), ios::Error> {
+    let file = ios::open(path, Mode::Write)?;
+    let res = ios::write(file, data);
+    let _ = ios::close(file);
+
+    if usize::try_from(res?).map_err(|_| ios::Error::Invalid)? != data.len() {
+        return Err(ios::Error::Invalid);
+    }
+    Ok(())
+}
+
+/// Read exactly `len` bytes from the start of `path`
+fn read_file(path: &CStr, len: usize) -> Result<Buf32, Error> {
+    let mut buf = Buf32::new(len);
+
+    let file = ios::open(path, Mode::Read)?;
+    let res = ios::read(file, &mut buf[..len]);
+    let _ = ios::close(file);
+
+    if usize::try_from(res?).map_err(|_| Error::Truncated)? != len {
+        return Err(Error::Truncated);
+    }
+    Ok(buf)
+}
+
+/// Read and parse `/shared2/sys/SYSCONF`
+/// # Errors
+/// See [`Error`]
+pub fn read() -> Result<SysConf, Error> {
+    SysConf::parse(&read_file(SYSCONF_PATH, SYSCONF_SIZE)?[..SYSCONF_SIZE])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn empty() -> SysConf {
+        SysConf { items: Vec::new() }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut conf = empty();
+        conf.set("IPL.LNG", Value::Byte(1)).unwrap();
+        conf.set("IPL.CB", Value::Long(0x1234_5678)).unwrap();
+        conf.set("IPL.NIK", Value::SmallArray(vec![b'W', 0, b'i', 0]))
+            .unwrap();
+        conf.set("IPL.UPT", Value::Short(0xBEEF)).unwrap();
+        conf.set("IPL.CD", Value::LongLong(u64::MAX - 1)).unwrap();
+        conf.set("IPL.SSV", Value::Bool(true)).unwrap();
+        conf.set("BT.DINF", Value::BigArray(vec![7; 0x461]))
+            .unwrap();
+
+        let data = conf.to_bytes().unwrap();
+        assert_eq!(data.len(), SYSCONF_SIZE);
+        assert_eq!(&data[..4], b"SCv0");
+        assert_eq!(&data[FOOTER_OFFSET..], b"SCed");
+        assert_eq!(&data[4..6], &7u16.to_be_bytes());
+
+        // The first item sits right after the 8 entry offset table.
+        let first = usize::from(u16::from_be_bytes([data[6], data[7]]));
+        assert_eq!(first, 6 + 8 * 2);
+        assert_eq!(data[first], 3 << 5 | 6);
+        assert_eq!(&data[first + 1..first + 8], b"IPL.LNG");
+        assert_eq!(data[first + 8], 1);
+
+        let parsed = SysConf::parse(&data).unwrap();
+        assert_eq!(parsed, conf);
+        assert_eq!(parsed.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let mut conf = empty();
+        conf.set_language(Language::French).unwrap();
+        conf.set_aspect_ratio(AspectRatio::Widescreen).unwrap();
+        conf.set_progressive_scan(true).unwrap();
+        conf.set_pal60(false).unwrap();
+        conf.set_sensor_bar_position(SensorBarPosition::Top)
+            .unwrap();
+        conf.set_sensor_bar_sensitivity(3).unwrap();
+        assert_eq!(conf.set_sensor_bar_sensitivity(6), Err(Error::InvalidValue));
+
+        let conf = SysConf::parse(&conf.to_bytes().unwrap()).unwrap();
+        assert_eq!(conf.language(), Some(Language::French));
+        assert_eq!(conf.aspect_ratio(), Some(AspectRatio::Widescreen));
+        assert_eq!(conf.progressive_scan(), Some(true));
+        assert_eq!(conf.pal60(), Some(false));
+        assert_eq!(conf.sensor_bar_position(), Some(SensorBarPosition::Top));
+        assert_eq!(conf.sensor_bar_sensitivity(), Some(3));
+        assert_eq!(conf.get("IPL.E60"), Some(&Value::Byte(0)));
+    }
+
+    #[test]
+    fn paired_devices_and_parental_controls() {
+        let mut device = PairedDevice {
+            address: [1, 2, 3, 4, 5, 6],
+            name: [0; 0x40],
+        };
+        device.name[..13].copy_from_slice(b"Nintendo RVL-");
+        let devices = PairedDevices {
+            registered: vec![device; 3],
+            active: [device; 6],
+        };
+        let controls = ParentalControls {
+            enabled: true,
+            organization: 2,
+            rating: 12,
+            question: 1,
+            pin: *b"1234",
+            answer: String::from("Zürich"),
+        };
+
+        let mut conf = empty();
+        conf.set_paired_devices(&devices).unwrap();
+        conf.set_parental_controls(&controls).unwrap();
+        let conf = SysConf::parse(&conf.to_bytes().unwrap()).unwrap();
+        assert_eq!(conf.paired_devices(), Some(Ok(devices)));
+        assert_eq!(conf.parental_controls(), Some(Ok(controls.clone())));
+        assert_eq!(device.name(), b"Nintendo RVL-");
+
+        let long = ParentalControls {
+            answer: "x".repeat(33),
+            ..controls
+        };
+        assert_eq!(
+            empty().set_parental_controls(&long),
+            Err(Error::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let data = empty().to_bytes().unwrap();
+        assert_eq!(
+            SysConf::parse(&data[..SYSCONF_SIZE - 1]),
+            Err(Error::Truncated)
+        );
+
+        let mut bad = data.clone();
+        bad[0] = b'X';
+        assert_eq!(SysConf::parse(&bad), Err(Error::BadMagic));
+
+        // One item whose offset points past the footer.
+        let mut bad = data.clone();
+        bad[4..6].copy_from_slice(&1u16.to_be_bytes());
+        bad[6..8].copy_from_slice(&0x3FFFu16.to_be_bytes());
+        assert_eq!(SysConf::parse(&bad), Err(Error::Truncated));
+
+        // An unknown type id.
+        let mut bad = data;
+        bad[4..6].copy_from_slice(&1u16.to_be_bytes());
+        bad[6..8].copy_from_slice(&10u16.to_be_bytes());
+        bad[10] = 0;
+        bad[11] = b'A';
+        assert_eq!(SysConf::parse(&bad), Err(Error::UnknownType(0)));
+
+        assert_eq!(empty().set("", Value::Byte(0)), Err(Error::InvalidName));
+        let mut conf = empty();
+        conf.set("IPL.LNG", Value::Byte(0)).unwrap();
+        assert_eq!(
+            conf.set("IPL.LNG", Value::Long(0)),
+            Err(Error::TypeMismatch)
+        );
+
+        let mut full = empty();
+        full.set("BIG1", Value::BigArray(vec![0; 0x3000])).unwrap();
+        full.set("BIG2", Value::BigArray(vec![0; 0x3000])).unwrap();
+        assert_eq!(full.to_bytes(), Err(Error::Full));
+    }
+}
@@ -0,0 +1,390 @@
+//! `setting.txt` support
+//!
+//! The system menu keeps the console region, model and serial number in
+//! `/title/00000001/00000002/data/setting.txt`. The file is `0x100` bytes of `KEY=VALUE\r\n`
+//! lines obfuscated with a rolling XOR key.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{ffi::CStr, fmt::Write};
+
+use super::{Error, read_file, replace_file};
+
+static SETTING_PATH: &CStr = c"/title/00000001/00000002/data/setting.txt";
+static SETTING_TMP_PATH: &CStr = c"/tmp/setting.txt";
+
+/// Size of `setting.txt`
+pub const SETTING_SIZE: usize = 0x100;
+
+const INITIAL_KEY: u32 = 0x73B5_DBFA;
+
+/// Encrypt or decrypt `data` in place, the operation is its own inverse.
+pub fn crypt(data: &mut [u8]) {
+    let mut key = INITIAL_KEY;
+    for byte in data {
+        *byte ^= key.to_be_bytes()[3];
+        key = key.rotate_left(1);
+    }
+}
+
+/// Console Region (`AREA`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Area {
+    /// Japan
+    Japan,
+    /// United States
+    Usa,
+    /// Europe
+    Europe,
+    /// Australia
+    Australia,
+    /// Brazil
+    Brazil,
+    /// Taiwan
+    Taiwan,
+    /// Republic of China
+    Roc,
+    /// Korea
+    Korea,
+    /// Hong Kong
+    HongKong,
+    /// Asia
+    Asia,
+    /// Latin America
+    LatinAmerica,
+    /// South Africa
+    SouthAfrica,
+    /// China
+    China,
+}
+
+impl Area {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Japan => "JPN",
+            Self::Usa => "USA",
+            Self::Europe => "EUR",
+            Self::Australia => "AUS",
+            Self::Brazil => "BRA",
+            Self::Taiwan => "TWN",
+            Self::Roc => "ROC",
+            Self::Korea => "KOR",
+            Self::HongKong => "HKG",
+            Self::Asia => "ASI",
+            Self::LatinAmerica => "LTN",
+            Self::SouthAfrica => "SAF",
+            Self::China => "CHN",
+        }
+    }
+}
+
+impl TryFrom<&str> for Area {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "JPN" => Ok(Self::Japan),
+            "USA" => Ok(Self::Usa),
+            "EUR" => Ok(Self::Europe),
+            "AUS" => Ok(Self::Australia),
+            "BRA" => Ok(Self::Brazil),
+            "TWN" => Ok(Self::Taiwan),
+            "ROC" => Ok(Self::Roc),
+            "KOR" => Ok(Self::Korea),
+            "HKG" => Ok(Self::HongKong),
+            "ASI" => Ok(Self::Asia),
+            "LTN" => Ok(Self::LatinAmerica),
+            "SAF" => Ok(Self::SouthAfrica),
+            "CHN" => Ok(Self::China),
+            _ => Err(Error::InvalidValue),
+        }
+    }
+}
+
+/// Video Standard (`VIDEO`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Video {
+    /// NTSC
+    Ntsc,
+    /// PAL
+    Pal,
+    /// PAL-M
+    Mpal,
+}
+
+impl Video {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Ntsc => "NTSC",
+            Self::Pal => "PAL",
+            Self::Mpal => "MPAL",
+        }
+    }
+}
+
+impl TryFrom<&str> for Video {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "NTSC" => Ok(Self::Ntsc),
+            "PAL" => Ok(Self::Pal),
+            "MPAL" => Ok(Self::Mpal),
+            _ => Err(Error::InvalidValue),
+        }
+    }
+}
+
+/// Game Region (`GAME`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Game {
+    /// Japan and Taiwan
+    Japan,
+    /// Americas
+    Usa,
+    /// Europe and Australia
+    Europe,
+    /// Korea
+    Korea,
+    /// China
+    China,
+}
+
+impl Game {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Japan => "JP",
+            Self::Usa => "US",
+            Self::Europe => "EU",
+            Self::Korea => "KR",
+            Self::China => "CN",
+        }
+    }
+}
+
+impl TryFrom<&str> for Game {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "JP" => Ok(Self::Japan),
+            "US" => Ok(Self::Usa),
+            "EU" => Ok(Self::Europe),
+            "KR" => Ok(Self::Korea),
+            "CN" => Ok(Self::China),
+            _ => Err(Error::InvalidValue),
+        }
+    }
+}
+
+/// Parsed `setting.txt` contents
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// Console region (`AREA`)
+    pub area: Area,
+    /// Model number, for example `RVL-001(USA)` (`MODEL`)
+    pub model: String,
+    /// Whether DVD video playback is available (`DVD`)
+    pub dvd: bool,
+    /// Unknown bitmask, usually `0x7FFE` (`MPCH`)
+    pub mpch: String,
+    /// Serial number prefix, for example `LU` (`CODE`)
+    pub code: String,
+    /// Serial number digits (`SERNO`)
+    pub serial_number: String,
+    /// Video standard (`VIDEO`)
+    pub video: Video,
+    /// Game region (`GAME`)
+    pub game: Game,
+}
+
+impl Settings {
+    /// Parse decrypted `setting.txt` contents
+    /// # Errors
+    /// Returns [`Error::InvalidValue`] if a field is missing or has an unknown value
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        let text = core::str::from_utf8(&data[..len]).map_err(|_| Error::InvalidValue)?;
+
+        let field = |key: &str| {
+            text.split(['\r', '\n'])
+                .filter_map(|line| line.split_once('='))
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value)
+                .ok_or(Error::InvalidValue)
+        };
+
+        Ok(Self {
+            area: Area::try_from(field("AREA")?)?,
+            model: field("MODEL")?.to_string(),
+            dvd: field("DVD")? != "0",
+            mpch: field("MPCH")?.to_string(),
+            code: field("CODE")?.to_string(),
+            serial_number: field("SERNO")?.to_string(),
+            video: Video::try_from(field("VIDEO")?)?,
+            game: Game::try_from(field("GAME")?)?,
+        })
+    }
+
+    /// Serialize into decrypted `setting.txt` contents, always [`SETTING_SIZE`] bytes long
+    /// # Errors
+    /// Returns [`Error::Full`] if the fields do not fit into [`SETTING_SIZE`] bytes
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut text = String::new();
+        write!(
+            text,
+            "AREA={}\r\nMODEL={}\r\nDVD={}\r\nMPCH={}\r\nCODE={}\r\nSERNO={}\r\nVIDEO={}\r\nGAME={}\r\n",
+            self.area.as_str(),
+            self.model,
+            u8::from(self.dvd),
+            self.mpch,
+            self.code,
+            self.serial_number,
+            self.video.as_str(),
+            self.game.as_str(),
+        )
+        .map_err(|_| Error::Full)?;
+
+        if text.len() > SETTING_SIZE {
+            return Err(Error::Full);
+        }
+
+        let mut data = text.into_bytes();
+        data.resize(SETTING_SIZE, 0);
+        Ok(data)
+    }
+
+    /// Full serial number as printed on the console, [`Settings::code`] followed by
+    /// [`Settings::serial_number`]
+    pub fn full_serial_number(&self) -> String {
+        let mut serial = self.code.clone();
+        serial.push_str(&self.serial_number);
+        serial
+    }
+
+    /// Encrypt and write the settings back to the NAND.
+    ///
+    /// Like [`SysConf::write`](super::SysConf::write) the file is written to `/tmp` first and
+    /// then renamed over the original.
+    /// # Errors
+    /// See [`Error`]
+    pub fn write(&self) -> Result<(), Error> {
+        let mut data = self.to_bytes()?;
+        crypt(&mut data);
+        Ok(replace_file(SETTING_PATH, SETTING_TMP_PATH, &data)?)
+    }
+}
+
+/// Read, decrypt and parse `setting.txt`
+/// # Errors
+/// See [`Error`]
+pub fn read() -> Result<Settings, Error> {
+    let mut buf = read_file(SETTING_PATH, SETTING_SIZE)?;
+    crypt(&mut buf[..SETTING_SIZE]);
+    Settings::parse(&buf[..SETTING_SIZE])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            area: Area::Europe,
+            model: String::from("RVL-001(EUR)"),
+            dvd: false,
+            mpch: String::from("0x7FFE"),
+            code: String::from("LEH"),
+            serial_number: String::from("123456789"),
+            video: Video::Pal,
+            game: Game::Europe,
+        }
+    }
+
+    #[test]
+    fn crypt_round_trip() {
+        let mut data = *b"AREA=USA\r\n";
+        crypt(&mut data);
+        // The key starts at 0x73B5DBFA and rotates left by one bit per byte.
+        assert_eq!(&data[..2], &[b'A' ^ 0xFA, b'R' ^ 0xF4]);
+        crypt(&mut data);
+        assert_eq!(&data, b"AREA=USA\r\n");
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = settings().to_bytes().unwrap();
+        assert_eq!(data.len(), SETTING_SIZE);
+        assert!(data.starts_with(b"AREA=EUR\r\nMODEL=RVL-001(EUR)\r\nDVD=0\r\n"));
+        assert_eq!(Settings::parse(&data), Ok(settings()));
+        assert_eq!(settings().full_serial_number(), "LEH123456789");
+
+        let mut encrypted = data.clone();
+        crypt(&mut encrypted);
+        crypt(&mut encrypted);
+        assert_eq!(encrypted, data);
+    }
+
+    #[test]
+    fn every_kind() {
+        let areas = [
+            Area::Japan,
+            Area::Usa,
+            Area::Europe,
+            Area::Australia,
+            Area::Brazil,
+            Area::Taiwan,
+            Area::Roc,
+            Area::Korea,
+            Area::HongKong,
+            Area::Asia,
+            Area::LatinAmerica,
+            Area::SouthAfrica,
+            Area::China,
+        ];
+        for area in areas {
+            assert_eq!(Area::try_from(area.as_str()), Ok(area));
+        }
+        for video in [Video::Ntsc, Video::Pal, Video::Mpal] {
+            assert_eq!(Video::try_from(video.as_str()), Ok(video));
+        }
+        for game in [
+            Game::Japan,
+            Game::Usa,
+            Game::Europe,
+            Game::Korea,
+            Game::China,
+        ] {
+            assert_eq!(Game::try_from(game.as_str()), Ok(game));
+        }
+        assert_eq!(Area::try_from("XYZ"), Err(Error::InvalidValue));
+        assert_eq!(Video::try_from("SECAM"), Err(Error::InvalidValue));
+        assert_eq!(Game::try_from("XX"), Err(Error::InvalidValue));
+
+        let data = Settings {
+            dvd: true,
+            ..settings()
+        }
+        .to_bytes()
+        .unwrap();
+        assert!(Settings::parse(&data).unwrap().dvd);
+    }
+
+    #[test]
+    fn malformed() {
+        let missing = b"AREA=USA\r\nMODEL=RVL-001(USA)\r\n";
+        assert_eq!(Settings::parse(missing), Err(Error::InvalidValue));
+
+        let data = String::from_utf8(settings().to_bytes().unwrap()).unwrap();
+        let unknown = data.replace("VIDEO=PAL", "VIDEO=XXX");
+        assert_eq!(
+            Settings::parse(unknown.as_bytes()),
+            Err(Error::InvalidValue)
+        );
+
+        let long = Settings {
+            model: "M".repeat(SETTING_SIZE),
+            ..settings()
+        };
+        assert_eq!(long.to_bytes(), Err(Error::Full));
+    }
+}
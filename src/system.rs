@@ -111,7 +111,7 @@ impl System {
         let r = unsafe { ffi::SYS_CreateAlarm(context) };
 
         if r < 0 {
-            Err(OgcError::System("system failed to create alarm".into()))
+            Err(OgcError::System(r))
         } else {
             Ok(())
         }
@@ -122,7 +122,7 @@ impl System {
         let r = unsafe { ffi::SYS_CancelAlarm(context) };
 
         if r < 0 {
-            Err(OgcError::System("system failed to cancel alarm".into()))
+            Err(OgcError::System(r))
         } else {
             Ok(())
         }
@@ -133,7 +133,7 @@ impl System {
         let r = unsafe { ffi::SYS_RemoveAlarm(context) };
 
         if r < 0 {
-            Err(OgcError::System("system failed to remove alarm".into()))
+            Err(OgcError::System(r))
         } else {
             Ok(())
         }
@@ -172,7 +172,7 @@ impl System {
         };
 
         if r < 0 {
-            Err(OgcError::System("system failed to set alarm".into()))
+            Err(OgcError::System(r))
         } else {
             Ok(())
         }
@@ -218,9 +218,7 @@ impl System {
         };
 
         if r < 0 {
-            Err(OgcError::System(
-                "system failed to set periodic alarm".into(),
-            ))
+            Err(OgcError::System(r))
         } else {
             Ok(())
         }
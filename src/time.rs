@@ -108,7 +108,8 @@ fn time_base_to_duration(upper: u32, lower: u32) -> Duration {
     Duration::from_micros(duration)
 }
 
-fn now() -> Duration {
+/// Time since the time base was last reset, usually at boot.
+pub(crate) fn now() -> Duration {
     let (upper, lower) = loop {
         let time_base_upper = move_from_time_base_upper();
         let time_base = move_from_time_base();
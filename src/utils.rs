@@ -113,6 +113,25 @@ impl Buf32 {
     /// Panics if rounding up `min_len` to the next multiple of 32 would
     /// overflow.
     pub fn new(min_len: usize) -> Self {
+        match Self::try_new(min_len) {
+            Ok(buf) => buf,
+            Err(err) => alloc::alloc::handle_alloc_error(
+                // SAFETY: `try_new` only fails after building this exact layout.
+                unsafe { Layout::from_size_align_unchecked(err.size, err.align) },
+            ),
+        }
+    }
+
+    /// Allocates a new buffer at least `min_len` bytes long, returning an
+    /// error instead of aborting if the allocation fails.
+    ///
+    /// # Panics
+    /// Panics if rounding up `min_len` to the next multiple of 32 would
+    /// overflow.
+    ///
+    /// # Errors
+    /// Returns [`AllocError`](crate::error::AllocError) if the allocator is out of memory.
+    pub fn try_new(min_len: usize) -> Result<Self, crate::error::AllocError> {
         // round len to lowest multiple of 32
         let padding = (32 - min_len % 32) % 32;
         min_len.checked_add(padding).expect("length overflow");
@@ -123,12 +142,10 @@ impl Buf32 {
         //   for alignment.
         let layout = unsafe { Layout::from_size_align_unchecked(min_len, 32) };
 
-        let block = match alloc::alloc::Global.allocate_zeroed(layout) {
-            Ok(block) => block,
-            Err(_) => alloc::alloc::handle_alloc_error(layout),
-        };
-
-        Buf32(block)
+        match alloc::alloc::Global.allocate_zeroed(layout) {
+            Ok(block) => Ok(Buf32(block)),
+            Err(_) => Err(layout.into()),
+        }
     }
 
     /// Extracts a slice of the entire buffer.